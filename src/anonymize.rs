@@ -0,0 +1,241 @@
+//! Pseudonymization of statements that are shared outside the bank.
+//!
+//! An [`Anonymizer`] replaces account numbers, IBANs, counterparty names
+//! and counterparty BICs with pseudonyms derived from the original value
+//! and a secret salt with HMAC-SHA256. The same value always gets the same
+//! pseudonym, so payments to one counterparty still match up, within a
+//! statement and across statements anonymized with the same salt:
+//!
+//! ```
+//! use ypbank_system::anonymize::Anonymizer;
+//! use ypbank_system::types::{Currency, Statement};
+//!
+//! let mut statement = Statement::new("1".into(), "DE89370400440532013000", Currency::EUR);
+//! statement.account_holder = Some("ACME GmbH".into());
+//! Anonymizer::new("secret").apply(&mut statement);
+//!
+//! assert_ne!(statement.account.id(), "DE89370400440532013000");
+//! assert!(statement.account.id().starts_with("DE"));
+//! assert!(statement.account_holder.unwrap().starts_with("Party "));
+//! ```
+
+use crate::error::{Error, Result};
+use crate::types::{AccountIdentifier, BankIdentifier, Bic, Iban, Statement};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+/// Replaces identifying data in statements with deterministic pseudonyms.
+///
+/// Pseudonymous IBANs keep their country and length and carry valid check
+/// digits, other account numbers keep their length and separators, and
+/// BICs keep their country; readers validating these fields accept them.
+/// Postal addresses of counterparties and of the account owner are
+/// removed, and names and account numbers are also replaced where they
+/// appear in descriptions. The servicing bank is kept.
+#[derive(Debug, Clone)]
+pub struct Anonymizer {
+    salt: Vec<u8>,
+}
+
+impl Anonymizer {
+    /// Anonymizer whose pseudonyms depend on `salt`; without knowing it,
+    /// a pseudonym can't be traced back by trying candidate values.
+    pub fn new(salt: impl AsRef<[u8]>) -> Self {
+        Anonymizer { salt: salt.as_ref().to_vec() }
+    }
+
+    /// Anonymizer with a random salt, for one-off copies: pseudonyms are
+    /// consistent within the anonymizer but can't be reproduced later.
+    pub fn random() -> Result<Self> {
+        let mut salt = [0; 32];
+        getrandom::getrandom(&mut salt).map_err(|e| Error::Io(e.into(), None))?;
+        Ok(Anonymizer { salt: salt.to_vec() })
+    }
+
+    /// Anonymize `statement` in place.
+    pub fn apply(&self, statement: &mut Statement) {
+        // Original values and their pseudonyms, to clean up free text
+        let mut replaced = Vec::new();
+
+        statement.account = self.account(&statement.account, &mut replaced);
+        statement.account_holder = statement.account_holder.take().map(|name| self.name(&name, &mut replaced));
+        if let Some(ref mut owner) = statement.account_owner {
+            owner.name = owner.name.take().map(|name| self.name(&name, &mut replaced));
+            owner.postal_address = None;
+        }
+
+        for tx in &mut statement.transactions {
+            tx.account = tx.account.take().map(|account| {
+                self.account(&AccountIdentifier::from(account), &mut replaced).to_string()
+            });
+            tx.counterparty_account = tx.counterparty_account.take().map(|acc| self.account(&acc, &mut replaced));
+            tx.counterparty_name = tx.counterparty_name.take().map(|name| self.name(&name, &mut replaced));
+            tx.ultimate_counterparty_name = tx.ultimate_counterparty_name.take().map(|name| self.name(&name, &mut replaced));
+            tx.counterparty_address = None;
+            tx.bank_identifier = tx.bank_identifier.take().map(|bank| self.bank(&bank));
+            // The original text has everything in the clear
+            tx.source_raw = None;
+            for detail in &mut tx.details {
+                detail.counterparty_account = detail.counterparty_account.take().map(|acc| self.account(&acc, &mut replaced));
+                detail.counterparty_name = detail.counterparty_name.take().map(|name| self.name(&name, &mut replaced));
+                detail.ultimate_counterparty_name = detail.ultimate_counterparty_name.take().map(|name| self.name(&name, &mut replaced));
+                detail.counterparty_address = None;
+                detail.bank_identifier = detail.bank_identifier.take().map(|bank| self.bank(&bank));
+            }
+        }
+
+        // Longest first, so a name isn't replaced piecemeal through a shorter one;
+        // very short values would hit unrelated words
+        replaced.retain(|(original, _)| original.chars().count() >= 4);
+        replaced.sort_by_key(|(original, _)| std::cmp::Reverse(original.len()));
+        let scrub = |text: &mut String| {
+            for (original, pseudonym) in &replaced {
+                if text.contains(original.as_str()) {
+                    *text = text.replace(original.as_str(), pseudonym);
+                }
+            }
+        };
+        for tx in &mut statement.transactions {
+            scrub(&mut tx.description);
+            tx.additional_info.as_mut().map(scrub);
+            for detail in &mut tx.details {
+                scrub(&mut detail.description);
+                detail.additional_info.as_mut().map(scrub);
+            }
+        }
+    }
+
+    fn name(&self, name: &str, replaced: &mut Vec<(String, String)>) -> String {
+        let hash = self.hash("name", name, 0);
+        let pseudonym = format!("Party {}", hash[..8].iter().map(|b| format!("{:02X}", b)).collect::<String>());
+        replaced.push((name.to_string(), pseudonym.clone()));
+        pseudonym
+    }
+
+    fn account(&self, account: &AccountIdentifier, replaced: &mut Vec<(String, String)>) -> AccountIdentifier {
+        let pseudonym = match account {
+            AccountIdentifier::Iban(iban) => {
+                let bban = self.characters("account", iban.as_str(), iban.bban().len(), b"0123456789");
+                let check = 98 - Iban::mod97(&format!("{}00{}", iban.country_code(), bban));
+                let pseudonym = format!("{}{:02}{}", iban.country_code(), check, bban);
+                match pseudonym.parse() {
+                    Ok(iban) => AccountIdentifier::Iban(iban),
+                    Err(_) => AccountIdentifier::from(pseudonym),
+                }
+            }
+            AccountIdentifier::Other { id, scheme, issuer } => AccountIdentifier::Other {
+                id: self.mask("account", id),
+                scheme: scheme.clone(),
+                issuer: issuer.clone(),
+            },
+        };
+        replaced.push((account.id().to_string(), pseudonym.id().to_string()));
+        pseudonym
+    }
+
+    fn bank(&self, bank: &BankIdentifier) -> BankIdentifier {
+        match bank {
+            BankIdentifier::Bic(bic) => {
+                let letters = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+                let pseudonym = format!(
+                    "{}{}{}{}",
+                    self.characters("bank", bic.as_str(), 4, letters),
+                    bic.country_code(),
+                    self.characters("location", bic.as_str(), 2, letters),
+                    bic.branch_code().map_or(String::new(), |_| self.characters("branch", bic.as_str(), 3, letters)),
+                );
+                pseudonym.parse::<Bic>().map_or_else(|_| bank.clone(), BankIdentifier::Bic)
+            }
+            BankIdentifier::ClearingSystem { id, system } => BankIdentifier::ClearingSystem {
+                id: self.mask("bank", id),
+                system: system.clone(),
+            },
+        }
+    }
+
+    /// `value` with its letters and digits replaced, keeping separators.
+    fn mask(&self, kind: &str, value: &str) -> String {
+        let digits = self.characters(kind, value, value.chars().count(), b"0123456789");
+        value.chars()
+            .zip(digits.chars())
+            .map(|(c, digit)| if c.is_ascii_alphanumeric() { digit } else { c })
+            .collect()
+    }
+
+    /// `length` characters out of `alphabet`, derived from `value`.
+    fn characters(&self, kind: &str, value: &str, length: usize, alphabet: &[u8]) -> String {
+        (0..)
+            .flat_map(|block| self.hash(kind, value, block))
+            .take(length)
+            .map(|byte| char::from(alphabet[byte as usize % alphabet.len()]))
+            .collect()
+    }
+
+    /// Block `block` of the HMAC-SHA256 stream of `value`, keyed by the salt.
+    fn hash(&self, kind: &str, value: &str, block: u64) -> [u8; 32] {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.salt).expect("HMAC takes keys of any length");
+        for part in [kind.as_bytes(), &[0], value.as_bytes(), &[0], &block.to_le_bytes()] {
+            mac.update(part);
+        }
+        mac.finalize().into_bytes().into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Currency, DebitCredit, Transaction};
+    use chrono::NaiveDate;
+    use rust_decimal::Decimal;
+
+    #[test]
+    fn test_anonymize() {
+        let mut statement = Statement::new("1".into(), "40702810900000000001", Currency::RUB);
+        for reference in ["1", "2"] {
+            statement.transactions.push(Transaction {
+                reference: reference.into(),
+                date: NaiveDate::from_ymd_opt(2024, 3, 1).unwrap().into(),
+                amount: Decimal::new(100, 0),
+                currency: Currency::RUB,
+                debit_credit: DebitCredit::Debit,
+                counterparty_account: Some("DE89370400440532013000".into()),
+                counterparty_name: Some("ООО Ромашка".into()),
+                bank_identifier: Some("COBADEFFXXX".parse().unwrap()),
+                description: "Оплата ООО Ромашка по счёту 17".into(),
+                ..Transaction::default()
+            });
+        }
+        let original = statement.clone();
+        let anonymizer = Anonymizer::new("salt");
+        anonymizer.apply(&mut statement);
+
+        let [first, second] = &statement.transactions[..] else { panic!() };
+        assert_eq!(first.counterparty_name, second.counterparty_name);
+        assert_ne!(first.counterparty_name, original.transactions[0].counterparty_name);
+        let name = first.counterparty_name.as_deref().unwrap();
+        assert_eq!(first.description, format!("Оплата {} по счёту 17", name));
+
+        let iban = first.counterparty_account.clone().unwrap();
+        assert!(matches!(iban, AccountIdentifier::Iban(_)));
+        assert_eq!(iban.id().len(), 22);
+        assert_ne!(iban.id(), "DE89370400440532013000");
+        let Some(BankIdentifier::Bic(ref bic)) = first.bank_identifier else { panic!() };
+        assert_eq!(bic.country_code(), "DE");
+        assert_eq!(bic.as_str().len(), 11);
+        assert_eq!(statement.account.id().len(), 20);
+        assert!(statement.account.id().bytes().all(|b| b.is_ascii_digit()));
+
+        let mut again = original.clone();
+        anonymizer.apply(&mut again);
+        assert_eq!(again, statement);
+        let mut other_salt = original;
+        Anonymizer::new("pepper").apply(&mut other_salt);
+        assert_ne!(other_salt.transactions[0].counterparty_name, first.counterparty_name);
+        assert_eq!(name.len(), "Party ".len() + 16);
+
+        let mut random = statement.clone();
+        Anonymizer::random().unwrap().apply(&mut random);
+        assert_ne!(random.transactions[0].counterparty_name, first.counterparty_name);
+        assert_eq!(random.transactions[0].counterparty_name, random.transactions[1].counterparty_name);
+    }
+}
@@ -0,0 +1,246 @@
+//! Processing many statement files at once.
+//!
+//! With the `parallel` feature, files are spread over rayon's
+//! work-stealing thread pool, so a batch of thousands of statements keeps
+//! every CPU busy; without it they are processed one after another. A file
+//! that fails doesn't stop the others: the [`BatchReport`] holds the
+//! outcome of each one, in the order of the input paths.
+//!
+//! ```no_run
+//! use std::path::PathBuf;
+//! use ypbank_system::batch;
+//! use ypbank_system::pipeline::Pipeline;
+//! use ypbank_system::Format;
+//!
+//! let inputs = vec![PathBuf::from("june.mt940"), PathBuf::from("july.mt940")];
+//! let pipeline = Pipeline::new(Format::Mt940, Format::Camt053);
+//! let report = batch::convert_all(&inputs, &pipeline, |input| input.with_extension("xml"));
+//! for (path, error) in report.failed() {
+//!     eprintln!("{}: {}", path.display(), error);
+//! }
+//! ```
+
+use crate::conversion::ConversionReport;
+use crate::error::{Error, ParseOutcome, Result};
+use crate::format::FormatRegistry;
+use crate::pipeline::Pipeline;
+use crate::types::Statement;
+use crate::Format;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// Outcome of one file of a batch.
+#[derive(Debug)]
+pub struct FileResult<T> {
+    /// Path of the input file.
+    pub path: PathBuf,
+    /// What processing it gave; errors name the file.
+    pub result: Result<T>,
+}
+
+/// Outcomes of every file of a batch, in the order of the input paths.
+#[derive(Debug)]
+pub struct BatchReport<T> {
+    /// Outcome of each file.
+    pub files: Vec<FileResult<T>>,
+}
+
+impl<T> BatchReport<T> {
+    /// Files processed without an error, with their results.
+    pub fn succeeded(&self) -> impl Iterator<Item = (&Path, &T)> {
+        self.files.iter().filter_map(|file| file.result.as_ref().ok().map(|value| (file.path.as_path(), value)))
+    }
+
+    /// Files that failed, with their errors.
+    pub fn failed(&self) -> impl Iterator<Item = (&Path, &Error)> {
+        self.files.iter().filter_map(|file| file.result.as_ref().err().map(|error| (file.path.as_path(), error)))
+    }
+
+    /// Whether every file was processed without an error.
+    pub fn is_success(&self) -> bool {
+        self.files.iter().all(|file| file.result.is_ok())
+    }
+}
+
+/// Run `process` on every path, in parallel with the `parallel` feature.
+///
+/// Errors get the path of their file, as with [`Error::in_file`].
+///
+/// # Examples
+///
+/// ```
+/// use std::path::{Path, PathBuf};
+/// use ypbank_system::{batch, Error};
+///
+/// let paths = [PathBuf::from("a.mt940"), PathBuf::from("b.csv")];
+/// let report = batch::for_each_file(&paths, |path: &Path| match path.extension() {
+///     Some(ext) if ext == "mt940" => Ok(1),
+///     _ => Err(Error::InvalidFormat("not MT940".to_string(), None)),
+/// });
+/// assert_eq!(report.succeeded().count(), 1);
+/// assert!(report.failed().next().unwrap().1.to_string().contains("b.csv"));
+/// ```
+pub fn for_each_file<T, F>(paths: &[PathBuf], process: F) -> BatchReport<T>
+where
+    T: Send,
+    F: Fn(&Path) -> Result<T> + Send + Sync,
+{
+    let run = |path: &PathBuf| FileResult {
+        path: path.clone(),
+        result: process(path).map_err(|e| e.in_file(path.display().to_string())),
+    };
+
+    #[cfg(feature = "parallel")]
+    let files = paths.par_iter().map(run).collect();
+    #[cfg(not(feature = "parallel"))]
+    let files = paths.iter().map(run).collect();
+
+    BatchReport { files }
+}
+
+/// Parse every file as `format` with the formats of `registry`.
+pub fn read_all(paths: &[PathBuf], format: Format, registry: &FormatRegistry) -> BatchReport<ParseOutcome<Statement>> {
+    for_each_file(paths, |path| {
+        let mut reader = BufReader::new(File::open(path)?);
+        registry.read_with_diagnostics(format, &mut reader)
+    })
+}
+
+/// Paths of the files `inputs` name: a directory stands for the files in
+/// it, and a file name with `*` or `?` wildcards for the matching files of
+/// its directory, in name order; other inputs are taken as they are.
+///
+/// A pattern matching no file is an error, rather than a batch quietly
+/// converting nothing.
+pub fn expand_inputs<S: AsRef<str>>(inputs: &[S]) -> Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for input in inputs {
+        let input = Path::new(input.as_ref());
+        let name = input.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+        let (dir, pattern) = if input.is_dir() {
+            (input, "*")
+        } else if name.contains(['*', '?']) {
+            (input.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new(".")), name)
+        } else {
+            paths.push(input.to_path_buf());
+            continue;
+        };
+
+        let mut matches = Vec::new();
+        for entry in std::fs::read_dir(dir).map_err(|e| Error::from(e).in_file(dir.display().to_string()))? {
+            let path = entry?.path();
+            let matched = path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| wildcard_match(pattern, name));
+            if matched && path.is_file() {
+                matches.push(path);
+            }
+        }
+        if matches.is_empty() {
+            return Err(Error::InvalidFormat(format!("No files match {}", input.display()), None));
+        }
+        matches.sort();
+        paths.extend(matches);
+    }
+    Ok(paths)
+}
+
+/// Whether `name` matches `pattern`, where `*` stands for any run of
+/// characters and `?` for any one character.
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Position of the last `*` and the name position it was tried at
+    let mut star = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = star {
+            // Let the `*` take one more character
+            p = star_p + 1;
+            n = star_n + 1;
+            star = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Convert every file with `pipeline`, writing it to the path `output`
+/// gives for its input path.
+pub fn convert_all<F>(paths: &[PathBuf], pipeline: &Pipeline, output: F) -> BatchReport<ConversionReport>
+where
+    F: Fn(&Path) -> PathBuf + Send + Sync,
+{
+    for_each_file(paths, |path| {
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut writer = BufWriter::new(File::create(output(path))?);
+        let report = pipeline.run(&mut reader, &mut writer)?;
+        writer.flush()?;
+        Ok(report)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert_all() {
+        let dir = std::env::temp_dir().join(format!("ypbank-batch-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let good = dir.join("good.mt940");
+        std::fs::write(&good, ":20:STMT\n:25:ACC001\n:60F:C240101EUR100,00\n:62F:C240101EUR100,00\n-}").unwrap();
+        let bad = dir.join("bad.mt940");
+        std::fs::write(&bad, ":20:STMT\n").unwrap();
+        let paths = vec![good.clone(), bad.clone(), dir.join("missing.mt940")];
+
+        let pipeline = Pipeline::new(Format::Mt940, Format::Camt053);
+        let report = convert_all(&paths, &pipeline, |input| input.with_extension("xml"));
+        assert_eq!(report.files.iter().map(|file| &file.path).collect::<Vec<_>>(), paths.iter().collect::<Vec<_>>());
+        assert_eq!(report.succeeded().map(|(path, _)| path).collect::<Vec<_>>(), [good.as_path()]);
+        assert!(good.with_extension("xml").exists());
+        let failed: Vec<&Path> = report.failed().map(|(path, _)| path).collect();
+        assert_eq!(failed, [bad.as_path(), dir.join("missing.mt940").as_path()]);
+        assert!(report.failed().all(|(path, error)| error.to_string().contains(&path.display().to_string())));
+        assert!(!report.is_success());
+
+        let statements = read_all(&paths[..1], Format::Mt940, &FormatRegistry::default());
+        assert_eq!(statements.files[0].result.as_ref().unwrap().statement.statement_id, "STMT");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_expand_inputs() {
+        assert!(wildcard_match("*.mt940", "june.mt940"));
+        assert!(wildcard_match("j?ne*", "june.mt940"));
+        assert!(wildcard_match("*-*-p?.xml", "stmt-2024-p1.xml"));
+        assert!(!wildcard_match("*.mt940", "june.mt940.bak"));
+        assert!(!wildcard_match("?.csv", "ab.csv"));
+
+        let dir = std::env::temp_dir().join(format!("ypbank-expand-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        for name in ["b.mt940", "a.mt940", "c.csv"] {
+            std::fs::write(dir.join(name), "").unwrap();
+        }
+        let pattern = dir.join("*.mt940").display().to_string();
+        let missing = dir.join("missing.mt940").display().to_string();
+        let paths = expand_inputs(&[pattern, missing.clone()]).unwrap();
+        assert_eq!(paths, [dir.join("a.mt940"), dir.join("b.mt940"), PathBuf::from(&missing)]);
+        // Directories inside a directory are left out
+        let paths = expand_inputs(&[dir.display().to_string()]).unwrap();
+        assert_eq!(paths, [dir.join("a.mt940"), dir.join("b.mt940"), dir.join("c.csv")]);
+        assert!(expand_inputs(&[dir.join("*.xml").display().to_string()]).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,12 +1,16 @@
 //! YP Bank Compare - CLI tool for comparing bank statements from different formats.
 
 use clap::Parser;
+use std::collections::HashMap;
 use std::fs::File;
+use std::io::BufReader;
+use std::time::Instant;
+use tracing::{debug, error, info};
 use ypbank_system::{
-    camt053_format::Camt053Statement,
-    csv_format::CsvStatement,
-    mt940_format::Mt940Statement,
-    Format, Result, Statement,
+    error::FailureClass,
+    format::{EntryReader, FormatRegistry},
+    logging::{self, LogFormat},
+    Format, Result, Statement, Transaction,
 };
 
 #[derive(Parser)]
@@ -17,7 +21,7 @@ struct Cli {
     #[arg(long = "file1")]
     file1: String,
 
-    /// First file format (mt940, camt053, csv)
+    /// First file format (mt940, camt053, csv, json)
     #[arg(long = "format1")]
     format1: String,
 
@@ -25,76 +29,160 @@ struct Cli {
     #[arg(long = "file2")]
     file2: String,
 
-    /// Second file format (mt940, camt053, csv)
+    /// Second file format (mt940, camt053, csv, json)
     #[arg(long = "format2")]
     format2: String,
+
+    /// More diagnostics on stderr: -v adds phase timings, -vv details of
+    /// every phase
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Only errors on stderr
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Format of diagnostics on stderr (text, json)
+    #[arg(long = "log-format", default_value = "text")]
+    log_format: LogFormat,
 }
 
 fn main() {
-    if let Err(e) = run() {
-        eprintln!("Error: {}", e);
-        std::process::exit(1);
+    let cli = Cli::try_parse().unwrap_or_else(|e| {
+        let _ = e.print();
+        std::process::exit(if e.use_stderr() { FailureClass::Usage.exit_code() } else { 0 })
+    });
+    logging::init(logging::max_level(cli.verbose, cli.quiet), cli.log_format);
+    match run(&cli) {
+        Ok(true) => {}
+        Ok(false) => std::process::exit(FailureClass::Differences.exit_code()),
+        Err(e) => {
+            error!(code = e.code().as_str(), "{}", e);
+            std::process::exit(e.failure_class().exit_code());
+        }
     }
 }
 
-fn run() -> Result<()> {
-    let cli = Cli::parse();
-
+/// Compare the files; whether they are identical.
+fn run(cli: &Cli) -> Result<bool> {
     // Parse formats
     let format1 = cli.format1.parse::<Format>()?;
     let format2 = cli.format2.parse::<Format>()?;
 
-    // Read and parse first file
-    let mut file1 = File::open(&cli.file1)?;
-    let statement1 = parse_statement(&mut file1, format1)?;
+    let registry = FormatRegistry::default();
 
-    // Read and parse second file
-    let mut file2 = File::open(&cli.file2)?;
-    let statement2 = parse_statement(&mut file2, format2)?;
+    // Open both files; transactions are read as the comparison goes
+    let started = Instant::now();
+    let side1 = Side::open(&registry, format1, &cli.file1)?;
+    let side2 = Side::open(&registry, format2, &cli.file2)?;
+    info!(phase = "open", elapsed_ms = started.elapsed().as_secs_f64() * 1000.0, "Phase finished");
 
     // Compare statements
-    let result = compare_statements(&statement1, &statement2);
+    let started = Instant::now();
+    let differences = compare_statements(side1, side2)?;
+    info!(phase = "compare", elapsed_ms = started.elapsed().as_secs_f64() * 1000.0, "Phase finished");
+
+    if differences.is_empty() {
+        println!("The transaction records in 'file1' and 'file2' are identical.");
+    } else {
+        println!("Differences found:");
+        for diff in &differences {
+            println!("  - {}", diff);
+        }
+        println!();
+    }
 
-    println!("{}", result);
+    Ok(differences.is_empty())
+}
 
-    Ok(())
+/// One of the compared files, read a transaction at a time.
+struct Side<'a> {
+    path: &'a str,
+    entries: Box<dyn EntryReader + 'a>,
+    /// Number of transactions read so far.
+    count: usize,
+    /// Transactions read but not matched on the other side yet, with their
+    /// index in the file, by fingerprint.
+    unmatched: HashMap<u64, Vec<(usize, Transaction)>>,
 }
 
-fn parse_statement<R: std::io::Read>(reader: &mut R, format: Format) -> Result<Statement> {
-    match format {
-        Format::Mt940 => {
-            let mt940 = Mt940Statement::from_read(reader)?;
-            Ok(mt940.statement)
-        }
-        Format::Camt053 => {
-            let camt053 = Camt053Statement::from_read(reader)?;
-            Ok(camt053.statement)
-        }
-        Format::Csv => {
-            let csv = CsvStatement::from_read(reader)?;
-            Ok(csv.statement)
+impl<'a> Side<'a> {
+    fn open(registry: &FormatRegistry, format: Format, path: &'a str) -> Result<Self> {
+        let file = BufReader::new(File::open(path)?);
+        let entries = registry.entries(format, Box::new(file)).map_err(|e| e.in_file(path))?;
+        Ok(Side { path, entries, count: 0, unmatched: HashMap::new() })
+    }
+
+    /// Read the next transaction and match it against the unmatched ones of
+    /// `other`, keeping it when there is none; false at the end of the file.
+    fn advance(&mut self, other: &mut Side) -> Result<bool> {
+        let transaction = match self.entries.next() {
+            Some(transaction) => transaction.map_err(|e| e.in_file(self.path))?,
+            None => return Ok(false),
+        };
+        let index = self.count;
+        self.count += 1;
+
+        let fingerprint = transaction.fingerprint();
+        match other.unmatched.get_mut(&fingerprint) {
+            Some(candidates) => {
+                // The earliest one, as when matching whole statements
+                candidates.remove(0);
+                if candidates.is_empty() {
+                    other.unmatched.remove(&fingerprint);
+                }
+            }
+            None => self.unmatched.entry(fingerprint).or_default().push((index, transaction)),
         }
+        Ok(true)
+    }
+
+    /// The unmatched transactions in canonical order, and the rest of the
+    /// statement.
+    fn finish(self) -> Result<(Vec<(usize, Transaction)>, Statement)> {
+        let statement = self.entries.collect_statement().map_err(|e| e.in_file(self.path))?;
+        let mut unmatched: Vec<(usize, Transaction)> = self.unmatched.into_values().flatten().collect();
+        unmatched.sort_by(|(i, a), (j, b)| a.canonical_cmp(b).then(i.cmp(j)));
+        Ok((unmatched, statement))
     }
 }
 
-fn compare_statements(stmt1: &Statement, stmt2: &Statement) -> String {
+/// Compare two statements read side by side, giving their differences.
+///
+/// Transactions present on both sides match by fingerprint whatever their
+/// order, and only the ones still waiting for a match are held in memory,
+/// so statements of any size compare in memory proportional to their
+/// differences. The rest are paired in canonical order and compared field
+/// by field.
+fn compare_statements(mut side1: Side, mut side2: Side) -> Result<Vec<String>> {
     let mut differences = Vec::new();
 
+    let (mut more1, mut more2) = (true, true);
+    while more1 || more2 {
+        if more1 {
+            more1 = side1.advance(&mut side2)?;
+        }
+        if more2 {
+            more2 = side2.advance(&mut side1)?;
+        }
+    }
+
+    debug!(transactions1 = side1.count, transactions2 = side2.count, "Transactions read");
+
     // Compare number of transactions
-    if stmt1.transactions.len() != stmt2.transactions.len() {
+    if side1.count != side2.count {
         differences.push(format!(
             "Number of transactions differs: {} vs {}",
-            stmt1.transactions.len(),
-            stmt2.transactions.len()
+            side1.count,
+            side2.count
         ));
     }
 
-    // Compare transactions
-    let min_len = std::cmp::min(stmt1.transactions.len(), stmt2.transactions.len());
-    for i in 0..min_len {
-        let tx1 = &stmt1.transactions[i];
-        let tx2 = &stmt2.transactions[i];
+    let (unmatched1, stmt1) = side1.finish()?;
+    let (unmatched2, stmt2) = side2.finish()?;
+    debug!(unmatched1 = unmatched1.len(), unmatched2 = unmatched2.len(), "Transactions matched");
 
+    for ((i, tx1), (_, tx2)) in unmatched1.iter().zip(&unmatched2) {
         // Compare key fields
         if tx1.date != tx2.date {
             differences.push(format!(
@@ -157,18 +245,7 @@ fn compare_statements(stmt1: &Statement, stmt2: &Statement) -> String {
         }
     }
 
-    if differences.is_empty() {
-        format!("The transaction records in '{}' and '{}' are identical.",
-                "file1", "file2")
-    } else {
-        let mut result = String::from("Differences found:\n");
-        for diff in differences {
-            result.push_str("  - ");
-            result.push_str(&diff);
-            result.push('\n');
-        }
-        result
-    }
+    Ok(differences)
 }
 
 fn normalize_string(s: &str) -> String {
@@ -1,108 +1,797 @@
 //! YP Bank Converter - CLI tool for converting between financial formats.
 
-use clap::Parser;
+use chrono::NaiveDate;
+use clap::{ArgGroup, Parser, Subcommand};
+use notify::Watcher;
+use regex::{Regex, RegexBuilder};
+use rust_decimal::Decimal;
+use std::collections::HashMap;
 use std::fs::File;
-use std::io::{self, Read, Write};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant};
+use tracing::{debug, error, info, info_span, warn};
 use ypbank_system::{
-    camt053_format::Camt053Statement,
-    csv_format::CsvStatement,
-    mt940_format::Mt940Statement,
-    Format, Result, Statement,
+    anonymize::Anonymizer,
+    validate::validate,
+    batch,
+    categorize::Categorizer,
+    camt053_format::{Camt053ReadOptions, Camt053WriteOptions},
+    conversion::{BatchPolicy, ChargesPolicy, ConversionOptions, TransactionHook, TruncationPolicy},
+    csv_format::{CsvField, CsvProfile, Encoding, StatementIdPolicy},
+    format::{Camt053Format, CsvFormat, FormatRegistry, JsonFormat, Mt940Format},
+    json_format::JsonWriteOptions,
+    logging::{self, LogFormat},
+    messages::{render, Language},
+    mt940_format::Mt940ReadOptions,
+    pipeline::{Pipeline, Step},
+    query::TransactionFilter,
+    types::MergeOptions,
+    error::FailureClass,
+    DebitCredit, Error, Format, PrecisionPolicy, Result, SortKey, SplitBy, Statement,
 };
 
+/// Number of leading bytes the input format is detected from.
+const DETECT_LENGTH: u64 = 1024;
+
+/// How long a file in the watched directory must go unchanged before it is
+/// converted, so that files still being uploaded are left alone.
+const SETTLE_TIME: Duration = Duration::from_secs(2);
+
 #[derive(Parser)]
 #[command(name = "ypbank_converter")]
 #[command(about = "Convert between bank statement formats (MT940, CAMT.053, CSV)", long_about = None)]
+#[command(group(ArgGroup::new("destination").args(["output", "output_dir"])))]
+#[command(subcommand_negates_reqs = true)]
 struct Cli {
-    /// Input file path (or stdin if not provided)
-    #[arg(short, long)]
-    input: Option<String>,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Input file path (or stdin if not provided); with --output-dir, any
+    /// number of files, directories or patterns such as 'statements/*.mt940'
+    #[arg(short, long, num_args = 1..)]
+    input: Vec<String>,
+
+    /// Keep converting the files dropped into this directory into
+    /// --output-dir, moving each one to its done/ or failed/ subdirectory
+    #[arg(long, requires = "output_dir", conflicts_with = "input")]
+    watch: Option<String>,
 
-    /// Input format (mt940, camt053, csv)
+    /// Merge the inputs, statements of one account, into a single statement:
+    /// repeated transactions are dropped and each closing balance must equal
+    /// the next opening balance
+    #[arg(long, requires = "input", conflicts_with = "output_dir")]
+    merge: bool,
+
+    /// Input format (auto, mt940, camt053, csv, json); auto, the default,
+    /// detects it from the first bytes of the input
     #[arg(long = "input-format")]
-    input_format: String,
+    input_format: Option<String>,
+
+    /// Output format (mt940, camt053, csv, json)
+    #[arg(long = "output-format", required_unless_present = "pipeline")]
+    output_format: Option<String>,
 
-    /// Output format (mt940, camt053, csv)
-    #[arg(long = "output-format")]
-    output_format: String,
+    /// TOML file describing the formats and processing steps; replaces the
+    /// format and processing options
+    #[arg(long)]
+    pipeline: Option<String>,
 
     /// Output file path (or stdout if not provided)
     #[arg(short, long)]
     output: Option<String>,
+
+    /// Convert every input into this directory, as <input name>.<ext>,
+    /// reporting the outcome of each file
+    #[arg(long = "output-dir")]
+    output_dir: Option<String>,
+
+    /// Indent CAMT.053 output by this many spaces (single line if not
+    /// provided), and JSON output (2 if not provided)
+    #[arg(long)]
+    indent: Option<usize>,
+
+    /// Write JSON on a single line, e.g. for jq or a message queue
+    #[arg(long, conflicts_with = "indent")]
+    compact: bool,
+
+    /// How MT940/CSV output represents entry charges (merge, separate, ignore)
+    #[arg(long)]
+    charges: Option<String>,
+
+    /// How MT940/CSV output represents batch bookings (expand, collapse)
+    #[arg(long)]
+    batches: Option<String>,
+
+    /// Fit amounts to their currency's decimal places (reject, round)
+    #[arg(long)]
+    precision: Option<String>,
+
+    /// How MT940 output handles fields over its length limits (truncate, reject);
+    /// truncated fields are listed as warnings
+    #[arg(long, default_value = "truncate")]
+    truncation: String,
+
+    /// Built-in CSV layout (sberbank, tinkoff, alfabank, raiffeisen)
+    #[arg(long = "csv-profile")]
+    csv_profile: Option<String>,
+
+    /// Character encoding of CSV input and output (e.g. windows-1251, utf-16le)
+    #[arg(long)]
+    encoding: Option<String>,
+
+    /// CSV output columns in order, as `field` or `Header=field`
+    /// (e.g. "Date=date,Amount=signed_amount,counterparty_name")
+    #[arg(long = "csv-columns")]
+    csv_columns: Option<String>,
+
+    /// Decimal separator of CSV amounts (e.g. "," for 1540,00)
+    #[arg(long = "decimal-separator")]
+    decimal_separator: Option<char>,
+
+    /// Digit group separator of CSV amounts: a character, "space" or "none"
+    #[arg(long = "thousands-separator")]
+    thousands_separator: Option<String>,
+
+    /// Write CSV for Russian-locale Excel (UTF-8 BOM, `;`, decimal comma, CRLF)
+    #[arg(long)]
+    excel: bool,
+
+    /// Write the servicing bank as Банк/БИК/BIC rows above the CSV table
+    #[arg(long = "csv-bank-rows")]
+    csv_bank_rows: bool,
+
+    /// Fail on unknown CSV columns and rows without an amount
+    #[arg(long)]
+    strict: bool,
+
+    /// Leave out transactions that fail to parse (MT940 :61: lines, CSV rows,
+    /// CAMT.053 entries), listing them in stderr, instead of stopping
+    #[arg(long = "skip-invalid")]
+    skip_invalid: bool,
+
+    /// Statement ID for CSV input (default: CSV-<timestamp>)
+    #[arg(long = "statement-id", conflicts_with = "derive_id")]
+    statement_id: Option<String>,
+
+    /// Derive the CSV statement ID from account, period and content
+    #[arg(long = "derive-id")]
+    derive_id: bool,
+
+    /// TOML file with transaction categorization rules
+    #[arg(long)]
+    categories: Option<String>,
+
+    /// Fail if the opening balance plus transactions doesn't give the closing balance
+    #[arg(long = "verify-balances")]
+    verify_balances: bool,
+
+    /// Fill in missing opening/closing balances, computing the closing balance
+    /// from the transactions (MT940 requires both)
+    #[arg(long = "synthesize-balances")]
+    synthesize_balances: bool,
+
+    /// Opening balance for --synthesize-balances when the input states none
+    /// (negative for a debit balance)
+    #[arg(long = "opening-balance", requires = "synthesize_balances", allow_hyphen_values = true)]
+    opening_balance: Option<Decimal>,
+
+    /// Fill in the balance after each transaction, counted from the opening balance
+    #[arg(long = "running-balances")]
+    running_balances: bool,
+
+    /// Currency of CSV rows without a currency column (default: RUB)
+    #[arg(long)]
+    currency: Option<String>,
+
+    /// Keep transactions booked on or after this date (YYYY-MM-DD)
+    #[arg(long = "from-date")]
+    from_date: Option<NaiveDate>,
+
+    /// Keep transactions booked on or before this date (YYYY-MM-DD)
+    #[arg(long = "to-date")]
+    to_date: Option<NaiveDate>,
+
+    /// Keep transactions of at least this amount
+    #[arg(long = "min-amount")]
+    min_amount: Option<Decimal>,
+
+    /// Keep transactions of at most this amount
+    #[arg(long = "max-amount")]
+    max_amount: Option<Decimal>,
+
+    /// Keep only debit or only credit transactions (debit, credit)
+    #[arg(long)]
+    direction: Option<String>,
+
+    /// Keep transactions whose counterparty name matches this regular
+    /// expression, ignoring case (e.g. "ромашка|acme")
+    #[arg(long)]
+    counterparty: Option<String>,
+
+    /// Fill in missing counterparty names from the description with this
+    /// regex, taking its `name` group (e.g. "от (?P<name>ООО [^,]+)")
+    #[arg(long = "counterparty-pattern")]
+    counterparty_pattern: Option<String>,
+
+    /// Rewrite references as PATTERN=>REPLACEMENT (e.g. "^0+(\d+)=>$1")
+    #[arg(long = "rewrite-reference")]
+    rewrite_reference: Option<String>,
+
+    /// Replace account numbers, IBANs, counterparty names and BICs with
+    /// pseudonyms before writing, the same value always getting the same one
+    #[arg(long)]
+    anonymize: bool,
+
+    /// Secret the pseudonyms of --anonymize depend on, to get the same ones
+    /// across runs; without it a random salt is used for this run only
+    #[arg(long = "anonymize-salt", requires = "anonymize", value_parser = clap::builder::NonEmptyStringValueParser::new())]
+    anonymize_salt: Option<String>,
+
+    /// Sort transactions by date, value-date, amount or reference
+    #[arg(long)]
+    sort: Option<String>,
+
+    /// Drop duplicate transactions (same dates, amount, reference and description)
+    #[arg(long)]
+    dedup: bool,
+
+    /// Write one file per account (<output>-<account>.<ext>), calendar month
+    /// (<output>-YYYY-MM.<ext>) or day (<output>-YYYY-MM-DD.<ext>)
+    #[arg(long = "split-by", requires = "destination")]
+    split_by: Option<String>,
+
+    /// Write a JSON document recording which input fields each output field
+    /// comes from, and how they are transformed
+    #[arg(long)]
+    mapping: Option<String>,
+
+    /// Write pages of at most this many transactions, one file per page named
+    /// <output>-pN.<ext> (MT940 :28C: sequence, CAMT.053 StmtPgntn)
+    #[arg(long = "page-size", requires = "destination")]
+    page_size: Option<usize>,
+
+    /// Language of errors and warnings (en, ru)
+    #[arg(long = "lang", default_value = "en")]
+    language: Language,
+
+    /// More diagnostics on stderr: -v adds phase timings, -vv details of
+    /// every phase
+    #[arg(short, long, action = clap::ArgAction::Count, global = true)]
+    verbose: u8,
+
+    /// Only errors on stderr, no warnings
+    #[arg(short, long, conflicts_with = "verbose", global = true)]
+    quiet: bool,
+
+    /// Format of diagnostics on stderr (text, json)
+    #[arg(long = "log-format", default_value = "text", global = true)]
+    log_format: LogFormat,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Check a statement file: parse it in full, then check balances, MT940
+    /// field lengths and mandatory fields and the CAMT.053 summary; exits
+    /// with 3 when the file breaks a rule, 2 when it can't be parsed
+    Validate {
+        /// Format of the file (auto, mt940, camt053, csv, json)
+        #[arg(long)]
+        format: Option<String>,
+
+        /// Statement file
+        file: String,
+    },
 }
 
 fn main() {
-    if let Err(e) = run() {
-        eprintln!("Error: {}", e);
-        std::process::exit(1);
+    let cli = Cli::try_parse().unwrap_or_else(|e| {
+        let _ = e.print();
+        std::process::exit(if e.use_stderr() { FailureClass::Usage.exit_code() } else { 0 })
+    });
+    logging::init(logging::max_level(cli.verbose, cli.quiet), cli.log_format);
+    if let Err(e) = run(&cli) {
+        // The output was piped into a reader that stopped early, such as `head`
+        if matches!(e, Error::Io(ref err, _) if err.kind() == io::ErrorKind::BrokenPipe) {
+            return;
+        }
+        report_error(cli.language, &e);
+        std::process::exit(e.failure_class().exit_code());
     }
 }
 
-fn run() -> Result<()> {
-    let cli = Cli::parse();
-
-    // Parse formats
-    let input_format = cli.input_format.parse::<Format>()?;
-    let output_format = cli.output_format.parse::<Format>()?;
+fn run(cli: &Cli) -> Result<()> {
+    if let Some(Command::Validate { ref format, ref file }) = cli.command {
+        if let Some(class) = validate_file(cli, format.as_deref(), Path::new(file))? {
+            std::process::exit(class.exit_code());
+        }
+        return Ok(());
+    }
 
-    // Process based on input file or stdin
-    let statement = if let Some(ref input_path) = cli.input {
-        let mut file = File::open(input_path)?;
-        parse_input(&mut file, input_format)?
-    } else {
-        let mut stdin = io::stdin();
-        parse_input(&mut stdin, input_format)?
+    let Some(ref output_dir) = cli.output_dir else {
+        let output = cli.output.as_deref().map(Path::new);
+        let result = if cli.merge {
+            batch::expand_inputs(&cli.input).and_then(|paths| merge(cli, &paths, output))
+        } else {
+            match cli.input.as_slice() {
+                [] => convert(cli, None, output),
+                [path] => convert(cli, Some(Path::new(path)), output),
+                _ => Err(Error::InvalidFormat("Several inputs need --output-dir or --merge".to_string(), None)),
+            }
+        };
+        return result.map(drop);
     };
 
-    // Output based on output file or stdout
-    if let Some(ref output_path) = cli.output {
-        let mut file = File::create(output_path)?;
-        write_output(&mut file, &statement, output_format)?;
-    } else {
-        let mut stdout = io::stdout();
-        write_output(&mut stdout, &statement, output_format)?;
+    if let Some(ref dir) = cli.watch {
+        return watch(cli, Path::new(dir), Path::new(output_dir));
+    }
+    if cli.input.is_empty() {
+        return Err(Error::InvalidFormat("--output-dir needs --input files".to_string(), None));
     }
+    let paths = batch::expand_inputs(&cli.input)?;
+    // Outputs are named after the inputs, which must not overwrite each other
+    let mut stems = HashMap::new();
+    for path in &paths {
+        if let Some(other) = stems.insert(path.file_stem(), path) {
+            return Err(Error::InvalidFormat(format!(
+                "{} and {} would be converted to the same file",
+                other.display(),
+                path.display()
+            ), None));
+        }
+    }
+    let output_dir = Path::new(output_dir);
+    std::fs::create_dir_all(output_dir)?;
 
-    Ok(())
+    let report = batch::for_each_file(&paths, |path| convert(cli, Some(path), Some(output_dir)));
+
+    for file in &report.files {
+        print_outcome(cli, &file.path, &file.result);
+    }
+    let total = report.files.len().to_string();
+    match report.failed().count() {
+        0 => {
+            println!("{}", render("Files converted: {}", &[&total], cli.language));
+            Ok(())
+        }
+        failed => Err(Error::ConversionError(format!("{} of {} files failed", failed, total), None)),
+    }
 }
 
-fn parse_input<R: Read>(reader: &mut R, format: Format) -> Result<Statement> {
-    match format {
-        Format::Mt940 => {
-            let mt940 = Mt940Statement::from_read(reader)?;
-            Ok(mt940.statement)
+/// Convert the files dropped into `dir` as they settle, including the ones
+/// already there, moving each one to `dir/done` or `dir/failed` afterwards.
+/// Runs until the directory can no longer be watched.
+fn watch(cli: &Cli, dir: &Path, output_dir: &Path) -> Result<()> {
+    let watch_error = |e: notify::Error| Error::from(io::Error::other(e)).in_file(dir.display().to_string());
+    let (sender, events) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender).map_err(watch_error)?;
+    watcher.watch(dir, notify::RecursiveMode::NonRecursive).map_err(watch_error)?;
+    let done = dir.join("done");
+    let failed = dir.join("failed");
+    for subdir in [output_dir, &done, &failed] {
+        std::fs::create_dir_all(subdir)?;
+    }
+    info!(dir = %dir.display(), "Watching for statements");
+
+    // Files dropped while the converter wasn't running, then every file
+    // changed since, by the time of its last change
+    let mut pending: HashMap<PathBuf, Instant> = HashMap::new();
+    for entry in std::fs::read_dir(dir)? {
+        pending.insert(entry?.path(), Instant::now());
+    }
+    loop {
+        match events.recv_timeout(SETTLE_TIME) {
+            Ok(event) => {
+                let event = event.map_err(watch_error)?;
+                if event.kind.is_create() || event.kind.is_modify() {
+                    for path in event.paths {
+                        pending.insert(path, Instant::now());
+                    }
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
         }
-        Format::Camt053 => {
-            let camt053 = Camt053Statement::from_read(reader)?;
-            Ok(camt053.statement)
+
+        let settled: Vec<PathBuf> = pending.iter()
+            .filter(|(_, changed)| changed.elapsed() >= SETTLE_TIME)
+            .map(|(path, _)| path.clone())
+            .collect();
+        for path in settled {
+            pending.remove(&path);
+            // Moved away or a directory; hidden files are uploads in progress
+            let hidden = path.file_name().and_then(|name| name.to_str()).is_none_or(|name| name.starts_with('.'));
+            if hidden || !path.is_file() {
+                continue;
+            }
+            let result = convert(cli, Some(&path), Some(output_dir)).map_err(|e| e.in_file(path.display().to_string()));
+            print_outcome(cli, &path, &result);
+            let target = if result.is_ok() { &done } else { &failed };
+            std::fs::rename(&path, target.join(path.file_name().unwrap_or_default()))?;
         }
-        Format::Csv => {
-            let csv = CsvStatement::from_read(reader)?;
-            Ok(csv.statement)
+    }
+}
+
+/// Print the findings of validating `path`; why the file is invalid, if it
+/// is.
+fn validate_file(cli: &Cli, format: Option<&str>, path: &Path) -> Result<Option<FailureClass>> {
+    let in_file = |e: Error| e.in_file(path.display().to_string());
+    let mut input: Box<dyn Read> = Box::new(File::open(path).map_err(|e| in_file(e.into()))?);
+    let format = match format {
+        None | Some("auto") => {
+            let (format, rewound) = detect_format(input).map_err(in_file)?;
+            input = rewound;
+            format
         }
+        Some(name) => name.parse()?,
+    };
+
+    let report = validate(format, &mut input);
+    print!("{}", report.localized(cli.language));
+    let path = path.display().to_string();
+    if report.is_valid() {
+        println!("{}", render("{}: valid {}", &[&path, format.name()], cli.language));
+    } else {
+        let errors = (report.failures.len() + usize::from(report.parse_error.is_some())).to_string();
+        println!("{}", render("{}: invalid {}, errors: {}", &[&path, format.name(), &errors], cli.language));
+    }
+    Ok(report.failure_class())
+}
+
+/// Print how converting `path` went: where it was written, or the error.
+fn print_outcome(cli: &Cli, path: &Path, result: &Result<PathBuf>) {
+    match result {
+        Ok(output) => println!("{} -> {}", path.display(), output.display()),
+        Err(e) => report_error(cli.language, e),
     }
 }
 
-fn write_output<W: Write>(writer: &mut W, statement: &Statement, format: Format) -> Result<()> {
-    match format {
-        Format::Mt940 => {
-            let mt940 = Mt940Statement {
-                statement: statement.clone(),
+/// Log `e` in `language` with its code.
+fn report_error(language: Language, e: &Error) {
+    error!(code = e.code().as_str(), "{}", e.localized(language));
+}
+
+/// Run `phase` of a conversion, logging how long it took.
+fn timed<T>(phase: &str, run: impl FnOnce() -> Result<T>) -> Result<T> {
+    let started = Instant::now();
+    let result = run();
+    info!(phase, elapsed_ms = started.elapsed().as_secs_f64() * 1000.0, "Phase finished");
+    result
+}
+
+/// Convert `input_path` (stdin if `None`), writing to `output` (stdout if
+/// `None`), or with --output-dir into that directory under the input's
+/// name. Warnings are logged as they come. Gives the path written to.
+fn convert(cli: &Cli, input_path: Option<&Path>, output: Option<&Path>) -> Result<PathBuf> {
+    let input_name = input_path.map_or_else(|| "-".to_string(), |path| path.display().to_string());
+    let _span = info_span!("convert", input = input_name).entered();
+    let (pipeline, statement) = read_input(cli, input_path)?;
+    write_output(cli, &pipeline, statement, input_path, output)
+}
+
+/// Merge the statements of `paths` into one, written to `output` as by
+/// [`convert`].
+fn merge(cli: &Cli, paths: &[PathBuf], output: Option<&Path>) -> Result<PathBuf> {
+    let mut pipeline = None;
+    let mut statements = Vec::new();
+    for path in paths {
+        let _span = info_span!("read", input = %path.display()).entered();
+        let (input_pipeline, statement) = read_input(cli, Some(path))?;
+        // The inputs may differ in format, but not in processing and output
+        pipeline = Some(input_pipeline);
+        statements.push(statement);
+    }
+    let pipeline = pipeline.ok_or_else(|| Error::InvalidFormat("--merge needs --input files".to_string(), None))?;
+
+    let options = MergeOptions { require_balance_chain: true, ..Default::default() };
+    let statement = timed("merge", || Statement::merge_all(statements, &options))?;
+    write_output(cli, &pipeline, statement, None, output)
+}
+
+/// The pipeline for `input_path` (stdin if `None`) and the statement read
+/// from it, as [`convert`] does.
+fn read_input(cli: &Cli, input_path: Option<&Path>) -> Result<(Pipeline, Statement)> {
+    // Input file or stdin, opened first to detect its format
+    let mut input: Box<dyn Read> = match input_path {
+        Some(path) => Box::new(File::open(path).map_err(|e| Error::from(e).in_file(path.display().to_string()))?),
+        None => Box::new(io::stdin()),
+    };
+    let in_input = |e: Error| match input_path {
+        Some(path) => e.in_file(path.display().to_string()),
+        None => e,
+    };
+
+    let pipeline = match cli.pipeline {
+        Some(ref path) => Pipeline::from_toml(&std::fs::read_to_string(path)?)?,
+        None => {
+            let input_format = match cli.input_format.as_deref() {
+                None | Some("auto") => {
+                    let (format, rewound) = detect_format(input).map_err(in_input)?;
+                    input = rewound;
+                    format
+                }
+                Some(name) => name.parse()?,
             };
-            mt940.write_to(writer)?;
+            cli_pipeline(cli, input_format)?
         }
-        Format::Camt053 => {
-            let camt053 = Camt053Statement {
-                statement: statement.clone(),
-            };
-            camt053.write_to(writer)?;
+    };
+    if let Some(ref path) = cli.mapping {
+        pipeline.field_mapping()?.write_to(&mut File::create(path)?)?;
+    }
+
+    debug!(input_format = pipeline.input().name(), output_format = pipeline.output().name(), "Pipeline ready");
+    let parsed = timed("read", || pipeline.read_with_diagnostics(&mut input).map_err(in_input))?;
+    debug!(transactions = parsed.statement.transactions.len(), "Statement read");
+    for warning in &parsed.warnings {
+        warn!(line = warning.line, "{}", warning.localized(cli.language));
+    }
+    for record in &parsed.rejected {
+        let skipped = render("Skipped: {}", &[&record.localized(cli.language)], cli.language);
+        warn!(line = record.line, record = record.text.trim(), "{}", skipped);
+    }
+    Ok((pipeline, parsed.statement))
+}
+
+/// Process `statement` and write it, as [`convert`] does.
+fn write_output(
+    cli: &Cli,
+    pipeline: &Pipeline,
+    statement: Statement,
+    input_path: Option<&Path>,
+    output: Option<&Path>,
+) -> Result<PathBuf> {
+    let split = cli.split_by.as_deref()
+        .map(|by| by.parse::<SplitBy>().map_err(|e| Error::InvalidFormat(e, None)))
+        .transpose()?;
+
+    // Each account is processed as a statement of its own, periods are
+    // cut from what processing leaves
+    let statements = match split {
+        Some(SplitBy::Account) => statement.split_by_account(),
+        _ => vec![statement],
+    };
+    let mut parts = Vec::new();
+    for statement in statements {
+        let processed = timed("process", || pipeline.process(statement))?;
+        for loss in &processed.report.losses {
+            warn!("{}", loss);
         }
-        Format::Csv => {
-            let csv = CsvStatement {
-                statement: statement.clone(),
-            };
-            csv.write_to(writer)?;
+        let statement = processed.output;
+        debug!(account = statement.account.id(), transactions = statement.transactions.len(), "Statement processed");
+        match split {
+            Some(SplitBy::Account) => parts.push((Some(file_name_part(statement.account.id())), statement)),
+            Some(by) => {
+                let date_format = if by == SplitBy::Day { "%Y-%m-%d" } else { "%Y-%m" };
+                parts.extend(statement.split(by).into_iter()
+                    .filter_map(|part| Some((Some(part.from_date?.date().format(date_format).to_string()), part))));
+            }
+            None => parts.push((None, statement)),
+        }
+    }
+
+    // Into the output directory under the input's name
+    let output = match (output, input_path) {
+        (Some(dir), Some(input_path)) if cli.output_dir.is_some() => {
+            let stem = input_path.file_stem().unwrap_or_default().to_string_lossy();
+            Some(dir.join(format!("{}.{}", stem, pipeline.output().extension())))
+        }
+        (output, _) => output.map(Path::to_path_buf),
+    };
+
+    // Output based on output file or stdout
+    timed("write", || {
+        if let (true, Some(path)) = (split.is_some() || cli.page_size.is_some(), &output) {
+            let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("statement");
+            for (suffix, part) in parts {
+                let name = match suffix {
+                    Some(suffix) => format!("{}-{}", stem, suffix),
+                    None => stem.to_string(),
+                };
+                let pages = match cli.page_size {
+                    Some(size) => part.paginate(size),
+                    None => vec![part],
+                };
+                for page in pages {
+                    let mut name = match page.pagination {
+                        Some(pagination) => format!("{}-p{}", name, pagination.page_number),
+                        None => name.clone(),
+                    };
+                    if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+                        name = format!("{}.{}", name, ext);
+                    }
+                    let page_path = path.with_file_name(name);
+                    pipeline.write(&page, &mut File::create(&page_path)?)?;
+                    debug!(output = %page_path.display(), transactions = page.transactions.len(), "Statement written");
+                }
+            }
+        } else {
+            // Without a split there is just the one statement
+            for (_, statement) in &parts {
+                match output {
+                    Some(ref output_path) => pipeline.write(statement, &mut File::create(output_path)?)?,
+                    None => pipeline.write(statement, &mut io::stdout())?,
+                }
+            }
         }
+        Ok(())
+    })?;
+
+    Ok(output.unwrap_or_else(|| PathBuf::from("-")))
+}
+
+/// `text` with the characters file names can't hold, such as the `/` of
+/// some account identifiers, replaced by `_`.
+fn file_name_part(text: &str) -> String {
+    text.chars()
+        .map(|c| if c.is_alphanumeric() || matches!(c, '-' | '_' | '.') { c } else { '_' })
+        .collect()
+}
+
+/// Format of `input` told by its first bytes, and the input to read again
+/// from the start.
+fn detect_format(mut input: Box<dyn Read>) -> Result<(Format, Box<dyn Read>)> {
+    let mut head = Vec::new();
+    input.by_ref().take(DETECT_LENGTH).read_to_end(&mut head)?;
+    let format = Format::detect(&head).ok_or_else(|| {
+        Error::InvalidFormat("Cannot detect the input format, use --input-format".to_string(), None)
+    })?;
+    Ok((format, Box::new(io::Cursor::new(head).chain(input))))
+}
+
+/// Pipeline of the format and processing options given on the command line.
+fn cli_pipeline(cli: &Cli, input_format: Format) -> Result<Pipeline> {
+    // clap requires the output format without --pipeline
+    let output_format = cli.output_format.as_deref().unwrap_or_default().parse::<Format>()?;
+
+    let mut csv_profile = match cli.csv_profile {
+        Some(ref name) => CsvProfile::named(name).ok_or_else(|| {
+            Error::InvalidFormat(format!(
+                "Unknown CSV profile: {} (expected one of: {})",
+                name,
+                CsvProfile::NAMES.join(", ")
+            ), None)
+        })?,
+        None => CsvProfile::default(),
+    };
+    if let Some(ref label) = cli.encoding {
+        let encoding = Encoding::for_label(label.as_bytes())
+            .ok_or_else(|| Error::InvalidFormat(format!("Unknown encoding: {}", label), None))?;
+        csv_profile.encoding = Some(encoding);
+    }
+    csv_profile.strict = cli.strict;
+    csv_profile.skip_invalid = cli.skip_invalid;
+    csv_profile.bank_rows = cli.csv_bank_rows;
+    if let Some(ref id) = cli.statement_id {
+        csv_profile.statement_id = StatementIdPolicy::Explicit(id.clone());
+    } else if cli.derive_id {
+        csv_profile.statement_id = StatementIdPolicy::Derived;
+    }
+    if cli.excel {
+        csv_profile = csv_profile.excel();
+    }
+    if let Some(separator) = cli.decimal_separator {
+        csv_profile.amount_format.decimal_separator = Some(separator);
+    }
+    if let Some(ref separator) = cli.thousands_separator {
+        csv_profile.amount_format.thousands_separator = match separator.as_str() {
+            "none" => None,
+            "space" => Some(' '),
+            "nbsp" => Some('\u{a0}'),
+            s if s.chars().count() == 1 => s.chars().next(),
+            s => return Err(Error::InvalidFormat(format!("Invalid thousands separator: {}", s), None)),
+        };
+    }
+    if let Some(ref spec) = cli.csv_columns {
+        csv_profile.output_columns = Some(parse_csv_columns(spec)?);
+    }
+    if let Some(ref currency) = cli.currency {
+        csv_profile.default_currency = currency.parse()
+            .map_err(|_| Error::InvalidCurrency(currency.clone(), None))?;
+    }
+
+    let mut registry = FormatRegistry::default();
+    registry.register(Format::Csv, CsvFormat { profile: csv_profile });
+    registry.register(Format::Camt053, Camt053Format {
+        read_options: Camt053ReadOptions {
+            skip_invalid: cli.skip_invalid,
+            ..Default::default()
+        },
+        write_options: Camt053WriteOptions {
+            indent: cli.indent,
+            ..Default::default()
+        },
+    });
+    registry.register(Format::Json, JsonFormat {
+        write_options: JsonWriteOptions {
+            indent: if cli.compact { None } else { Some(cli.indent.unwrap_or(2)) },
+        },
+    });
+    registry.register(Format::Mt940, Mt940Format {
+        read_options: Mt940ReadOptions { skip_invalid: cli.skip_invalid },
+    });
+
+    let mut pipeline = Pipeline::new(input_format, output_format).registry(registry);
+
+    if cli.dedup {
+        pipeline = pipeline.step(Step::Dedup);
     }
-    Ok(())
+    if let Some(ref key) = cli.sort {
+        pipeline = pipeline.step(Step::Sort(key.parse::<SortKey>().map_err(|e| Error::InvalidFormat(e, None))?));
+    }
+    if let Some(ref path) = cli.categories {
+        pipeline = pipeline.step(Step::Categorize(Categorizer::from_read(&mut File::open(path)?)?));
+    }
+    if cli.synthesize_balances {
+        pipeline = pipeline.step(Step::SynthesizeBalances(cli.opening_balance));
+    }
+    if cli.verify_balances {
+        pipeline = pipeline.step(Step::VerifyBalances);
+    }
+    if cli.running_balances {
+        pipeline = pipeline.step(Step::RunningBalances);
+    }
+
+    // Filters apply after the balance check, which needs every transaction
+    pipeline = pipeline.step(Step::Filter(TransactionFilter {
+        from_date: cli.from_date,
+        to_date: cli.to_date,
+        min_amount: cli.min_amount,
+        max_amount: cli.max_amount,
+        direction: cli.direction.as_deref()
+            .map(|direction| direction.parse::<DebitCredit>().map_err(|e| Error::InvalidFormat(e, None)))
+            .transpose()?,
+        counterparty_pattern: cli.counterparty.as_deref()
+            .map(|pattern| RegexBuilder::new(pattern).case_insensitive(true).build())
+            .transpose()
+            .map_err(|e| Error::InvalidFormat(format!("Invalid pattern: {}", e), None))?,
+        ..Default::default()
+    }));
+
+    let mut options = ConversionOptions::default();
+    // CAMT.053 and JSON carry charges natively, other formats need a policy
+    if !matches!(output_format, Format::Camt053 | Format::Json) {
+        options.charges = cli.charges.as_deref().map(str::parse::<ChargesPolicy>).transpose()?;
+        options.batches = cli.batches.as_deref().map(str::parse::<BatchPolicy>).transpose()?;
+    }
+    if let Some(ref pattern) = cli.counterparty_pattern {
+        options.hooks.push(TransactionHook::counterparty_from_description(parse_regex(pattern)?));
+    }
+    if let Some(ref spec) = cli.rewrite_reference {
+        let (pattern, replacement) = spec.split_once("=>")
+            .ok_or_else(|| Error::InvalidFormat(format!("Expected PATTERN=>REPLACEMENT: {}", spec), None))?;
+        options.hooks.push(TransactionHook::rewrite_reference(parse_regex(pattern)?, replacement));
+    }
+    pipeline = pipeline.step(Step::Convert(options));
+
+    // Last, as earlier steps may match on names or fill them in
+    if cli.anonymize {
+        let anonymizer = match cli.anonymize_salt {
+            Some(ref salt) => Anonymizer::new(salt),
+            None => {
+                info!("No --anonymize-salt, pseudonyms are random for this run");
+                Anonymizer::random()?
+            }
+        };
+        pipeline = pipeline.step(Step::Anonymize(anonymizer));
+    }
+
+    if let Some(ref policy) = cli.precision {
+        pipeline = pipeline.step(Step::Precision(policy.parse::<PrecisionPolicy>().map_err(|e| Error::InvalidFormat(e, None))?));
+    }
+    // SWIFT validators bounce MT940 files with over-length fields
+    Ok(pipeline.step(Step::FitMt940(cli.truncation.parse::<TruncationPolicy>()?)))
+}
+
+fn parse_regex(pattern: &str) -> Result<Regex> {
+    Regex::new(pattern).map_err(|e| Error::InvalidFormat(format!("Invalid pattern: {}", e), None))
+}
+
+fn parse_csv_columns(spec: &str) -> Result<Vec<(String, CsvField)>> {
+    spec.split(',')
+        .map(|column| {
+            let (header, field) = column.split_once('=').unwrap_or((column, column));
+            Ok((header.trim().to_string(), field.trim().parse()?))
+        })
+        .collect()
 }
@@ -1,14 +1,22 @@
 //! CAMT.053 (ISO 20022) format parser and serializer.
 //!
 //! CAMT.053 is an XML-based bank-to-customer account statement format
-//! defined by the ISO 20022 standard.
-
-use crate::error::{Error, Result};
-use crate::types::{Balance, BalanceType, DebitCredit, Statement, Transaction};
-use chrono::NaiveDate;
+//! defined by the ISO 20022 standard. Intraday account reports (CAMT.052)
+//! share the same layout and are accepted by the parser as well.
+
+use crate::error::{Error, Location, ParseOutcome, RejectedRecord, Result, RuleId, ValidationError, Warning, WarningKind};
+use crate::format::{EntryReader, StatementFormat};
+use crate::types::{
+    AccountIdentifier, Balance, BalanceType, BankDate, BankIdentifier, BankInfo, Bic, Charge, Currency, DebitCredit,
+    EntryStatus, FundsAvailability, Iban, Pagination, Party, PostalAddress, PrecisionPolicy, ReferredDocument,
+    ReturnReason, SourceFormat, Statement, StructuredRemittance, TransactionsSummary, Transaction, TransactionDetail, TransactionRefs,
+};
+use chrono::{NaiveDate, NaiveDateTime};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
-use std::io::{Read, Write};
+use quick_xml::events::{BytesEnd, BytesStart, Event};
+use std::collections::BTreeSet;
+use std::io::{BufRead, BufReader, Read, Write};
 use std::str::FromStr;
 
 /// Represents a CAMT.053 statement.
@@ -18,6 +26,92 @@ pub struct Camt053Statement {
     pub statement: Statement,
 }
 
+/// Options for writing CAMT.053 statements.
+#[derive(Debug, Clone, Default)]
+pub struct Camt053WriteOptions {
+    /// Indent nested elements by this many spaces; `None` writes a single line.
+    pub indent: Option<usize>,
+
+    /// Message recipient written to `GrpHdr/MsgRcpt`.
+    pub message_recipient: Option<Party>,
+
+    /// Message pagination written to `GrpHdr/MsgPgntn`.
+    pub message_pagination: Option<Pagination>,
+
+    /// Fit amounts to their currency's minor unit before writing; banks
+    /// reject e.g. EUR amounts with three decimals. `None` writes amounts as they are.
+    pub precision: Option<PrecisionPolicy>,
+}
+
+/// Path of the statement element, the start of element paths in errors.
+const STATEMENT_PATH: &str = "BkToCstmrStmt/Stmt[0]";
+
+/// Options for reading CAMT.053 statements.
+#[derive(Debug, Clone, Default)]
+pub struct Camt053ReadOptions {
+    /// Fail when the bank-reported `TxsSummry` does not match the entries;
+    /// not checked when entries were rejected.
+    pub verify_summary: bool,
+
+    /// Leave out `Ntry` entries that fail to parse, listing them in
+    /// [`ParseOutcome::rejected`], instead of failing on the first one.
+    /// The rest of the document must still be well-formed.
+    pub skip_invalid: bool,
+}
+
+/// CAMT.053 as a [`StatementFormat`].
+#[derive(Debug, Clone, Default)]
+pub struct Camt053Format {
+    /// Options used when reading.
+    pub read_options: Camt053ReadOptions,
+
+    /// Options used when writing.
+    pub write_options: Camt053WriteOptions,
+}
+
+impl StatementFormat for Camt053Format {
+    fn read(&self, mut reader: &mut dyn Read) -> Result<Statement> {
+        Ok(Camt053Statement::from_read_with(&mut reader, &self.read_options)?.statement)
+    }
+
+    fn read_with_diagnostics(&self, mut reader: &mut dyn Read) -> Result<ParseOutcome<Statement>> {
+        Ok(Camt053Statement::from_read_with_diagnostics(&mut reader, &self.read_options)?.map(|parsed| parsed.statement))
+    }
+
+    fn write(&self, statement: &Statement, mut writer: &mut dyn Write) -> Result<()> {
+        Camt053Statement { statement: statement.clone() }.write_to_with(&mut writer, &self.write_options)
+    }
+
+    fn entries<'a>(&self, reader: Box<dyn Read + 'a>) -> Result<Box<dyn EntryReader + 'a>> {
+        Ok(Box::new(Camt053EntryReader::with_options(BufReader::new(reader), self.read_options.clone())))
+    }
+}
+
+#[cfg(feature = "async")]
+impl Camt053Statement {
+    /// Parse a CAMT.053 statement from an async source, such as a socket.
+    ///
+    /// [`Camt053Statement::from_read`] runs on tokio's blocking pool and reads the
+    /// input as it arrives, without holding all of it or blocking the
+    /// executor.
+    pub async fn from_async_read<R>(reader: R) -> Result<Self>
+    where
+        R: tokio::io::AsyncRead + Unpin + Send + 'static,
+    {
+        crate::format::read_async(reader, |mut input| Self::from_read(&mut input)).await
+    }
+
+    /// Write the statement to an async destination, like
+    /// [`Camt053Statement::write_to`] run on tokio's blocking pool; returns the
+    /// destination.
+    pub async fn write_to_async<W>(self, writer: W) -> Result<W>
+    where
+        W: tokio::io::AsyncWrite + Unpin + Send + 'static,
+    {
+        crate::format::write_async(writer, move |mut output| self.write_to(&mut output)).await
+    }
+}
+
 impl Camt053Statement {
     /// Parse a CAMT.053 statement from any source implementing `Read`.
     ///
@@ -36,12 +130,34 @@ impl Camt053Statement {
     /// # Ok::<(), Box<dyn std::error::Error>>(())
     /// ```
     pub fn from_read<R: Read>(reader: &mut R) -> Result<Self> {
-        let mut xml_content = String::new();
-        reader.read_to_string(&mut xml_content)?;
+        Self::from_read_with(reader, &Camt053ReadOptions::default())
+    }
 
-        let document: Document = serde_xml_rs::from_str(&xml_content)?;
+    /// Parse a CAMT.053 statement with explicit read options.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::fs::File;
+    /// use ypbank_system::camt053_format::{Camt053ReadOptions, Camt053Statement};
+    ///
+    /// let options = Camt053ReadOptions { verify_summary: true, ..Default::default() };
+    /// let mut file = File::open("statement.xml")?;
+    /// let statement = Camt053Statement::from_read_with(&mut file, &options)?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn from_read_with<R: Read>(reader: &mut R, options: &Camt053ReadOptions) -> Result<Self> {
+        Ok(Self::from_read_with_diagnostics(reader, options)?.statement)
+    }
 
-        Self::from_document(document)
+    /// Parse like [`Camt053Statement::from_read_with`], also returning
+    /// warnings for the values that had to be assumed, such as a missing
+    /// entry reference or booking date, or were left out as unreadable.
+    pub fn from_read_with_diagnostics<R: Read>(
+        reader: &mut R,
+        options: &Camt053ReadOptions,
+    ) -> Result<ParseOutcome<Self>> {
+        Camt053EntryReader::with_options(BufReader::new(reader), options.clone()).collect_outcome()
     }
 
     /// Write a CAMT.053 statement to any destination implementing `Write`.
@@ -55,59 +171,202 @@ impl Camt053Statement {
     /// ```no_run
     /// use std::fs::File;
     /// use ypbank_system::camt053_format::Camt053Statement;
-    /// use ypbank_system::types::Statement;
+    /// use ypbank_system::types::{Currency, Statement};
     ///
-    /// let statement = Statement::new("123".into(), "ACC001".into(), "USD".into());
+    /// let statement = Statement::new("123".into(), "ACC001", Currency::USD);
     /// let camt053 = Camt053Statement { statement };
     /// let mut file = File::create("output.xml")?;
     /// camt053.write_to(&mut file)?;
     /// # Ok::<(), Box<dyn std::error::Error>>(())
     /// ```
     pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<()> {
-        let document = self.to_document();
-        let xml = serde_xml_rs::to_string(&document)
-            .map_err(|e| Error::XmlError(e.to_string()))?;
+        self.write_to_with(writer, &Camt053WriteOptions::default())
+    }
+
+    /// Write a CAMT.053 statement with explicit write options.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::fs::File;
+    /// use ypbank_system::camt053_format::{Camt053Statement, Camt053WriteOptions};
+    /// use ypbank_system::types::{Currency, Statement};
+    ///
+    /// let statement = Statement::new("123".into(), "ACC001", Currency::USD);
+    /// let camt053 = Camt053Statement { statement };
+    /// let options = Camt053WriteOptions { indent: Some(2), ..Default::default() };
+    /// let mut file = File::create("output.xml")?;
+    /// camt053.write_to_with(&mut file, &options)?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn write_to_with<W: Write>(&self, writer: &mut W, options: &Camt053WriteOptions) -> Result<()> {
+        let document = match options.precision {
+            Some(policy) => {
+                let mut statement = self.statement.clone();
+                statement.enforce_precision(policy)?;
+                Camt053Statement { statement }.to_document(options)
+            }
+            None => self.to_document(options),
+        };
+
+        let mut xml = String::new();
+        let mut serializer = quick_xml::se::Serializer::new(&mut xml);
+        if let Some(indent) = options.indent {
+            serializer.indent(' ', indent);
+        }
+        document.serialize(serializer)
+            .map_err(Error::xml)?;
 
         // Write XML declaration and formatted output
         writeln!(writer, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
         write!(writer, "{}", xml)?;
+        if options.indent.is_some() {
+            writeln!(writer)?;
+        }
 
         Ok(())
     }
 
-    fn from_document(document: Document) -> Result<Self> {
+    /// Assemble the pages of a paginated statement into one logical statement.
+    ///
+    /// Pages may be given in any order. They must belong to the same statement
+    /// and account, be numbered contiguously from 1, and end with a page carrying
+    /// the last page indicator. Transactions are concatenated in page order, the
+    /// opening balance is taken from the first page and the closing balance from
+    /// the last one.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::fs::File;
+    /// use ypbank_system::camt053_format::Camt053Statement;
+    ///
+    /// let page1 = Camt053Statement::from_read(&mut File::open("page1.xml")?)?;
+    /// let page2 = Camt053Statement::from_read(&mut File::open("page2.xml")?)?;
+    /// let statement = Camt053Statement::assemble_pages(vec![page2, page1])?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn assemble_pages(mut pages: Vec<Camt053Statement>) -> Result<Self> {
+        pages.sort_by_key(|p| p.statement.pagination.map(|pg| pg.page_number).unwrap_or(1));
+
+        let mut pages = pages.into_iter();
+        let Some(first) = pages.next() else {
+            return Err(Error::MissingField("statement pages".to_string(), None));
+        };
+        let mut assembled = first.statement;
+        let mut expected_page = 1;
+        let mut last_page = assembled.pagination.map(|p| p.last_page).unwrap_or(true);
+
+        if assembled.pagination.map(|p| p.page_number).unwrap_or(1) != expected_page {
+            return Err(Error::ParseError("Statement page 1 is missing".to_string(), None));
+        }
+
+        for page in pages {
+            let page = page.statement;
+            expected_page += 1;
+
+            if last_page {
+                return Err(Error::ParseError(format!(
+                    "Statement page {} follows the last page",
+                    expected_page
+                ), None));
+            }
+            if page.statement_id != assembled.statement_id || page.account != assembled.account {
+                return Err(Error::ParseError(format!(
+                    "Page {} belongs to statement {} ({}), expected {} ({})",
+                    expected_page, page.statement_id, page.account,
+                    assembled.statement_id, assembled.account
+                ), None));
+            }
+
+            let pagination = page.pagination.ok_or_else(|| {
+                Error::MissingField(format!("pagination of statement page {}", expected_page), None)
+            })?;
+            if pagination.page_number != expected_page {
+                return Err(Error::ParseError(format!(
+                    "Statement page {} is missing",
+                    expected_page
+                ), None));
+            }
+            last_page = pagination.last_page;
+
+            assembled.transactions.extend(page.transactions);
+            if page.closing_balance.is_some() {
+                assembled.closing_balance = page.closing_balance;
+            }
+            assembled.from_date = match (assembled.from_date, page.from_date) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            };
+            assembled.to_date = match (assembled.to_date, page.to_date) {
+                (Some(a), Some(b)) => Some(a.max(b)),
+                (a, b) => a.or(b),
+            };
+        }
+
+        if !last_page {
+            return Err(Error::ParseError(format!(
+                "Statement page {} is missing",
+                expected_page + 1
+            ), None));
+        }
+
+        assembled.pagination = None;
+        Ok(Camt053Statement { statement: assembled })
+    }
+
+    fn from_document(document: &Document) -> Result<Self> {
         let stmt_data = &document.bk_to_cstmr_stmt.stmt;
 
         let statement_id = stmt_data.id.clone();
-        let account_id = stmt_data.acct.id.iban.clone()
-            .or_else(|| stmt_data.acct.id.othr.as_ref().map(|o| o.id.clone()))
-            .unwrap_or_else(|| "UNKNOWN".to_string());
+        let account_id = parse_account_id(&stmt_data.acct.id).map_err(|e| e.within("Acct/Id"))?
+            .unwrap_or_else(|| AccountIdentifier::from("UNKNOWN"));
 
-        let currency = stmt_data.acct.ccy.clone();
+        let currency = parse_currency(&stmt_data.acct.ccy).map_err(|e| e.within("Acct/Ccy"))?;
         let sequence_number = stmt_data.elctrnic_seq_nb.as_ref().map(|n| n.to_string());
 
         let mut statement = Statement::new(statement_id, account_id, currency);
         statement.sequence_number = sequence_number;
         statement.account_holder = stmt_data.acct.nm.clone();
+        statement.account_owner = stmt_data.acct.ownr.as_ref().map(|ownr| Party {
+            name: ownr.nm.clone(),
+            postal_address: ownr.pstl_adr.as_ref().map(parse_postal_address),
+        });
+        statement.servicing_bank = stmt_data.acct.svcr.as_ref()
+            .map(|svcr| -> Result<BankInfo> {
+                let id = &svcr.fin_instn_id;
+                let member = id.clr_sys_mmb_id.as_ref();
+                Ok(BankInfo {
+                    bic: id.bic.as_deref().map(parse_bic).transpose()?,
+                    name: id.nm.clone(),
+                    address: id.pstl_adr.as_ref().map(parse_postal_address),
+                    clearing_code: member.map(|m| m.mmb_id.clone()),
+                    clearing_system: member
+                        .and_then(|m| m.clr_sys_id.as_ref())
+                        .and_then(|s| s.cd.clone().or_else(|| s.prtry.clone())),
+                })
+            })
+            .transpose()
+            .map_err(|e| e.within("Acct/Svcr"))?;
+        statement.pagination = stmt_data.stmt_pgntn.as_ref().map(|p| Pagination {
+            page_number: p.pg_nb,
+            last_page: p.last_pg_ind,
+        });
 
         // Parse creation date
         if let Some(ref cre_dt_tm) = stmt_data.cre_dt_tm {
-            statement.creation_date = parse_camt_date(cre_dt_tm).ok();
+            statement.creation_date = parse_camt_datetime(cre_dt_tm).ok();
         }
 
         // Parse date range
         if let Some(ref fr_to_dt) = stmt_data.fr_to_dt {
-            if let Some(ref from) = fr_to_dt.fr_dt_tm {
-                statement.from_date = parse_camt_date(from).ok();
-            }
-            if let Some(ref to) = fr_to_dt.to_dt_tm {
-                statement.to_date = parse_camt_date(to).ok();
-            }
+            statement.from_date = fr_to_dt.fr_dt_tm.as_deref().and_then(|date| parse_camt_datetime(date).ok());
+            statement.to_date = fr_to_dt.to_dt_tm.as_deref().and_then(|date| parse_camt_datetime(date).ok());
         }
 
         // Parse balances
-        for bal in &stmt_data.bal {
-            let balance = Self::parse_balance(bal)?;
+        for (i, bal) in stmt_data.bal.iter().enumerate() {
+            let balance = Self::parse_balance(bal, statement.currency).map_err(|e| e.within(&format!("Bal[{}]", i)))?;
             match balance.balance_type {
                 BalanceType::Opening => statement.opening_balance = Some(balance),
                 BalanceType::Closing => statement.closing_balance = Some(balance),
@@ -116,15 +375,16 @@ impl Camt053Statement {
         }
 
         // Parse transactions
-        for entry in &stmt_data.ntry {
-            let transaction = Self::parse_entry(entry, &statement.currency)?;
+        for (i, entry) in stmt_data.ntry.iter().enumerate() {
+            let transaction = Self::parse_entry(entry, Some(statement.currency))
+                .map_err(|e| e.within(&format!("Ntry[{}]", i)))?;
             statement.add_transaction(transaction);
         }
 
         Ok(Camt053Statement { statement })
     }
 
-    fn parse_balance(bal: &BalanceXml) -> Result<Balance> {
+    fn parse_balance(bal: &BalanceXml, default_currency: Currency) -> Result<Balance> {
         let balance_type = match bal.tp.cd_or_prtry.cd.as_str() {
             "OPBD" | "OPAV" => BalanceType::Opening,
             "CLBD" | "CLAV" => BalanceType::Closing,
@@ -133,21 +393,20 @@ impl Camt053Statement {
         };
 
         let amount = Decimal::from_str(&bal.amt.value)
-            .map_err(|_| Error::InvalidAmount(bal.amt.value.clone()))?;
+            .map_err(|_| Error::InvalidAmount(bal.amt.value.clone(), None))?;
 
         let debit_credit = bal.cdt_dbt_ind.parse::<DebitCredit>()
-            .map_err(|_| Error::ParseError(format!("Invalid D/C indicator: {}", bal.cdt_dbt_ind)))?;
+            .map_err(|_| Error::ParseError(format!("Invalid D/C indicator: {}", bal.cdt_dbt_ind), None))?;
 
         let date = if let Some(ref dt) = bal.dt.dt {
             parse_date_only(dt)?
         } else if let Some(ref dt_tm) = bal.dt.dt_tm {
             parse_camt_date(dt_tm)?
         } else {
-            return Err(Error::MissingField("balance date".to_string()));
+            return Err(Error::MissingField("balance date".to_string(), None));
         };
 
-        let currency = bal.amt.ccy()
-            .unwrap_or_else(|| "XXX".to_string());
+        let currency = bal.amt.currency()?.unwrap_or(default_currency);
 
         Ok(Balance {
             balance_type,
@@ -158,91 +417,114 @@ impl Camt053Statement {
         })
     }
 
-    fn parse_entry(entry: &EntryXml, default_currency: &str) -> Result<Transaction> {
+    fn parse_entry(entry: &EntryXml, default_currency: Option<Currency>) -> Result<Transaction> {
         let reference = entry.ntry_ref.clone().unwrap_or_else(|| "UNKNOWN".to_string());
 
         let amount = Decimal::from_str(&entry.amt.value)
-            .map_err(|_| Error::InvalidAmount(entry.amt.value.clone()))?;
+            .map_err(|_| Error::InvalidAmount(entry.amt.value.clone(), None).within("Amt"))?;
+        let currency = entry.amt.currency().map_err(|e| e.within("Amt"))?
+            .or(default_currency)
+            .ok_or_else(|| Error::MissingField("entry currency".to_string(), None).within("Amt"))?;
 
         let debit_credit = entry.cdt_dbt_ind.parse::<DebitCredit>()
-            .map_err(|_| Error::ParseError(format!("Invalid D/C indicator: {}", entry.cdt_dbt_ind)))?;
+            .map_err(|_| Error::ParseError(format!("Invalid D/C indicator: {}", entry.cdt_dbt_ind), None).within("CdtDbtInd"))?;
 
-        let date = if let Some(ref dt) = entry.bookg_dt {
-            if let Some(ref d) = dt.dt {
-                parse_date_only(d)?
-            } else if let Some(ref dt_tm) = dt.dt_tm {
-                parse_camt_date(dt_tm)?
-            } else {
-                chrono::Utc::now().date_naive()
-            }
-        } else {
-            chrono::Utc::now().date_naive()
+        // Unknown codes are reported by entry_warnings
+        let status = match entry.sts.status().unwrap_or(EntryStatus::Information) {
+            EntryStatus::Booked if entry.rvsl_ind == Some(true) => EntryStatus::Reversed,
+            status => status,
         };
 
-        let value_date = if let Some(ref dt) = entry.val_dt {
-            if let Some(ref d) = dt.dt {
-                Some(parse_date_only(d)?)
-            } else if let Some(ref dt_tm) = dt.dt_tm {
-                Some(parse_camt_date(dt_tm)?)
-            } else {
-                None
-            }
-        } else {
-            None
-        };
+        let date = entry.bookg_dt.as_ref().map(parse_date_xml).transpose().map_err(|e| e.within("BookgDt"))?.flatten()
+            .unwrap_or_else(|| chrono::Utc::now().date_naive().into());
+        let value_date = entry.val_dt.as_ref().map(parse_date_xml).transpose().map_err(|e| e.within("ValDt"))?.flatten();
 
         let mut description = String::new();
         let mut counterparty_name = None;
+        let mut counterparty_address = None;
+        let mut ultimate_counterparty_name = None;
         let mut counterparty_account = None;
+        let mut account = None;
         let mut bank_identifier = None;
         let mut additional_info = None;
+        let mut details = Vec::new();
+        let mut structured_remittance = None;
+        let mut return_reason = None;
+        let mut purpose_code = None;
+        let mut refs = TransactionRefs::default();
+        let mut charges = Self::parse_charges(&entry.chrgs, currency).map_err(|e| e.within("Chrgs"))?;
+        let tx_path = |i: usize| format!("NtryDtls/TxDtls[{}]", i);
 
         // Extract details from transaction details
         if let Some(ref ntry_dtls) = entry.ntry_dtls {
-            if let Some(ref tx_dtls) = ntry_dtls.tx_dtls {
-                // Remittance information
-                if let Some(ref rmt_inf) = tx_dtls.rmt_inf {
-                    if let Some(ref ustrd) = rmt_inf.ustrd {
-                        description = ustrd.clone();
-                    }
-                }
-
-                // Related parties
-                if let Some(ref rltd_pties) = tx_dtls.rltd_pties {
-                    if let Some(ref dbtr) = rltd_pties.dbtr {
-                        counterparty_name = dbtr.nm.clone();
-                    }
-                    if let Some(ref cdtr) = rltd_pties.cdtr {
-                        counterparty_name = cdtr.nm.clone();
-                    }
-
-                    if let Some(ref dbtr_acct) = rltd_pties.dbtr_acct {
-                        counterparty_account = dbtr_acct.id.iban.clone()
-                            .or_else(|| dbtr_acct.id.othr.as_ref().map(|o| o.id.clone()));
-                    }
-                    if let Some(ref cdtr_acct) = rltd_pties.cdtr_acct {
-                        counterparty_account = cdtr_acct.id.iban.clone()
-                            .or_else(|| cdtr_acct.id.othr.as_ref().map(|o| o.id.clone()));
-                    }
-                }
+            for (i, tx_dtls) in ntry_dtls.tx_dtls.iter().enumerate() {
+                charges.extend(Self::parse_charges(&tx_dtls.chrgs, currency)
+                    .map_err(|e| e.within("Chrgs").within(&tx_path(i)))?);
+            }
+            // The account holder's own side of the first payment; every
+            // payment of a batch books to the same account
+            if let Some(rltd_pties) = ntry_dtls.tx_dtls.first().and_then(|t| t.rltd_pties.as_ref()) {
+                let (_, own) = rltd_pties.sides(debit_credit);
+                account = own.account.map(|acct| parse_account_id(&acct.id)).transpose()
+                    .map_err(|e| e.within("RltdPties").within(&tx_path(0)))?
+                    .flatten()
+                    .map(|acct| acct.id().to_string());
+            }
 
-                // Related agents (banks)
-                if let Some(ref rltd_agts) = tx_dtls.rltd_agts {
-                    if let Some(ref dbtr_agt) = rltd_agts.dbtr_agt {
-                        bank_identifier = dbtr_agt.fin_instn_id.bic.clone();
-                    }
-                    if let Some(ref cdtr_agt) = rltd_agts.cdtr_agt {
-                        bank_identifier = cdtr_agt.fin_instn_id.bic.clone();
+            match ntry_dtls.tx_dtls.as_slice() {
+                [] => {}
+                [tx_dtls] => {
+                    if let Some(ref tx_refs) = tx_dtls.refs {
+                        refs = TransactionRefs {
+                            account_servicer_reference: tx_refs.acct_svcr_ref.clone(),
+                            instruction_id: tx_refs.instr_id.clone(),
+                            end_to_end_id: tx_refs.end_to_end_id.clone(),
+                            mandate_id: tx_refs.mndt_id.clone(),
+                        };
                     }
+                    let detail = Self::parse_tx_details(tx_dtls, debit_credit).map_err(|e| e.within(&tx_path(0)))?;
+                    description = detail.description;
+                    counterparty_name = detail.counterparty_name;
+                    counterparty_address = detail.counterparty_address;
+                    ultimate_counterparty_name = detail.ultimate_counterparty_name;
+                    counterparty_account = detail.counterparty_account;
+                    bank_identifier = detail.bank_identifier;
+                    additional_info = detail.additional_info;
+                    structured_remittance = tx_dtls.rmt_inf.as_ref()
+                        .and_then(|r| r.strd.as_ref())
+                        .map(Self::parse_structured_remittance);
+                    purpose_code = tx_dtls.purp.as_ref().and_then(|p| p.cd.clone().or_else(|| p.prtry.clone()));
+                    return_reason = tx_dtls.rtr_inf.as_ref().map(|rtr| ReturnReason {
+                        code: rtr.rsn.as_ref().and_then(|r| r.cd.clone().or_else(|| r.prtry.clone())),
+                        additional_info: rtr.addtl_inf.clone(),
+                    });
                 }
-
-                // Additional transaction info
-                if let Some(ref addtl) = tx_dtls.addtl_tx_inf {
-                    additional_info = Some(addtl.clone());
+                batch => {
+                    // Batch booking: keep every underlying transaction
+                    details = batch.iter()
+                        .enumerate()
+                        .map(|(i, tx_dtls)| Self::parse_tx_details(tx_dtls, debit_credit).map_err(|e| e.within(&tx_path(i))))
+                        .collect::<Result<_>>()?;
                 }
             }
         }
 
+        if entry.acct_svcr_ref.is_some() {
+            refs.account_servicer_reference = entry.acct_svcr_ref.clone();
+        }
+
+        // Instructed amount and exchange rate, preferring entry-level details
+        let amt_dtls = entry.amt_dtls.as_ref().or_else(|| {
+            entry.ntry_dtls.as_ref()
+                .and_then(|d| d.tx_dtls.first())
+                .and_then(|t| t.amt_dtls.as_ref())
+        });
+        let amt_dtls_path = if entry.amt_dtls.is_some() { "AmtDtls".to_string() } else { format!("{}/AmtDtls", tx_path(0)) };
+        let amounts = match amt_dtls {
+            Some(amt_dtls) => Self::parse_amount_details(amt_dtls).map_err(|e| e.within(&amt_dtls_path))?,
+            None => AmountDetails::default(),
+        };
+
         // Fallback to bank transaction code for description
         if description.is_empty() {
             if let Some(ref bk_tx_cd) = entry.bk_tx_cd {
@@ -257,20 +539,451 @@ impl Camt053Statement {
             date,
             value_date,
             amount,
-            currency: entry.amt.ccy.clone().unwrap_or_else(|| default_currency.to_string()),
+            currency,
             debit_credit,
-            account: None,
+            account,
             counterparty_account,
             counterparty_name,
+            counterparty_address,
+            ultimate_counterparty_name,
             bank_identifier,
             description,
             additional_info,
+            details,
+            charges,
+            original_amount: amounts.original_amount,
+            original_currency: amounts.original_currency,
+            exchange_rate: amounts.exchange_rate,
+            counter_value_amount: amounts.counter_value_amount,
+            counter_value_currency: amounts.counter_value_currency,
+            counter_value_rate: amounts.counter_value_rate,
+            structured_remittance,
+            status,
+            return_reason,
+            purpose_code,
+            transaction_type: None,
+            funds_availability: Self::parse_availability(&entry.avlbty).map_err(|e| e.within("Avlbty"))?,
+            running_balance: None,
+            category: None,
+            tags: BTreeSet::new(),
+            refs,
+            source_raw: None,
         })
     }
 
-    fn to_document(&self) -> Document {
+    fn parse_structured_remittance(strd: &StructuredRemittanceXml) -> StructuredRemittance {
+        let cdtr_ref_inf = strd.cdtr_ref_inf.as_ref();
+
+        StructuredRemittance {
+            reference_type: cdtr_ref_inf
+                .and_then(|c| c.tp.as_ref())
+                .and_then(|tp| tp.cd_or_prtry.code()),
+            reference: cdtr_ref_inf.and_then(|c| c.ref_val.clone()),
+            referred_documents: strd.rfrd_doc_inf.iter().map(|doc| ReferredDocument {
+                document_type: doc.tp.as_ref().and_then(|tp| tp.cd_or_prtry.code()),
+                number: doc.nb.clone(),
+                date: doc.rltd_dt.as_deref().and_then(|d| parse_date_only(d).ok()),
+            }).collect(),
+        }
+    }
+
+    /// Instructed amount and counter value; the counter value is the
+    /// booked amount converted for information, not the original one.
+    fn parse_amount_details(amt_dtls: &AmountDetailsXml) -> Result<AmountDetails> {
+        let amount = |amt: &AmountAndCurrencyXml| {
+            let value = Decimal::from_str(&amt.amt.value).map_err(|_| Error::InvalidAmount(amt.amt.value.clone(), None))?;
+            Ok::<_, Error>((value, amt.amt.currency()?))
+        };
+        let rate = |amt: &Option<AmountAndCurrencyXml>| amt.as_ref()
+            .and_then(|amt| amt.ccy_xchg.as_ref())
+            .map(|xchg| Decimal::from_str(&xchg.xchg_rate).map_err(|_| Error::InvalidAmount(xchg.xchg_rate.clone(), None)))
+            .transpose();
+
+        let mut details = AmountDetails::default();
+        if let Some(ref instd_amt) = amt_dtls.instd_amt {
+            (details.original_amount, details.original_currency) =
+                amount(instd_amt).map(|(value, currency)| (Some(value), currency)).map_err(|e| e.within("InstdAmt"))?;
+        }
+        // A rate given with the booked amount converts from the instructed one too
+        details.exchange_rate = match rate(&amt_dtls.instd_amt).map_err(|e| e.within("InstdAmt"))? {
+            Some(rate) => Some(rate),
+            None => rate(&amt_dtls.tx_amt).map_err(|e| e.within("TxAmt"))?,
+        };
+        if let Some(ref cntr_val_amt) = amt_dtls.cntr_val_amt {
+            (details.counter_value_amount, details.counter_value_currency) =
+                amount(cntr_val_amt).map(|(value, currency)| (Some(value), currency)).map_err(|e| e.within("CntrValAmt"))?;
+            details.counter_value_rate = rate(&amt_dtls.cntr_val_amt).map_err(|e| e.within("CntrValAmt"))?;
+        }
+        Ok(details)
+    }
+
+    fn parse_charges(chrgs: &[ChargesXml], default_currency: Currency) -> Result<Vec<Charge>> {
+        let mut charges = Vec::new();
+
+        for chrg in chrgs {
+            // Version 02 lists charges directly, later versions wrap them in Rcrd
+            for record in std::iter::once(chrg).chain(&chrg.rcrd) {
+                let Some(ref amt) = record.amt else { continue };
+
+                let amount = Decimal::from_str(&amt.value)
+                    .map_err(|_| Error::InvalidAmount(amt.value.clone(), None))?;
+                let debit_credit = match record.cdt_dbt_ind {
+                    Some(ref ind) => ind.parse::<DebitCredit>()
+                        .map_err(|_| Error::ParseError(format!("Invalid D/C indicator: {}", ind), None))?,
+                    None => DebitCredit::Debit,
+                };
+
+                charges.push(Charge {
+                    amount,
+                    currency: amt.currency()?.unwrap_or(default_currency),
+                    debit_credit,
+                    charge_type: record.tp.as_ref()
+                        .and_then(|tp| tp.cd.clone().or_else(|| tp.prtry.as_ref().map(|p| p.id.clone()))),
+                    bearer: record.br.clone(),
+                });
+            }
+        }
+
+        Ok(charges)
+    }
+
+    fn parse_availability(avlbty: &[AvailabilityXml]) -> Result<Vec<FundsAvailability>> {
+        avlbty.iter().map(|avl| {
+            let days = match avl.dt.nb_of_days {
+                Some(ref nb) => Some(nb.trim_start_matches('+').parse::<i32>()
+                    .map_err(|_| Error::ParseError(format!("Invalid availability days: {}", nb), None))?),
+                None => None,
+            };
+            Ok(FundsAvailability {
+                days,
+                date: avl.dt.actl_dt.as_deref().map(parse_date_only).transpose()?,
+                amount: Decimal::from_str(&avl.amt.value)
+                    .map_err(|_| Error::InvalidAmount(avl.amt.value.clone(), None))?,
+                debit_credit: avl.cdt_dbt_ind.parse::<DebitCredit>()
+                    .map_err(|_| Error::ParseError(format!("Invalid D/C indicator: {}", avl.cdt_dbt_ind), None))?,
+            })
+        }).collect()
+    }
+
+    fn parse_tx_details(tx_dtls: &TransactionDetailsXml, debit_credit: DebitCredit) -> Result<TransactionDetail> {
+        let mut detail = TransactionDetail {
+            reference: tx_dtls.refs.as_ref().and_then(|r| r.end_to_end_id.clone()),
+            amount: None,
+            currency: None,
+            counterparty_account: None,
+            counterparty_name: None,
+            counterparty_address: None,
+            ultimate_counterparty_name: None,
+            bank_identifier: None,
+            description: String::new(),
+            additional_info: tx_dtls.addtl_tx_inf.clone(),
+        };
+
+        // Transaction amount
+        if let Some(tx_amt) = tx_dtls.amt_dtls.as_ref().and_then(|a| a.tx_amt.as_ref()) {
+            detail.amount = Some(Decimal::from_str(&tx_amt.amt.value)
+                .map_err(|_| Error::InvalidAmount(tx_amt.amt.value.clone(), None).within("AmtDtls/TxAmt"))?);
+            detail.currency = tx_amt.amt.currency().map_err(|e| e.within("AmtDtls/TxAmt"))?;
+        }
+
+        // Remittance information
+        if let Some(ref rmt_inf) = tx_dtls.rmt_inf {
+            if let Some(ref ustrd) = rmt_inf.ustrd {
+                detail.description = ustrd.clone();
+            }
+        }
+
+        // Related parties, of which only the counterparty's side is kept here
+        if let Some(ref rltd_pties) = tx_dtls.rltd_pties {
+            let (counterparty, _) = rltd_pties.sides(debit_credit);
+            if let Some(party) = counterparty.party {
+                detail.counterparty_name = party.nm.clone();
+                detail.counterparty_address = party.pstl_adr.as_ref().map(parse_postal_address);
+            }
+            detail.ultimate_counterparty_name = counterparty.ultimate_party.and_then(|party| party.nm.clone());
+            if let Some(acct) = counterparty.account {
+                detail.counterparty_account = parse_account_id(&acct.id)?;
+            }
+        }
+
+        // Related agents (banks): the counterparty's bank is the debtor
+        // agent of a credit, unless only the other one is given
+        if let Some(ref rltd_agts) = tx_dtls.rltd_agts {
+            let (counterparty, other) = match debit_credit {
+                DebitCredit::Credit => (&rltd_agts.dbtr_agt, &rltd_agts.cdtr_agt),
+                DebitCredit::Debit => (&rltd_agts.cdtr_agt, &rltd_agts.dbtr_agt),
+            };
+            if let Some(agent) = counterparty.as_ref().or(other.as_ref()) {
+                detail.bank_identifier = parse_bank_identifier(&agent.fin_instn_id)?;
+            }
+        }
+
+        Ok(detail)
+    }
+
+    fn structured_remittance_to_xml(strd: &StructuredRemittance) -> StructuredRemittanceXml {
+        StructuredRemittanceXml {
+            rfrd_doc_inf: strd.referred_documents.iter().map(|doc| ReferredDocumentXml {
+                tp: doc.document_type.as_ref().map(|cd| DocumentTypeXml {
+                    cd_or_prtry: DocumentCodeXml {
+                        cd: Some(cd.clone()),
+                        prtry: None,
+                    },
+                    issr: None,
+                }),
+                nb: doc.number.clone(),
+                rltd_dt: doc.date.as_ref().map(format_date_only),
+            }).collect(),
+            cdtr_ref_inf: if strd.reference.is_some() || strd.reference_type.is_some() {
+                Some(CreditorReferenceXml {
+                    tp: strd.reference_type.as_ref().map(|cd| DocumentTypeXml {
+                        cd_or_prtry: DocumentCodeXml {
+                            cd: Some(cd.clone()),
+                            prtry: None,
+                        },
+                        issr: None,
+                    }),
+                    ref_val: strd.reference.clone(),
+                })
+            } else {
+                None
+            },
+        }
+    }
+
+    fn tx_details_to_xml(
+        detail: &TransactionDetail,
+        debit_credit: DebitCredit,
+        own_account: Option<&str>,
+    ) -> TransactionDetailsXml {
+        // The counterparty is the debtor of a credit and the creditor of a debit
+        let party = if detail.counterparty_name.is_some() || detail.counterparty_address.is_some() {
+            Some(PartyXml {
+                nm: detail.counterparty_name.clone(),
+                pstl_adr: detail.counterparty_address.as_ref().map(postal_address_to_xml),
+            })
+        } else {
+            None
+        };
+        let party_account = detail.counterparty_account.as_ref().map(|acc| AccountXml {
+            id: account_id_to_xml(acc),
+        });
+        // The account holder's side, when the transaction names its account
+        let own_party_account = own_account.map(|acc| AccountXml {
+            id: account_id_to_xml(&AccountIdentifier::from(acc)),
+        });
+        let ultimate_party = detail.ultimate_counterparty_name.as_ref().map(|name| PartyXml {
+            nm: Some(name.clone()),
+            pstl_adr: None,
+        });
+        let agent = detail.bank_identifier.as_ref().map(|bank| AgentXml {
+            fin_instn_id: bank_identifier_to_xml(bank),
+        });
+
+        TransactionDetailsXml {
+            refs: detail.reference.as_ref().map(|r| ReferencesXml {
+                acct_svcr_ref: None,
+                instr_id: None,
+                end_to_end_id: Some(r.clone()),
+                mndt_id: None,
+            }),
+            amt_dtls: detail.amount.map(|amount| AmountDetailsXml {
+                instd_amt: None,
+                tx_amt: Some(AmountAndCurrencyXml {
+                    amt: AmountXml {
+                        value: amount.to_string(),
+                        ccy: detail.currency.map(String::from),
+                    },
+                    ccy_xchg: None,
+                }),
+                cntr_val_amt: None,
+            }),
+            chrgs: Vec::new(),
+            rltd_pties: if party.is_some() || party_account.is_some() || ultimate_party.is_some()
+                || own_party_account.is_some()
+            {
+                let is_credit = debit_credit == DebitCredit::Credit;
+                let ((dbtr, dbtr_acct, ultmt_dbtr), (cdtr, cdtr_acct, ultmt_cdtr)) = if is_credit {
+                    ((party, party_account, ultimate_party), (None, own_party_account, None))
+                } else {
+                    ((None, own_party_account, None), (party, party_account, ultimate_party))
+                };
+                Some(RelatedPartiesXml {
+                    dbtr,
+                    dbtr_acct,
+                    ultmt_dbtr,
+                    cdtr,
+                    cdtr_acct,
+                    ultmt_cdtr,
+                })
+            } else {
+                None
+            },
+            rltd_agts: agent.map(|agent| {
+                // Likewise the counterparty's bank is the debtor agent of a credit
+                if debit_credit == DebitCredit::Credit {
+                    RelatedAgentsXml { dbtr_agt: Some(agent), cdtr_agt: None }
+                } else {
+                    RelatedAgentsXml { dbtr_agt: None, cdtr_agt: Some(agent) }
+                }
+            }),
+            purp: None,
+            rmt_inf: if !detail.description.is_empty() {
+                Some(RemittanceInformationXml {
+                    ustrd: Some(detail.description.clone()),
+                    strd: None,
+                })
+            } else {
+                None
+            },
+            rltd_dts: None,
+            rtr_inf: None,
+            addtl_tx_inf: detail.additional_info.clone(),
+        }
+    }
+
+    fn to_document(&self, options: &Camt053WriteOptions) -> Document {
         let stmt = &self.statement;
 
+        let summary = if stmt.transactions.is_empty() {
+            None
+        } else {
+            Some(summary_to_xml(&stmt.transactions_summary()))
+        };
+        let entries = stmt.transactions.iter().map(Self::entry_to_xml).collect();
+        Self::document(stmt, summary, entries, options)
+    }
+
+    /// `Ntry` element of a transaction.
+    fn entry_to_xml(tx: &Transaction) -> EntryXml {
+        let tx_dtls = if tx.details.is_empty() {
+            let mut tx_dtls = Self::tx_details_to_xml(&TransactionDetail {
+                reference: tx.refs.end_to_end_id.clone(),
+                amount: None,
+                currency: None,
+                counterparty_account: tx.counterparty_account.clone(),
+                counterparty_name: tx.counterparty_name.clone(),
+                counterparty_address: tx.counterparty_address.clone(),
+                ultimate_counterparty_name: tx.ultimate_counterparty_name.clone(),
+                bank_identifier: tx.bank_identifier.clone(),
+                description: tx.description.clone(),
+                additional_info: tx.additional_info.clone(),
+            }, tx.debit_credit, tx.account.as_deref());
+            if tx.refs.instruction_id.is_some() || tx.refs.mandate_id.is_some() {
+                let refs = tx_dtls.refs.get_or_insert(ReferencesXml {
+                    acct_svcr_ref: None,
+                    instr_id: None,
+                    end_to_end_id: None,
+                    mndt_id: None,
+                });
+                refs.instr_id = tx.refs.instruction_id.clone();
+                refs.mndt_id = tx.refs.mandate_id.clone();
+            }
+            tx_dtls.purp = tx.purpose_code.as_ref().map(|cd| PurposeXml {
+                cd: Some(cd.clone()),
+                prtry: None,
+            });
+            tx_dtls.rtr_inf = tx.return_reason.as_ref().map(|rtr| ReturnInformationXml {
+                rsn: rtr.code.as_ref().map(|cd| ReturnReasonXml {
+                    cd: Some(cd.clone()),
+                    prtry: None,
+                }),
+                addtl_inf: rtr.additional_info.clone(),
+            });
+            if let Some(ref strd) = tx.structured_remittance {
+                tx_dtls.rmt_inf
+                    .get_or_insert(RemittanceInformationXml { ustrd: None, strd: None })
+                    .strd = Some(Self::structured_remittance_to_xml(strd));
+            }
+            vec![tx_dtls]
+        } else {
+            tx.details.iter()
+                .map(|detail| Self::tx_details_to_xml(detail, tx.debit_credit, tx.account.as_deref()))
+                .collect()
+        };
+
+        EntryXml {
+            ntry_ref: Some(tx.reference.clone()),
+            amt: AmountXml {
+                value: tx.amount.to_string(),
+                ccy: Some(tx.currency.to_string()),
+            },
+            cdt_dbt_ind: tx.debit_credit.to_iso_format().to_string(),
+            rvsl_ind: (tx.status == EntryStatus::Reversed).then_some(true),
+            sts: StatusXml { code: Some(tx.status.to_iso_format().to_string()), ..StatusXml::default() },
+            bookg_dt: Some(date_to_xml(&tx.date)),
+            val_dt: tx.value_date.as_ref().map(date_to_xml),
+            acct_svcr_ref: tx.refs.account_servicer_reference.clone(),
+            avlbty: tx.funds_availability.iter().map(|avl| AvailabilityXml {
+                dt: AvailabilityDateXml {
+                    nb_of_days: avl.days.map(|d| d.to_string()),
+                    actl_dt: avl.date.as_ref().map(format_date_only),
+                },
+                amt: AmountXml {
+                    value: avl.amount.to_string(),
+                    ccy: Some(tx.currency.to_string()),
+                },
+                cdt_dbt_ind: avl.debit_credit.to_iso_format().to_string(),
+            }).collect(),
+            bk_tx_cd: Some(BankTransactionCodeXml {
+                domn: None,
+                prtry: Some(ProprietaryCodeXml {
+                    cd: tx.description.clone(),
+                }),
+            }),
+            amt_dtls: (tx.original_amount.is_some() || tx.counter_value_amount.is_some()).then(|| AmountDetailsXml {
+                instd_amt: tx.original_amount.map(|original| AmountAndCurrencyXml {
+                    amt: AmountXml {
+                        value: original.to_string(),
+                        ccy: tx.original_currency.map(String::from),
+                    },
+                    ccy_xchg: tx.exchange_rate.map(|rate| CurrencyExchangeXml {
+                        src_ccy: tx.original_currency.map(String::from),
+                        trgt_ccy: Some(tx.currency.to_string()),
+                        xchg_rate: rate.to_string(),
+                    }),
+                }),
+                tx_amt: None,
+                cntr_val_amt: tx.counter_value_amount.map(|value| AmountAndCurrencyXml {
+                    amt: AmountXml {
+                        value: value.to_string(),
+                        ccy: tx.counter_value_currency.map(String::from),
+                    },
+                    ccy_xchg: tx.counter_value_rate.map(|rate| CurrencyExchangeXml {
+                        src_ccy: Some(tx.currency.to_string()),
+                        trgt_ccy: tx.counter_value_currency.map(String::from),
+                        xchg_rate: rate.to_string(),
+                    }),
+                }),
+            }),
+            chrgs: tx.charges.iter().map(|charge| ChargesXml {
+                amt: Some(AmountXml {
+                    value: charge.amount.to_string(),
+                    ccy: Some(charge.currency.to_string()),
+                }),
+                cdt_dbt_ind: Some(charge.debit_credit.to_iso_format().to_string()),
+                tp: charge.charge_type.as_ref().map(|cd| ChargeTypeXml {
+                    cd: Some(cd.clone()),
+                    prtry: None,
+                }),
+                br: charge.bearer.clone(),
+                rcrd: Vec::new(),
+            }).collect(),
+            ntry_dtls: Some(EntryDetailsXml {
+                btch: if tx.details.is_empty() {
+                    None
+                } else {
+                    Some(BatchXml {
+                        nb_of_txs: tx.details.len().to_string(),
+                    })
+                },
+                tx_dtls,
+            }),
+        }
+    }
+
+    /// `Bal` elements of the opening and closing balances.
+    fn balances_to_xml(stmt: &Statement) -> Vec<BalanceXml> {
         let mut balances = Vec::new();
 
         if let Some(ref opening) = stmt.opening_balance {
@@ -282,8 +995,7 @@ impl Camt053Statement {
                 },
                 amt: AmountXml {
                     value: opening.amount.to_string(),
-                    ccy: Some(opening.currency.clone()),
-                    ccy_alt: None,
+                    ccy: Some(opening.currency.to_string()),
                 },
                 cdt_dbt_ind: opening.debit_credit.to_iso_format().to_string(),
                 dt: DateXml {
@@ -302,8 +1014,7 @@ impl Camt053Statement {
                 },
                 amt: AmountXml {
                     value: closing.amount.to_string(),
-                    ccy: Some(closing.currency.clone()),
-                    ccy_alt: None,
+                    ccy: Some(closing.currency.to_string()),
                 },
                 cdt_dbt_ind: closing.debit_credit.to_iso_format().to_string(),
                 dt: DateXml {
@@ -312,109 +1023,45 @@ impl Camt053Statement {
                 },
             });
         }
+        balances
+    }
 
-        let entries: Vec<EntryXml> = stmt.transactions.iter().map(|tx| {
-            EntryXml {
-                ntry_ref: Some(tx.reference.clone()),
-                amt: AmountXml {
-                    value: tx.amount.to_string(),
-                    ccy: Some(tx.currency.clone()),
-                    ccy_alt: None,
-                },
-                cdt_dbt_ind: tx.debit_credit.to_iso_format().to_string(),
-                sts: "BOOK".to_string(),
-                bookg_dt: Some(DateXml {
-                    dt: Some(format_date_only(&tx.date)),
-                    dt_tm: None,
-                }),
-                val_dt: tx.value_date.as_ref().map(|vd| DateXml {
-                    dt: Some(format_date_only(vd)),
-                    dt_tm: None,
-                }),
-                acct_svcr_ref: None,
-                bk_tx_cd: Some(BankTransactionCodeXml {
-                    domn: None,
-                    prtry: Some(ProprietaryCodeXml {
-                        cd: tx.description.clone(),
-                    }),
-                }),
-                ntry_dtls: Some(EntryDetailsXml {
-                    tx_dtls: Some(TransactionDetailsXml {
-                        refs: None,
-                        amt_dtls: None,
-                        rltd_pties: if tx.counterparty_name.is_some() || tx.counterparty_account.is_some() {
-                            Some(RelatedPartiesXml {
-                                dbtr: if tx.debit_credit == DebitCredit::Credit {
-                                    tx.counterparty_name.as_ref().map(|name| PartyXml {
-                                        nm: Some(name.clone()),
-                                        pstl_adr: None,
-                                    })
-                                } else {
-                                    None
-                                },
-                                dbtr_acct: if tx.debit_credit == DebitCredit::Credit {
-                                    tx.counterparty_account.as_ref().map(|acc| AccountXml {
-                                        id: AccountIdXml {
-                                            iban: Some(acc.clone()),
-                                            othr: None,
-                                        },
-                                    })
-                                } else {
-                                    None
-                                },
-                                cdtr: if tx.debit_credit == DebitCredit::Debit {
-                                    tx.counterparty_name.as_ref().map(|name| PartyXml {
-                                        nm: Some(name.clone()),
-                                        pstl_adr: None,
-                                    })
-                                } else {
-                                    None
-                                },
-                                cdtr_acct: if tx.debit_credit == DebitCredit::Debit {
-                                    tx.counterparty_account.as_ref().map(|acc| AccountXml {
-                                        id: AccountIdXml {
-                                            iban: Some(acc.clone()),
-                                            othr: None,
-                                        },
-                                    })
-                                } else {
-                                    None
-                                },
-                            })
-                        } else {
-                            None
-                        },
-                        rltd_agts: None,
-                        rmt_inf: if !tx.description.is_empty() {
-                            Some(RemittanceInformationXml {
-                                ustrd: Some(tx.description.clone()),
-                                strd: None,
-                            })
-                        } else {
-                            None
-                        },
-                        rltd_dts: None,
-                        addtl_tx_inf: tx.additional_info.clone(),
-                    }),
-                    btch: None,
-                }),
-            }
-        }).collect();
+    /// Document of the statement `stmt`, with `summary` and `entries` for
+    /// its transactions.
+    fn document(
+        stmt: &Statement,
+        summary: Option<TransactionsSummaryXml>,
+        entries: Vec<EntryXml>,
+        options: &Camt053WriteOptions,
+    ) -> Document {
+        let balances = Self::balances_to_xml(stmt);
+        let creation_date_time = stmt.creation_date.as_ref().map(format_date_time);
 
         Document {
             bk_to_cstmr_stmt: BankToCustomerStatementXml {
                 grp_hdr: GroupHeaderXml {
                     msg_id: stmt.statement_id.clone(),
-                    cre_dt_tm: stmt.creation_date
-                        .as_ref()
-                        .map(format_date_time)
-                        .unwrap_or_else(|| format_date_time(&chrono::Utc::now().date_naive())),
+                    cre_dt_tm: creation_date_time
+                        .clone()
+                        .unwrap_or_else(|| format_date_time(&chrono::Utc::now().date_naive().into())),
+                    msg_rcpt: options.message_recipient.as_ref().map(|rcpt| PartyXml {
+                        nm: rcpt.name.clone(),
+                        pstl_adr: rcpt.postal_address.as_ref().map(postal_address_to_xml),
+                    }),
+                    msg_pgntn: options.message_pagination.map(|p| PaginationXml {
+                        pg_nb: p.page_number,
+                        last_pg_ind: p.last_page,
+                    }),
                 },
                 stmt: StatementXml {
                     id: stmt.statement_id.clone(),
+                    stmt_pgntn: stmt.pagination.map(|p| PaginationXml {
+                        pg_nb: p.page_number,
+                        last_pg_ind: p.last_page,
+                    }),
                     elctrnic_seq_nb: stmt.sequence_number.as_ref().and_then(|s| s.parse().ok()),
                     lgl_seq_nb: None,
-                    cre_dt_tm: stmt.creation_date.as_ref().map(format_date_time),
+                    cre_dt_tm: creation_date_time,
                     fr_to_dt: if stmt.from_date.is_some() || stmt.to_date.is_some() {
                         Some(FromToDateXml {
                             fr_dt_tm: stmt.from_date.as_ref().map(format_date_time),
@@ -424,17 +1071,25 @@ impl Camt053Statement {
                         None
                     },
                     acct: AccountInfoXml {
-                        id: AccountIdXml {
-                            iban: Some(stmt.account.clone()),
-                            othr: None,
-                        },
-                        ccy: stmt.currency.clone(),
+                        id: account_id_to_xml(&stmt.account),
+                        ccy: stmt.currency.to_string(),
                         nm: stmt.account_holder.clone(),
-                        ownr: None,
-                        svcr: None,
+                        ownr: stmt.account_owner.as_ref().map(|owner| OwnerXml {
+                            nm: owner.name.clone(),
+                            pstl_adr: owner.postal_address.as_ref().map(postal_address_to_xml),
+                        }),
+                        svcr: stmt.servicing_bank.as_ref().map(|bank| ServicerXml {
+                            fin_instn_id: FinancialInstitutionIdXml {
+                                bic: bank.bic.as_ref().map(Bic::to_string),
+                                clr_sys_mmb_id: bank.clearing_member()
+                                    .and_then(|member| bank_identifier_to_xml(&member).clr_sys_mmb_id),
+                                nm: bank.name.clone(),
+                                pstl_adr: bank.address.as_ref().map(postal_address_to_xml),
+                            },
+                        }),
                     },
                     bal: balances,
-                    txs_summry: None,
+                    txs_summry: summary,
                     ntry: entries,
                 },
             },
@@ -442,44 +1097,443 @@ impl Camt053Statement {
     }
 }
 
-// XML structure definitions
-#[derive(Debug, Deserialize, Serialize)]
-#[serde(rename = "Document")]
-struct Document {
-    #[serde(rename = "BkToCstmrStmt")]
-    bk_to_cstmr_stmt: BankToCustomerStatementXml,
+/// Incremental CAMT.053 writer: the statement header, then the entries one
+/// at a time, then the end of the document.
+///
+/// Unlike [`Camt053Statement::write_to_with`], the statement never has to be
+/// held in memory as a whole, so a converted statement can be written while
+/// its input is still being read. Both balances precede the entries in
+/// CAMT.053 and are written with the header; the `TxsSummry` totals would
+/// need every entry in advance and are left out.
+///
+/// # Examples
+///
+/// ```
+/// use ypbank_system::camt053_format::{Camt053Statement, Camt053Writer};
+/// use ypbank_system::mt940_format::Mt940EntryReader;
+/// use ypbank_system::types::{Currency, Statement};
+///
+/// let input = ":20:STMT\n:25:ACC001\n:60F:C240101EUR100,00\n:61:2401020102D10,00NTRF//REF1\n:62F:C240102EUR90,00\n-}";
+/// let header = Statement::new("STMT".into(), "ACC001", Currency::EUR);
+/// let mut writer = Camt053Writer::new(Vec::new());
+/// writer.write_header(&header)?;
+/// for transaction in Mt940EntryReader::new(input.as_bytes()) {
+///     writer.write_transaction(&transaction?)?;
+/// }
+/// let output = writer.finish()?;
+/// let parsed = Camt053Statement::from_read(&mut output.as_slice())?;
+/// assert_eq!(parsed.statement.transactions[0].reference, "REF1");
+/// # Ok::<(), ypbank_system::Error>(())
+/// ```
+pub struct Camt053Writer<W: Write> {
+    writer: quick_xml::Writer<W>,
+    options: Camt053WriteOptions,
+    /// Statement currency, known once the header has been written.
+    currency: Option<Currency>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-struct BankToCustomerStatementXml {
-    #[serde(rename = "GrpHdr")]
-    grp_hdr: GroupHeaderXml,
-    #[serde(rename = "Stmt")]
-    stmt: StatementXml,
-}
+impl<W: Write> Camt053Writer<W> {
+    /// Create a writer to `writer`.
+    pub fn new(writer: W) -> Self {
+        Self::with_options(writer, Camt053WriteOptions::default())
+    }
 
-#[derive(Debug, Deserialize, Serialize)]
-struct GroupHeaderXml {
-    #[serde(rename = "MsgId")]
-    msg_id: String,
-    #[serde(rename = "CreDtTm")]
-    cre_dt_tm: String,
-}
+    /// Create a writer with explicit write options.
+    pub fn with_options(writer: W, options: Camt053WriteOptions) -> Self {
+        let writer = match options.indent {
+            Some(indent) => quick_xml::Writer::new_with_indent(writer, b' ', indent),
+            None => quick_xml::Writer::new(writer),
+        };
+        Camt053Writer { writer, options, currency: None }
+    }
 
-#[derive(Debug, Deserialize, Serialize)]
-struct StatementXml {
-    #[serde(rename = "Id")]
-    id: String,
-    #[serde(rename = "ElctrncSeqNb", skip_serializing_if = "Option::is_none")]
-    elctrnic_seq_nb: Option<u32>,
-    #[serde(rename = "LglSeqNb", skip_serializing_if = "Option::is_none")]
-    lgl_seq_nb: Option<u32>,
-    #[serde(rename = "CreDtTm", skip_serializing_if = "Option::is_none")]
-    cre_dt_tm: Option<String>,
-    #[serde(rename = "FrToDt", skip_serializing_if = "Option::is_none")]
-    fr_to_dt: Option<FromToDateXml>,
-    #[serde(rename = "Acct")]
-    acct: AccountInfoXml,
+    /// Write everything up to the entries: the group header and the
+    /// statement identification, account and balances. The transactions of
+    /// `header` are not written.
+    pub fn write_header(&mut self, header: &Statement) -> Result<()> {
+        self.currency = Some(header.currency);
+        let document = match self.options.precision {
+            Some(policy) => {
+                let mut header = header.clone();
+                header.transactions.clear();
+                header.enforce_precision(policy)?;
+                Camt053Statement::document(&header, None, Vec::new(), &self.options)
+            }
+            None => Camt053Statement::document(header, None, Vec::new(), &self.options),
+        };
+        let BankToCustomerStatementXml { grp_hdr, stmt } = document.bk_to_cstmr_stmt;
+
+        writeln!(self.writer.get_mut(), "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+        self.writer.write_event(Event::Start(BytesStart::new("Document")))?;
+        self.writer.write_event(Event::Start(BytesStart::new("BkToCstmrStmt")))?;
+        self.element("GrpHdr", &grp_hdr)?;
+        self.writer.write_event(Event::Start(BytesStart::new("Stmt")))?;
+        self.element("Id", &stmt.id)?;
+        if let Some(ref pagination) = stmt.stmt_pgntn {
+            self.element("StmtPgntn", pagination)?;
+        }
+        if let Some(number) = stmt.elctrnic_seq_nb {
+            self.element("ElctrncSeqNb", &number)?;
+        }
+        if let Some(number) = stmt.lgl_seq_nb {
+            self.element("LglSeqNb", &number)?;
+        }
+        if let Some(ref created) = stmt.cre_dt_tm {
+            self.element("CreDtTm", created)?;
+        }
+        if let Some(ref period) = stmt.fr_to_dt {
+            self.element("FrToDt", period)?;
+        }
+        self.element("Acct", &stmt.acct)?;
+        for balance in &stmt.bal {
+            self.element("Bal", balance)?;
+        }
+        Ok(())
+    }
+
+    /// Write the `Ntry` element of a transaction.
+    pub fn write_transaction(&mut self, transaction: &Transaction) -> Result<()> {
+        let entry = match (self.options.precision, self.currency) {
+            (Some(policy), Some(currency)) => {
+                // Fitted as the only transaction of a statement in the header currency
+                let mut statement = Statement::new(String::new(), "", currency);
+                statement.transactions.push(transaction.clone());
+                statement.enforce_precision(policy)?;
+                Camt053Statement::entry_to_xml(&statement.transactions[0])
+            }
+            _ => Camt053Statement::entry_to_xml(transaction),
+        };
+        self.element("Ntry", &entry)
+    }
+
+    /// Close the statement and the document, returning the underlying writer.
+    pub fn finish(mut self) -> Result<W> {
+        for name in ["Stmt", "BkToCstmrStmt", "Document"] {
+            self.writer.write_event(Event::End(BytesEnd::new(name)))?;
+        }
+        let mut writer = self.writer.into_inner();
+        if self.options.indent.is_some() {
+            writeln!(writer)?;
+        }
+        Ok(writer)
+    }
+
+    fn element<T: Serialize>(&mut self, name: &str, value: &T) -> Result<()> {
+        self.writer.write_serializable(name, value).map_err(Error::xml)
+    }
+}
+
+/// Streaming reader yielding the entries of a CAMT.053 statement one at a time.
+///
+/// Unlike [`Camt053Statement::from_read`], only the entry currently being parsed
+/// is held in memory, so statements with any number of entries can be processed;
+/// [`Camt053EntryReader::collect_statement`] reads the rest into a whole statement.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::fs::File;
+/// use std::io::BufReader;
+/// use ypbank_system::camt053_format::Camt053EntryReader;
+///
+/// let file = BufReader::new(File::open("statement.xml")?);
+/// for transaction in Camt053EntryReader::new(file) {
+///     let transaction = transaction?;
+///     println!("{} {}", transaction.reference, transaction.amount);
+/// }
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub struct Camt053EntryReader<R: BufRead> {
+    reader: quick_xml::Reader<LineCounter<R>>,
+    buf: Vec<u8>,
+    path: Vec<Vec<u8>>,
+    options: Camt053ReadOptions,
+    currency: Option<Currency>,
+    account_currency: String,
+    /// The document without its `Ntry` elements.
+    rest: quick_xml::Writer<Vec<u8>>,
+    entries: usize,
+    warnings: Vec<Warning>,
+    rejected: Vec<RejectedRecord>,
+    finished: bool,
+}
+
+impl<R: BufRead> Camt053EntryReader<R> {
+    /// Create a streaming reader over a CAMT.053 document.
+    pub fn new(reader: R) -> Self {
+        Self::with_options(reader, Camt053ReadOptions::default())
+    }
+
+    /// Create a streaming reader with explicit read options; with
+    /// `skip_invalid`, entries that fail to parse are left out instead of
+    /// ending the iteration.
+    pub fn with_options(reader: R, options: Camt053ReadOptions) -> Self {
+        Camt053EntryReader {
+            reader: quick_xml::Reader::from_reader(LineCounter { inner: reader, consumed: 0, line: 0, column: 0 }),
+            buf: Vec::new(),
+            path: Vec::new(),
+            options,
+            currency: None,
+            account_currency: String::new(),
+            rest: quick_xml::Writer::new(Vec::new()),
+            entries: 0,
+            warnings: Vec::new(),
+            rejected: Vec::new(),
+            finished: false,
+        }
+    }
+
+    /// Account currency, known once the `Acct` block has been read.
+    pub fn currency(&self) -> Option<Currency> {
+        self.currency
+    }
+
+    /// Read the remaining entries into the whole statement.
+    pub fn collect_statement(self) -> Result<Camt053Statement> {
+        Ok(self.collect_outcome()?.statement)
+    }
+
+    /// Read the remaining entries into the whole statement, with the
+    /// warnings and the rejected entries.
+    pub(crate) fn collect_outcome(mut self) -> Result<ParseOutcome<Camt053Statement>> {
+        let mut transactions = Vec::new();
+        while let Some(transaction) = self.next_transaction()? {
+            transactions.push(transaction);
+        }
+
+        let document: Document = quick_xml::de::from_reader(self.rest.into_inner().as_slice())?;
+        let mut camt053 = Camt053Statement::from_document(&document).map_err(|e| e.within(STATEMENT_PATH))?;
+        let mut warnings = document_warnings(&document);
+        warnings.append(&mut self.warnings);
+        for transaction in transactions {
+            camt053.statement.add_transaction(transaction);
+        }
+        if self.options.verify_summary && self.rejected.is_empty() {
+            if let Some(ref reported) = document.bk_to_cstmr_stmt.stmt.txs_summry {
+                verify_summary(reported, &camt053.statement.transactions_summary())?;
+            }
+        }
+
+        Ok(ParseOutcome { statement: camt053, warnings, rejected: self.rejected })
+    }
+
+    fn next_transaction(&mut self) -> Result<Option<Transaction>> {
+        while let Some((location, xml)) = self.next_fragment()? {
+            let index = self.entries;
+            self.entries += 1;
+            let parsed = quick_xml::de::from_str::<EntryXml>(&xml)
+                .map_err(Error::from)
+                .and_then(|entry| Ok((Camt053Statement::parse_entry(&entry, self.currency)?, entry)))
+                .map_err(|e| e.within(&format!("Ntry[{}]", index)).within(STATEMENT_PATH));
+            match parsed {
+                Ok((mut transaction, entry)) => {
+                    self.warnings.extend(entry_warnings(index + 1, &entry, &self.account_currency));
+                    transaction.attach_source_raw(SourceFormat::Camt053, xml);
+                    return Ok(Some(transaction));
+                }
+                Err(error) if self.options.skip_invalid => self.rejected.push(RejectedRecord {
+                    line: location.line.unwrap_or_default(),
+                    text: xml,
+                    error,
+                }),
+                Err(error) => return Err(error.at(location)),
+            }
+        }
+        Ok(None)
+    }
+
+    /// Where the input has been read up to.
+    fn location(&self) -> Location {
+        let counter = self.reader.get_ref();
+        let offset = self.reader.buffer_position() as usize;
+        // The parser may have consumed the `<` of the markup it stands before
+        let ahead = counter.consumed.saturating_sub(offset);
+        Location {
+            line: Some(counter.line + 1),
+            column: Some(counter.column.saturating_sub(ahead) + 1),
+            offset: Some(offset),
+            ..Default::default()
+        }
+    }
+
+    /// XML of the next `Ntry` element with the location it starts at; the
+    /// other elements go to the rest of the document.
+    fn next_fragment(&mut self) -> Result<Option<(Location, String)>> {
+        loop {
+            self.buf.clear();
+            let start = self.location();
+            let event = match self.reader.read_event_into(&mut self.buf) {
+                Ok(event) => event.into_owned(),
+                Err(e) => return Err(Error::from(e).at(self.location())),
+            };
+            match event {
+                Event::Start(ref start_tag) if start_tag.local_name().as_ref() == b"Ntry" => {
+                    let xml = self.capture_entry(start_tag.clone()).map_err(|e| e.at(self.location()))?;
+                    let snippet = Location::in_text(&xml, 0).snippet;
+                    return Ok(Some((Location { snippet, ..start }, xml)));
+                }
+                Event::Start(ref start) => self.path.push(start.local_name().as_ref().to_vec()),
+                Event::End(_) => {
+                    self.path.pop();
+                }
+                Event::Text(ref text) if self.path.ends_with(&[b"Acct".to_vec(), b"Ccy".to_vec()]) => {
+                    self.account_currency = text.unescape()?.trim().to_string();
+                    let currency = parse_currency(&self.account_currency);
+                    self.currency = Some(currency.map_err(|e| e.within("Acct/Ccy").within(STATEMENT_PATH).at(start))?);
+                }
+                Event::Eof => return Ok(None),
+                _ => {}
+            }
+            self.rest.write_event(event)?;
+        }
+    }
+
+    /// Re-serialize a single `Ntry` element so it can be deserialized on its own.
+    fn capture_entry(&mut self, start: BytesStart<'static>) -> Result<String> {
+        let mut writer = quick_xml::Writer::new(Vec::new());
+        writer.write_event(Event::Start(start))?;
+
+        let mut depth = 1;
+        while depth > 0 {
+            self.buf.clear();
+            let event = self.reader.read_event_into(&mut self.buf)?;
+            match event {
+                Event::Start(_) => depth += 1,
+                Event::End(_) => depth -= 1,
+                Event::Eof => return Err(Error::XmlError {
+                    message: "Unexpected end of document inside Ntry".to_string(),
+                    source: None,
+                    location: None,
+                }),
+                _ => {}
+            }
+            writer.write_event(event)?;
+        }
+
+        String::from_utf8(writer.into_inner()).map_err(Error::xml)
+    }
+}
+
+impl<R: BufRead> Iterator for Camt053EntryReader<R> {
+    type Item = Result<Transaction>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        let next = self.next_transaction().transpose();
+        if !matches!(next, Some(Ok(_))) {
+            self.finished = true;
+        }
+        next
+    }
+}
+
+impl<R: BufRead> EntryReader for Camt053EntryReader<R> {
+    fn collect_statement(self: Box<Self>) -> Result<Statement> {
+        Ok(Camt053EntryReader::collect_statement(*self)?.statement)
+    }
+}
+
+/// Amounts of `AmtDtls` besides the booked one, as the transaction holds them.
+#[derive(Debug, Default)]
+struct AmountDetails {
+    original_amount: Option<Decimal>,
+    original_currency: Option<Currency>,
+    exchange_rate: Option<Decimal>,
+    counter_value_amount: Option<Decimal>,
+    counter_value_currency: Option<Currency>,
+    counter_value_rate: Option<Decimal>,
+}
+
+/// `BufRead` adapter counting the lines and columns consumed from it.
+struct LineCounter<R> {
+    inner: R,
+    consumed: usize,
+    line: usize,
+    column: usize,
+}
+
+impl<R: BufRead> Read for LineCounter<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let available = self.fill_buf()?;
+        let length = buf.len().min(available.len());
+        buf[..length].copy_from_slice(&available[..length]);
+        self.consume(length);
+        Ok(length)
+    }
+}
+
+impl<R: BufRead> BufRead for LineCounter<R> {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amount: usize) {
+        // The consumed bytes are still at the start of the buffer
+        if let Ok(buffered) = self.inner.fill_buf() {
+            for &byte in &buffered[..amount.min(buffered.len())] {
+                match byte {
+                    b'\n' => {
+                        self.line += 1;
+                        self.column = 0;
+                    }
+                    // Count characters, not UTF-8 continuation bytes
+                    _ if byte & 0xC0 != 0x80 => self.column += 1,
+                    _ => {}
+                }
+            }
+        }
+        self.consumed += amount;
+        self.inner.consume(amount);
+    }
+}
+
+// XML structure definitions
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename = "Document")]
+struct Document {
+    // camt.052 account reports share the statement layout
+    #[serde(rename = "BkToCstmrStmt", alias = "BkToCstmrAcctRpt")]
+    bk_to_cstmr_stmt: BankToCustomerStatementXml,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct BankToCustomerStatementXml {
+    #[serde(rename = "GrpHdr")]
+    grp_hdr: GroupHeaderXml,
+    #[serde(rename = "Stmt", alias = "Rpt")]
+    stmt: StatementXml,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct GroupHeaderXml {
+    #[serde(rename = "MsgId")]
+    msg_id: String,
+    #[serde(rename = "CreDtTm")]
+    cre_dt_tm: String,
+    #[serde(rename = "MsgRcpt", skip_serializing_if = "Option::is_none")]
+    msg_rcpt: Option<PartyXml>,
+    #[serde(rename = "MsgPgntn", skip_serializing_if = "Option::is_none")]
+    msg_pgntn: Option<PaginationXml>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct StatementXml {
+    #[serde(rename = "Id")]
+    id: String,
+    #[serde(rename = "StmtPgntn", skip_serializing_if = "Option::is_none")]
+    stmt_pgntn: Option<PaginationXml>,
+    #[serde(rename = "ElctrncSeqNb", skip_serializing_if = "Option::is_none")]
+    elctrnic_seq_nb: Option<u32>,
+    #[serde(rename = "LglSeqNb", skip_serializing_if = "Option::is_none")]
+    lgl_seq_nb: Option<u32>,
+    #[serde(rename = "CreDtTm", skip_serializing_if = "Option::is_none")]
+    cre_dt_tm: Option<String>,
+    #[serde(rename = "FrToDt", skip_serializing_if = "Option::is_none")]
+    fr_to_dt: Option<FromToDateXml>,
+    #[serde(rename = "Acct")]
+    acct: AccountInfoXml,
     #[serde(rename = "Bal", default)]
     bal: Vec<BalanceXml>,
     #[serde(rename = "TxsSummry", skip_serializing_if = "Option::is_none")]
@@ -488,6 +1542,14 @@ struct StatementXml {
     ntry: Vec<EntryXml>,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+struct PaginationXml {
+    #[serde(rename = "PgNb")]
+    pg_nb: u32,
+    #[serde(rename = "LastPgInd")]
+    last_pg_ind: bool,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 struct FromToDateXml {
     #[serde(rename = "FrDtTm", skip_serializing_if = "Option::is_none")]
@@ -522,12 +1584,26 @@ struct AccountIdXml {
 struct OtherAccountIdXml {
     #[serde(rename = "Id")]
     id: String,
+    #[serde(rename = "SchmeNm", skip_serializing_if = "Option::is_none")]
+    schme_nm: Option<AccountSchemeNameXml>,
+    #[serde(rename = "Issr", skip_serializing_if = "Option::is_none")]
+    issr: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct AccountSchemeNameXml {
+    #[serde(rename = "Cd", skip_serializing_if = "Option::is_none")]
+    cd: Option<String>,
+    #[serde(rename = "Prtry", skip_serializing_if = "Option::is_none")]
+    prtry: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 struct OwnerXml {
     #[serde(rename = "Nm", skip_serializing_if = "Option::is_none")]
     nm: Option<String>,
+    #[serde(rename = "PstlAdr", skip_serializing_if = "Option::is_none")]
+    pstl_adr: Option<PostalAddressXml>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -540,6 +1616,20 @@ struct ServicerXml {
 struct FinancialInstitutionIdXml {
     #[serde(rename = "BIC", skip_serializing_if = "Option::is_none")]
     bic: Option<String>,
+    #[serde(rename = "ClrSysMmbId", skip_serializing_if = "Option::is_none")]
+    clr_sys_mmb_id: Option<ClearingSystemMemberIdXml>,
+    #[serde(rename = "Nm", skip_serializing_if = "Option::is_none")]
+    nm: Option<String>,
+    #[serde(rename = "PstlAdr", skip_serializing_if = "Option::is_none")]
+    pstl_adr: Option<PostalAddressXml>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct ClearingSystemMemberIdXml {
+    #[serde(rename = "ClrSysId", skip_serializing_if = "Option::is_none")]
+    clr_sys_id: Option<PurposeXml>,
+    #[serde(rename = "MmbId")]
+    mmb_id: String,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -566,22 +1656,99 @@ struct CodeOrProprietaryXml {
     cd: String,
 }
 
+/// Entry status, a code of its own up to version 07 and wrapped in `Cd`
+/// or `Prtry` from version 08 on.
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct StatusXml {
+    #[serde(rename = "$text", default, skip_serializing_if = "Option::is_none")]
+    code: Option<String>,
+    #[serde(rename = "Cd", skip_serializing_if = "Option::is_none")]
+    cd: Option<String>,
+    #[serde(rename = "Prtry", skip_serializing_if = "Option::is_none")]
+    prtry: Option<String>,
+}
+
+impl StatusXml {
+    fn code(&self) -> &str {
+        self.cd.as_deref().or(self.prtry.as_deref()).or(self.code.as_deref()).unwrap_or("").trim()
+    }
+
+    /// The status, `None` for codes the parser doesn't know.
+    fn status(&self) -> Option<EntryStatus> {
+        self.code().parse().ok()
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 struct AmountXml {
-    #[serde(rename = "$value")]
+    #[serde(rename = "$text")]
     value: String,
     #[serde(rename = "@Ccy", skip_serializing_if = "Option::is_none")]
     ccy: Option<String>,
-    #[serde(rename = "Ccy", skip_serializing_if = "Option::is_none")]
-    ccy_alt: Option<String>,
 }
 
 impl AmountXml {
-    fn ccy(&self) -> Option<String> {
-        self.ccy.clone().or_else(|| self.ccy_alt.clone())
+    fn currency(&self) -> Result<Option<Currency>> {
+        self.ccy.as_deref().map(parse_currency).transpose()
     }
 }
 
+/// Values of `document` the parser had to assume or leave out.
+fn document_warnings(document: &Document) -> Vec<Warning> {
+    let stmt = &document.bk_to_cstmr_stmt.stmt;
+    let mut warnings = Vec::new();
+    let mut warn = |kind, message: String| warnings.push(Warning::new(kind, None, message));
+
+    if stmt.acct.id.iban.is_none() && stmt.acct.id.othr.is_none() {
+        warn(WarningKind::AssumedDefault, "no account identification, using UNKNOWN".to_string());
+    }
+    let datetimes = [
+        ("CreDtTm", stmt.cre_dt_tm.as_deref()),
+        ("FrDtTm", stmt.fr_to_dt.as_ref().and_then(|d| d.fr_dt_tm.as_deref())),
+        ("ToDtTm", stmt.fr_to_dt.as_ref().and_then(|d| d.to_dt_tm.as_deref())),
+    ];
+    for (element, value) in datetimes {
+        if let Some(value) = value.filter(|v| parse_camt_datetime(v).is_err()) {
+            warn(WarningKind::IgnoredValue, format!("unreadable {} {} ignored", element, value));
+        }
+    }
+    for bal in &stmt.bal {
+        let code = &bal.tp.cd_or_prtry.cd;
+        if !matches!(code.as_str(), "OPBD" | "OPAV" | "CLBD" | "CLAV" | "PRCD") {
+            warn(WarningKind::UnknownField, format!("unknown balance type {} read as intermediate", code));
+        }
+        if bal.amt.ccy.is_none() {
+            warn(WarningKind::AssumedDefault, format!("balance {} has no currency, using {}", code, stmt.acct.ccy));
+        }
+    }
+    for (i, entry) in stmt.ntry.iter().enumerate() {
+        warnings.extend(entry_warnings(i + 1, entry, &stmt.acct.ccy));
+    }
+    warnings
+}
+
+/// Values of the `n`th entry the parser had to assume.
+fn entry_warnings(n: usize, entry: &EntryXml, account_currency: &str) -> Vec<Warning> {
+    let mut messages = Vec::new();
+    if entry.ntry_ref.is_none() {
+        messages.push(format!("entry {} has no NtryRef, using UNKNOWN", n));
+    }
+    if entry.amt.ccy.is_none() {
+        messages.push(format!("entry {} has no currency, using {}", n, account_currency));
+    }
+    if entry.bookg_dt.is_none() {
+        messages.push(format!("entry {} has no booking date, using today", n));
+    }
+    if entry.sts.status().is_none() {
+        messages.push(format!("entry {} has unknown status {}, read as INFO", n, entry.sts.code()));
+    }
+    messages.into_iter().map(|message| Warning::new(WarningKind::AssumedDefault, None, message)).collect()
+}
+
+fn parse_currency(code: &str) -> Result<Currency> {
+    code.parse().map_err(|_| Error::InvalidCurrency(code.to_string(), None))
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 struct DateXml {
     #[serde(rename = "Dt", skip_serializing_if = "Option::is_none")]
@@ -594,12 +1761,30 @@ struct DateXml {
 struct TransactionsSummaryXml {
     #[serde(rename = "TtlNtries", skip_serializing_if = "Option::is_none")]
     ttl_ntries: Option<TotalEntriesXml>,
+    #[serde(rename = "TtlCdtNtries", skip_serializing_if = "Option::is_none")]
+    ttl_cdt_ntries: Option<NumberAndSumXml>,
+    #[serde(rename = "TtlDbtNtries", skip_serializing_if = "Option::is_none")]
+    ttl_dbt_ntries: Option<NumberAndSumXml>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 struct TotalEntriesXml {
-    #[serde(rename = "NbOfNtries")]
-    nb_of_ntries: String,
+    #[serde(rename = "NbOfNtries", skip_serializing_if = "Option::is_none")]
+    nb_of_ntries: Option<String>,
+    #[serde(rename = "Sum", skip_serializing_if = "Option::is_none")]
+    sum: Option<String>,
+    #[serde(rename = "TtlNetNtryAmt", skip_serializing_if = "Option::is_none")]
+    ttl_net_ntry_amt: Option<String>,
+    #[serde(rename = "CdtDbtInd", skip_serializing_if = "Option::is_none")]
+    cdt_dbt_ind: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct NumberAndSumXml {
+    #[serde(rename = "NbOfNtries", skip_serializing_if = "Option::is_none")]
+    nb_of_ntries: Option<String>,
+    #[serde(rename = "Sum", skip_serializing_if = "Option::is_none")]
+    sum: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -610,20 +1795,46 @@ struct EntryXml {
     amt: AmountXml,
     #[serde(rename = "CdtDbtInd")]
     cdt_dbt_ind: String,
+    #[serde(rename = "RvslInd", skip_serializing_if = "Option::is_none")]
+    rvsl_ind: Option<bool>,
     #[serde(rename = "Sts")]
-    sts: String,
+    sts: StatusXml,
     #[serde(rename = "BookgDt", skip_serializing_if = "Option::is_none")]
     bookg_dt: Option<DateXml>,
     #[serde(rename = "ValDt", skip_serializing_if = "Option::is_none")]
     val_dt: Option<DateXml>,
     #[serde(rename = "AcctSvcrRef", skip_serializing_if = "Option::is_none")]
     acct_svcr_ref: Option<String>,
+    #[serde(rename = "Avlbty", default, skip_serializing_if = "Vec::is_empty")]
+    avlbty: Vec<AvailabilityXml>,
     #[serde(rename = "BkTxCd", skip_serializing_if = "Option::is_none")]
     bk_tx_cd: Option<BankTransactionCodeXml>,
+    #[serde(rename = "AmtDtls", skip_serializing_if = "Option::is_none")]
+    amt_dtls: Option<AmountDetailsXml>,
+    #[serde(rename = "Chrgs", default, skip_serializing_if = "Vec::is_empty")]
+    chrgs: Vec<ChargesXml>,
     #[serde(rename = "NtryDtls", skip_serializing_if = "Option::is_none")]
     ntry_dtls: Option<EntryDetailsXml>,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+struct AvailabilityXml {
+    #[serde(rename = "Dt")]
+    dt: AvailabilityDateXml,
+    #[serde(rename = "Amt")]
+    amt: AmountXml,
+    #[serde(rename = "CdtDbtInd")]
+    cdt_dbt_ind: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct AvailabilityDateXml {
+    #[serde(rename = "NbOfDays", skip_serializing_if = "Option::is_none")]
+    nb_of_days: Option<String>,
+    #[serde(rename = "ActlDt", skip_serializing_if = "Option::is_none")]
+    actl_dt: Option<String>,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 struct BankTransactionCodeXml {
     #[serde(rename = "Domn", skip_serializing_if = "Option::is_none")]
@@ -644,12 +1855,40 @@ struct ProprietaryCodeXml {
     cd: String,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+struct ChargesXml {
+    #[serde(rename = "Amt", skip_serializing_if = "Option::is_none")]
+    amt: Option<AmountXml>,
+    #[serde(rename = "CdtDbtInd", skip_serializing_if = "Option::is_none")]
+    cdt_dbt_ind: Option<String>,
+    #[serde(rename = "Tp", skip_serializing_if = "Option::is_none")]
+    tp: Option<ChargeTypeXml>,
+    #[serde(rename = "Br", skip_serializing_if = "Option::is_none")]
+    br: Option<String>,
+    #[serde(rename = "Rcrd", default, skip_serializing_if = "Vec::is_empty")]
+    rcrd: Vec<ChargesXml>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct ChargeTypeXml {
+    #[serde(rename = "Cd", skip_serializing_if = "Option::is_none")]
+    cd: Option<String>,
+    #[serde(rename = "Prtry", skip_serializing_if = "Option::is_none")]
+    prtry: Option<GenericIdentificationXml>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct GenericIdentificationXml {
+    #[serde(rename = "Id")]
+    id: String,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 struct EntryDetailsXml {
     #[serde(rename = "Btch", skip_serializing_if = "Option::is_none")]
     btch: Option<BatchXml>,
-    #[serde(rename = "TxDtls", skip_serializing_if = "Option::is_none")]
-    tx_dtls: Option<TransactionDetailsXml>,
+    #[serde(rename = "TxDtls", default)]
+    tx_dtls: Vec<TransactionDetailsXml>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -664,28 +1903,62 @@ struct TransactionDetailsXml {
     refs: Option<ReferencesXml>,
     #[serde(rename = "AmtDtls", skip_serializing_if = "Option::is_none")]
     amt_dtls: Option<AmountDetailsXml>,
+    #[serde(rename = "Chrgs", default, skip_serializing_if = "Vec::is_empty")]
+    chrgs: Vec<ChargesXml>,
     #[serde(rename = "RltdPties", skip_serializing_if = "Option::is_none")]
     rltd_pties: Option<RelatedPartiesXml>,
     #[serde(rename = "RltdAgts", skip_serializing_if = "Option::is_none")]
     rltd_agts: Option<RelatedAgentsXml>,
+    #[serde(rename = "Purp", skip_serializing_if = "Option::is_none")]
+    purp: Option<PurposeXml>,
     #[serde(rename = "RmtInf", skip_serializing_if = "Option::is_none")]
     rmt_inf: Option<RemittanceInformationXml>,
     #[serde(rename = "RltdDts", skip_serializing_if = "Option::is_none")]
     rltd_dts: Option<RelatedDatesXml>,
+    #[serde(rename = "RtrInf", skip_serializing_if = "Option::is_none")]
+    rtr_inf: Option<ReturnInformationXml>,
     #[serde(rename = "AddtlTxInf", skip_serializing_if = "Option::is_none")]
     addtl_tx_inf: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 struct ReferencesXml {
+    #[serde(rename = "AcctSvcrRef", skip_serializing_if = "Option::is_none")]
+    acct_svcr_ref: Option<String>,
+    #[serde(rename = "InstrId", skip_serializing_if = "Option::is_none")]
+    instr_id: Option<String>,
     #[serde(rename = "EndToEndId", skip_serializing_if = "Option::is_none")]
     end_to_end_id: Option<String>,
+    #[serde(rename = "MndtId", skip_serializing_if = "Option::is_none")]
+    mndt_id: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 struct AmountDetailsXml {
+    #[serde(rename = "InstdAmt", skip_serializing_if = "Option::is_none")]
+    instd_amt: Option<AmountAndCurrencyXml>,
     #[serde(rename = "TxAmt", skip_serializing_if = "Option::is_none")]
-    tx_amt: Option<AmountXml>,
+    tx_amt: Option<AmountAndCurrencyXml>,
+    #[serde(rename = "CntrValAmt", skip_serializing_if = "Option::is_none")]
+    cntr_val_amt: Option<AmountAndCurrencyXml>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct AmountAndCurrencyXml {
+    #[serde(rename = "Amt")]
+    amt: AmountXml,
+    #[serde(rename = "CcyXchg", skip_serializing_if = "Option::is_none")]
+    ccy_xchg: Option<CurrencyExchangeXml>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct CurrencyExchangeXml {
+    #[serde(rename = "SrcCcy", skip_serializing_if = "Option::is_none")]
+    src_ccy: Option<String>,
+    #[serde(rename = "TrgtCcy", skip_serializing_if = "Option::is_none")]
+    trgt_ccy: Option<String>,
+    #[serde(rename = "XchgRate")]
+    xchg_rate: String,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -694,10 +1967,53 @@ struct RelatedPartiesXml {
     dbtr: Option<PartyXml>,
     #[serde(rename = "DbtrAcct", skip_serializing_if = "Option::is_none")]
     dbtr_acct: Option<AccountXml>,
+    #[serde(rename = "UltmtDbtr", skip_serializing_if = "Option::is_none")]
+    ultmt_dbtr: Option<PartyXml>,
     #[serde(rename = "Cdtr", skip_serializing_if = "Option::is_none")]
     cdtr: Option<PartyXml>,
     #[serde(rename = "CdtrAcct", skip_serializing_if = "Option::is_none")]
     cdtr_acct: Option<AccountXml>,
+    #[serde(rename = "UltmtCdtr", skip_serializing_if = "Option::is_none")]
+    ultmt_cdtr: Option<PartyXml>,
+}
+
+/// One side of a payment in `RltdPties`.
+#[derive(Clone, Copy)]
+struct PartySide<'a> {
+    party: Option<&'a PartyXml>,
+    account: Option<&'a AccountXml>,
+    ultimate_party: Option<&'a PartyXml>,
+}
+
+impl PartySide<'_> {
+    fn is_empty(&self) -> bool {
+        self.party.is_none() && self.account.is_none() && self.ultimate_party.is_none()
+    }
+}
+
+impl RelatedPartiesXml {
+    /// Counterparty side and account holder's own side of an entry: the
+    /// counterparty is the debtor of a credit and the creditor of a debit.
+    /// When only the other side is given, it is taken as the counterparty.
+    fn sides(&self, debit_credit: DebitCredit) -> (PartySide<'_>, PartySide<'_>) {
+        let debtor = PartySide {
+            party: self.dbtr.as_ref(),
+            account: self.dbtr_acct.as_ref(),
+            ultimate_party: self.ultmt_dbtr.as_ref(),
+        };
+        let creditor = PartySide {
+            party: self.cdtr.as_ref(),
+            account: self.cdtr_acct.as_ref(),
+            ultimate_party: self.ultmt_cdtr.as_ref(),
+        };
+        let none = PartySide { party: None, account: None, ultimate_party: None };
+        match debit_credit {
+            DebitCredit::Credit if debtor.is_empty() => (creditor, none),
+            DebitCredit::Credit => (debtor, creditor),
+            DebitCredit::Debit if creditor.is_empty() => (debtor, none),
+            DebitCredit::Debit => (creditor, debtor),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -710,8 +2026,18 @@ struct PartyXml {
 
 #[derive(Debug, Deserialize, Serialize)]
 struct PostalAddressXml {
+    #[serde(rename = "StrtNm", skip_serializing_if = "Option::is_none")]
+    strt_nm: Option<String>,
+    #[serde(rename = "BldgNb", skip_serializing_if = "Option::is_none")]
+    bldg_nb: Option<String>,
+    #[serde(rename = "PstCd", skip_serializing_if = "Option::is_none")]
+    pst_cd: Option<String>,
+    #[serde(rename = "TwnNm", skip_serializing_if = "Option::is_none")]
+    twn_nm: Option<String>,
     #[serde(rename = "Ctry", skip_serializing_if = "Option::is_none")]
     ctry: Option<String>,
+    #[serde(rename = "AdrLine", default, skip_serializing_if = "Vec::is_empty")]
+    adr_line: Vec<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -720,6 +2046,14 @@ struct AccountXml {
     id: AccountIdXml,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+struct PurposeXml {
+    #[serde(rename = "Cd", skip_serializing_if = "Option::is_none")]
+    cd: Option<String>,
+    #[serde(rename = "Prtry", skip_serializing_if = "Option::is_none")]
+    prtry: Option<String>,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 struct RelatedAgentsXml {
     #[serde(rename = "DbtrAgt", skip_serializing_if = "Option::is_none")]
@@ -744,41 +2078,290 @@ struct RemittanceInformationXml {
 
 #[derive(Debug, Deserialize, Serialize)]
 struct StructuredRemittanceXml {
+    #[serde(rename = "RfrdDocInf", default, skip_serializing_if = "Vec::is_empty")]
+    rfrd_doc_inf: Vec<ReferredDocumentXml>,
     #[serde(rename = "CdtrRefInf", skip_serializing_if = "Option::is_none")]
     cdtr_ref_inf: Option<CreditorReferenceXml>,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+struct ReferredDocumentXml {
+    #[serde(rename = "Tp", skip_serializing_if = "Option::is_none")]
+    tp: Option<DocumentTypeXml>,
+    #[serde(rename = "Nb", skip_serializing_if = "Option::is_none")]
+    nb: Option<String>,
+    #[serde(rename = "RltdDt", skip_serializing_if = "Option::is_none")]
+    rltd_dt: Option<String>,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 struct CreditorReferenceXml {
+    #[serde(rename = "Tp", skip_serializing_if = "Option::is_none")]
+    tp: Option<DocumentTypeXml>,
     #[serde(rename = "Ref", skip_serializing_if = "Option::is_none")]
     ref_val: Option<String>,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+struct DocumentTypeXml {
+    #[serde(rename = "CdOrPrtry")]
+    cd_or_prtry: DocumentCodeXml,
+    #[serde(rename = "Issr", skip_serializing_if = "Option::is_none")]
+    issr: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct DocumentCodeXml {
+    #[serde(rename = "Cd", skip_serializing_if = "Option::is_none")]
+    cd: Option<String>,
+    #[serde(rename = "Prtry", skip_serializing_if = "Option::is_none")]
+    prtry: Option<String>,
+}
+
+impl DocumentCodeXml {
+    fn code(&self) -> Option<String> {
+        self.cd.clone().or_else(|| self.prtry.clone())
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct ReturnInformationXml {
+    #[serde(rename = "Rsn", skip_serializing_if = "Option::is_none")]
+    rsn: Option<ReturnReasonXml>,
+    #[serde(rename = "AddtlInf", default, skip_serializing_if = "Vec::is_empty")]
+    addtl_inf: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct ReturnReasonXml {
+    #[serde(rename = "Cd", skip_serializing_if = "Option::is_none")]
+    cd: Option<String>,
+    #[serde(rename = "Prtry", skip_serializing_if = "Option::is_none")]
+    prtry: Option<String>,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 struct RelatedDatesXml {
     #[serde(rename = "AccptncDtTm", skip_serializing_if = "Option::is_none")]
     accptnc_dt_tm: Option<String>,
 }
 
+/// Account from `Id`; an `IBAN` must pass validation, accounts without one
+/// are reported under `Othr`.
+fn parse_account_id(id: &AccountIdXml) -> Result<Option<AccountIdentifier>> {
+    if let Some(ref iban) = id.iban {
+        let iban = iban.parse::<Iban>().map_err(|_| Error::InvalidIban(iban.clone(), None))?;
+        return Ok(Some(AccountIdentifier::Iban(iban)));
+    }
+    Ok(id.othr.as_ref().map(|othr| AccountIdentifier::Other {
+        id: othr.id.clone(),
+        scheme: othr.schme_nm.as_ref().and_then(|s| s.cd.clone().or_else(|| s.prtry.clone())),
+        issuer: othr.issr.clone(),
+    }))
+}
+
+fn parse_bic(bic: &str) -> Result<Bic> {
+    bic.parse().map_err(|_| Error::InvalidBic(bic.to_string(), None))
+}
+
+/// Bank from `FinInstnId`: its BIC, else its clearing system membership.
+fn parse_bank_identifier(id: &FinancialInstitutionIdXml) -> Result<Option<BankIdentifier>> {
+    if let Some(ref bic) = id.bic {
+        return Ok(Some(BankIdentifier::Bic(parse_bic(bic)?)));
+    }
+    Ok(id.clr_sys_mmb_id.as_ref().map(|member| BankIdentifier::ClearingSystem {
+        id: member.mmb_id.clone(),
+        system: member.clr_sys_id.as_ref().and_then(|s| s.cd.clone().or_else(|| s.prtry.clone())),
+    }))
+}
+
+fn bank_identifier_to_xml(bank: &BankIdentifier) -> FinancialInstitutionIdXml {
+    match bank {
+        BankIdentifier::Bic(bic) => FinancialInstitutionIdXml {
+            bic: Some(bic.to_string()),
+            clr_sys_mmb_id: None,
+            nm: None,
+            pstl_adr: None,
+        },
+        BankIdentifier::ClearingSystem { id, system } => FinancialInstitutionIdXml {
+            bic: None,
+            clr_sys_mmb_id: Some(ClearingSystemMemberIdXml {
+                clr_sys_id: system.as_ref().map(|code| PurposeXml { cd: Some(code.clone()), prtry: None }),
+                mmb_id: id.clone(),
+            }),
+            nm: None,
+            pstl_adr: None,
+        },
+    }
+}
+
+fn account_id_to_xml(account: &AccountIdentifier) -> AccountIdXml {
+    match account {
+        AccountIdentifier::Iban(iban) => AccountIdXml {
+            iban: Some(iban.to_string()),
+            othr: None,
+        },
+        AccountIdentifier::Other { id, scheme, issuer } => AccountIdXml {
+            iban: None,
+            othr: Some(OtherAccountIdXml {
+                id: id.clone(),
+                schme_nm: scheme.as_ref().map(|scheme| {
+                    // Only ISO external codes are valid in `Cd`
+                    if ["AIIN", "BBAN", "CUID", "UPIC"].contains(&scheme.as_str()) {
+                        AccountSchemeNameXml { cd: Some(scheme.clone()), prtry: None }
+                    } else {
+                        AccountSchemeNameXml { cd: None, prtry: Some(scheme.clone()) }
+                    }
+                }),
+                issr: issuer.clone(),
+            }),
+        },
+    }
+}
+
+fn parse_postal_address(adr: &PostalAddressXml) -> PostalAddress {
+    PostalAddress {
+        street_name: adr.strt_nm.clone(),
+        building_number: adr.bldg_nb.clone(),
+        post_code: adr.pst_cd.clone(),
+        town_name: adr.twn_nm.clone(),
+        country: adr.ctry.clone(),
+        address_lines: adr.adr_line.clone(),
+    }
+}
+
+fn postal_address_to_xml(adr: &PostalAddress) -> PostalAddressXml {
+    PostalAddressXml {
+        strt_nm: adr.street_name.clone(),
+        bldg_nb: adr.building_number.clone(),
+        pst_cd: adr.post_code.clone(),
+        twn_nm: adr.town_name.clone(),
+        ctry: adr.country.clone(),
+        adr_line: adr.address_lines.clone(),
+    }
+}
+
+fn summary_to_xml(summary: &TransactionsSummary) -> TransactionsSummaryXml {
+    let net = summary.net_amount();
+
+    TransactionsSummaryXml {
+        ttl_ntries: Some(TotalEntriesXml {
+            nb_of_ntries: Some(summary.entry_count().to_string()),
+            sum: Some(summary.total_sum().to_string()),
+            ttl_net_ntry_amt: Some(net.abs().to_string()),
+            cdt_dbt_ind: Some(if net < Decimal::ZERO { "DBIT" } else { "CRDT" }.to_string()),
+        }),
+        ttl_cdt_ntries: Some(NumberAndSumXml {
+            nb_of_ntries: Some(summary.credit_count.to_string()),
+            sum: Some(summary.credit_sum.to_string()),
+        }),
+        ttl_dbt_ntries: Some(NumberAndSumXml {
+            nb_of_ntries: Some(summary.debit_count.to_string()),
+            sum: Some(summary.debit_sum.to_string()),
+        }),
+    }
+}
+
+/// Compare a bank-reported transactions summary with the actual entries.
+fn verify_summary(reported: &TransactionsSummaryXml, actual: &TransactionsSummary) -> Result<()> {
+    let mut mismatches = Vec::new();
+
+    let mut check = |label: &str, value: &Option<String>, expected: String| {
+        if let Some(ref value) = value {
+            let matches = match (Decimal::from_str(value.trim()), Decimal::from_str(&expected)) {
+                (Ok(a), Ok(b)) => a == b,
+                _ => value.trim() == expected,
+            };
+            if !matches {
+                mismatches.push(format!("{} is {} but entries give {}", label, value.trim(), expected));
+            }
+        }
+    };
+
+    if let Some(ref ttl) = reported.ttl_ntries {
+        check("TtlNtries/NbOfNtries", &ttl.nb_of_ntries, actual.entry_count().to_string());
+        check("TtlNtries/Sum", &ttl.sum, actual.total_sum().to_string());
+        let signed_net = ttl.ttl_net_ntry_amt.as_ref().map(|amt| match ttl.cdt_dbt_ind.as_deref() {
+            Some("DBIT") => format!("-{}", amt.trim()),
+            _ => amt.trim().to_string(),
+        });
+        check("TtlNtries/TtlNetNtryAmt", &signed_net, actual.net_amount().to_string());
+    }
+    if let Some(ref cdt) = reported.ttl_cdt_ntries {
+        check("TtlCdtNtries/NbOfNtries", &cdt.nb_of_ntries, actual.credit_count.to_string());
+        check("TtlCdtNtries/Sum", &cdt.sum, actual.credit_sum.to_string());
+    }
+    if let Some(ref dbt) = reported.ttl_dbt_ntries {
+        check("TtlDbtNtries/NbOfNtries", &dbt.nb_of_ntries, actual.debit_count.to_string());
+        check("TtlDbtNtries/Sum", &dbt.sum, actual.debit_sum.to_string());
+    }
+
+    if mismatches.is_empty() {
+        Ok(())
+    } else {
+        let message = format!("Transactions summary does not match entries: {}", mismatches.join("; "));
+        Err(ValidationError::new(RuleId::TransactionsSummary, message).at_path(format!("{}/TxsSummry", STATEMENT_PATH)).into())
+    }
+}
+
 // Helper functions for date parsing and formatting
 fn parse_camt_date(date_str: &str) -> Result<NaiveDate> {
-    // Try different date formats
-    // ISO 8601 with time: 2023-04-20T23:24:31
-    if let Ok(dt) = chrono::NaiveDateTime::parse_from_str(date_str, "%Y-%m-%dT%H:%M:%S") {
-        return Ok(dt.date());
+    parse_camt_datetime(date_str).map(|date| date.date())
+}
+
+/// Parse an ISO date or date-time, keeping the time of day when present.
+fn parse_camt_datetime(date_str: &str) -> Result<BankDate> {
+    // ISO 8601 with time: 2023-04-20T23:24:31, optionally with fractions
+    if let Ok(dt) = NaiveDateTime::parse_from_str(date_str, "%Y-%m-%dT%H:%M:%S%.f") {
+        return Ok(dt.into());
+    }
+
+    // With UTC offset: 2023-04-20T23:24:31+02:00, converted to UTC like
+    // MT940's :13D:
+    if let Ok(dt) = chrono::DateTime::parse_from_rfc3339(date_str) {
+        return Ok(dt.naive_utc().into());
     }
 
     // ISO 8601 date only: 2023-04-20
-    parse_date_only(date_str)
+    parse_date_only(date_str).map(BankDate::from)
+}
+
+/// Parse a `Dt`/`DtTm` choice, returning `None` when neither is present.
+fn parse_date_xml(dt: &DateXml) -> Result<Option<BankDate>> {
+    if let Some(ref d) = dt.dt {
+        Ok(Some(parse_date_only(d)?.into()))
+    } else if let Some(ref dt_tm) = dt.dt_tm {
+        parse_camt_datetime(dt_tm).map(Some)
+    } else {
+        Ok(None)
+    }
+}
+
+/// Write `DtTm` when the time of day is known, `Dt` otherwise.
+fn date_to_xml(date: &BankDate) -> DateXml {
+    match date.time() {
+        Some(_) => DateXml {
+            dt: None,
+            dt_tm: Some(date.to_string()),
+        },
+        None => DateXml {
+            dt: Some(date.to_string()),
+            dt_tm: None,
+        },
+    }
 }
 
 fn parse_date_only(date_str: &str) -> Result<NaiveDate> {
     NaiveDate::parse_from_str(date_str, "%Y-%m-%d")
-        .map_err(|_| Error::InvalidDate(date_str.to_string()))
+        .map_err(|_| Error::InvalidDate(date_str.to_string(), None))
 }
 
-fn format_date_time(date: &NaiveDate) -> String {
-    format!("{}T00:00:00", date.format("%Y-%m-%d"))
+/// `DtTm`-only value: the time of day when known, midnight otherwise.
+fn format_date_time(date: &BankDate) -> String {
+    match date.time() {
+        Some(_) => date.to_string(),
+        None => format!("{}T00:00:00", date),
+    }
 }
 
 fn format_date_only(date: &NaiveDate) -> String {
@@ -790,6 +2373,46 @@ mod tests {
     use super::*;
     use chrono::Datelike;
 
+    const BATCH_ENTRY: &str = r#"<Ntry><NtryRef>B1</NtryRef><Amt Ccy="DKK">300.00</Amt><CdtDbtInd>DBIT</CdtDbtInd>
+<Sts>BOOK</Sts><BookgDt><Dt>2024-01-15</Dt></BookgDt>
+<Chrgs><Amt Ccy="DKK">5.00</Amt><CdtDbtInd>DBIT</CdtDbtInd><Tp><Cd>COMM</Cd></Tp><Br>DEBT</Br></Chrgs>
+<NtryDtls><Btch><NbOfTxs>2</NbOfTxs></Btch>
+<TxDtls><Refs><EndToEndId>E2E-1</EndToEndId></Refs><AmtDtls><TxAmt><Amt Ccy="DKK">100.00</Amt></TxAmt></AmtDtls>
+<RltdPties><Cdtr><Nm>Supplier A</Nm></Cdtr></RltdPties><RmtInf><Ustrd>Invoice 1</Ustrd></RmtInf></TxDtls>
+<TxDtls><Refs><EndToEndId>E2E-2</EndToEndId></Refs><AmtDtls><TxAmt><Amt Ccy="DKK">200.00</Amt></TxAmt></AmtDtls>
+<RltdPties><Cdtr><Nm>Supplier B</Nm></Cdtr></RltdPties><RmtInf><Ustrd>Invoice 2</Ustrd></RmtInf></TxDtls>
+</NtryDtls></Ntry>"#;
+
+    /// Wrap entries into a minimal camt.053 document.
+    fn document(entries: &str) -> String {
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<Document><BkToCstmrStmt><GrpHdr><MsgId>MSG1</MsgId><CreDtTm>2024-01-15T10:00:00</CreDtTm></GrpHdr>
+<Stmt><Id>STMT1</Id><Acct><Id><IBAN>DK5000400440116243</IBAN></Id><Ccy>DKK</Ccy></Acct>
+{}</Stmt></BkToCstmrStmt></Document>"#,
+            entries
+        )
+    }
+
+    /// Single-detail debit entry whose TxDtls content is supplied by the test.
+    fn entry(tx_dtls: &str) -> String {
+        format!(
+            r#"<Ntry><NtryRef>E1</NtryRef><Amt Ccy="DKK">300.00</Amt><CdtDbtInd>DBIT</CdtDbtInd>
+<Sts>BOOK</Sts><BookgDt><Dt>2024-01-15</Dt></BookgDt><NtryDtls><TxDtls>{}</TxDtls></NtryDtls></Ntry>"#,
+            tx_dtls
+        )
+    }
+
+    fn parse(xml: &str) -> Camt053Statement {
+        Camt053Statement::from_read(&mut xml.as_bytes()).unwrap()
+    }
+
+    fn round_trip(camt: &Camt053Statement) -> Camt053Statement {
+        let mut output = Vec::new();
+        camt.write_to(&mut output).unwrap();
+        Camt053Statement::from_read(&mut output.as_slice()).unwrap()
+    }
+
     #[test]
     fn test_parse_date() {
         let date = parse_camt_date("2023-04-20T23:24:31").unwrap();
@@ -797,4 +2420,640 @@ mod tests {
         assert_eq!(date.month(), 4);
         assert_eq!(date.day(), 20);
     }
+
+    #[test]
+    fn test_batch_entry_round_trip() {
+        let camt = parse(&document(BATCH_ENTRY));
+        let tx = &camt.statement.transactions[0];
+        assert_eq!(tx.details.len(), 2);
+        assert_eq!(tx.details[1].reference.as_deref(), Some("E2E-2"));
+        assert_eq!(tx.details[1].counterparty_name.as_deref(), Some("Supplier B"));
+
+        let reparsed = round_trip(&camt);
+        assert_eq!(reparsed.statement.transactions[0].details, tx.details);
+
+        let expanded = tx.expand_details();
+        assert_eq!(expanded.len(), 2);
+        assert_eq!(expanded[0].amount.to_string(), "100.00");
+        assert_eq!(expanded[1].description, "Invoice 2");
+    }
+
+    #[test]
+    fn test_parse_charges() {
+        let camt = parse(&document(BATCH_ENTRY));
+        let charges = &camt.statement.transactions[0].charges;
+        assert_eq!(charges.len(), 1);
+        assert_eq!(charges[0].amount.to_string(), "5.00");
+        assert_eq!(charges[0].charge_type.as_deref(), Some("COMM"));
+        assert_eq!(charges[0].bearer.as_deref(), Some("DEBT"));
+
+        let reparsed = round_trip(&camt);
+        assert_eq!(&reparsed.statement.transactions[0].charges, charges);
+    }
+
+    #[test]
+    fn test_parse_fx_amount_details() {
+        let camt = parse(&document(&entry(
+            r#"<AmtDtls><InstdAmt><Amt Ccy="EUR">40.00</Amt><CcyXchg><SrcCcy>EUR</SrcCcy>
+<TrgtCcy>DKK</TrgtCcy><XchgRate>7.4500</XchgRate></CcyXchg></InstdAmt></AmtDtls>"#,
+        )));
+        let tx = &camt.statement.transactions[0];
+        assert_eq!(tx.original_amount, Some(Decimal::from_str("40.00").unwrap()));
+        assert_eq!(tx.original_currency, Some(Currency::EUR));
+        assert_eq!(tx.exchange_rate, Some(Decimal::from_str("7.4500").unwrap()));
+
+        let reparsed = round_trip(&camt);
+        let tx2 = &reparsed.statement.transactions[0];
+        assert_eq!(tx2.original_amount, tx.original_amount);
+        assert_eq!(tx2.original_currency, tx.original_currency);
+        assert_eq!(tx2.exchange_rate, tx.exchange_rate);
+
+        // The counter value is a conversion of the booked amount, not the original
+        let camt = parse(&document(&entry(
+            r#"<AmtDtls><TxAmt><Amt Ccy="DKK">300.00</Amt></TxAmt><CntrValAmt><Amt Ccy="EUR">40.27</Amt><CcyXchg>
+<SrcCcy>DKK</SrcCcy><TrgtCcy>EUR</TrgtCcy><XchgRate>0.1342</XchgRate></CcyXchg></CntrValAmt></AmtDtls>"#,
+        )));
+        let tx = &camt.statement.transactions[0];
+        assert_eq!((tx.original_amount, tx.original_currency, tx.exchange_rate), (None, None, None));
+        assert_eq!(tx.counter_value_amount, Some(Decimal::from_str("40.27").unwrap()));
+        assert_eq!(tx.counter_value_currency, Some(Currency::EUR));
+        assert_eq!(tx.counter_value_rate, Some(Decimal::from_str("0.1342").unwrap()));
+        let tx2 = &round_trip(&camt).statement.transactions[0];
+        assert_eq!(
+            (tx2.counter_value_amount, tx2.counter_value_currency, tx2.counter_value_rate),
+            (tx.counter_value_amount, tx.counter_value_currency, tx.counter_value_rate)
+        );
+    }
+
+    #[test]
+    fn test_structured_remittance_round_trip() {
+        let camt = parse(&document(&entry(
+            "<RmtInf><Strd><RfrdDocInf><Tp><CdOrPrtry><Cd>CINV</Cd></CdOrPrtry></Tp><Nb>INV-1</Nb>\
+             <RltdDt>2024-01-10</RltdDt></RfrdDocInf><CdtrRefInf><Tp><CdOrPrtry><Cd>SCOR</Cd></CdOrPrtry></Tp>\
+             <Ref>RF18539007547034</Ref></CdtrRefInf></Strd></RmtInf>",
+        )));
+        let strd = camt.statement.transactions[0].structured_remittance.clone().unwrap();
+        assert_eq!(strd.reference_type.as_deref(), Some("SCOR"));
+        assert_eq!(strd.reference.as_deref(), Some("RF18539007547034"));
+        assert_eq!(strd.referred_documents[0].number.as_deref(), Some("INV-1"));
+
+        let reparsed = round_trip(&camt);
+        assert_eq!(reparsed.statement.transactions[0].structured_remittance, Some(strd));
+    }
+
+    #[test]
+    fn test_entry_status_camt052() {
+        let xml = document(&entry("").replace("<Sts>BOOK</Sts>", "<Sts>PDNG</Sts>"))
+            .replace("BkToCstmrStmt>", "BkToCstmrAcctRpt>")
+            .replace("Stmt>", "Rpt>");
+        let camt = parse(&xml);
+        assert_eq!(camt.statement.transactions[0].status, EntryStatus::Pending);
+
+        let reparsed = round_trip(&camt);
+        assert_eq!(reparsed.statement.transactions[0].status, EntryStatus::Pending);
+    }
+
+    #[test]
+    fn test_entry_status_code() {
+        let status = |sts: &str| {
+            let xml = document(&entry("").replace("<Sts>BOOK</Sts>", sts));
+            let outcome = Camt053Statement::from_read_with_diagnostics(&mut xml.as_bytes(), &Camt053ReadOptions::default()).unwrap();
+            let warnings = outcome.warnings.into_iter().map(|w| w.message).collect::<Vec<_>>();
+            (outcome.statement.statement.transactions[0].status, warnings)
+        };
+        assert_eq!(status("<Sts>\n  <Cd>PDNG</Cd>\n</Sts>"), (EntryStatus::Pending, vec![]));
+        assert_eq!(status("<Sts><Cd>BOOK</Cd></Sts>"), (EntryStatus::Booked, vec![]));
+        assert_eq!(status("<Sts><Prtry>INFO</Prtry></Sts>"), (EntryStatus::Information, vec![]));
+        assert_eq!(status("<Sts><Cd>FUTR</Cd></Sts>"), (EntryStatus::Pending, vec![]));
+        assert_eq!(
+            status("<Sts>LOST</Sts>"),
+            (EntryStatus::Information, vec!["entry 1 has unknown status LOST, read as INFO".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_reversal_indicator() {
+        let xml = document(&entry("").replace("<Sts>BOOK</Sts>", "<RvslInd>true</RvslInd><Sts>BOOK</Sts>"));
+        let camt = parse(&xml);
+        assert_eq!(camt.statement.transactions[0].status, EntryStatus::Reversed);
+
+        let mut output = Vec::new();
+        camt.write_to(&mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("<RvslInd>true</RvslInd>"));
+
+        let reparsed = round_trip(&camt);
+        assert_eq!(reparsed.statement.transactions[0].status, EntryStatus::Reversed);
+    }
+
+    fn page(number: u32, last: bool, reference: &str) -> Camt053Statement {
+        let xml = document(&entry("").replace("E1", reference)).replace(
+            "<Id>STMT1</Id>",
+            &format!("<Id>STMT1</Id><StmtPgntn><PgNb>{}</PgNb><LastPgInd>{}</LastPgInd></StmtPgntn>", number, last),
+        );
+        parse(&xml)
+    }
+
+    #[test]
+    fn test_pagination_round_trip() {
+        let camt = page(2, true, "E2");
+        assert_eq!(camt.statement.pagination, Some(Pagination { page_number: 2, last_page: true }));
+        assert_eq!(round_trip(&camt).statement.pagination, camt.statement.pagination);
+    }
+
+    #[test]
+    fn test_assemble_pages() {
+        let assembled = Camt053Statement::assemble_pages(vec![
+            page(2, true, "E2"),
+            page(1, false, "E1"),
+        ]).unwrap();
+        let refs: Vec<_> = assembled.statement.transactions.iter().map(|t| t.reference.as_str()).collect();
+        assert_eq!(refs, ["E1", "E2"]);
+        assert_eq!(assembled.statement.pagination, None);
+
+        assert!(Camt053Statement::assemble_pages(vec![page(1, false, "E1"), page(3, true, "E3")]).is_err());
+        assert!(Camt053Statement::assemble_pages(vec![page(1, false, "E1")]).is_err());
+
+        // Pages written by paginate assemble back into the statement
+        let camt = parse(&document(&format!("{}{}{}", entry(""), BATCH_ENTRY, entry("").replace("E1", "E3"))));
+        let pages: Vec<_> = camt.statement.paginate(2).into_iter()
+            .map(|statement| round_trip(&Camt053Statement { statement }))
+            .collect();
+        assert_eq!(pages.len(), 2);
+        let assembled = Camt053Statement::assemble_pages(pages).unwrap();
+        let refs: Vec<_> = assembled.statement.transactions.iter().map(|t| t.reference.as_str()).collect();
+        assert_eq!(refs, ["E1", "B1", "E3"]);
+    }
+
+    #[test]
+    fn test_transactions_summary() {
+        let credit = BATCH_ENTRY.replace("DBIT</CdtDbtInd>\n<Sts>", "CRDT</CdtDbtInd>\n<Sts>");
+        let xml = document(&format!("{}{}", entry(""), credit));
+        let camt = parse(&xml);
+        let statement = &camt.statement;
+        assert_eq!(statement.transactions[0].signed_amount(), Decimal::from_str("-300.00").unwrap());
+        assert_eq!(statement.total_debits(), Decimal::from_str("300.00").unwrap());
+        assert_eq!(statement.total_credits(), Decimal::from_str("300.00").unwrap());
+        assert!(statement.net_change().is_zero());
+
+        // Written summary matches the entries and passes verification
+        let mut output = Vec::new();
+        camt.write_to(&mut output).unwrap();
+        let written = String::from_utf8(output.clone()).unwrap();
+        assert!(written.contains("<TtlNtries><NbOfNtries>2</NbOfNtries><Sum>600.00</Sum>"));
+        let options = Camt053ReadOptions { verify_summary: true, ..Default::default() };
+        assert!(Camt053Statement::from_read_with(&mut output.as_slice(), &options).is_ok());
+
+        // A wrong bank summary is rejected in verifying mode only
+        let wrong = xml.replacen(
+            "<Ntry>",
+            "<TxsSummry><TtlNtries><NbOfNtries>3</NbOfNtries></TtlNtries></TxsSummry><Ntry>",
+            1,
+        );
+        let err = Camt053Statement::from_read_with(&mut wrong.as_bytes(), &options).unwrap_err();
+        assert!(err.to_string().contains("NbOfNtries"));
+        assert_eq!(err.validation().unwrap().rule, RuleId::TransactionsSummary);
+        assert!(Camt053Statement::from_read(&mut wrong.as_bytes()).is_ok());
+    }
+
+    #[test]
+    fn test_indented_output() {
+        let camt = parse(&document(&entry("")));
+        let options = Camt053WriteOptions { indent: Some(2), ..Default::default() };
+        let mut output = Vec::new();
+        camt.write_to_with(&mut output, &options).unwrap();
+
+        let xml = String::from_utf8(output.clone()).unwrap();
+        assert!(xml.contains("\n  <BkToCstmrStmt>\n    <GrpHdr>"));
+        assert!(xml.contains(r#"<Amt Ccy="DKK">300.00</Amt>"#));
+
+        let reparsed = Camt053Statement::from_read(&mut output.as_slice()).unwrap();
+        assert_eq!(reparsed.statement.transactions.len(), 1);
+    }
+
+    #[test]
+    fn test_account_owner_and_servicer() {
+        let xml = document("").replace(
+            "<Ccy>DKK</Ccy></Acct>",
+            "<Ccy>DKK</Ccy><Ownr><Nm>Account owner</Nm><PstlAdr><StrtNm>Streetname</StrtNm>\
+             <BldgNb>20</BldgNb><PstCd>1234</PstCd><TwnNm>Townname</TwnNm><Ctry>DK</Ctry></PstlAdr>\
+             </Ownr><Svcr><FinInstnId><BIC>DABADKKK</BIC>\
+             <ClrSysMmbId><ClrSysId><Cd>DKNCC</Cd></ClrSysId><MmbId>3000</MmbId></ClrSysMmbId>\
+             <Nm>Danske Bank</Nm><PstlAdr><TwnNm>Copenhagen</TwnNm><Ctry>DK</Ctry></PstlAdr></FinInstnId></Svcr></Acct>",
+        );
+        let camt = parse(&xml);
+        let owner = camt.statement.account_owner.clone().unwrap();
+        assert_eq!(owner.name.as_deref(), Some("Account owner"));
+        assert_eq!(owner.postal_address.as_ref().unwrap().town_name.as_deref(), Some("Townname"));
+        let bank = camt.statement.servicing_bank.clone().unwrap();
+        assert_eq!(bank.bic.as_ref().map(Bic::as_str), Some("DABADKKK"));
+        assert_eq!(bank.name.as_deref(), Some("Danske Bank"));
+        assert_eq!((bank.clearing_code.as_deref(), bank.clearing_system.as_deref()), (Some("3000"), Some("DKNCC")));
+        assert_eq!(bank.address.unwrap().town_name.as_deref(), Some("Copenhagen"));
+
+        let reparsed = round_trip(&camt);
+        assert_eq!(reparsed.statement.account_owner, camt.statement.account_owner);
+        assert_eq!(reparsed.statement.servicing_bank, camt.statement.servicing_bank);
+    }
+
+    #[test]
+    fn test_return_information() {
+        let camt = parse(&document(&entry(
+            "<RtrInf><Rsn><Cd>AC04</Cd></Rsn><AddtlInf>Account closed</AddtlInf></RtrInf>",
+        )));
+        let tx = &camt.statement.transactions[0];
+        assert!(tx.is_return());
+        let reason = tx.return_reason.as_ref().unwrap();
+        assert_eq!(reason.code.as_deref(), Some("AC04"));
+        assert_eq!(reason.additional_info, ["Account closed"]);
+
+        let reparsed = round_trip(&camt);
+        assert_eq!(reparsed.statement.transactions[0].return_reason, tx.return_reason);
+    }
+
+    #[test]
+    fn test_counterparty_postal_address() {
+        let camt = parse(&document(&entry(
+            "<RltdPties><Cdtr><Nm>Creditor</Nm><PstlAdr><StrtNm>Main St</StrtNm><BldgNb>5</BldgNb>\
+             <PstCd>10115</PstCd><TwnNm>Berlin</TwnNm><Ctry>DE</Ctry><AdrLine>Floor 3</AdrLine>\
+             </PstlAdr></Cdtr></RltdPties>",
+        )));
+        let tx = &camt.statement.transactions[0];
+        let address = tx.counterparty_address.as_ref().unwrap();
+        assert_eq!(address.street_name.as_deref(), Some("Main St"));
+        assert_eq!(address.post_code.as_deref(), Some("10115"));
+        assert_eq!(address.country.as_deref(), Some("DE"));
+        assert_eq!(address.address_lines, ["Floor 3"]);
+
+        let reparsed = round_trip(&camt);
+        assert_eq!(reparsed.statement.transactions[0].counterparty_address, tx.counterparty_address);
+    }
+
+    #[test]
+    fn test_ultimate_counterparty() {
+        let camt = parse(&document(&entry(
+            "<RltdPties><Cdtr><Nm>Payment Provider</Nm></Cdtr><UltmtCdtr><Nm>Merchant</Nm></UltmtCdtr></RltdPties>",
+        )));
+        let tx = &camt.statement.transactions[0];
+        assert_eq!(tx.counterparty_name.as_deref(), Some("Payment Provider"));
+        assert_eq!(tx.ultimate_counterparty_name.as_deref(), Some("Merchant"));
+
+        let mut output = Vec::new();
+        camt.write_to(&mut output).unwrap();
+        let xml = String::from_utf8(output).unwrap();
+        assert!(xml.contains("<UltmtCdtr><Nm>Merchant</Nm></UltmtCdtr>"));
+        let reparsed = round_trip(&camt);
+        assert_eq!(reparsed.statement.transactions[0].ultimate_counterparty_name, tx.ultimate_counterparty_name);
+    }
+
+    #[test]
+    fn test_transaction_refs() {
+        let xml = document(&entry(
+            "<Refs><AcctSvcrRef>TX-ASR</AcctSvcrRef><InstrId>INSTR-1</InstrId>\
+             <EndToEndId>E2E-1</EndToEndId><MndtId>MANDATE-7</MndtId></Refs>",
+        ))
+        .replace("</BookgDt>", "</BookgDt><AcctSvcrRef>ASR-1</AcctSvcrRef>");
+        let camt = parse(&xml);
+        let refs = &camt.statement.transactions[0].refs;
+        assert_eq!(refs.account_servicer_reference.as_deref(), Some("ASR-1"));
+        assert_eq!(refs.instruction_id.as_deref(), Some("INSTR-1"));
+        assert_eq!(refs.end_to_end_id.as_deref(), Some("E2E-1"));
+        assert_eq!(refs.mandate_id.as_deref(), Some("MANDATE-7"));
+
+        let reparsed = round_trip(&camt);
+        assert_eq!(&reparsed.statement.transactions[0].refs, refs);
+    }
+
+    #[test]
+    fn test_entry_reader_streams_entries() {
+        let xml = document(&format!("{}{}", entry(""), BATCH_ENTRY));
+        let expected = parse(&xml).statement.transactions;
+
+        let mut reader = Camt053EntryReader::new(xml.as_bytes());
+        let streamed: Vec<Transaction> = reader.by_ref().collect::<Result<_>>().unwrap();
+        assert_eq!(streamed, expected);
+        assert_eq!(reader.currency().unwrap(), "DKK");
+
+        // The first entry is streamed, the rest collected with the document
+        let mut reader = Camt053EntryReader::new(xml.as_bytes());
+        assert_eq!(reader.next().unwrap().unwrap(), expected[0]);
+        let rest = reader.collect_statement().unwrap().statement;
+        assert_eq!(rest.transactions, expected[1..]);
+        assert_eq!(rest.account, parse(&xml).statement.account);
+    }
+
+    #[test]
+    fn test_parse_warnings() {
+        let xml = document(&entry("")).replace("<NtryRef>E1</NtryRef>", "")
+            .replace("<BookgDt><Dt>2024-01-15</Dt></BookgDt>", "")
+            .replace("<Id>STMT1</Id>", "<Id>STMT1</Id><CreDtTm>yesterday</CreDtTm>");
+        let outcome = Camt053Statement::from_read_with_diagnostics(&mut xml.as_bytes(), &Default::default()).unwrap();
+        let messages: Vec<&str> = outcome.warnings.iter().map(|w| w.message.as_str()).collect();
+        assert_eq!(messages, [
+            "unreadable CreDtTm yesterday ignored",
+            "entry 1 has no NtryRef, using UNKNOWN",
+            "entry 1 has no booking date, using today",
+        ]);
+        assert_eq!(outcome.statement.statement.transactions[0].reference, "UNKNOWN");
+        assert!(Camt053Statement::from_read_with_diagnostics(&mut document(&entry("")).as_bytes(), &Default::default())
+            .unwrap().warnings.is_empty());
+    }
+
+    #[test]
+    fn test_skip_invalid_entries() {
+        let bad = entry("").replace("E1", "E2").replace("300.00", "abc");
+        let xml = document(&format!("{}\n{}\n{}", entry(""), bad, entry("").replace("E1", "E3")));
+        assert!(Camt053Statement::from_read(&mut xml.as_bytes()).is_err());
+
+        let options = Camt053ReadOptions { skip_invalid: true, ..Default::default() };
+        let outcome = Camt053Statement::from_read_with_diagnostics(&mut xml.as_bytes(), &options).unwrap();
+        let references: Vec<&str> = outcome.statement.statement.transactions.iter().map(|tx| tx.reference.as_str()).collect();
+        assert_eq!(references, ["E1", "E3"]);
+        assert_eq!(outcome.rejected.len(), 1);
+        assert_eq!(outcome.rejected[0].line, 6);
+        assert!(outcome.rejected[0].text.starts_with("<Ntry><NtryRef>E2</NtryRef>"));
+        assert!(matches!(outcome.rejected[0].error, Error::InvalidAmount(..)));
+        assert!(outcome.rejected[0].error.to_string().contains("element BkToCstmrStmt/Stmt[0]/Ntry[1]/Amt"));
+        assert_eq!(outcome.statement.statement.account, parse(&document("")).statement.account);
+    }
+
+    #[test]
+    fn test_entry_reader_stops_on_error() {
+        let xml = document(&entry("").replace("300.00", "abc"));
+        let mut reader = Camt053EntryReader::new(xml.as_bytes());
+        let err = reader.next().unwrap().unwrap_err();
+        assert!(matches!(err, Error::InvalidAmount(..)));
+        assert_eq!(err.location().unwrap().path.as_deref(), Some("BkToCstmrStmt/Stmt[0]/Ntry[0]/Amt"));
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn test_malformed_xml_error() {
+        let xml = document(&entry("")).replace("</Sts>", "</Status>");
+        let err = Camt053Statement::from_read(&mut xml.as_bytes()).unwrap_err();
+        assert!(matches!(err, Error::XmlError { source: Some(_), .. }));
+        assert!(std::error::Error::source(&err).is_some());
+        assert_eq!(err.format(), Some(crate::Format::Camt053));
+        assert!(!err.is_recoverable() && !err.is_io());
+    }
+
+    #[test]
+    fn test_deserialize_attributes_and_text() {
+        let xml = document(&entry("<RmtInf><Ustrd>Tea &amp; coffee</Ustrd></RmtInf>"))
+            .replace("<Document>", r#"<Document xmlns="urn:iso:std:iso:20022:tech:xsd:camt.053.001.02">"#)
+            .replace(r#"<Amt Ccy="DKK">300.00</Amt>"#, "<Amt>300.00</Amt>");
+        let outcome = Camt053Statement::from_read_with_diagnostics(&mut xml.as_bytes(), &Camt053ReadOptions::default()).unwrap();
+        let tx = &outcome.statement.statement.transactions[0];
+        assert_eq!((tx.amount.to_string().as_str(), tx.currency), ("300.00", Currency::from_str("DKK").unwrap()));
+        assert_eq!(tx.description, "Tea & coffee");
+        assert!(outcome.warnings.iter().any(|w| w.message == "entry 1 has no currency, using DKK"));
+
+        let xml = document(&entry("")).replace("<NtryRef>E1</NtryRef>", "<NtryRef><Id>E1</Id></NtryRef>");
+        let err = Camt053Statement::from_read(&mut xml.as_bytes()).unwrap_err();
+        assert!(matches!(err, Error::XmlError { source: Some(ref source), .. } if source.is::<quick_xml::DeError>()));
+    }
+
+    #[test]
+    fn test_error_paths() {
+        let path = |xml: &str| {
+            let err = Camt053Statement::from_read(&mut xml.as_bytes()).unwrap_err();
+            err.location().and_then(|location| location.path.clone()).unwrap_or_else(|| panic!("{}", err))
+        };
+        let balance = r#"<Bal><Tp><CdOrPrtry><Cd>OPBD</Cd></CdOrPrtry></Tp><Amt Ccy="DKK">1OO.00</Amt>
+<CdtDbtInd>CRDT</CdtDbtInd><Dt><Dt>2024-01-15</Dt></Dt></Bal>"#;
+        assert_eq!(path(&document(balance)), "BkToCstmrStmt/Stmt[0]/Bal[0]");
+        let second = BATCH_ENTRY.replace(
+            "<Nm>Supplier B</Nm></Cdtr>",
+            "<Nm>Supplier B</Nm></Cdtr><CdtrAcct><Id><IBAN>DK5000400440116244</IBAN></Id></CdtrAcct>",
+        );
+        assert_eq!(path(&document(&format!("{}{}", entry(""), second))), "BkToCstmrStmt/Stmt[0]/Ntry[1]/NtryDtls/TxDtls[1]");
+        let amount = BATCH_ENTRY.replace("200.00</Amt></TxAmt>", "2OO.00</Amt></TxAmt>");
+        assert_eq!(path(&document(&amount)), "BkToCstmrStmt/Stmt[0]/Ntry[0]/NtryDtls/TxDtls[1]/AmtDtls/TxAmt");
+        let currency = BATCH_ENTRY.replace(r#"<TxAmt><Amt Ccy="DKK">200.00"#, r#"<TxAmt><Amt Ccy="DKX">200.00"#);
+        assert_eq!(path(&document(&currency)), "BkToCstmrStmt/Stmt[0]/Ntry[0]/NtryDtls/TxDtls[1]/AmtDtls/TxAmt");
+        assert_eq!(path(&document("").replace("<Ccy>DKK</Ccy>", "<Ccy>DKX</Ccy>")), "BkToCstmrStmt/Stmt[0]/Acct/Ccy");
+    }
+
+    #[test]
+    fn test_error_location() {
+        let xml = "<?xml version=\"1.0\"?>\n<Document><BkToCstmrStmt>\n<Stmt><Id>S</Id>\n\
+                   <Ntry><NtryRef>E1</NtryRef><Sts>BOOK</Status></Ntry>\n</Stmt></BkToCstmrStmt></Document>";
+        let err = Camt053Statement::from_read(&mut xml.as_bytes()).unwrap_err();
+        let location = err.location().unwrap();
+        assert_eq!((location.line, location.column, location.offset), (Some(4), Some(46), Some(110)));
+
+        let xml = document(&entry("")).replace("<Amt Ccy=\"DKK\">300.00", "<Amt Ccy=\"DKK\">3OO.00");
+        let err = Camt053Statement::from_read(&mut xml.as_bytes()).unwrap_err();
+        assert!(matches!(err, Error::InvalidAmount(ref amount, _) if amount == "3OO.00"));
+        let location = err.location().unwrap();
+        assert_eq!((location.line, location.column), (Some(4), Some(1)));
+        assert_eq!(location.path.as_deref(), Some("BkToCstmrStmt/Stmt[0]/Ntry[0]/Amt"));
+        assert_eq!(location.snippet.as_deref(), Some("<Ntry><NtryRef>E1</NtryRef><Amt Ccy=\"DKK\">3OO.00</Amt><CdtDbtInd>DBIT</CdtDbtInd…"));
+    }
+
+    #[test]
+    fn test_booking_and_value_times_round_trip() {
+        let xml = document(&entry("").replace(
+            "<BookgDt><Dt>2024-01-15</Dt></BookgDt>",
+            "<BookgDt><DtTm>2024-01-15T09:30:15</DtTm></BookgDt><ValDt><DtTm>2024-01-16T08:00:00+01:00</DtTm></ValDt>",
+        ))
+        .replace(
+            "<Id>STMT1</Id>",
+            "<Id>STMT1</Id><CreDtTm>2024-01-15T10:00:00</CreDtTm>\
+             <FrToDt><FrDtTm>2024-01-01T00:00:00</FrDtTm><ToDtTm>2024-01-15T09:59:59</ToDtTm></FrToDt>",
+        );
+        let camt = parse(&xml);
+        let tx = &camt.statement.transactions[0];
+        assert_eq!(tx.date.date(), NaiveDate::from_ymd_opt(2024, 1, 15).unwrap());
+        assert_eq!(tx.date.to_string(), "2024-01-15T09:30:15");
+        assert_eq!(tx.value_date.unwrap().to_string(), "2024-01-16T07:00:00");
+        assert_eq!(camt.statement.creation_date.unwrap().to_string(), "2024-01-15T10:00:00");
+        assert_eq!(camt.statement.from_date.unwrap().to_string(), "2024-01-01T00:00:00");
+        assert_eq!(camt.statement.to_date.unwrap().to_string(), "2024-01-15T09:59:59");
+
+        let reparsed = round_trip(&camt);
+        let reparsed_tx = &reparsed.statement.transactions[0];
+        assert_eq!(reparsed_tx.date, tx.date);
+        assert_eq!(reparsed_tx.value_date, tx.value_date);
+        assert_eq!(reparsed.statement.creation_date, camt.statement.creation_date);
+        assert_eq!(reparsed.statement.from_date, camt.statement.from_date);
+        assert_eq!(reparsed.statement.to_date, camt.statement.to_date);
+    }
+
+    #[test]
+    fn test_write_group_header_options() {
+        let camt = parse(&document(&entry("")));
+        let options = Camt053WriteOptions {
+            message_recipient: Some(Party {
+                name: Some("ERP Gateway".to_string()),
+                postal_address: None,
+            }),
+            message_pagination: Some(Pagination {
+                page_number: 1,
+                last_page: true,
+            }),
+            ..Default::default()
+        };
+
+        let mut output = Vec::new();
+        camt.write_to_with(&mut output, &options).unwrap();
+        let xml = String::from_utf8(output.clone()).unwrap();
+        assert!(xml.contains(
+            "</CreDtTm><MsgRcpt><Nm>ERP Gateway</Nm></MsgRcpt>\
+             <MsgPgntn><PgNb>1</PgNb><LastPgInd>true</LastPgInd></MsgPgntn></GrpHdr>"
+        ));
+        // The entry is written from its fields, not as read
+        let without_raw = |mut camt: Camt053Statement| {
+            camt.statement.transactions.iter_mut().for_each(|tx| tx.source_raw = None);
+            camt
+        };
+        let reparsed = Camt053Statement::from_read(&mut output.as_slice()).unwrap();
+        assert_eq!(without_raw(reparsed), without_raw(camt));
+    }
+
+    #[test]
+    fn test_write_precision() {
+        let camt = parse(&document(&entry("").replace("300.00", "300.005")));
+        let write = |precision| {
+            let mut output = Vec::new();
+            let options = Camt053WriteOptions { precision: Some(precision), ..Default::default() };
+            camt.write_to_with(&mut output, &options).map(|_| String::from_utf8(output).unwrap())
+        };
+
+        assert!(matches!(write(PrecisionPolicy::Reject), Err(Error::InvalidAmount(..))));
+        let xml = write(PrecisionPolicy::Round).unwrap();
+        assert!(xml.contains(">300.01</Amt>"));
+        assert!(!xml.contains("300.005"));
+    }
+
+    #[test]
+    fn test_other_account_identification() {
+        let xml = document(&entry(
+            "<RltdPties><CdtrAcct><Id><Othr><Id>12345678</Id><SchmeNm><Cd>BBAN</Cd></SchmeNm>\
+             <Issr>DK Bank</Issr></Othr></Id></CdtrAcct></RltdPties>",
+        ))
+        .replace(
+            "<IBAN>DK5000400440116243</IBAN>",
+            "<Othr><Id>3000-1234567</Id><SchmeNm><Prtry>DOMESTIC</Prtry></SchmeNm></Othr>",
+        );
+        let camt = parse(&xml);
+        assert_eq!(
+            camt.statement.account,
+            AccountIdentifier::Other {
+                id: "3000-1234567".to_string(),
+                scheme: Some("DOMESTIC".to_string()),
+                issuer: None,
+            }
+        );
+        let counterparty = camt.statement.transactions[0].counterparty_account.clone();
+        assert_eq!(
+            counterparty,
+            Some(AccountIdentifier::Other {
+                id: "12345678".to_string(),
+                scheme: Some("BBAN".to_string()),
+                issuer: Some("DK Bank".to_string()),
+            })
+        );
+
+        let reparsed = round_trip(&camt);
+        assert_eq!(reparsed.statement.account, camt.statement.account);
+        assert_eq!(reparsed.statement.transactions[0].counterparty_account, counterparty);
+    }
+
+    #[test]
+    fn test_related_agents() {
+        let camt = parse(&document(&entry(
+            "<RltdAgts><CdtrAgt><FinInstnId><ClrSysMmbId><ClrSysId><Cd>RUCBC</Cd></ClrSysId>\
+             <MmbId>044525545</MmbId></ClrSysMmbId></FinInstnId></CdtrAgt></RltdAgts>",
+        )));
+        let bank = BankIdentifier::ClearingSystem { id: "044525545".into(), system: Some("RUCBC".into()) };
+        assert_eq!(camt.statement.transactions[0].bank_identifier, Some(bank.clone()));
+        assert_eq!(round_trip(&camt).statement.transactions[0].bank_identifier, Some(bank));
+
+        let xml = document(&entry("<RltdAgts><DbtrAgt><FinInstnId><BIC>DABA-DKKK</BIC></FinInstnId></DbtrAgt></RltdAgts>"));
+        let err = Camt053Statement::from_read(&mut xml.as_bytes()).unwrap_err();
+        assert!(matches!(err, Error::InvalidBic(..)));
+    }
+
+    #[test]
+    fn test_invalid_iban() {
+        let xml = document(&entry("")).replace("DK5000400440116243", "DK5000400440116244");
+        let err = Camt053Statement::from_read(&mut xml.as_bytes()).unwrap_err();
+        assert!(matches!(err, Error::InvalidIban(ref iban, _) if iban == "DK5000400440116244"));
+        assert_eq!(err.location().unwrap().path.as_deref(), Some("BkToCstmrStmt/Stmt[0]/Acct/Id"));
+    }
+
+    #[test]
+    fn test_purpose_code() {
+        let camt = parse(&document(&entry(
+            "<RltdPties><Cdtr><Nm>Employee</Nm></Cdtr></RltdPties><Purp><Cd>SALA</Cd></Purp>",
+        )));
+        assert_eq!(camt.statement.transactions[0].purpose_code.as_deref(), Some("SALA"));
+
+        let reparsed = round_trip(&camt);
+        assert_eq!(reparsed.statement.transactions[0].purpose_code.as_deref(), Some("SALA"));
+    }
+
+    #[test]
+    fn test_funds_availability() {
+        let xml = document(&entry("")).replace(
+            "</BookgDt>",
+            "</BookgDt><Avlbty><Dt><NbOfDays>+1</NbOfDays></Dt><Amt Ccy=\"DKK\">200.00</Amt>\
+             <CdtDbtInd>DBIT</CdtDbtInd></Avlbty><Avlbty><Dt><ActlDt>2024-01-18</ActlDt></Dt>\
+             <Amt Ccy=\"DKK\">100.00</Amt><CdtDbtInd>DBIT</CdtDbtInd></Avlbty>",
+        );
+        let camt = parse(&xml);
+        let availability = &camt.statement.transactions[0].funds_availability;
+        assert_eq!(availability.len(), 2);
+        assert_eq!(availability[0].days, Some(1));
+        assert_eq!(availability[0].amount, Decimal::from_str("200.00").unwrap());
+        assert_eq!(availability[1].date, NaiveDate::from_ymd_opt(2024, 1, 18));
+
+        let reparsed = round_trip(&camt);
+        assert_eq!(&reparsed.statement.transactions[0].funds_availability, availability);
+    }
+
+    #[test]
+    fn test_currency_validation() {
+        let balance = r#"<Bal><Tp><CdOrPrtry><Cd>OPBD</Cd></CdOrPrtry></Tp><Amt>100.00</Amt>
+<CdtDbtInd>CRDT</CdtDbtInd><Dt><Dt>2024-01-15</Dt></Dt></Bal>"#;
+        let camt = parse(&document(balance));
+        assert_eq!(camt.statement.opening_balance.unwrap().currency, Currency::from_str("DKK").unwrap());
+
+        let xml = document(&entry("")).replace(r#"<Amt Ccy="DKK">"#, r#"<Amt Ccy="DKX">"#);
+        let err = Camt053Statement::from_read(&mut xml.as_bytes()).unwrap_err();
+        assert!(matches!(err, Error::InvalidCurrency(ref code, _) if code == "DKX"));
+        let location = err.location().unwrap();
+        assert_eq!((location.line, location.column), (Some(4), Some(1)));
+        assert_eq!(location.path.as_deref(), Some("BkToCstmrStmt/Stmt[0]/Ntry[0]/Amt"));
+        assert!(location.snippet.as_ref().unwrap().starts_with("<Ntry><NtryRef>E1</NtryRef>"));
+    }
+
+    #[test]
+    fn test_incremental_writer() {
+        let camt = parse(&document(BATCH_ENTRY));
+        let mut header = camt.statement.clone();
+        let transactions = std::mem::take(&mut header.transactions);
+        let fingerprints = |statement: &Statement| statement.transactions.iter().map(Transaction::fingerprint).collect::<Vec<_>>();
+
+        for indent in [None, Some(2)] {
+            let options = Camt053WriteOptions { indent, ..Default::default() };
+
+            // Without entries the output is the whole-statement writer's
+            let mut expected = Vec::new();
+            Camt053Statement { statement: header.clone() }.write_to_with(&mut expected, &options).unwrap();
+            let mut writer = Camt053Writer::with_options(Vec::new(), options.clone());
+            writer.write_header(&header).unwrap();
+            assert_eq!(String::from_utf8(writer.finish().unwrap()).unwrap(), String::from_utf8(expected).unwrap());
+
+            let mut writer = Camt053Writer::with_options(Vec::new(), options);
+            writer.write_header(&header).unwrap();
+            for transaction in &transactions {
+                writer.write_transaction(transaction).unwrap();
+            }
+            let output = writer.finish().unwrap();
+            let parsed = Camt053Statement::from_read(&mut output.as_slice()).unwrap();
+            assert_eq!(fingerprints(&parsed.statement), fingerprints(&round_trip(&camt).statement));
+        }
+    }
 }
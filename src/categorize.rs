@@ -0,0 +1,237 @@
+//! Rule-based transaction categorization.
+//!
+//! A [`Categorizer`] holds an ordered list of [`Rule`]s; each transaction gets
+//! the category of the first rule it matches. Rules are built in code or
+//! loaded from a TOML file:
+//!
+//! ```toml
+//! [[rule]]
+//! category = "Salary"
+//! purpose_codes = ["SALA"]
+//!
+//! [[rule]]
+//! category = "Rent"
+//! description = "(?i)аренд|rent"
+//! direction = "debit"
+//! min_amount = 10000
+//! ```
+
+use crate::error::{Error, Result};
+use crate::types::{DebitCredit, Statement, Transaction};
+use regex::Regex;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use std::io::Read;
+
+/// A categorization rule; every condition that is set must match.
+#[derive(Debug, Clone)]
+pub struct Rule {
+    /// Category assigned to matching transactions.
+    pub category: String,
+
+    /// Pattern searched for in the description.
+    pub description: Option<Regex>,
+
+    /// Pattern searched for in the counterparty name.
+    pub counterparty: Option<Regex>,
+
+    /// Smallest matching amount, inclusive.
+    pub min_amount: Option<Decimal>,
+
+    /// Largest matching amount, inclusive.
+    pub max_amount: Option<Decimal>,
+
+    /// Required direction of the transaction.
+    pub direction: Option<DebitCredit>,
+
+    /// Accepted ISO 20022 purpose codes (e.g. SALA, SUPP); empty accepts any.
+    pub purpose_codes: Vec<String>,
+}
+
+impl Rule {
+    /// A rule assigning `category` to every transaction; narrow it down by
+    /// setting the condition fields.
+    pub fn new(category: impl Into<String>) -> Self {
+        Rule {
+            category: category.into(),
+            description: None,
+            counterparty: None,
+            min_amount: None,
+            max_amount: None,
+            direction: None,
+            purpose_codes: Vec::new(),
+        }
+    }
+
+    /// Whether `transaction` satisfies all conditions of the rule.
+    pub fn matches(&self, transaction: &Transaction) -> bool {
+        let counterparty = transaction.counterparty_name.as_deref().unwrap_or("");
+
+        self.description.as_ref().is_none_or(|re| re.is_match(&transaction.description))
+            && self.counterparty.as_ref().is_none_or(|re| re.is_match(counterparty))
+            && self.min_amount.is_none_or(|min| transaction.amount >= min)
+            && self.max_amount.is_none_or(|max| transaction.amount <= max)
+            && self.direction.is_none_or(|direction| transaction.debit_credit == direction)
+            && (self.purpose_codes.is_empty()
+                || transaction.purpose_code.as_ref().is_some_and(|code| {
+                    self.purpose_codes.iter().any(|c| c.eq_ignore_ascii_case(code))
+                }))
+    }
+}
+
+/// Ordered set of categorization rules.
+///
+/// # Examples
+///
+/// ```
+/// use regex::Regex;
+/// use ypbank_system::categorize::{Categorizer, Rule};
+///
+/// let mut rule = Rule::new("Taxes");
+/// rule.description = Some(Regex::new("(?i)налог")?);
+/// let categorizer = Categorizer::new(vec![rule]);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Categorizer {
+    rules: Vec<Rule>,
+}
+
+/// A rule as written in a TOML file.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleSpec {
+    category: String,
+    description: Option<String>,
+    counterparty: Option<String>,
+    min_amount: Option<Decimal>,
+    max_amount: Option<Decimal>,
+    direction: Option<String>,
+    #[serde(default)]
+    purpose_codes: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RulesFile {
+    #[serde(default)]
+    rule: Vec<RuleSpec>,
+}
+
+impl Categorizer {
+    /// Create a categorizer from rules in priority order.
+    pub fn new(rules: Vec<Rule>) -> Self {
+        Categorizer { rules }
+    }
+
+    /// Parse rules from a TOML document with one `[[rule]]` table per rule.
+    pub fn from_toml(toml: &str) -> Result<Self> {
+        let file: RulesFile = toml::from_str(toml)
+            .map_err(|e| Error::InvalidFormat(format!("Invalid categorization rules: {}", e), None))?;
+
+        let regex = |pattern: Option<String>| {
+            pattern
+                .map(|p| Regex::new(&p))
+                .transpose()
+                .map_err(|e| Error::InvalidFormat(format!("Invalid categorization pattern: {}", e), None))
+        };
+
+        let rules = file.rule.into_iter()
+            .map(|spec| {
+                Ok(Rule {
+                    description: regex(spec.description)?,
+                    counterparty: regex(spec.counterparty)?,
+                    min_amount: spec.min_amount,
+                    max_amount: spec.max_amount,
+                    direction: spec.direction
+                        .map(|d| d.parse::<DebitCredit>().map_err(|e| Error::InvalidFormat(e, None)))
+                        .transpose()?,
+                    purpose_codes: spec.purpose_codes,
+                    category: spec.category,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Categorizer { rules })
+    }
+
+    /// Read TOML rules from any source implementing `Read`.
+    pub fn from_read<R: Read>(reader: &mut R) -> Result<Self> {
+        let mut toml = String::new();
+        reader.read_to_string(&mut toml)?;
+        Self::from_toml(&toml)
+    }
+
+    /// Category of the first rule matching `transaction`.
+    pub fn category(&self, transaction: &Transaction) -> Option<&str> {
+        self.rules.iter()
+            .find(|rule| rule.matches(transaction))
+            .map(|rule| rule.category.as_str())
+    }
+
+    /// Set the category of every transaction of `statement` that matches a
+    /// rule; transactions matching none keep their current category.
+    pub fn apply(&self, statement: &mut Statement) {
+        for transaction in &mut statement.transactions {
+            if let Some(category) = self.category(transaction) {
+                transaction.category = Some(category.to_string());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Currency;
+    use chrono::NaiveDate;
+    use std::str::FromStr;
+
+    fn transaction(description: &str, amount: &str, debit_credit: DebitCredit) -> Transaction {
+        Transaction {
+            reference: "REF".into(),
+            date: NaiveDate::from_ymd_opt(2024, 2, 20).unwrap().into(),
+            amount: Decimal::from_str(amount).unwrap(),
+            currency: Currency::RUB,
+            debit_credit,
+            counterparty_name: Some("ООО Ромашка".into()),
+            description: description.into(),
+            ..Transaction::default()
+        }
+    }
+
+    #[test]
+    fn test_rules_from_toml() {
+        let categorizer = Categorizer::from_toml(
+            r#"
+            [[rule]]
+            category = "Rent"
+            description = "(?i)аренд"
+            direction = "debit"
+            min_amount = 10000
+
+            [[rule]]
+            category = "Suppliers"
+            counterparty = "Ромашка"
+            max_amount = "500.00"
+            "#,
+        )
+        .unwrap();
+
+        let rent = transaction("Оплата аренды за февраль", "50000.00", DebitCredit::Debit);
+        assert_eq!(categorizer.category(&rent), Some("Rent"));
+
+        let small = transaction("Оплата аренды за февраль", "300.00", DebitCredit::Debit);
+        assert_eq!(categorizer.category(&small), Some("Suppliers"));
+
+        let refund = transaction("Возврат аренды", "50000.00", DebitCredit::Credit);
+        assert_eq!(categorizer.category(&refund), None);
+    }
+
+    #[test]
+    fn test_invalid_rules() {
+        assert!(Categorizer::from_toml("[[rule]]\ncategory = \"X\"\ndescription = \"(\"").is_err());
+        assert!(Categorizer::from_toml("[[rule]]\ncategory = \"X\"\ndirection = \"up\"").is_err());
+        assert!(Categorizer::from_toml("[[rule]]\ncategory = \"X\"\ncolour = \"red\"").is_err());
+    }
+}
@@ -1,83 +1,1229 @@
 //! Format conversion traits.
 //!
 //! This module provides conversion between different financial formats
-//! using Rust's `From` trait.
+//! using Rust's `From` trait. Conversions that need tuning accept
+//! [`ConversionOptions`].
+//!
+//! The `From` conversions quietly adapt data the target format can't hold.
+//! Their `TryFrom` counterparts into [`Converted`] produce the same output,
+//! with fields over MT940's length limits cut by [`fit_mt940_limits`],
+//! together with a [`ConversionReport`] of everything that was dropped,
+//! truncated, folded into free text or synthesized by the time the output
+//! is written, and fail where the target format can't represent the
+//! statement at all:
+//!
+//! ```
+//! use ypbank_system::camt053_format::Camt053Statement;
+//! use ypbank_system::conversion::Converted;
+//! use ypbank_system::mt940_format::Mt940Statement;
+//! use ypbank_system::types::{Currency, Statement};
+//!
+//! let mut statement = Statement::new("1".into(), "ACC001", Currency::EUR);
+//! statement.account_holder = Some("ACME".into());
+//! let converted = Converted::<Mt940Statement>::try_from(Camt053Statement { statement })?;
+//! assert_eq!(converted.report.to_string(), "account_holder: dropped\n");
+//! # Ok::<(), ypbank_system::Error>(())
+//! ```
 
 use crate::camt053_format::Camt053Statement;
-use crate::mt940_format::Mt940Statement;
+use crate::csv_format::CsvStatement;
+use crate::error::{Error, Result, RuleId, ValidationError};
+use crate::format::FormatRegistry;
+use crate::mapping::Derivation;
+use crate::mt940_format::{self, Mt940Statement};
+use crate::types::{
+    AccountIdentifier, BankIdentifier, Charge, Currency, DebitCredit, EntryStatus, Iban, SourceFormat, Statement,
+    StructuredRemittance, Transaction, TransactionRefs,
+};
+use crate::Format;
+use chrono::NaiveDate;
+use regex::Regex;
+use rust_decimal::Decimal;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, LazyLock};
 
-/// Convert from MT940 to CAMT.053 format.
-impl From<Mt940Statement> for Camt053Statement {
-    fn from(mt940: Mt940Statement) -> Self {
-        // The conversion is straightforward since both formats
-        // use the same underlying Statement structure.
-        // Missing information in MT940 is represented with placeholders or None.
-        let mut statement = mt940.statement.clone();
+/// Options controlling how statement data is adapted between formats.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConversionOptions {
+    /// How transaction charges are represented in the target format.
+    ///
+    /// `None` keeps charges attached to their transactions unchanged.
+    pub charges: Option<ChargesPolicy>,
+
+    /// Entry statuses to drop from the output (e.g. pending entries for MT940).
+    pub excluded_statuses: Vec<EntryStatus>,
+
+    /// How batch bookings are flattened for formats without nesting.
+    ///
+    /// `None` keeps the details; MT940 and CSV writers then expand them.
+    pub batches: Option<BatchPolicy>,
+
+    /// How fields without an MT940 counterpart are folded into the `:86:`
+    /// text, and taken back out of it.
+    pub information: InformationMapping,
+
+    /// Caller-defined transformations run on every transaction, in order,
+    /// after all other options.
+    pub hooks: Vec<TransactionHook>,
+
+    /// Fill in missing balances before anything else, with
+    /// [`Statement::fill_missing_balances`]; MT940 readers reject statements
+    /// without `:60F:`/`:62F:`, which CSV exports usually lack.
+    pub synthesize_balances: bool,
+
+    /// Opening balance for synthesized balances when the statement states
+    /// none, negative for a debit balance.
+    pub opening_balance: Option<Decimal>,
+}
+
+/// Caller-defined transformation of a transaction during conversion.
+///
+/// # Examples
+///
+/// ```
+/// use ypbank_system::conversion::{ConversionOptions, TransactionHook};
+///
+/// let options = ConversionOptions {
+///     hooks: vec![TransactionHook::new(|tx| tx.reference = tx.reference.to_uppercase())],
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Clone)]
+pub struct TransactionHook(Arc<dyn Fn(&mut Transaction) + Send + Sync>, Option<Derivation>);
+
+impl TransactionHook {
+    /// Hook running `hook` on each transaction.
+    pub fn new(hook: impl Fn(&mut Transaction) + Send + Sync + 'static) -> Self {
+        TransactionHook(Arc::new(hook), None)
+    }
+
+    /// Fill in missing counterparty names from the description, taking the
+    /// `name` capture group of `pattern` (or the whole match without one).
+    pub fn counterparty_from_description(pattern: Regex) -> Self {
+        let derivation = Derivation::new(
+            "transactions[].counterparty_name",
+            &["transactions[].description"],
+            format!("matched by {} when missing", pattern),
+        );
+        let hook = TransactionHook::new(move |tx| {
+            if tx.counterparty_name.is_some() {
+                return;
+            }
+            if let Some(captures) = pattern.captures(&tx.description) {
+                let name = captures.name("name").or_else(|| captures.get(0)).map(|m| m.as_str().trim());
+                tx.counterparty_name = name.filter(|name| !name.is_empty()).map(str::to_string);
+            }
+        });
+        TransactionHook(hook.0, Some(derivation))
+    }
+
+    /// Rewrite references with [`Regex::replace_all`], e.g. `$1` for the
+    /// first capture group.
+    pub fn rewrite_reference(pattern: Regex, replacement: impl Into<String>) -> Self {
+        let replacement = replacement.into();
+        let derivation = Derivation::new(
+            "transactions[].reference",
+            &["transactions[].reference"],
+            format!("rewritten with {} => {:?}", pattern, replacement),
+        );
+        let hook = TransactionHook::new(move |tx| {
+            tx.reference = pattern.replace_all(&tx.reference, replacement.as_str()).into_owned();
+        });
+        TransactionHook(hook.0, Some(derivation))
+    }
+
+    /// Run the hook on `transaction`.
+    pub fn apply(&self, transaction: &mut Transaction) {
+        (self.0)(transaction)
+    }
+
+    /// Field the hook derives and from what, for [`crate::mapping`].
+    pub(crate) fn derivation(&self) -> Option<&Derivation> {
+        self.1.as_ref()
+    }
+}
+
+impl fmt::Debug for TransactionHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("TransactionHook")
+    }
+}
+
+/// Hooks are equal when they are clones of the same hook.
+impl PartialEq for TransactionHook {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for TransactionHook {}
+
+/// A field folded into the MT940 `:86:` text after the description.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InformationPart {
+    /// [`Transaction::additional_info`], as is.
+    AdditionalInfo,
+    /// [`Transaction::counterparty_name`], after the counterparty label.
+    Counterparty,
+    /// [`Transaction::purpose_code`], as `/PURP/<code>`.
+    PurposeCode,
+    /// Creditor reference of the structured remittance information, as
+    /// `/RFB/<reference>`.
+    CreditorReference,
+}
+
+/// Layout of the MT940 `:86:` text built from CAMT.053 fields.
+///
+/// The default reproduces the historical layout,
+/// `Description | Extra info | Counterparty: ACME | /PURP/SALA | /RFB/RF18...`.
+///
+/// # Examples
+///
+/// ```
+/// use ypbank_system::conversion::{InformationMapping, InformationPart};
+///
+/// // "Description; CP=ACME", without additional info or codes
+/// let mapping = InformationMapping {
+///     separator: "; ".into(),
+///     parts: vec![InformationPart::Counterparty],
+///     counterparty_label: "CP=".into(),
+///     decompose: true,
+/// };
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InformationMapping {
+    /// Separator between the description and each folded part.
+    pub separator: String,
+
+    /// Parts folded in, in this order; parts left out are dropped.
+    pub parts: Vec<InformationPart>,
+
+    /// Text preceding the counterparty name.
+    pub counterparty_label: String,
+
+    /// Whether MT940 to CAMT.053 conversion splits `:86:` text laid out
+    /// this way back into its fields.
+    pub decompose: bool,
+}
+
+impl Default for InformationMapping {
+    fn default() -> Self {
+        InformationMapping {
+            separator: " | ".to_string(),
+            parts: vec![
+                InformationPart::AdditionalInfo,
+                InformationPart::Counterparty,
+                InformationPart::PurposeCode,
+                InformationPart::CreditorReference,
+            ],
+            counterparty_label: "Counterparty: ".to_string(),
+            decompose: false,
+        }
+    }
+}
+
+impl InformationMapping {
+    /// Fold the parts into the description of `transaction`.
+    pub fn fold(&self, transaction: &mut Transaction) {
+        for part in &self.parts {
+            let text = match part {
+                InformationPart::AdditionalInfo => transaction.additional_info.clone(),
+                InformationPart::Counterparty => transaction.counterparty_name.as_ref()
+                    .map(|name| format!("{}{}", self.counterparty_label, name)),
+                InformationPart::PurposeCode => transaction.purpose_code.as_ref().map(|code| format!("/PURP/{}", code)),
+                InformationPart::CreditorReference => creditor_reference(transaction).map(|r| format!("/RFB/{}", r)),
+            };
+            if let Some(text) = text {
+                if !transaction.description.is_empty() {
+                    transaction.description.push_str(&self.separator);
+                }
+                transaction.description.push_str(&text);
+            }
+        }
+    }
+
+    /// Split a description folded by [`InformationMapping::fold`] back into
+    /// its fields; fields already set are left alone.
+    ///
+    /// The first segment stays the description and segments recognized by
+    /// their label or code fill their field, the rest becoming additional
+    /// info.
+    pub fn decompose(&self, transaction: &mut Transaction) {
+        if self.separator.is_empty() {
+            return;
+        }
+        let text = std::mem::take(&mut transaction.description);
+        let mut segments = text.split(self.separator.as_str());
+        transaction.description = segments.next().unwrap_or_default().to_string();
+
+        let mut additional = Vec::new();
+        for segment in segments {
+            let wanted = |part| self.parts.contains(&part);
+            if let (true, Some(name)) = (wanted(InformationPart::Counterparty), segment.strip_prefix(self.counterparty_label.as_str())) {
+                transaction.counterparty_name.get_or_insert_with(|| name.to_string());
+            } else if let (true, Some(code)) = (wanted(InformationPart::PurposeCode), segment.strip_prefix("/PURP/")) {
+                transaction.purpose_code.get_or_insert_with(|| code.to_string());
+            } else if let (true, Some(reference)) = (wanted(InformationPart::CreditorReference), segment.strip_prefix("/RFB/")) {
+                transaction.structured_remittance.get_or_insert_with(|| StructuredRemittance {
+                    reference_type: None,
+                    reference: Some(reference.to_string()),
+                    referred_documents: Vec::new(),
+                });
+            } else {
+                additional.push(segment);
+            }
+        }
+        if !additional.is_empty() && transaction.additional_info.is_none() {
+            transaction.additional_info = Some(additional.join(&self.separator));
+        } else if !additional.is_empty() {
+            // Nowhere to put them, so keep them where they were
+            transaction.description.push_str(&self.separator);
+            transaction.description.push_str(&additional.join(&self.separator));
+        }
+    }
+}
+
+fn creditor_reference(transaction: &Transaction) -> Option<&String> {
+    let strd = transaction.structured_remittance.as_ref()?;
+    strd.reference.as_ref().or_else(|| strd.referred_documents.iter().find_map(|d| d.number.as_ref()))
+}
+
+/// Representation of batch bookings for formats that can't nest entries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatchPolicy {
+    /// One entry per underlying payment, see [`Transaction::expand_details`].
+    Expand,
+    /// A single entry for the batch total, dropping the underlying payments.
+    Collapse,
+}
+
+impl FromStr for BatchPolicy {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "expand" | "split" => Ok(BatchPolicy::Expand),
+            "collapse" | "total" => Ok(BatchPolicy::Collapse),
+            _ => Err(Error::ConversionError(format!("Unknown batch policy: {}", s), None)),
+        }
+    }
+}
+
+/// Representation of charges for formats without a dedicated charges element.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChargesPolicy {
+    /// Fold charge amounts into the amount of the entry they belong to.
+    Merge,
+    /// Emit each charge as a separate synthetic transaction.
+    SeparateEntries,
+    /// Drop charges and keep the entry amount as booked.
+    Ignore,
+}
+
+impl FromStr for ChargesPolicy {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "merge" | "net" => Ok(ChargesPolicy::Merge),
+            "separate" | "separate-entries" => Ok(ChargesPolicy::SeparateEntries),
+            "ignore" | "drop" => Ok(ChargesPolicy::Ignore),
+            _ => Err(Error::ConversionError(format!("Unknown charges policy: {}", s), None)),
+        }
+    }
+}
+
+/// Handling of fields longer than MT940 allows, see [`fit_mt940_limits`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TruncationPolicy {
+    /// Cut such fields to the maximum length and report them.
+    #[default]
+    Truncate,
+    /// Fail on the first such field.
+    Reject,
+}
+
+impl FromStr for TruncationPolicy {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "truncate" | "cut" => Ok(TruncationPolicy::Truncate),
+            "reject" | "fail" => Ok(TruncationPolicy::Reject),
+            _ => Err(Error::ConversionError(format!("Unknown truncation policy: {}", s), None)),
+        }
+    }
+}
+
+/// What a conversion did to a field the target format can't hold as is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LossKind {
+    /// Left out of the output.
+    Dropped,
+    /// Kept only in part, e.g. a timestamp reduced to its date.
+    Truncated,
+    /// Moved into free text, e.g. the MT940 `:86:` line, where it no longer
+    /// has a field of its own.
+    Folded,
+    /// Not in the source and made up to satisfy the target format, or
+    /// replaced by made-up entries, e.g. a batch by one entry per payment.
+    Synthesized,
+}
+
+impl fmt::Display for LossKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LossKind::Dropped => "dropped",
+            LossKind::Truncated => "truncated",
+            LossKind::Folded => "folded into the description",
+            LossKind::Synthesized => "synthesized",
+        })
+    }
+}
+
+/// A field a conversion didn't carry over faithfully.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConversionLoss {
+    /// Reference of the transaction the field belongs to, `None` for
+    /// statement fields.
+    pub transaction: Option<String>,
+
+    /// Name of the field in [`Statement`] or [`Transaction`].
+    pub field: &'static str,
+
+    /// What happened to the field.
+    pub kind: LossKind,
+}
+
+impl fmt::Display for ConversionLoss {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.transaction {
+            Some(ref reference) => write!(f, "transaction {}: {}: {}", reference, self.field, self.kind),
+            None => write!(f, "{}: {}", self.field, self.kind),
+        }
+    }
+}
+
+/// Every field a conversion dropped, truncated, folded or synthesized, in
+/// statement order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConversionReport {
+    /// The individual losses.
+    pub losses: Vec<ConversionLoss>,
+}
+
+impl ConversionReport {
+    /// Whether the conversion carried every field over as it was.
+    pub fn is_lossless(&self) -> bool {
+        self.losses.is_empty()
+    }
+
+    fn statement(&mut self, present: bool, field: &'static str, kind: LossKind) {
+        if present {
+            self.losses.push(ConversionLoss { transaction: None, field, kind });
+        }
+    }
+
+    fn transaction(&mut self, transaction: &Transaction, present: bool, field: &'static str, kind: LossKind) {
+        if present {
+            self.losses.push(ConversionLoss { transaction: Some(transaction.reference.clone()), field, kind });
+        }
+    }
+}
+
+impl fmt::Display for ConversionReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for loss in &self.losses {
+            writeln!(f, "{}", loss)?;
+        }
+        Ok(())
+    }
+}
+
+/// Result of a reporting conversion: the converted statement and what got
+/// lost on the way.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Converted<T> {
+    /// The converted statement, as the `From` conversion produces it.
+    pub output: T,
+
+    /// Fields that weren't carried over faithfully.
+    pub report: ConversionReport,
+}
+
+/// Apply conversion options to a statement in place.
+pub fn apply_options(statement: &mut Statement, options: &ConversionOptions) {
+    if options.synthesize_balances {
+        statement.fill_missing_balances(options.opening_balance);
+    }
+    if !options.excluded_statuses.is_empty() {
+        statement.transactions.retain(|tx| !options.excluded_statuses.contains(&tx.status));
+    }
+    match options.batches {
+        Some(BatchPolicy::Expand) => {
+            statement.transactions = statement.transactions.iter().flat_map(Transaction::expand_details).collect();
+        }
+        Some(BatchPolicy::Collapse) => {
+            for transaction in &mut statement.transactions {
+                transaction.details.clear();
+            }
+        }
+        None => {}
+    }
+    if let Some(policy) = options.charges {
+        apply_charges_policy(statement, policy);
+    }
+    for transaction in &mut statement.transactions {
+        for hook in &options.hooks {
+            hook.apply(transaction);
+        }
+    }
+}
+
+/// Exchange rates for [`convert_currency`], as units of the target currency
+/// per unit of the source currency.
+///
+/// A rate applies from its date on until the next rate of the same pair;
+/// an undated rate applies before the first dated one. The inverse of a
+/// pair is used when only the opposite direction is known.
+///
+/// # Examples
+///
+/// ```
+/// use chrono::NaiveDate;
+/// use rust_decimal::Decimal;
+/// use ypbank_system::conversion::RateTable;
+/// use ypbank_system::types::Currency;
+///
+/// let mut rates = RateTable::new();
+/// rates.insert(Currency::USD, Currency::RUB, Decimal::new(90, 0));
+/// rates.insert_on(Currency::USD, Currency::RUB, NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(), Decimal::new(92, 0));
+///
+/// let february = NaiveDate::from_ymd_opt(2024, 2, 20).unwrap();
+/// assert_eq!(rates.rate(Currency::USD, Currency::RUB, february), Some(Decimal::new(90, 0)));
+/// assert_eq!(rates.rate(Currency::RUB, Currency::USD, february).map(|r| r.round_dp(4)), Some(Decimal::new(111, 4)));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RateTable {
+    rates: HashMap<(Currency, Currency), BTreeMap<Option<NaiveDate>, Decimal>>,
+}
+
+impl RateTable {
+    /// Table without any rate.
+    pub fn new() -> Self {
+        RateTable::default()
+    }
+
+    /// Set the rate from `from` to `to` regardless of date.
+    pub fn insert(&mut self, from: Currency, to: Currency, rate: Decimal) {
+        self.rates.entry((from, to)).or_default().insert(None, rate);
+    }
+
+    /// Set the rate from `from` to `to` applying from `date` on.
+    pub fn insert_on(&mut self, from: Currency, to: Currency, date: NaiveDate, rate: Decimal) {
+        self.rates.entry((from, to)).or_default().insert(Some(date), rate);
+    }
+
+    /// Rate from `from` to `to` on `date`; 1 between a currency and itself.
+    pub fn rate(&self, from: Currency, to: Currency, date: NaiveDate) -> Option<Decimal> {
+        let lookup = |pair| {
+            let rates: &BTreeMap<Option<NaiveDate>, Decimal> = self.rates.get(&pair)?;
+            rates.range(..=Some(date)).next_back().map(|(_, rate)| *rate)
+        };
+        if from == to {
+            return Some(Decimal::ONE);
+        }
+        lookup((from, to)).or_else(|| lookup((to, from)).filter(|rate| !rate.is_zero()).map(|rate| Decimal::ONE / rate))
+    }
+}
+
+/// Restate `statement` in `target` currency using `rates`, e.g. to report a
+/// foreign-currency account in the reporting currency.
+///
+/// Every amount is converted at the rate of its own date (the booking date
+/// for transactions) and rounded to the target currency's minor unit.
+/// Transactions record the amount, currency and rate they had before
+/// unless they already carry an original amount, which is kept, with the
+/// rate then chained on. Since balances and transactions may be converted
+/// at different rates, the result reconciles only under a single rate.
+///
+/// Fails without changing the statement when a rate is missing.
+///
+/// # Examples
+///
+/// ```
+/// use rust_decimal::Decimal;
+/// use ypbank_system::conversion::{convert_currency, RateTable};
+/// use ypbank_system::types::{Currency, Statement};
+///
+/// let mut statement = Statement::new("1".into(), "ACC001", Currency::USD);
+/// let mut rates = RateTable::new();
+/// rates.insert(Currency::USD, Currency::RUB, Decimal::new(90, 0));
+/// convert_currency(&mut statement, Currency::RUB, &rates)?;
+/// assert_eq!(statement.currency, Currency::RUB);
+/// # Ok::<(), ypbank_system::Error>(())
+/// ```
+pub fn convert_currency(statement: &mut Statement, target: Currency, rates: &RateTable) -> Result<()> {
+    let convert = |amount: Decimal, currency: Currency, date: NaiveDate| -> Result<(Decimal, Decimal)> {
+        let rate = rates.rate(currency, target, date).ok_or_else(|| {
+            Error::ConversionError(format!("No exchange rate from {} to {} on {}", currency, target, date), None)
+        })?;
+        Ok((target.round(amount * rate), rate))
+    };
+
+    let mut converted = statement.clone();
+    for balance in converted.opening_balance.iter_mut().chain(converted.closing_balance.iter_mut()) {
+        balance.amount = convert(balance.amount, balance.currency, balance.date)?.0;
+        balance.currency = target;
+    }
+    for tx in &mut converted.transactions {
+        let (amount, rate) = convert(tx.amount, tx.currency, tx.date.date())?;
+        if let Some(ref mut balance) = tx.running_balance {
+            *balance = convert(*balance, statement.currency, tx.date.date())?.0;
+        }
+        for availability in &mut tx.funds_availability {
+            availability.amount = convert(availability.amount, tx.currency, tx.date.date())?.0;
+        }
+        for charge in &mut tx.charges {
+            charge.amount = convert(charge.amount, charge.currency, tx.date.date())?.0;
+            charge.currency = target;
+        }
+        for detail in &mut tx.details {
+            if let Some(ref mut detail_amount) = detail.amount {
+                *detail_amount = convert(*detail_amount, detail.currency.unwrap_or(tx.currency), tx.date.date())?.0;
+                detail.currency = detail.currency.map(|_| target);
+            }
+        }
+
+        if tx.currency != target {
+            if tx.original_amount.is_none() {
+                tx.original_amount = Some(tx.amount);
+                tx.original_currency = Some(tx.currency);
+                tx.exchange_rate = Some(rate);
+            } else {
+                tx.exchange_rate = tx.exchange_rate.map(|previous| previous * rate);
+            }
+        }
+        tx.amount = amount;
+        tx.currency = target;
+    }
+    converted.currency = target;
+
+    *statement = converted;
+    Ok(())
+}
+
+/// Convert CAMT.053 to MT940 using explicit conversion options.
+///
+/// Entries read from MT940 in the first place and unchanged since keep
+/// their original `:61:` and `:86:` lines, see [`Transaction::source_raw`].
+pub fn camt053_to_mt940(camt053: Camt053Statement, options: &ConversionOptions) -> Mt940Statement {
+    // Information that doesn't fit in MT940 goes into the :86: field
+    // (Information to Account Owner)
+    let mut statement = camt053.statement;
+    for transaction in &mut statement.transactions {
+        // Entries first read from MT940 keep their original :86: text
+        if transaction.unchanged_source_raw(SourceFormat::Mt940).is_none() {
+            options.information.fold(transaction);
+        }
+    }
+    apply_options(&mut statement, options);
+    Mt940Statement { statement }
+}
+
+/// Fit a statement into the field lengths of MT940, which SWIFT validators
+/// enforce: 16 characters for the statement and transaction references
+/// and 6 lines of 65 characters for the `:86:` text.
+///
+/// Depending on `policy`, over-length fields are cut and listed in the
+/// returned report as truncated, or the first one fails the conversion.
+/// Account identifications over 35 characters always fail, since a
+/// shortened one would name a different account.
+///
+/// # Examples
+///
+/// ```
+/// use ypbank_system::conversion::{fit_mt940_limits, TruncationPolicy};
+/// use ypbank_system::types::{Currency, Statement};
+///
+/// let mut statement = Statement::new("STATEMENT-2024-01-31".into(), "ACC001", Currency::EUR);
+/// assert!(fit_mt940_limits(&mut statement.clone(), TruncationPolicy::Reject).is_err());
+///
+/// let report = fit_mt940_limits(&mut statement, TruncationPolicy::Truncate)?;
+/// assert_eq!(statement.statement_id, "STATEMENT-2024-0");
+/// assert_eq!(report.to_string(), "statement_id: truncated\n");
+/// # Ok::<(), ypbank_system::Error>(())
+/// ```
+pub fn fit_mt940_limits(statement: &mut Statement, policy: TruncationPolicy) -> Result<ConversionReport> {
+    if statement.account.id().chars().count() > mt940_format::ACCOUNT_LENGTH {
+        let message = format!(
+            "Account {} is longer than the {} characters MT940 allows",
+            statement.account, mt940_format::ACCOUNT_LENGTH
+        );
+        return Err(ValidationError::new(RuleId::Mt940AccountLength, message).at_path("account").into());
+    }
+
+    let mut report = ConversionReport::default();
+    // `path` locates the field in the statement, e.g. transactions[3].reference
+    let mut fit = |value: &mut String, transaction: Option<&str>, field: &'static str, path: String, fits: fn(&str) -> Option<String>| {
+        let Some(shortened) = fits(value) else { return Ok(()) };
+        if policy == TruncationPolicy::Reject {
+            let message = match transaction {
+                Some(reference) => format!("Transaction {}: {} is too long for MT940", reference, field),
+                None => format!("{} is too long for MT940", field),
+            };
+            return Err(Error::from(ValidationError::new(RuleId::Mt940FieldLength, message).at_path(path)));
+        }
+        report.losses.push(ConversionLoss { transaction: transaction.map(str::to_string), field, kind: LossKind::Truncated });
+        *value = shortened;
+        Ok(())
+    };
+
+    fit(&mut statement.statement_id, None, "statement_id", "statement_id".to_string(), shorten_reference)?;
+    for (n, tx) in statement.transactions.iter_mut().enumerate() {
+        // Report under the reference the source statement used
+        let reference = tx.reference.clone();
+        let path = |field: &str| format!("transactions[{}].{}", n, field);
+        fit(&mut tx.reference, Some(&reference), "reference", path("reference"), shorten_reference)?;
+        fit(&mut tx.description, Some(&reference), "description", path("description"), shorten_narrative)?;
+        // Expanded payments without a reference of their own get one
+        // derived from the entry's, see Transaction::expand_details
+        let expanded = tx.is_expandable();
+        for (i, detail) in tx.details.iter_mut().enumerate() {
+            if expanded && detail.reference.is_none() {
+                let derived = format!("{}/{}", tx.reference, i + 1);
+                if shorten_reference(&derived).is_some() {
+                    detail.reference = Some(derived);
+                }
+            }
+            if let Some(ref mut detail_reference) = detail.reference {
+                fit(detail_reference, Some(&reference), "details.reference", path(&format!("details[{}].reference", i)), shorten_reference)?;
+            }
+            fit(&mut detail.description, Some(&reference), "details.description", path(&format!("details[{}].description", i)), shorten_narrative)?;
+        }
+    }
+    Ok(report)
+}
+
+/// The reference cut to MT940's length, if it is longer.
+fn shorten_reference(reference: &str) -> Option<String> {
+    (reference.chars().count() > mt940_format::REFERENCE_LENGTH)
+        .then(|| reference.chars().take(mt940_format::REFERENCE_LENGTH).collect())
+}
+
+/// The `:86:` text cut to the lines MT940 allows, if it needs more.
+fn shorten_narrative(narrative: &str) -> Option<String> {
+    let lines = mt940_format::wrap_narrative(narrative);
+    (lines.len() > mt940_format::NARRATIVE_LINES).then(|| lines[..mt940_format::NARRATIVE_LINES].join(" "))
+}
+
+/// Convert MT940 to CAMT.053 using explicit conversion options.
+pub fn mt940_to_camt053(mt940: Mt940Statement, options: &ConversionOptions) -> Camt053Statement {
+    let mut statement = mt940.statement;
+
+    // Ensure statement has creation date
+    if statement.creation_date.is_none() {
+        statement.creation_date = Some(chrono::Utc::now().date_naive().into());
+    }
+    for transaction in &mut statement.transactions {
+        if options.information.decompose {
+            options.information.decompose(transaction);
+        }
+        recover_counterparty(transaction);
+    }
+    apply_options(&mut statement, options);
+    Camt053Statement { statement }
+}
+
+/// Convert CSV to CAMT.053 using explicit conversion options.
+///
+/// Every entry names both parties: the counterparty from the CSV row, and
+/// the account holder with the row's own account (the debit account of a
+/// debit, the credit account of a credit), so the `Dbtr`/`Cdtr` related
+/// parties match the CSV's debit and credit account columns.
+///
+/// # Examples
+///
+/// ```
+/// use ypbank_system::camt053_format::Camt053Statement;
+/// use ypbank_system::conversion::{csv_to_camt053, ConversionOptions};
+/// use ypbank_system::csv_format::{CsvProfile, CsvStatement};
+///
+/// let input = "Дата проводки,Счет Дебет,Счет Кредит,Сумма по дебету,Сумма по кредиту\n\
+///              20.02.2024,40702810000000000001,40817810000000000009,100.00,\n";
+/// let csv = CsvStatement::from_read_with(&mut input.as_bytes(), &CsvProfile::default())?;
+/// let camt053 = csv_to_camt053(csv, &ConversionOptions::default());
+///
+/// let mut xml = Vec::new();
+/// camt053.write_to(&mut xml)?;
+/// let xml = String::from_utf8(xml).unwrap();
+/// assert!(xml.contains("<DbtrAcct><Id><Othr><Id>40702810000000000001</Id>"));
+/// assert!(xml.contains("<CdtrAcct><Id><Othr><Id>40817810000000000009</Id>"));
+/// # Ok::<(), ypbank_system::Error>(())
+/// ```
+pub fn csv_to_camt053(csv: CsvStatement, options: &ConversionOptions) -> Camt053Statement {
+    let mut statement = csv.statement;
+
+    if statement.creation_date.is_none() {
+        statement.creation_date = Some(chrono::Utc::now().date_naive().into());
+    }
+    let own_account = statement.account.to_string();
+    for transaction in &mut statement.transactions {
+        transaction.account.get_or_insert_with(|| own_account.clone());
+    }
+    apply_options(&mut statement, options);
+    Camt053Statement { statement }
+}
+
+/// Convert CAMT.053 to CSV using explicit conversion options.
+///
+/// The account holder's side of each entry's related parties goes to the
+/// debit account column of a debit and the credit account column of a
+/// credit, the counterparty's to the other one; write the result with
+/// [`CsvStatement::write_to_with`] for a specific [`CsvProfile`](crate::csv_format::CsvProfile).
+pub fn camt053_to_csv(camt053: Camt053Statement, options: &ConversionOptions) -> CsvStatement {
+    let mut statement = camt053.statement;
+
+    // The CSV writer falls back to the statement's account
+    let own_account = statement.account.to_string();
+    for transaction in &mut statement.transactions {
+        if transaction.account.as_deref() == Some(own_account.as_str()) {
+            transaction.account = None;
+        }
+    }
+    apply_options(&mut statement, options);
+    CsvStatement { statement }
+}
+
+/// Fill in the counterparty name, account and bank of an MT940 transaction
+/// from its `:86:` narrative, so CAMT.053 can write them as related parties
+/// and agents rather than only as unstructured text.
+///
+/// Recognizes SWIFT-style codes (`/CRNM/`, `/DBNM/`, `/NAME/` for the name,
+/// `/CACT/`, `/DACT/`, `/IBAN/`, `/ACCT/` for the account, `/CBIC/`,
+/// `/DBIC/`, `/BIC/` for the bank), German structured subfields (`?30` bank,
+/// `?31` account, `?32`/`?33` name) and, in free text, a valid IBAN. The
+/// creditor's codes are preferred for debits and the debtor's for credits.
+/// Fields already set are left alone and the description is kept as is.
+///
+/// # Examples
+///
+/// ```
+/// # use ypbank_system::mt940_format::Mt940Statement;
+/// use ypbank_system::conversion::recover_counterparty;
+///
+/// let input = ":20:1\n:25:ACC\n:60F:C240101EUR0,00\n:61:240102D10,00NTRF//R1\n\
+///              :86:/CRNM/ACME BV/CACT/NL91ABNA0417164300/CBIC/ABNANL2A/REMI/Invoice 12\n";
+/// let mut tx = Mt940Statement::from_read(&mut input.as_bytes())?.statement.transactions.remove(0);
+/// recover_counterparty(&mut tx);
+/// assert_eq!(tx.counterparty_name.as_deref(), Some("ACME BV"));
+/// assert_eq!(tx.counterparty_account.unwrap().id(), "NL91ABNA0417164300");
+/// assert_eq!(tx.bank_identifier.unwrap().id(), "ABNANL2A");
+/// # Ok::<(), ypbank_system::Error>(())
+/// ```
+pub fn recover_counterparty(transaction: &mut Transaction) {
+    let fields = narrative_fields(&transaction.description);
+    let field = |codes: &[&str]| {
+        codes.iter().find_map(|code| fields.iter().find(|(c, v)| c == code && !v.is_empty()).map(|(_, v)| v.clone()))
+    };
+    let (name, account, bank) = match transaction.debit_credit {
+        DebitCredit::Debit => (
+            field(&["CRNM", "NAME", "32"]),
+            field(&["CACT", "IBAN", "ACCT", "31"]),
+            field(&["CBIC", "BIC", "30"]),
+        ),
+        DebitCredit::Credit => (
+            field(&["DBNM", "NAME", "32"]),
+            field(&["DACT", "IBAN", "ACCT", "31"]),
+            field(&["DBIC", "BIC", "30"]),
+        ),
+    };
 
-        // Ensure statement has creation date
-        if statement.creation_date.is_none() {
-            statement.creation_date = Some(chrono::Utc::now().date_naive());
+    if transaction.counterparty_name.is_none() {
+        // ?33 continues the name of ?32
+        transaction.counterparty_name = name.map(|name| match field(&["33"]) {
+            Some(rest) if fields.iter().any(|(c, _)| c == "32") => format!("{} {}", name, rest),
+            _ => name,
+        });
+    }
+    if transaction.counterparty_account.is_none() {
+        transaction.counterparty_account = account.map(AccountIdentifier::from).or_else(|| {
+            transaction.description
+                .split(|c: char| c.is_whitespace() || c == '/')
+                .find_map(|token| token.parse::<Iban>().ok())
+                .map(AccountIdentifier::from)
+        });
+    }
+    if transaction.bank_identifier.is_none() {
+        transaction.bank_identifier = bank.and_then(|bank| bank.parse::<BankIdentifier>().ok());
+    }
+}
+
+/// `(code, value)` pairs of an `:86:` narrative: `/CODE/value` subfields
+/// or `?NN` German structured subfields.
+fn narrative_fields(narrative: &str) -> Vec<(String, String)> {
+    static SWIFT_CODES: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"/([A-Z]{3,4})/").expect("valid pattern"));
+    static GERMAN_SUBFIELDS: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\?(\d{2})").expect("valid pattern"));
+    let pattern = if narrative.contains("?3") || narrative.contains("?2") { &GERMAN_SUBFIELDS } else { &SWIFT_CODES };
+
+    let matches: Vec<_> = pattern.captures_iter(narrative).filter_map(|c| Some((c.get(0)?, c.get(1)?))).collect();
+    matches.iter()
+        .enumerate()
+        .map(|(i, (whole, code))| {
+            let end = matches.get(i + 1).map_or(narrative.len(), |(next, _)| next.start());
+            let value = narrative[whole.end()..end].trim().trim_end_matches('/').trim();
+            (code.as_str().to_string(), value.to_string())
+        })
+        .collect()
+}
+
+fn apply_charges_policy(statement: &mut Statement, policy: ChargesPolicy) {
+    let transactions = std::mem::take(&mut statement.transactions);
+
+    for mut transaction in transactions {
+        let charges = std::mem::take(&mut transaction.charges);
+
+        match policy {
+            ChargesPolicy::Merge => {
+                let mut net = transaction.signed_amount();
+                for charge in charges {
+                    if charge.currency == transaction.currency {
+                        net += signed(charge.amount, charge.debit_credit);
+                    } else {
+                        // Charges in another currency cannot be netted
+                        transaction.charges.push(charge);
+                    }
+                }
+                transaction.amount = net.abs();
+                if net < Decimal::ZERO {
+                    transaction.debit_credit = DebitCredit::Debit;
+                } else if net > Decimal::ZERO {
+                    transaction.debit_credit = DebitCredit::Credit;
+                }
+                statement.transactions.push(transaction);
+            }
+            ChargesPolicy::SeparateEntries => {
+                let charge_entries: Vec<Transaction> = charges
+                    .iter()
+                    .enumerate()
+                    .map(|(i, charge)| charge_transaction(&transaction, charge, i + 1))
+                    .collect();
+                statement.transactions.push(transaction);
+                statement.transactions.extend(charge_entries);
+            }
+            ChargesPolicy::Ignore => statement.transactions.push(transaction),
         }
+    }
+}
 
-        Camt053Statement { statement }
+fn charge_transaction(parent: &Transaction, charge: &Charge, index: usize) -> Transaction {
+    let mut description = String::from("Charges");
+    if let Some(ref charge_type) = charge.charge_type {
+        description.push(' ');
+        description.push_str(charge_type);
+    }
+    description.push_str(" for ");
+    description.push_str(&parent.reference);
+
+    Transaction {
+        reference: format!("{}-CHG{}", parent.reference, index),
+        transaction_type: Some("NCHG".to_string()),
+        date: parent.date,
+        value_date: parent.value_date,
+        amount: charge.amount,
+        currency: charge.currency,
+        debit_credit: charge.debit_credit,
+        account: parent.account.clone(),
+        description,
+        additional_info: charge.bearer.as_ref().map(|br| format!("Charge bearer: {}", br)),
+        status: parent.status,
+        ..Transaction::default()
+    }
+}
+
+fn signed(amount: Decimal, debit_credit: DebitCredit) -> Decimal {
+    match debit_credit {
+        DebitCredit::Debit => -amount,
+        DebitCredit::Credit => amount,
+    }
+}
+
+/// Convert from MT940 to CAMT.053 format.
+impl From<Mt940Statement> for Camt053Statement {
+    fn from(mt940: Mt940Statement) -> Self {
+        // Both formats use the same underlying Statement structure; missing
+        // information in MT940 is represented with placeholders or None
+        mt940_to_camt053(mt940, &ConversionOptions::default())
     }
 }
 
 /// Convert from CAMT.053 to MT940 format.
 impl From<Camt053Statement> for Mt940Statement {
     fn from(camt053: Camt053Statement) -> Self {
-        // When converting from CAMT.053 to MT940, some information
-        // that doesn't fit in MT940 can be placed in the :86: field
-        // (Information to Account Owner).
-        let mut statement = camt053.statement.clone();
-
-        // Combine additional info into transaction descriptions for MT940
-        for transaction in &mut statement.transactions {
-            if let Some(ref addtl) = transaction.additional_info {
-                if !transaction.description.is_empty() {
-                    transaction.description.push_str(" | ");
-                }
-                transaction.description.push_str(addtl);
+        camt053_to_mt940(camt053, &ConversionOptions::default())
+    }
+}
+
+/// Convert from CSV to CAMT.053 format.
+impl From<CsvStatement> for Camt053Statement {
+    fn from(csv: CsvStatement) -> Self {
+        csv_to_camt053(csv, &ConversionOptions::default())
+    }
+}
+
+/// Convert from CAMT.053 to CSV format.
+impl From<Camt053Statement> for CsvStatement {
+    fn from(camt053: Camt053Statement) -> Self {
+        camt053_to_csv(camt053, &ConversionOptions::default())
+    }
+}
+
+/// Convert from MT940 to CAMT.053, reporting the fields CAMT.053 had to
+/// make up.
+impl TryFrom<Mt940Statement> for Converted<Camt053Statement> {
+    type Error = Error;
+
+    fn try_from(mt940: Mt940Statement) -> Result<Self> {
+        let mut report = ConversionReport::default();
+        report.statement(mt940.statement.creation_date.is_none(), "creation_date", LossKind::Synthesized);
+        for tx in &mt940.statement.transactions {
+            report_annotations(&mut report, tx);
+        }
+
+        Ok(Converted { output: mt940.into(), report })
+    }
+}
+
+/// Convert from CAMT.053 to MT940, reporting everything MT940 can't hold.
+///
+/// Unlike the `From` conversion, cuts over-length fields with
+/// [`fit_mt940_limits`]. Fails for statements with transactions in a
+/// currency other than the account's, which an MT940 message can't
+/// express; see [`Statement::by_currency`].
+impl TryFrom<Camt053Statement> for Converted<Mt940Statement> {
+    type Error = Error;
+
+    fn try_from(camt053: Camt053Statement) -> Result<Self> {
+        let stmt = &camt053.statement;
+        if let Some(tx) = stmt.transactions.iter().find(|tx| tx.currency != stmt.currency) {
+            return Err(Error::ConversionError(format!(
+                "Transaction {} is in {}, but MT940 statement {} is in {}",
+                tx.reference, tx.currency, stmt.statement_id, stmt.currency
+            ), None));
+        }
+
+        let mut report = ConversionReport::default();
+        report.statement(stmt.account_holder.is_some(), "account_holder", LossKind::Dropped);
+        report.statement(stmt.account_owner.is_some(), "account_owner", LossKind::Dropped);
+        report.statement(stmt.creation_date.is_some(), "creation_date", LossKind::Dropped);
+        report.statement(stmt.from_date.is_some() || stmt.to_date.is_some(), "period", LossKind::Dropped);
+        report.statement(stmt.pagination.is_some(), "pagination", LossKind::Dropped);
+        // Only the BIC fits, as the sender of the message
+        report.statement(
+            stmt.servicing_bank.as_ref().is_some_and(|bank| {
+                bank.name.is_some() || bank.address.is_some() || bank.clearing_code.is_some()
+            }),
+            "servicing_bank",
+            LossKind::Truncated,
+        );
+
+        let information = InformationMapping::default();
+        let folded = |part| if information.parts.contains(&part) { LossKind::Folded } else { LossKind::Dropped };
+        for tx in &stmt.transactions {
+            // :61: has dates only
+            report.transaction(tx, tx.date.time().is_some(), "date", LossKind::Truncated);
+            report.transaction(tx, tx.value_date.is_some_and(|date| date.time().is_some()), "value_date", LossKind::Truncated);
+            report.transaction(tx, tx.account.is_some(), "account", LossKind::Dropped);
+            report.transaction(tx, tx.counterparty_account.is_some(), "counterparty_account", LossKind::Dropped);
+            report.transaction(tx, tx.counterparty_name.is_some(), "counterparty_name", folded(InformationPart::Counterparty));
+            report.transaction(tx, tx.counterparty_address.is_some(), "counterparty_address", LossKind::Dropped);
+            report.transaction(tx, tx.ultimate_counterparty_name.is_some(), "ultimate_counterparty_name", LossKind::Dropped);
+            report.transaction(tx, tx.bank_identifier.is_some(), "bank_identifier", LossKind::Dropped);
+            report.transaction(tx, tx.additional_info.is_some(), "additional_info", folded(InformationPart::AdditionalInfo));
+            // Batches are written as one :61: line per underlying payment;
+            // payments without an amount share what the others leave
+            if !tx.details.is_empty() {
+                let expandable = tx.is_expandable();
+                report.transaction(tx, true, "details", if expandable { LossKind::Synthesized } else { LossKind::Dropped });
+                let partial = expandable && tx.details.iter().any(|detail| detail.amount.is_none());
+                report.transaction(tx, partial, "details.amount", LossKind::Synthesized);
+            }
+            report.transaction(tx, !tx.charges.is_empty(), "charges", LossKind::Dropped);
+            report.transaction(tx, tx.original_amount.is_some() || tx.exchange_rate.is_some(), "original_amount", LossKind::Dropped);
+            report.transaction(tx, tx.counter_value_amount.is_some(), "counter_value_amount", LossKind::Dropped);
+            if tx.structured_remittance.is_some() {
+                let kind = match creditor_reference(tx) {
+                    Some(_) => folded(InformationPart::CreditorReference),
+                    None => LossKind::Dropped,
+                };
+                report.transaction(tx, true, "structured_remittance", kind);
             }
+            report.transaction(tx, matches!(tx.status, EntryStatus::Pending | EntryStatus::Information), "status", LossKind::Dropped);
+            report.transaction(tx, tx.return_reason.is_some(), "return_reason", LossKind::Dropped);
+            report.transaction(tx, tx.purpose_code.is_some(), "purpose_code", folded(InformationPart::PurposeCode));
+            report.transaction(tx, !tx.funds_availability.is_empty(), "funds_availability", LossKind::Dropped);
+            report.transaction(tx, tx.running_balance.is_some(), "running_balance", LossKind::Dropped);
+            report.transaction(tx, tx.refs != TransactionRefs::default(), "refs", LossKind::Dropped);
+            report_annotations(&mut report, tx);
+        }
 
-            // Add counterparty info to description if present
-            if let Some(ref name) = transaction.counterparty_name {
-                if !transaction.description.is_empty() {
-                    transaction.description.push_str(" | ");
-                }
-                transaction.description.push_str("Counterparty: ");
-                transaction.description.push_str(name);
+        let mut output: Mt940Statement = camt053.into();
+        report.losses.extend(fit_mt940_limits(&mut output.statement, TruncationPolicy::Truncate)?.losses);
+        Ok(Converted { output, report })
+    }
+}
+
+/// A statement field that came back different from a round trip.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldChange {
+    /// Path of the field, e.g. `transactions[2].counterparty_name`.
+    pub path: String,
+
+    /// Value before the round trip, `None` when it was unset.
+    pub before: Option<String>,
+
+    /// Value after the round trip, `None` when it came back unset.
+    pub after: Option<String>,
+}
+
+impl fmt::Display for FieldChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = |v: &Option<String>| v.clone().unwrap_or_else(|| "(none)".to_string());
+        write!(f, "{}: {} -> {}", self.path, value(&self.before), value(&self.after))
+    }
+}
+
+/// Fields changed by a round trip through another format, as found by
+/// [`verify_round_trip`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RoundTripDiff {
+    /// Changed fields, ordered by path.
+    pub changes: Vec<FieldChange>,
+}
+
+impl RoundTripDiff {
+    /// Whether the statement came back unchanged.
+    pub fn is_lossless(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+impl fmt::Display for RoundTripDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for change in &self.changes {
+            writeln!(f, "{}", change)?;
+        }
+        Ok(())
+    }
+}
+
+/// Convert `statement` from `a` to `b` and back to `a` with the built-in
+/// formats, and report every field that changed.
+///
+/// The statement is first written and read back in `a`, so that only what
+/// the detour through `b` loses shows up, not what `a` itself can't hold.
+///
+/// # Examples
+///
+/// ```
+/// use ypbank_system::conversion::verify_round_trip;
+/// use ypbank_system::types::{Currency, Statement};
+/// use ypbank_system::Format;
+///
+/// let statement = Statement::new("1".into(), "ACC001", Currency::EUR);
+/// let diff = verify_round_trip(&statement, Format::Camt053, Format::Json)?;
+/// assert!(diff.is_lossless());
+/// # Ok::<(), ypbank_system::Error>(())
+/// ```
+pub fn verify_round_trip(statement: &Statement, a: Format, b: Format) -> Result<RoundTripDiff> {
+    verify_round_trip_with(statement, a, b, &FormatRegistry::default())
+}
+
+/// [`verify_round_trip`] with the formats of `registry`, e.g. a CSV format
+/// with a particular profile.
+pub fn verify_round_trip_with(statement: &Statement, a: Format, b: Format, registry: &FormatRegistry) -> Result<RoundTripDiff> {
+    let pass = |statement: &Statement, format: Format| -> Result<Statement> {
+        let mut buffer = Vec::new();
+        registry.write(format, statement, &mut buffer)?;
+        registry.read(format, &mut buffer.as_slice())
+    };
+    // The original text of each transaction comes from the last read, it isn't a field
+    let fields = |mut statement: Statement| {
+        statement.transactions.iter_mut().for_each(|tx| tx.source_raw = None);
+        serde_json::to_value(statement)
+    };
+    let before = pass(statement, a)?;
+    let after = pass(&pass(&before, b)?, a)?;
+
+    let mut diff = RoundTripDiff::default();
+    diff_values(String::new(), &fields(before)?, &fields(after)?, &mut diff.changes);
+    Ok(diff)
+}
+
+fn diff_values(path: String, before: &Value, after: &Value, changes: &mut Vec<FieldChange>) {
+    let child = |key: &str| if path.is_empty() { key.to_string() } else { format!("{}.{}", path, key) };
+    match (before, after) {
+        (Value::Object(before), Value::Object(after)) => {
+            let keys: BTreeSet<&String> = before.keys().chain(after.keys()).collect();
+            for key in keys {
+                let (b, a) = (before.get(key).unwrap_or(&Value::Null), after.get(key).unwrap_or(&Value::Null));
+                diff_values(child(key), b, a, changes);
+            }
+        }
+        (Value::Array(before), Value::Array(after)) => {
+            for i in 0..before.len().max(after.len()) {
+                let (b, a) = (before.get(i).unwrap_or(&Value::Null), after.get(i).unwrap_or(&Value::Null));
+                diff_values(format!("{}[{}]", path, i), b, a, changes);
             }
         }
+        _ if before != after => changes.push(FieldChange {
+            path,
+            before: value_text(before),
+            after: value_text(after),
+        }),
+        _ => {}
+    }
+}
 
-        Mt940Statement { statement }
+fn value_text(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::String(s) => Some(s.clone()),
+        other => Some(other.to_string()),
     }
 }
 
+/// Category and tags, which neither MT940 nor CAMT.053 carries.
+fn report_annotations(report: &mut ConversionReport, tx: &Transaction) {
+    report.transaction(tx, tx.category.is_some(), "category", LossKind::Dropped);
+    report.transaction(tx, !tx.tags.is_empty(), "tags", LossKind::Dropped);
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::types::{Statement, Transaction, DebitCredit};
-    use rust_decimal::Decimal;
+    use crate::types::{Balance, BalanceType, BankDate, Currency, TransactionDetail};
     use std::str::FromStr;
     use chrono::NaiveDate;
 
     #[test]
     fn test_mt940_to_camt053() {
-        let mut statement = Statement::new("TEST001".into(), "ACC123".into(), "USD".into());
+        let mut statement = Statement::new("TEST001".into(), "ACC123", Currency::USD);
         statement.transactions.push(Transaction {
             reference: "REF001".into(),
-            date: NaiveDate::from_ymd_opt(2024, 1, 15).unwrap(),
-            value_date: Some(NaiveDate::from_ymd_opt(2024, 1, 15).unwrap()),
+            date: NaiveDate::from_ymd_opt(2024, 1, 15).unwrap().into(),
+            value_date: Some(NaiveDate::from_ymd_opt(2024, 1, 15).unwrap().into()),
             amount: Decimal::from_str("100.50").unwrap(),
-            currency: "USD".into(),
+            currency: Currency::USD,
             debit_credit: DebitCredit::Credit,
-            account: None,
             counterparty_account: Some("ACC456".into()),
             counterparty_name: Some("Test Company".into()),
-            bank_identifier: Some("TESTUS33".into()),
+            bank_identifier: Some("TESTUS33".parse().unwrap()),
             description: "Test transaction".into(),
-            additional_info: None,
+            ..Transaction::default()
         });
 
         let mt940 = Mt940Statement { statement };
@@ -89,20 +1235,20 @@ mod tests {
 
     #[test]
     fn test_camt053_to_mt940() {
-        let mut statement = Statement::new("TEST002".into(), "ACC789".into(), "EUR".into());
+        let mut statement = Statement::new("TEST002".into(), "ACC789", Currency::EUR);
         statement.transactions.push(Transaction {
             reference: "REF002".into(),
-            date: NaiveDate::from_ymd_opt(2024, 2, 20).unwrap(),
-            value_date: Some(NaiveDate::from_ymd_opt(2024, 2, 20).unwrap()),
+            date: NaiveDate::from_ymd_opt(2024, 2, 20).unwrap().into(),
+            value_date: Some(NaiveDate::from_ymd_opt(2024, 2, 20).unwrap().into()),
             amount: Decimal::from_str("250.75").unwrap(),
-            currency: "EUR".into(),
+            currency: Currency::EUR,
             debit_credit: DebitCredit::Debit,
-            account: None,
             counterparty_account: Some("ACC999".into()),
             counterparty_name: Some("Another Company".into()),
-            bank_identifier: Some("TESTDE33".into()),
+            bank_identifier: Some("TESTDE33".parse().unwrap()),
             description: "Another test".into(),
             additional_info: Some("Extra info".into()),
+            ..Transaction::default()
         });
 
         let camt053 = Camt053Statement { statement };
@@ -112,4 +1258,434 @@ mod tests {
         assert_eq!(mt940.statement.transactions.len(), 1);
         assert!(mt940.statement.transactions[0].description.contains("Extra info"));
     }
+
+    #[test]
+    fn test_source_raw_round_trip() {
+        let input = ":20:STMT\n:25:ACC001\n:60F:C240101EUR100,00\n\
+                     :61:2401020102D10,00NMSCNONREF//BANK-REF-1\nSUPPLEMENTARY 1\n\
+                     :86:/CRNM/ACME GmbH\n/REMI/Invoice 17\n\
+                     :61:2401030103C5,00NTRF//REF2\n:86:Refund\n:62F:C240103EUR95,00\n-}";
+        let mt940 = Mt940Statement::from_read(&mut input.as_bytes()).unwrap();
+        assert_eq!(
+            mt940.statement.transactions[0].source_raw.as_ref().map(|raw| raw.text.as_str()),
+            Some(":61:2401020102D10,00NMSCNONREF//BANK-REF-1\nSUPPLEMENTARY 1\n:86:/CRNM/ACME GmbH\n/REMI/Invoice 17")
+        );
+
+        let camt053: Camt053Statement = mt940.into();
+        assert_eq!(camt053.statement.transactions[0].counterparty_name.as_deref(), Some("ACME GmbH"));
+        let mut back: Mt940Statement = camt053.into();
+        back.statement.transactions[1].amount = Decimal::new(600, 2);
+
+        let mut output = Vec::new();
+        back.write_to(&mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains(":61:2401020102D10,00NMSCNONREF//BANK-REF-1\nSUPPLEMENTARY 1\n:86:/CRNM/ACME GmbH\n/REMI/Invoice 17\n"));
+        assert!(output.contains(":61:2401030103C6,00NTRF//REF2\n"));
+    }
+
+    #[test]
+    fn test_camt053_to_mt940_excludes_pending() {
+        let mut statement = charged_statement();
+        let mut pending = statement.transactions[0].clone();
+        pending.reference = "PENDING".into();
+        pending.status = EntryStatus::Pending;
+        statement.transactions.push(pending);
+
+        let options = ConversionOptions {
+            excluded_statuses: vec![EntryStatus::Pending],
+            ..Default::default()
+        };
+        let mt940 = camt053_to_mt940(Camt053Statement { statement }, &options);
+
+        assert_eq!(mt940.statement.transactions.len(), 1);
+        assert_eq!(mt940.statement.transactions[0].reference, "REF003");
+    }
+
+    #[test]
+    fn test_batch_policy() {
+        let mut statement = charged_statement();
+        let detail = |reference: &str, amount: i64| TransactionDetail {
+            reference: Some(reference.into()),
+            amount: Some(Decimal::new(amount, 2)),
+            currency: None,
+            counterparty_account: None,
+            counterparty_name: None,
+            counterparty_address: None,
+            ultimate_counterparty_name: None,
+            bank_identifier: None,
+            description: String::new(),
+            additional_info: None,
+        };
+        statement.transactions[0].details = vec![detail("E2E1", 6000), detail("E2E2", 4000)];
+
+        let mut expanded = statement.clone();
+        apply_options(&mut expanded, &ConversionOptions {
+            batches: Some("expand".parse().unwrap()),
+            ..Default::default()
+        });
+        let references: Vec<_> = expanded.transactions.iter().map(|tx| tx.reference.as_str()).collect();
+        assert_eq!(references, ["E2E1", "E2E2"]);
+        assert!(expanded.transactions.iter().all(|tx| tx.details.is_empty()));
+
+        let statement_with_details = statement.clone();
+        let mut collapsed = statement;
+        apply_options(&mut collapsed, &ConversionOptions {
+            batches: Some(BatchPolicy::Collapse),
+            ..Default::default()
+        });
+        assert_eq!(collapsed.transactions.len(), 1);
+        assert_eq!(collapsed.transactions[0].reference, "REF003");
+        assert!(collapsed.transactions[0].details.is_empty());
+
+        // A payment without an amount gets what the others leave of the entry
+        let mut partial = statement_with_details.clone();
+        partial.transactions[0].details[1].amount = None;
+        let expanded = partial.transactions[0].expand_details();
+        let amounts: Vec<String> = expanded.iter().map(|tx| tx.amount.to_string()).collect();
+        assert_eq!(amounts, ["60.00", "40.00"]);
+        assert_eq!(expanded[1].reference, "E2E2");
+        partial.transactions[0].details[0].amount = None;
+        assert_eq!(partial.transactions[0].expand_details().len(), 1);
+
+        assert!("nest".parse::<BatchPolicy>().is_err());
+    }
+
+    #[test]
+    fn test_camt053_to_mt940_structured_reference() {
+        let mut statement = charged_statement();
+        statement.transactions[0].structured_remittance = Some(StructuredRemittance {
+            reference_type: Some("SCOR".into()),
+            reference: Some("RF18539007547034".into()),
+            referred_documents: Vec::new(),
+        });
+
+        let mt940: Mt940Statement = Camt053Statement { statement }.into();
+        assert!(mt940.statement.transactions[0].description.ends_with("/RFB/RF18539007547034"));
+    }
+
+    #[test]
+    fn test_purpose_code_in_mt940_description() {
+        let mut statement = charged_statement();
+        statement.transactions[0].purpose_code = Some("SALA".into());
+
+        let mt940: Mt940Statement = Camt053Statement { statement }.into();
+        assert!(mt940.statement.transactions[0].description.ends_with(" | /PURP/SALA"));
+    }
+
+    #[test]
+    fn test_conversion_report() {
+        let mut statement = charged_statement();
+        statement.creation_date = NaiveDate::from_ymd_opt(2024, 3, 2).map(BankDate::from);
+        statement.transactions[0].counterparty_name = Some("ACME".into());
+        statement.transactions[0].date = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap().and_hms_opt(9, 30, 0).unwrap().into();
+
+        let converted = Converted::<Mt940Statement>::try_from(Camt053Statement { statement: statement.clone() }).unwrap();
+        assert_eq!(converted.output, Mt940Statement::from(Camt053Statement { statement: statement.clone() }));
+        assert_eq!(
+            converted.report.to_string(),
+            "creation_date: dropped\n\
+             transaction REF003: date: truncated\n\
+             transaction REF003: counterparty_name: folded into the description\n\
+             transaction REF003: charges: dropped\n"
+        );
+
+        let camt053 = Converted::<Camt053Statement>::try_from(Mt940Statement { statement: statement.clone() }).unwrap();
+        assert!(camt053.report.is_lossless());
+        statement.creation_date = None;
+        let camt053 = Converted::<Camt053Statement>::try_from(Mt940Statement { statement: statement.clone() }).unwrap();
+        assert_eq!(camt053.report.losses[0].kind, LossKind::Synthesized);
+
+        statement.transactions[0].currency = Currency::USD;
+        assert!(Converted::<Mt940Statement>::try_from(Camt053Statement { statement }).is_err());
+    }
+
+    #[test]
+    fn test_information_mapping() {
+        let mut statement = charged_statement();
+        let tx = &mut statement.transactions[0];
+        tx.additional_info = Some("Invoice 12".into());
+        tx.counterparty_name = Some("ACME".into());
+        tx.purpose_code = Some("SUPP".into());
+
+        let options = ConversionOptions {
+            information: InformationMapping {
+                separator: "; ".into(),
+                parts: vec![InformationPart::Counterparty, InformationPart::AdditionalInfo],
+                counterparty_label: "CP=".into(),
+                decompose: true,
+            },
+            ..Default::default()
+        };
+        let mt940 = camt053_to_mt940(Camt053Statement { statement }, &options);
+        assert_eq!(mt940.statement.transactions[0].description, "Payment; CP=ACME; Invoice 12");
+
+        let mut mt940 = mt940;
+        let tx = &mut mt940.statement.transactions[0];
+        (tx.counterparty_name, tx.additional_info) = (None, None);
+        let camt053 = mt940_to_camt053(mt940, &options);
+        let tx = &camt053.statement.transactions[0];
+        assert_eq!(tx.description, "Payment");
+        assert_eq!(tx.counterparty_name.as_deref(), Some("ACME"));
+        assert_eq!(tx.additional_info.as_deref(), Some("Invoice 12"));
+    }
+
+    #[test]
+    fn test_verify_round_trip() {
+        let mut statement = charged_statement();
+        statement.transactions[0].charges.clear();
+        statement.transactions[0].counterparty_name = Some("ACME".into());
+        let date = statement.transactions[0].date.date();
+        statement.opening_balance = Some(Balance::from_signed(BalanceType::Opening, Decimal::new(50000, 2), Currency::EUR, date));
+        statement.closing_balance = Some(Balance::from_signed(BalanceType::Closing, Decimal::new(40000, 2), Currency::EUR, date));
+
+        assert!(verify_round_trip(&statement, Format::Camt053, Format::Json).unwrap().is_lossless());
+
+        let diff = verify_round_trip(&statement, Format::Camt053, Format::Mt940).unwrap();
+        assert!(diff.changes.contains(&FieldChange {
+            path: "transactions[0].counterparty_name".into(),
+            before: Some("ACME".into()),
+            after: None,
+        }));
+        assert!(diff.to_string().contains("transactions[0].counterparty_name: ACME -> (none)\n"));
+    }
+
+    #[test]
+    fn test_convert_currency() {
+        let mut statement = charged_statement();
+        let date = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        statement.opening_balance = Some(Balance::from_signed(BalanceType::Opening, Decimal::new(50000, 2), Currency::EUR, date));
+        let mut usd = statement.transactions[0].clone();
+        usd.reference = "REF004".into();
+        usd.currency = Currency::USD;
+        usd.charges.clear();
+        statement.transactions.push(usd);
+
+        let mut rates = RateTable::new();
+        rates.insert(Currency::EUR, Currency::RUB, Decimal::from_str("98.5").unwrap());
+        let mut unconverted = statement.clone();
+        assert!(convert_currency(&mut unconverted, Currency::RUB, &rates).is_err());
+        assert_eq!(unconverted, statement);
+
+        rates.insert_on(Currency::RUB, Currency::USD, date, Decimal::from_str("0.0125").unwrap());
+        convert_currency(&mut statement, Currency::RUB, &rates).unwrap();
+
+        assert_eq!(statement.currency, Currency::RUB);
+        assert_eq!(statement.opening_balance.unwrap().amount, Decimal::from_str("49250.00").unwrap());
+        let [eur, usd] = &statement.transactions[..] else { panic!() };
+        assert_eq!((eur.amount, eur.currency), (Decimal::from_str("9850.00").unwrap(), Currency::RUB));
+        assert_eq!((eur.original_amount, eur.original_currency), (Some(Decimal::from_str("100.00").unwrap()), Some(Currency::EUR)));
+        assert_eq!(eur.charges[0].amount, Decimal::from_str("246.25").unwrap());
+        assert_eq!(usd.amount, Decimal::from_str("8000.00").unwrap());
+        assert_eq!(usd.exchange_rate, Some(Decimal::from(80)));
+    }
+
+    #[test]
+    fn test_synthesize_balances() {
+        let mut statement = charged_statement();
+        apply_options(&mut statement, &ConversionOptions {
+            synthesize_balances: true,
+            opening_balance: Some(Decimal::from_str("500.00").unwrap()),
+            ..Default::default()
+        });
+
+        let closing = statement.closing_balance.as_ref().unwrap();
+        assert_eq!(closing.signed_amount(), Decimal::from_str("400.00").unwrap());
+        assert_eq!(closing.date, NaiveDate::from_ymd_opt(2024, 3, 1).unwrap());
+
+        let mut output = Vec::new();
+        Mt940Statement { statement }.write_to(&mut output).unwrap();
+        let text = String::from_utf8(output).unwrap();
+        assert!(text.contains(":60F:C240301EUR500,00\n") && text.contains(":62F:C240301EUR400,00\n"));
+        assert!(Mt940Statement::from_read(&mut text.as_bytes()).unwrap().statement.verify_balances().is_ok());
+    }
+
+    #[test]
+    fn test_transaction_hooks() {
+        let mut statement = charged_statement();
+        statement.transactions[0].description = "Оплата от ООО Ромашка по счёту 12".into();
+
+        let options = ConversionOptions {
+            charges: Some(ChargesPolicy::SeparateEntries),
+            hooks: vec![
+                TransactionHook::counterparty_from_description(Regex::new(r"от (?P<name>ООО \w+)").unwrap()),
+                TransactionHook::rewrite_reference(Regex::new(r"^REF(\d+)").unwrap(), "PAY-$1"),
+                TransactionHook::new(|tx| {
+                    tx.tags.insert("imported".into());
+                }),
+            ],
+            ..Default::default()
+        };
+        let camt053 = mt940_to_camt053(Mt940Statement { statement }, &options);
+
+        let references: Vec<_> = camt053.statement.transactions.iter().map(|tx| tx.reference.as_str()).collect();
+        assert_eq!(references, ["PAY-003", "PAY-003-CHG1"]);
+        assert_eq!(camt053.statement.transactions[0].counterparty_name.as_deref(), Some("ООО Ромашка"));
+        assert!(camt053.statement.transactions.iter().all(|tx| tx.tags.contains("imported")));
+        assert_eq!(options.clone(), options);
+    }
+
+    #[test]
+    fn test_recover_counterparty() {
+        let mut statement = charged_statement();
+        let mut tx = statement.transactions.remove(0);
+        tx.description = "/EREF/GS0DUTB31IOUHRS /DACT/8348577826/DBIC/CITIUS30XXX /REMI/Refund".into();
+        tx.debit_credit = DebitCredit::Credit;
+        let mut german = tx.clone();
+        let mut free_text = tx.clone();
+
+        recover_counterparty(&mut tx);
+        assert_eq!(tx.counterparty_name, None);
+        assert_eq!(tx.counterparty_account, Some("8348577826".into()));
+        assert_eq!(tx.bank_identifier, Some("CITIUS30XXX".parse().unwrap()));
+
+        german.description = "166?00GUTSCHRIFT?20RE 12?30COBADEFFXXX?31DE89370400440532013000?32MUSTERMANN?33GMBH".into();
+        recover_counterparty(&mut german);
+        assert_eq!(german.counterparty_name.as_deref(), Some("MUSTERMANN GMBH"));
+        assert_eq!(german.counterparty_account.unwrap().id(), "DE89370400440532013000");
+
+        free_text.description = "NL91ABNA0417164300 hr j jansen Betaling".into();
+        free_text.counterparty_name = Some("J. Jansen".into());
+        recover_counterparty(&mut free_text);
+        assert_eq!(free_text.counterparty_name.as_deref(), Some("J. Jansen"));
+        assert_eq!(free_text.counterparty_account.unwrap().id(), "NL91ABNA0417164300");
+        assert_eq!(free_text.bank_identifier, None);
+    }
+
+    #[test]
+    fn test_csv_camt053_parties() {
+        let input = "Дата проводки,Счет Дебет,Счет Кредит,Сумма по дебету,Сумма по кредиту,№ документа\n\
+                     20.02.2024,\"40702810000000000001\n7701000001\nООО Альфа\",\"40702810000000000002\n7702000002\nООО Бета\",100.00,,1\n\
+                     21.02.2024,\"40702810000000000003\n7703000003\nООО Гамма\",40702810000000000001,,50.00,2\n";
+        let csv = CsvStatement::from_read(&mut input.as_bytes()).unwrap();
+        assert_eq!(csv.statement.transactions[0].counterparty_name.as_deref(), Some("ООО Бета"));
+        assert_eq!(csv.statement.transactions[1].counterparty_name.as_deref(), Some("ООО Гамма"));
+
+        let mut xml = Vec::new();
+        Camt053Statement::from(csv).write_to(&mut xml).unwrap();
+        let camt053 = Camt053Statement::from_read(&mut xml.as_slice()).unwrap();
+        let debit = &camt053.statement.transactions[0];
+        assert_eq!(debit.account.as_deref(), Some("40702810000000000001"));
+        assert_eq!(debit.counterparty_account, Some("40702810000000000002".into()));
+        let credit = &camt053.statement.transactions[1];
+        assert_eq!(credit.counterparty_account, Some("40702810000000000003".into()));
+
+        let mut output = Vec::new();
+        CsvStatement::from(camt053).write_to(&mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("20.02.2024,40702810000000000001,40702810000000000002,100.00,,1,"));
+        assert!(output.contains("21.02.2024,40702810000000000003,40702810000000000001,,50.00,2,"));
+    }
+
+    #[test]
+    fn test_fit_mt940_limits() {
+        let mut statement = charged_statement();
+        statement.transactions[0].reference = "E2E-2024-03-01-000042".into();
+        statement.transactions[0].description = "Invoice 2024/117 ".repeat(30);
+        let err = fit_mt940_limits(&mut statement.clone(), TruncationPolicy::Reject).unwrap_err();
+        let failure = err.validation().unwrap();
+        assert_eq!(failure.rule, RuleId::Mt940FieldLength);
+        assert_eq!(failure.location.as_ref().unwrap().path.as_deref(), Some("transactions[0].reference"));
+
+        let report = fit_mt940_limits(&mut statement, "truncate".parse().unwrap()).unwrap();
+        assert_eq!(
+            report.to_string(),
+            "transaction E2E-2024-03-01-000042: reference: truncated\n\
+             transaction E2E-2024-03-01-000042: description: truncated\n"
+        );
+        let tx = &statement.transactions[0];
+        assert_eq!(tx.reference, "E2E-2024-03-01-0");
+        assert_eq!(mt940_format::wrap_narrative(&tx.description).len(), mt940_format::NARRATIVE_LINES);
+
+        let mut output = Vec::new();
+        Mt940Statement { statement: statement.clone() }.write_to(&mut output).unwrap();
+        let text = String::from_utf8(output).unwrap();
+        assert!(text.lines().all(|line| line.chars().count() <= 4 + mt940_format::LINE_LENGTH));
+        assert!(fit_mt940_limits(&mut statement, TruncationPolicy::Reject).unwrap().is_lossless());
+
+        statement.account = "40702810900000000001/ACME-TRADING-HOUSE".into();
+        let err = fit_mt940_limits(&mut statement, TruncationPolicy::Truncate).unwrap_err();
+        assert_eq!(err.validation().unwrap().rule, RuleId::Mt940AccountLength);
+    }
+
+    fn charged_statement() -> Statement {
+        let mut statement = Statement::new("TEST003".into(), "ACC111", Currency::EUR);
+        statement.transactions.push(Transaction {
+            reference: "REF003".into(),
+            date: NaiveDate::from_ymd_opt(2024, 3, 1).unwrap().into(),
+            amount: Decimal::from_str("100.00").unwrap(),
+            currency: Currency::EUR,
+            debit_credit: DebitCredit::Debit,
+            description: "Payment".into(),
+            charges: vec![Charge {
+                amount: Decimal::from_str("2.50").unwrap(),
+                currency: Currency::EUR,
+                debit_credit: DebitCredit::Debit,
+                charge_type: Some("COMM".into()),
+                bearer: None,
+            }],
+            ..Transaction::default()
+        });
+        statement
+    }
+
+    #[test]
+    fn test_charges_merge() {
+        let mut statement = charged_statement();
+        let options = ConversionOptions {
+            charges: Some(ChargesPolicy::Merge),
+            ..Default::default()
+        };
+        apply_options(&mut statement, &options);
+
+        assert_eq!(statement.transactions.len(), 1);
+        assert_eq!(statement.transactions[0].amount, Decimal::from_str("102.50").unwrap());
+        assert!(statement.transactions[0].charges.is_empty());
+    }
+
+    #[test]
+    fn test_charges_separate_entries() {
+        let mut statement = charged_statement();
+        let options = ConversionOptions {
+            charges: Some(ChargesPolicy::SeparateEntries),
+            ..Default::default()
+        };
+        apply_options(&mut statement, &options);
+
+        assert_eq!(statement.transactions.len(), 2);
+        assert_eq!(statement.transactions[0].amount, Decimal::from_str("100.00").unwrap());
+        assert_eq!(statement.transactions[1].reference, "REF003-CHG1");
+        assert_eq!(statement.transactions[1].amount, Decimal::from_str("2.50").unwrap());
+    }
+
+    #[test]
+    fn test_charges_written_as_nchg() {
+        let options = ConversionOptions {
+            charges: Some(ChargesPolicy::SeparateEntries),
+            ..Default::default()
+        };
+        let mt940 = camt053_to_mt940(Camt053Statement { statement: charged_statement() }, &options);
+
+        let mut output = Vec::new();
+        mt940.write_to(&mut output).unwrap();
+        let text = String::from_utf8(output).unwrap();
+        let lines: Vec<_> = text.lines().filter(|line| line.starts_with(":61:")).collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].ends_with("NTRF//REF003"));
+        assert!(lines[1].ends_with("NCHG//REF003-CHG1"));
+    }
+
+    #[test]
+    fn test_charges_ignore() {
+        let mut statement = charged_statement();
+        let options = ConversionOptions {
+            charges: Some("ignore".parse().unwrap()),
+            ..Default::default()
+        };
+        apply_options(&mut statement, &options);
+
+        assert_eq!(statement.transactions.len(), 1);
+        assert_eq!(statement.transactions[0].amount, Decimal::from_str("100.00").unwrap());
+        assert!(statement.transactions[0].charges.is_empty());
+        assert!("gross".parse::<ChargesPolicy>().is_err());
+    }
 }
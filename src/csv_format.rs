@@ -2,15 +2,23 @@
 //!
 //! This module provides parsing and writing capabilities for CSV bank statements.
 
-use crate::error::{Error, Result};
-use crate::types::{DebitCredit, Statement, Transaction};
-use chrono::NaiveDate;
-use csv::{Reader, Writer};
+use crate::error::{Error, ErrorCode, Location, ParseOutcome, RejectedRecord, Result, Warning, WarningKind};
+use crate::format::{EntryReader, StatementFormat};
+use crate::types::{
+    fnv1a, fnv1a_extend, parse_decimal, AccountIdentifier, Balance, BalanceType, BankDate, BankIdentifier, BankInfo, Currency,
+    DebitCredit,
+    EntryStatus, Statement, Transaction,
+};
+use chrono::{NaiveDate, NaiveTime};
+use csv::{ReaderBuilder, StringRecord, Terminator, WriterBuilder};
+use encoding_rs::{Decoder, DecoderResult, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1251};
 use rust_decimal::Decimal;
-use serde::{Deserialize, Serialize};
-use std::io::{Read, Write};
+use std::collections::HashMap;
+use std::io::{self, BufRead, Read, Write};
 use std::str::FromStr;
 
+pub use encoding_rs::Encoding;
+
 /// Represents a CSV statement.
 #[derive(Debug, Clone, PartialEq)]
 pub struct CsvStatement {
@@ -18,127 +26,1473 @@ pub struct CsvStatement {
     pub statement: Statement,
 }
 
-/// CSV transaction record structure.
-#[derive(Debug, Serialize, Deserialize)]
-struct CsvRecord {
-    #[serde(rename = "Дата проводки", alias = "Date", alias = "date")]
-    date: String,
-    #[serde(rename = "Счет Дебет", alias = "Debit Account", alias = "debit_account", default)]
-    debit_account: String,
-    #[serde(rename = "Счет Кредит", alias = "Credit Account", alias = "credit_account", default)]
-    credit_account: String,
-    #[serde(rename = "Сумма по дебету", alias = "Debit Amount", alias = "debit_amount", default)]
-    debit_amount: String,
-    #[serde(rename = "Сумма по кредиту", alias = "Credit Amount", alias = "credit_amount", default)]
-    credit_amount: String,
-    #[serde(rename = "№ документа", alias = "Document No", alias = "reference", default)]
-    reference: String,
-    #[serde(rename = "Назначение платежа", alias = "Purpose", alias = "description", default)]
-    description: String,
-    #[serde(rename = "Банк (БИК и наименование)", alias = "Bank", alias = "bank", default)]
-    bank: String,
+/// CSV as a [`StatementFormat`], read and written with a [`CsvProfile`].
+#[derive(Debug, Clone, Default)]
+pub struct CsvFormat {
+    /// Layout used for reading and writing.
+    pub profile: CsvProfile,
+}
+
+impl StatementFormat for CsvFormat {
+    fn read(&self, mut reader: &mut dyn Read) -> Result<Statement> {
+        Ok(CsvStatement::from_read_with(&mut reader, &self.profile)?.statement)
+    }
+
+    fn read_with_diagnostics(&self, mut reader: &mut dyn Read) -> Result<ParseOutcome<Statement>> {
+        Ok(CsvStatement::from_read_with_diagnostics(&mut reader, &self.profile)?.map(|parsed| parsed.statement))
+    }
+
+    fn write(&self, statement: &Statement, mut writer: &mut dyn Write) -> Result<()> {
+        CsvStatement { statement: statement.clone() }.write_to_with(&mut writer, &self.profile)
+    }
+
+    fn entries<'a>(&self, reader: Box<dyn Read + 'a>) -> Result<Box<dyn EntryReader + 'a>> {
+        Ok(Box::new(CsvEntryReader::new(reader, &self.profile)?))
+    }
+}
+
+/// Transaction attribute a CSV column maps to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CsvField {
+    /// Booking date, optionally followed by the time of day.
+    Date,
+    /// Booking time of day, when given apart from the date.
+    Time,
+    /// Account debited by the transaction.
+    DebitAccount,
+    /// Account credited by the transaction.
+    CreditAccount,
+    /// Amount of a debit transaction.
+    DebitAmount,
+    /// Amount of a credit transaction.
+    CreditAmount,
+    /// Transaction amount, see [`DebitCreditConvention`].
+    Amount,
+    /// Debit/credit indicator.
+    DebitCredit,
+    /// Transaction currency.
+    Currency,
+    /// Document number or transaction reference.
+    Reference,
+    /// Payment purpose.
+    Description,
+    /// Counterparty bank (BIC and name).
+    Bank,
+    /// Counterparty name.
+    CounterpartyName,
+    /// Counterparty account.
+    CounterpartyAccount,
+    /// Account the row belongs to, when it isn't given by the debit and
+    /// credit account columns.
+    Account,
+    /// Amount with a minus sign for debits, whatever the profile's
+    /// [`DebitCreditConvention`]; output only.
+    SignedAmount,
+    /// Account balance after the transaction; negative for a debit balance.
+    RunningBalance,
+    /// Transaction category.
+    Category,
+    /// Transaction tags, separated by commas.
+    Tags,
+    /// Entry status: booked, pending, information or reversed.
+    Status,
+}
+
+impl FromStr for CsvField {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().replace('-', "_").as_str() {
+            "date" => Ok(CsvField::Date),
+            "time" => Ok(CsvField::Time),
+            "debit_account" => Ok(CsvField::DebitAccount),
+            "credit_account" => Ok(CsvField::CreditAccount),
+            "debit_amount" => Ok(CsvField::DebitAmount),
+            "credit_amount" => Ok(CsvField::CreditAmount),
+            "amount" => Ok(CsvField::Amount),
+            "debit_credit" | "dc" => Ok(CsvField::DebitCredit),
+            "currency" => Ok(CsvField::Currency),
+            "reference" => Ok(CsvField::Reference),
+            "description" => Ok(CsvField::Description),
+            "bank" => Ok(CsvField::Bank),
+            "counterparty_name" => Ok(CsvField::CounterpartyName),
+            "counterparty_account" => Ok(CsvField::CounterpartyAccount),
+            "account" => Ok(CsvField::Account),
+            "signed_amount" => Ok(CsvField::SignedAmount),
+            "running_balance" | "balance" => Ok(CsvField::RunningBalance),
+            "category" => Ok(CsvField::Category),
+            "tags" => Ok(CsvField::Tags),
+            "status" => Ok(CsvField::Status),
+            _ => Err(Error::InvalidFormat(format!("Unknown CSV field: {}", s), None)),
+        }
+    }
+}
+
+/// How a CSV layout tells debits from credits.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DebitCreditConvention {
+    /// Separate debit and credit amount (and account) columns.
+    SeparateColumns,
+    /// A single signed amount column; negative amounts are debits.
+    SignedAmount,
+    /// An unsigned amount column plus an indicator column.
+    Indicator {
+        /// Indicator value marking debits.
+        debit: String,
+        /// Indicator value marking credits.
+        credit: String,
+    },
+}
+
+/// How the statement ID of an imported CSV is chosen.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum StatementIdPolicy {
+    /// `CSV-` followed by the current Unix timestamp.
+    #[default]
+    Timestamp,
+    /// `CSV-` followed by a hash of the account, period and file content, so
+    /// re-importing the same file gives the same ID.
+    Derived,
+    /// The given ID.
+    Explicit(String),
+}
+
+/// Number formatting rules for CSV amounts.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AmountFormat {
+    /// Decimal separator; `None` accepts both `.` and `,` on read and writes `.`.
+    pub decimal_separator: Option<char>,
+
+    /// Digit group separator, removed on read and inserted on write.
+    /// Spaces are always accepted on read.
+    pub thousands_separator: Option<char>,
+
+    /// Negative amounts are written in parentheses, e.g. `(100.00)`.
+    pub negative_parentheses: bool,
+}
+
+impl AmountFormat {
+    /// Russian number style, e.g. `1 540,00`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ypbank_system::csv_format::AmountFormat;
+    ///
+    /// let format = AmountFormat::russian();
+    /// assert_eq!(format.decimal_separator, Some(','));
+    /// assert_eq!(format.thousands_separator, Some(' '));
+    /// ```
+    pub fn russian() -> Self {
+        AmountFormat {
+            decimal_separator: Some(','),
+            thousands_separator: Some(' '),
+            negative_parentheses: false,
+        }
+    }
+}
+
+/// Description of a bank's CSV layout.
+///
+/// The default profile reads the Russian and English column names of the
+/// standard export and writes the Russian ones.
+///
+/// # Examples
+///
+/// ```
+/// use ypbank_system::csv_format::{
+///     AmountFormat, CsvField, CsvProfile, DebitCreditConvention, StatementIdPolicy,
+/// };
+/// use ypbank_system::types::Currency;
+///
+/// let profile = CsvProfile {
+///     columns: vec![
+///         ("Booking Date".into(), CsvField::Date),
+///         ("Amount".into(), CsvField::Amount),
+///         ("Text".into(), CsvField::Description),
+///     ],
+///     output_columns: None,
+///     date_formats: vec!["%Y-%m-%d".into()],
+///     amount_format: AmountFormat {
+///         decimal_separator: Some(','),
+///         thousands_separator: Some('.'),
+///         negative_parentheses: true,
+///     },
+///     debit_credit: DebitCreditConvention::SignedAmount,
+///     delimiter: Some(b';'),
+///     encoding: Some(encoding_rs::WINDOWS_1251),
+///     bom: false,
+///     crlf: true,
+///     default_currency: Currency::EUR,
+///     statement_id: StatementIdPolicy::Derived,
+///     strict: true,
+///     skip_invalid: false,
+///     bank_rows: false,
+/// };
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsvProfile {
+    /// Column names and the field each one holds; several names may map to the
+    /// same field. Output uses the first name of each field, in this order,
+    /// unless `output_columns` is set.
+    pub columns: Vec<(String, CsvField)>,
+
+    /// Columns to write, in order, e.g. to match an import template.
+    /// A field may appear more than once.
+    pub output_columns: Option<Vec<(String, CsvField)>>,
+
+    /// Accepted date formats (chrono syntax); output uses the first one.
+    ///
+    /// A date that parses to different days under two formats, such as
+    /// `02/03/2024` under `%d/%m/%Y` and `%m/%d/%Y`, is rejected as ambiguous.
+    pub date_formats: Vec<String>,
+
+    /// Amount parsing and formatting rules.
+    pub amount_format: AmountFormat,
+
+    /// How debits are distinguished from credits.
+    pub debit_credit: DebitCreditConvention,
+
+    /// Field delimiter; `None` detects `,`, `;` or tab on read and writes `,`.
+    pub delimiter: Option<u8>,
+
+    /// Character encoding; `None` detects a BOM or falls back from UTF-8 to
+    /// windows-1251 on read, and writes UTF-8.
+    pub encoding: Option<&'static Encoding>,
+
+    /// Start UTF-8 output with a byte order mark.
+    pub bom: bool,
+
+    /// End output lines with `\r\n` instead of `\n`.
+    pub crlf: bool,
+
+    /// Currency of rows without a currency column or with an empty cell.
+    pub default_currency: Currency,
+
+    /// Statement ID assigned on read.
+    pub statement_id: StatementIdPolicy,
+
+    /// Reject unknown columns, missing amount columns and rows without an
+    /// amount instead of skipping them; errors carry row and column.
+    pub strict: bool,
+
+    /// Leave out rows that fail to parse, listing them in
+    /// [`ParseOutcome::rejected`], instead of failing on the first one.
+    pub skip_invalid: bool,
+
+    /// Write the servicing bank as `Банк`, `БИК` and `BIC` rows above the
+    /// table; such rows are read whether or not this is set.
+    pub bank_rows: bool,
+}
+
+impl Default for CsvProfile {
+    fn default() -> Self {
+        let columns = [
+            (CsvField::Date, ["Дата проводки", "Date", "date"]),
+            (CsvField::Time, ["Время проводки", "Time", "time"]),
+            (CsvField::DebitAccount, ["Счет Дебет", "Debit Account", "debit_account"]),
+            (CsvField::CreditAccount, ["Счет Кредит", "Credit Account", "credit_account"]),
+            (CsvField::DebitAmount, ["Сумма по дебету", "Debit Amount", "debit_amount"]),
+            (CsvField::CreditAmount, ["Сумма по кредиту", "Credit Amount", "credit_amount"]),
+            (CsvField::Reference, ["№ документа", "Document No", "reference"]),
+            (CsvField::Description, ["Назначение платежа", "Purpose", "description"]),
+            (CsvField::Bank, ["Банк (БИК и наименование)", "Bank", "bank"]),
+            (CsvField::Currency, ["Валюта", "Currency", "currency"]),
+            (CsvField::RunningBalance, ["Остаток", "Balance", "running_balance"]),
+            (CsvField::Category, ["Категория", "Category", "category"]),
+            (CsvField::Tags, ["Метки", "Tags", "tags"]),
+            (CsvField::Status, ["Статус", "Status", "status"]),
+        ];
+
+        CsvProfile {
+            columns: columns
+                .iter()
+                .flat_map(|(field, names)| names.iter().map(move |name| (name.to_string(), *field)))
+                .collect(),
+            output_columns: None,
+            date_formats: ["%d.%m.%Y", "%Y-%m-%d", "%d/%m/%Y", "%m/%d/%Y"]
+                .iter()
+                .map(|f| f.to_string())
+                .collect(),
+            amount_format: AmountFormat::default(),
+            debit_credit: DebitCreditConvention::SeparateColumns,
+            delimiter: None,
+            encoding: None,
+            bom: false,
+            crlf: false,
+            default_currency: Currency::RUB,
+            statement_id: StatementIdPolicy::Timestamp,
+            strict: false,
+            skip_invalid: false,
+            bank_rows: false,
+        }
+    }
+}
+
+impl CsvProfile {
+    /// Names accepted by [`CsvProfile::named`].
+    pub const NAMES: &'static [&'static str] = &["sberbank", "tinkoff", "alfabank", "raiffeisen"];
+
+    /// Built-in profile for a bank's CSV export, looked up by name.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ypbank_system::csv_format::CsvProfile;
+    ///
+    /// let profile = CsvProfile::named("tinkoff").unwrap();
+    /// assert_eq!(profile.delimiter, Some(b';'));
+    /// assert!(CsvProfile::named("unknown").is_none());
+    /// ```
+    pub fn named(name: &str) -> Option<Self> {
+        let columns = |columns: &[(&str, CsvField)]| {
+            columns.iter().map(|(name, field)| (name.to_string(), *field)).collect()
+        };
+
+        match name.to_lowercase().as_str() {
+            // SberBusiness statement export, the default layout
+            "sberbank" | "sber" => Some(CsvProfile::default()),
+            // Tinkoff (T-Bank) operations export
+            "tinkoff" | "tbank" => Some(CsvProfile {
+                columns: columns(&[
+                    ("Дата платежа", CsvField::Date),
+                    ("Сумма платежа", CsvField::Amount),
+                    ("Валюта платежа", CsvField::Currency),
+                    ("Описание", CsvField::Description),
+                ]),
+                date_formats: vec!["%d.%m.%Y".to_string()],
+                amount_format: AmountFormat {
+                    decimal_separator: Some(','),
+                    ..Default::default()
+                },
+                debit_credit: DebitCreditConvention::SignedAmount,
+                delimiter: Some(b';'),
+                encoding: Some(WINDOWS_1251),
+                ..Default::default()
+            }),
+            // Alfa-Bank account operations export
+            "alfabank" | "alfa" => Some(CsvProfile {
+                columns: columns(&[
+                    ("Дата операции", CsvField::Date),
+                    ("Референс проводки", CsvField::Reference),
+                    ("Описание операции", CsvField::Description),
+                    ("Валюта", CsvField::Currency),
+                    ("Приход", CsvField::CreditAmount),
+                    ("Расход", CsvField::DebitAmount),
+                ]),
+                date_formats: vec!["%d.%m.%y".to_string()],
+                amount_format: AmountFormat {
+                    decimal_separator: Some(','),
+                    ..Default::default()
+                },
+                debit_credit: DebitCreditConvention::SeparateColumns,
+                delimiter: Some(b';'),
+                encoding: Some(WINDOWS_1251),
+                ..Default::default()
+            }),
+            // Raiffeisen Business Online statement export
+            "raiffeisen" | "raif" => Some(CsvProfile {
+                columns: columns(&[
+                    ("Дата", CsvField::Date),
+                    ("Номер документа", CsvField::Reference),
+                    ("Дебет", CsvField::DebitAmount),
+                    ("Кредит", CsvField::CreditAmount),
+                    ("Контрагент", CsvField::CounterpartyName),
+                    ("Счет контрагента", CsvField::CounterpartyAccount),
+                    ("БИК банка контрагента", CsvField::Bank),
+                    ("Назначение платежа", CsvField::Description),
+                ]),
+                date_formats: vec!["%d.%m.%Y".to_string()],
+                amount_format: AmountFormat {
+                    decimal_separator: Some(','),
+                    ..Default::default()
+                },
+                debit_credit: DebitCreditConvention::SeparateColumns,
+                delimiter: Some(b';'),
+                encoding: None,
+                ..Default::default()
+            }),
+            _ => None,
+        }
+    }
+
+    /// Field held by the column with the given header, if any.
+    fn field_of(&self, header: &str) -> Option<CsvField> {
+        let header = header.trim();
+        self.columns.iter().find(|(name, _)| name == header).map(|(_, field)| *field)
+    }
+
+    /// Adjust the profile's output for Russian-locale Excel: UTF-8 with a BOM,
+    /// `;` delimiter, decimal comma and CRLF line endings.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ypbank_system::csv_format::CsvProfile;
+    ///
+    /// let profile = CsvProfile::default().excel();
+    /// assert!(profile.bom);
+    /// assert_eq!(profile.delimiter, Some(b';'));
+    /// ```
+    pub fn excel(mut self) -> Self {
+        self.encoding = Some(UTF_8);
+        self.bom = true;
+        self.crlf = true;
+        self.delimiter = Some(b';');
+        self.amount_format.decimal_separator = Some(',');
+        self
+    }
+
+    /// Fields in output order, each with the column name it is written under.
+    fn write_columns(&self) -> Vec<(&str, CsvField)> {
+        if let Some(ref columns) = self.output_columns {
+            return columns.iter().map(|(name, field)| (name.as_str(), *field)).collect();
+        }
+
+        let mut columns: Vec<(&str, CsvField)> = Vec::new();
+        for (name, field) in &self.columns {
+            if !columns.iter().any(|(_, f)| f == field) {
+                columns.push((name, *field));
+            }
+        }
+        columns
+    }
+}
+
+/// Statement attributes found in metadata rows around the transaction table.
+#[derive(Debug, Default)]
+struct StatementMetadata {
+    account: Option<String>,
+    from_date: Option<NaiveDate>,
+    to_date: Option<NaiveDate>,
+    /// Signed balance (credit positive) and its date, if stated.
+    opening_balance: Option<(Decimal, Option<NaiveDate>)>,
+    closing_balance: Option<(Decimal, Option<NaiveDate>)>,
+    bank: Option<BankInfo>,
+}
+
+impl StatementMetadata {
+    fn parse(rows: &[&StringRecord]) -> Self {
+        let mut metadata = StatementMetadata::default();
+
+        for row in rows {
+            let cells: Vec<&str> = row.iter().map(str::trim).filter(|c| !c.is_empty()).collect();
+            let Some(label) = cells.first().map(|c| c.to_lowercase()) else { continue };
+            let text = cells.join(" ");
+
+            if metadata.account.is_none() {
+                metadata.account = cells.iter().find(|c| is_account_number(c)).map(|c| c.to_string());
+            }
+
+            let lower = text.to_lowercase();
+            if lower.contains("период") || lower.contains("period") {
+                let dates = find_dates(&text);
+                metadata.from_date = metadata.from_date.or(dates.first().copied());
+                metadata.to_date = metadata.to_date.or(dates.get(1).copied());
+            }
+
+            let balance = || balance_amount(&cells[1..]).map(|amount| (amount, find_dates(&text).first().copied()));
+            if label.starts_with("входящий остаток") || label.starts_with("opening balance") {
+                metadata.opening_balance = balance();
+            } else if label.starts_with("исходящий остаток") || label.starts_with("closing balance") {
+                metadata.closing_balance = balance();
+            }
+
+            // Servicing bank as `Банк,<name>` or `БИК: <code>`
+            let (key, value) = match cells[0].split_once(':') {
+                Some((key, value)) if cells.len() == 1 => (key.trim().to_lowercase(), value.trim().to_string()),
+                _ => (label.trim_end_matches(':').to_string(), cells[1..].join(" ")),
+            };
+            let mut bank = metadata.bank.clone().unwrap_or_default();
+            match key.as_str() {
+                _ if value.is_empty() => continue,
+                "банк" | "bank" => bank.name = Some(value),
+                "бик" if value.len() == 9 && value.bytes().all(|b| b.is_ascii_digit()) => {
+                    bank.clearing_code = Some(value);
+                    bank.clearing_system = Some(BankIdentifier::RUSSIAN_BIK.to_string());
+                }
+                "bic" | "swift" => match value.parse() {
+                    Ok(bic) => bank.bic = Some(bic),
+                    Err(_) => continue,
+                },
+                _ => continue,
+            }
+            metadata.bank = Some(bank);
+        }
+
+        metadata
+    }
+}
+
+/// Whether a metadata cell holds an account number (Russian 20-digit or IBAN).
+fn is_account_number(cell: &str) -> bool {
+    (cell.len() == 20 && cell.bytes().all(|b| b.is_ascii_digit()))
+        || matches!(AccountIdentifier::from(cell), AccountIdentifier::Iban(_))
+}
+
+/// Signed balance from the numbers following a balance label.
+///
+/// Two numbers are read as debit and credit columns, one as a signed amount.
+fn balance_amount(cells: &[&str]) -> Option<Decimal> {
+    let numbers: Vec<Decimal> = cells.iter()
+        .filter(|c| c.bytes().all(|b| b.is_ascii_digit() || b" ,.-".contains(&b)))
+        .filter_map(|c| parse_decimal(c, ',', None))
+        .collect();
+    match numbers.as_slice() {
+        [debit, credit, ..] => Some(credit - debit),
+        [amount] => Some(*amount),
+        [] => None,
+    }
+}
+
+fn metadata_balance(balance_type: BalanceType, amount: Decimal, currency: Currency, date: Option<NaiveDate>) -> Option<Balance> {
+    Some(Balance::from_signed(balance_type, amount, currency, date?))
+}
+
+/// Earliest and latest transactions of an export listed in either date order.
+fn chronological_ends(transactions: &[Transaction]) -> (Option<&Transaction>, Option<&Transaction>) {
+    match (transactions.first(), transactions.last()) {
+        (Some(first), Some(last)) if first.date > last.date => (Some(last), Some(first)),
+        ends => ends,
+    }
+}
+
+/// Dates written as `01.02.2024` or `1 февраля 2024` / `1 February 2024`.
+fn find_dates(text: &str) -> Vec<NaiveDate> {
+    const MONTHS: [[&str; 2]; 12] = [
+        ["января", "january"], ["февраля", "february"], ["марта", "march"],
+        ["апреля", "april"], ["мая", "may"], ["июня", "june"],
+        ["июля", "july"], ["августа", "august"], ["сентября", "september"],
+        ["октября", "october"], ["ноября", "november"], ["декабря", "december"],
+    ];
+
+    let tokens: Vec<&str> = text.split_whitespace().map(|t| t.trim_end_matches([',', ';'])).collect();
+    let mut dates = Vec::new();
+    for (i, token) in tokens.iter().enumerate() {
+        if let Ok(date) = NaiveDate::parse_from_str(token, "%d.%m.%Y") {
+            dates.push(date);
+            continue;
+        }
+        let Some(&[_, month, year]) = tokens.get(i..i + 3) else {
+            continue;
+        };
+        let month = MONTHS.iter().position(|names| names.contains(&month.to_lowercase().as_str()));
+        if let (Ok(day), Some(month), Ok(year)) = (token.parse::<u32>(), month, year.parse::<i32>()) {
+            if let Some(date) = NaiveDate::from_ymd_opt(year, month as u32 + 1, day) {
+                dates.push(date);
+            }
+        }
+    }
+    dates
+}
+
+/// Combine a two-row header, e.g. `Счет` over `Дебет`/`Кредит`.
+///
+/// Returns `None` unless `sub_headers` names at least one known column.
+fn merge_sub_headers(headers: &[String], sub_headers: &StringRecord, profile: &CsvProfile) -> Option<Vec<String>> {
+    let mut merged = Vec::with_capacity(headers.len());
+    let mut group = "";
+    let mut matched = false;
+
+    for (i, header) in headers.iter().enumerate() {
+        if !header.is_empty() {
+            group = header;
+        }
+        let sub_header = sub_headers.get(i).unwrap_or("").trim();
+        if sub_header.is_empty() {
+            merged.push(header.clone());
+        } else {
+            let name = format!("{} {}", group, sub_header);
+            matched |= profile.field_of(&name).is_some();
+            merged.push(name);
+        }
+    }
+
+    matched.then_some(merged)
+}
+
+/// Values of one CSV row, looked up by field.
+struct CsvRow<'a> {
+    record: &'a StringRecord,
+    indices: &'a HashMap<CsvField, usize>,
+    headers: &'a [String],
+    strict: bool,
+}
+
+impl CsvRow<'_> {
+    fn get(&self, field: CsvField) -> &str {
+        self.indices
+            .get(&field)
+            .and_then(|&i| self.record.get(i))
+            .unwrap_or("")
+    }
+
+    /// Line number of the row in the input, starting at 1.
+    fn line(&self) -> usize {
+        self.record.position().map_or(0, |p| p.line() as usize)
+    }
+
+    /// Locate `err` in the cell of the first of `fields` the row has, with
+    /// its header and raw value, and in strict mode add the row and column
+    /// names.
+    fn context(&self, fields: &[CsvField], err: Error) -> Error {
+        let index = fields.iter().find_map(|field| self.indices.get(field).copied());
+        let location = Location {
+            line: Some(self.line()),
+            column: index.map(|i| i + 1),
+            header: index.map(|i| self.headers[i].clone()),
+            offset: self.record.position().map(|p| p.byte() as usize),
+            snippet: Some(index.and_then(|i| self.record.get(i)).unwrap_or("").to_string()),
+            ..Default::default()
+        };
+        if !self.strict {
+            return err.at(location);
+        }
+        let column = fields.iter()
+            .filter_map(|field| self.indices.get(field).map(|&i| self.headers[i].as_str()))
+            .collect::<Vec<_>>()
+            .join("\", \"");
+        let code = match (fields[0], &err) {
+            (_, Error::MissingField(..)) => ErrorCode::CsvMissingValue,
+            (CsvField::Date | CsvField::Time, _) => ErrorCode::CsvBadDate,
+            (CsvField::Amount | CsvField::DebitAmount | CsvField::CreditAmount | CsvField::RunningBalance, _) => {
+                ErrorCode::CsvBadAmount
+            }
+            (CsvField::Currency, _) => ErrorCode::CsvBadCurrency,
+            _ => ErrorCode::CsvBadValue,
+        };
+        Error::CsvRowError { row: self.line(), column, code, message: err.to_string(), location: None }.at(location)
+    }
+}
+
+/// Rows of a parsed CSV document, before they are assembled into statements.
+struct CsvRows {
+    /// Hash of the decoded document, for derived statement IDs.
+    digest: u64,
+    transactions: Vec<(String, Transaction)>,
+    metadata: StatementMetadata,
+    warnings: Vec<Warning>,
+    rejected: Vec<RejectedRecord>,
+}
+
+impl CsvRows {
+    /// ID of the statement for `account` under the profile's policy;
+    /// `index` numbers it among several statements split from the document.
+    fn derive_statement_id(
+        &self,
+        account: &str,
+        transactions: &[Transaction],
+        index: Option<usize>,
+        profile: &CsvProfile,
+        warnings: &mut Vec<Warning>,
+    ) -> String {
+        let statement_id = match profile.statement_id {
+            StatementIdPolicy::Timestamp => {
+                let id = format!("CSV-{}", chrono::Utc::now().timestamp());
+                warnings.push(Warning::new(WarningKind::AssumedDefault, None,
+                    format!("no statement ID, using {} from the current time", id)));
+                id
+            }
+            StatementIdPolicy::Derived => {
+                let from_date = self.metadata.from_date.or_else(|| transactions.iter().map(|tx| tx.date.date()).min());
+                let to_date = self.metadata.to_date.or_else(|| transactions.iter().map(|tx| tx.date.date()).max());
+                let key = format!("{}|{:?}|{:?}|{:016x}", account, from_date, to_date, self.digest);
+                // 12 hex digits keep the ID within the 16 characters of MT940 :20:
+                return format!("CSV-{:012x}", fnv1a(key.as_bytes()) >> 16);
+            }
+            StatementIdPolicy::Explicit(ref id) => id.clone(),
+        };
+        // Derived IDs already differ by account
+        match index {
+            Some(index) => format!("{}-{}", statement_id, index),
+            None => statement_id,
+        }
+    }
+
+    /// Statement for `account`; `index` numbers it among several statements
+    /// split from the same document.
+    fn statement(
+        &self,
+        account: String,
+        mut transactions: Vec<Transaction>,
+        balances: bool,
+        index: Option<usize>,
+        profile: &CsvProfile,
+        warnings: &mut Vec<Warning>,
+    ) -> CsvStatement {
+        let assumed = |message: String| Warning::new(WarningKind::AssumedDefault, None, message);
+        let account = if account.is_empty() {
+            warnings.push(assumed("no account, using UNKNOWN".to_string()));
+            "UNKNOWN".to_string()
+        } else {
+            account
+        };
+        // Rows keep their own account only where it isn't the statement's
+        for tx in &mut transactions {
+            if tx.account.as_deref() == Some(account.as_str()) {
+                tx.account = None;
+            }
+        }
+        let currency = transactions.first().map_or_else(|| {
+            warnings.push(assumed(format!("no transactions, using currency {}", profile.default_currency)));
+            profile.default_currency
+        }, |tx| tx.currency);
+        let statement_id = self.derive_statement_id(&account, &transactions, index, profile, warnings);
+        let mut statement = Statement::new(statement_id, account, currency);
+        statement.from_date = self.metadata.from_date.map(BankDate::from);
+        statement.to_date = self.metadata.to_date.map(BankDate::from);
+        statement.servicing_bank = self.metadata.bank.clone();
+        if balances {
+            statement.opening_balance = self.metadata.opening_balance.and_then(|(amount, date)| {
+                metadata_balance(BalanceType::Opening, amount, currency, date.or(self.metadata.from_date))
+            });
+            statement.closing_balance = self.metadata.closing_balance.and_then(|(amount, date)| {
+                metadata_balance(BalanceType::Closing, amount, currency, date.or(self.metadata.to_date))
+            });
+        }
+
+        // Without stated balances, fall back to the running balance column
+        let (first, last) = chronological_ends(&transactions);
+        if statement.opening_balance.is_none() {
+            statement.opening_balance = first.and_then(|tx| {
+                let balance = tx.running_balance? - tx.signed_amount();
+                metadata_balance(BalanceType::Opening, balance, currency, Some(tx.date.date()))
+            });
+        }
+        if statement.closing_balance.is_none() {
+            statement.closing_balance = last.and_then(|tx| {
+                metadata_balance(BalanceType::Closing, tx.running_balance?, currency, Some(tx.date.date()))
+            });
+        }
+        statement.transactions = transactions;
+
+        CsvStatement { statement }
+    }
+}
+
+/// Streaming reader yielding the transactions of a CSV statement one row at
+/// a time.
+///
+/// The input is decoded and parsed as it is read, so only the current row
+/// is held in memory besides the metadata rows;
+/// [`CsvEntryReader::collect_statement`] reads the rest into a whole
+/// statement. Without an encoding in the profile, it is detected from the
+/// start of the input.
+///
+/// # Examples
+///
+/// ```
+/// use ypbank_system::csv_format::{CsvEntryReader, CsvProfile};
+///
+/// let input = "Дата проводки,Счет Дебет,Сумма по дебету,Сумма по кредиту\n\
+///              20.02.2024,40702810000000000001,100.00,\n";
+/// let mut entries = CsvEntryReader::new(input.as_bytes(), &CsvProfile::default())?;
+/// assert_eq!(entries.next().unwrap()?.amount.to_string(), "100.00");
+/// assert!(entries.next().is_none());
+/// # Ok::<(), ypbank_system::error::Error>(())
+/// ```
+pub struct CsvEntryReader<R: Read> {
+    records: csv::Reader<DecodingReader<R>>,
+    profile: CsvProfile,
+    delimiter: u8,
+    headers: Vec<String>,
+    indices: HashMap<CsvField, usize>,
+    /// Record read ahead while looking for a sub-header.
+    pending: Option<StringRecord>,
+    metadata: Vec<StringRecord>,
+    warnings: Vec<Warning>,
+    rejected: Vec<RejectedRecord>,
+    finished: bool,
+}
+
+impl<R: Read> CsvEntryReader<R> {
+    /// Start reading a CSV statement laid out as described by `profile`,
+    /// up to its header row.
+    pub fn new(reader: R, profile: &CsvProfile) -> Result<Self> {
+        let mut decoded = DecodingReader::new(reader, profile.encoding)?;
+        // Sniffed from the first decoded chunk only, without reading further
+        let delimiter = match profile.delimiter {
+            Some(delimiter) => delimiter,
+            None => match decoded.fill_buf() {
+                Ok(text) => sniff_delimiter(text),
+                Err(e) => return Err(decoded.error(e)),
+            },
+        };
+        let records = ReaderBuilder::new()
+            .delimiter(delimiter)
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(decoded);
+        let mut entries = CsvEntryReader {
+            records,
+            profile: profile.clone(),
+            delimiter,
+            headers: Vec::new(),
+            indices: HashMap::new(),
+            pending: None,
+            metadata: Vec::new(),
+            warnings: Vec::new(),
+            rejected: Vec::new(),
+            finished: false,
+        };
+
+        // Bank exports may put metadata rows (account, period) above the header
+        let header_record = loop {
+            let record = entries.read_record()?.ok_or_else(|| Error::MissingField("date column".to_string(), None))?;
+            if record.iter().any(|cell| profile.field_of(cell) == Some(CsvField::Date)) {
+                break record;
+            }
+            entries.metadata.push(record);
+        };
+
+        let mut headers: Vec<String> = header_record.iter().map(|h| h.trim().to_string()).collect();
+        if let Some(sub_headers) = entries.read_record()? {
+            match merge_sub_headers(&headers, &sub_headers, profile) {
+                Some(merged) => headers = merged,
+                None => entries.pending = Some(sub_headers),
+            }
+        }
+
+        // Map every known header to its column, the first match wins
+        for (i, header) in headers.iter().enumerate() {
+            if let Some(field) = profile.field_of(header) {
+                entries.indices.entry(field).or_insert(i);
+            }
+        }
+
+        if profile.strict {
+            check_columns(&header_record, &headers, &entries.indices, profile)?;
+        } else {
+            let line = header_record.position().map(|p| p.line() as usize);
+            for header in headers.iter().filter(|h| !h.is_empty() && profile.field_of(h).is_none()) {
+                entries.warnings.push(Warning::new(WarningKind::UnknownField, line,
+                    format!("unknown column \"{}\" ignored", header)));
+            }
+            if !entries.indices.contains_key(&CsvField::Currency) {
+                entries.warnings.push(Warning::new(WarningKind::AssumedDefault, line,
+                    format!("no currency column, using {}", profile.default_currency)));
+            }
+        }
+        entries.headers = headers;
+        Ok(entries)
+    }
+
+    /// Read the remaining rows into the whole statement.
+    pub fn collect_statement(self) -> Result<CsvStatement> {
+        Ok(self.collect_outcome()?.statement)
+    }
+
+    /// Read the remaining rows into the whole statement, with the warnings
+    /// and the rejected rows.
+    pub(crate) fn collect_outcome(mut self) -> Result<ParseOutcome<CsvStatement>> {
+        let mut transactions = Vec::new();
+        while let Some(tx) = self.next_transaction()? {
+            transactions.push(tx);
+        }
+        let profile = self.profile.clone();
+        let mut rows = self.into_rows(Vec::new());
+        let mut warnings = std::mem::take(&mut rows.warnings);
+
+        // The metadata account wins over the first account seen in the rows
+        let account = rows.metadata.account.clone()
+            .or_else(|| transactions.iter().filter_map(|tx| tx.account.clone()).next())
+            .unwrap_or_default();
+        let statement = rows.statement(account, transactions, true, None, &profile, &mut warnings);
+        Ok(ParseOutcome { statement, warnings, rejected: rows.rejected })
+    }
+
+    /// What is left of the document once its rows have been read.
+    fn into_rows(self, transactions: Vec<(String, Transaction)>) -> CsvRows {
+        let metadata: Vec<&StringRecord> = self.metadata.iter().collect();
+        CsvRows {
+            digest: self.records.get_ref().digest,
+            transactions,
+            metadata: StatementMetadata::parse(&metadata),
+            warnings: self.warnings,
+            rejected: self.rejected,
+        }
+    }
+
+    fn read_record(&mut self) -> Result<Option<StringRecord>> {
+        if let Some(record) = self.pending.take() {
+            return Ok(Some(record));
+        }
+        let mut record = StringRecord::new();
+        match self.records.read_record(&mut record) {
+            Ok(true) => Ok(Some(record)),
+            Ok(false) => Ok(None),
+            Err(e) => {
+                if let csv::ErrorKind::Io(_) = e.kind() {
+                    if let Some(error) = self.records.get_ref().malformed() {
+                        return Err(error);
+                    }
+                }
+                // The CSV library's message tells the line and byte
+                Err(Error::from(e))
+            }
+        }
+    }
+
+    fn next_transaction(&mut self) -> Result<Option<Transaction>> {
+        while let Some(record) = self.read_record()? {
+            let row = CsvRow { record: &record, indices: &self.indices, headers: &self.headers, strict: self.profile.strict };
+
+            // Skip empty rows
+            if row.get(CsvField::Date).trim().is_empty() {
+                continue;
+            }
+
+            // Summary rows (turnovers, balances) have a label in the date column
+            if !row.get(CsvField::Date).bytes().any(|b| b.is_ascii_digit()) {
+                self.metadata.push(record);
+                continue;
+            }
+
+            match CsvStatement::parse_row(&row, &self.profile, &mut self.warnings) {
+                Ok(Some((_, tx))) => return Ok(Some(tx)),
+                Ok(None) => {}
+                Err(error) if self.profile.skip_invalid => self.rejected.push(RejectedRecord {
+                    line: row.line(),
+                    text: record.iter().collect::<Vec<_>>().join(&char::from(self.delimiter).to_string()),
+                    error,
+                }),
+                Err(error) => return Err(error),
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl<R: Read> Iterator for CsvEntryReader<R> {
+    type Item = Result<Transaction>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        let next = self.next_transaction().transpose();
+        if !matches!(next, Some(Ok(_))) {
+            self.finished = true;
+        }
+        next
+    }
+}
+
+impl<R: Read> EntryReader for CsvEntryReader<R> {
+    fn collect_statement(self: Box<Self>) -> Result<Statement> {
+        Ok(CsvEntryReader::collect_statement(*self)?.statement)
+    }
+}
+
+/// Incremental CSV writer: the statement header, then the transactions one
+/// row at a time.
+///
+/// Unlike [`CsvStatement::write_to_with`], the statement never has to be held
+/// in memory as a whole, so a converted statement can be written while its
+/// input is still being read. Which transactions will follow is not known,
+/// so the optional columns of the default layout are written even when they
+/// end up empty; set [`CsvProfile::output_columns`] to choose the columns.
+///
+/// # Examples
+///
+/// ```
+/// use ypbank_system::csv_format::{CsvProfile, CsvWriter};
+/// use ypbank_system::mt940_format::Mt940EntryReader;
+/// use ypbank_system::types::{Currency, Statement};
+///
+/// let input = ":20:STMT\n:25:ACC001\n:60F:C240101EUR100,00\n:61:2401020102D10,00NTRF//REF1\n:62F:C240102EUR90,00\n-}";
+/// let profile = CsvProfile { output_columns: Some(vec![("Reference".into(), "reference".parse()?)]), ..Default::default() };
+/// let mut writer = CsvWriter::new(Vec::new(), &profile);
+/// writer.write_header(&Statement::new("STMT".into(), "ACC001", Currency::EUR))?;
+/// for transaction in Mt940EntryReader::new(input.as_bytes()) {
+///     writer.write_transaction(&transaction?)?;
+/// }
+/// assert_eq!(writer.finish()?, b"Reference\nREF1\n");
+/// # Ok::<(), ypbank_system::Error>(())
+/// ```
+pub struct CsvWriter<W: Write> {
+    records: csv::Writer<EncodingWriter<W>>,
+    profile: CsvProfile,
+    columns: Vec<(String, CsvField)>,
+    /// Statement account, the own account of transactions without one.
+    account: String,
+}
+
+impl<W: Write> CsvWriter<W> {
+    /// Create a writer to `writer` laid out as described by `profile`.
+    pub fn new(writer: W, profile: &CsvProfile) -> Self {
+        let columns = profile.write_columns().into_iter().map(|(name, field)| (name.to_string(), field)).collect();
+        Self::with_columns(writer, profile, columns)
+    }
+
+    fn with_columns(writer: W, profile: &CsvProfile, columns: Vec<(String, CsvField)>) -> Self {
+        let bom: &[u8] = match profile.encoding {
+            Some(encoding) if encoding == UTF_16LE => b"\xFF\xFE",
+            Some(encoding) if encoding == UTF_16BE => b"\xFE\xFF",
+            Some(encoding) if encoding != UTF_8 => b"",
+            _ if profile.bom => b"\xEF\xBB\xBF",
+            _ => b"",
+        };
+        let records = WriterBuilder::new()
+            .flexible(true)
+            .delimiter(profile.delimiter.unwrap_or(b','))
+            .terminator(if profile.crlf { Terminator::CRLF } else { Terminator::Any(b'\n') })
+            .from_writer(EncodingWriter {
+                inner: writer,
+                encoding: profile.encoding.filter(|&encoding| encoding != UTF_8),
+                bom,
+                pending: Vec::new(),
+            });
+        CsvWriter { records, profile: profile.clone(), columns, account: String::new() }
+    }
+
+    /// Write the rows above the transactions: the servicing bank, when
+    /// known, and the column headers. The transactions of `header` are not
+    /// written.
+    pub fn write_header(&mut self, header: &Statement) -> Result<()> {
+        self.account = header.account.to_string();
+        // The servicing bank goes into metadata rows above the table
+        if let Some(bank) = header.servicing_bank.as_ref().filter(|_| self.profile.bank_rows) {
+            let rows = [
+                ("Банк", bank.name.clone()),
+                ("БИК", bank.clearing_code.clone()),
+                ("BIC", bank.bic.as_ref().map(ToString::to_string)),
+            ];
+            for (label, value) in rows {
+                if let Some(value) = value {
+                    self.records.write_record([label, value.as_str()])?;
+                }
+            }
+        }
+        self.records.write_record(self.columns.iter().map(|(name, _)| name))?;
+        Ok(())
+    }
+
+    /// Write the row of a transaction, one per underlying transaction of a
+    /// batch.
+    pub fn write_transaction(&mut self, transaction: &Transaction) -> Result<()> {
+        let date_format = self.profile.date_formats.first().map_or("%d.%m.%Y", String::as_str);
+
+        // Batched entries are written as one row per underlying transaction
+        for transaction in transaction.expand_details() {
+            let counterparty_account = transaction.counterparty_account.as_ref()
+                .map(ToString::to_string)
+                .unwrap_or_default();
+            let amount = CsvStatement::format_amount(transaction.amount, &self.profile.amount_format);
+            let signed_amount = CsvStatement::format_amount(transaction.signed_amount(), &self.profile.amount_format);
+            let is_debit = transaction.debit_credit == DebitCredit::Debit;
+            let own_account = transaction.account.clone().unwrap_or_else(|| self.account.clone());
+
+            let record = self.columns.iter().map(|(_, field)| match field {
+                CsvField::Date => transaction.date.date().format(date_format).to_string(),
+                CsvField::Time => transaction.date.time()
+                    .map(|time| time.format("%H:%M:%S").to_string())
+                    .unwrap_or_default(),
+                CsvField::DebitAccount if is_debit => own_account.clone(),
+                CsvField::DebitAccount => counterparty_account.clone(),
+                CsvField::CreditAccount if is_debit => counterparty_account.clone(),
+                CsvField::CreditAccount => own_account.clone(),
+                CsvField::DebitAmount if is_debit => amount.clone(),
+                CsvField::CreditAmount if !is_debit => amount.clone(),
+                CsvField::DebitAmount | CsvField::CreditAmount => String::new(),
+                CsvField::Amount if self.profile.debit_credit == DebitCreditConvention::SignedAmount => signed_amount.clone(),
+                CsvField::Amount => amount.clone(),
+                CsvField::SignedAmount => signed_amount.clone(),
+                CsvField::DebitCredit => match self.profile.debit_credit {
+                    DebitCreditConvention::Indicator { ref debit, ref credit } => {
+                        if is_debit { debit.clone() } else { credit.clone() }
+                    }
+                    _ => transaction.debit_credit.to_string().into(),
+                },
+                CsvField::Currency => transaction.currency.to_string(),
+                CsvField::Reference => transaction.reference.clone(),
+                CsvField::Description => transaction.description.clone(),
+                CsvField::Bank => transaction.bank_identifier.as_ref().map(ToString::to_string).unwrap_or_default(),
+                CsvField::CounterpartyName => transaction.counterparty_name.clone().unwrap_or_default(),
+                CsvField::CounterpartyAccount => counterparty_account.clone(),
+                CsvField::Account => own_account.clone(),
+                CsvField::RunningBalance => transaction.running_balance
+                    .map(|balance| CsvStatement::format_amount(balance, &self.profile.amount_format))
+                    .unwrap_or_default(),
+                CsvField::Category => transaction.category.clone().unwrap_or_default(),
+                CsvField::Tags => transaction.tags.iter().map(String::as_str).collect::<Vec<_>>().join(", "),
+                CsvField::Status => String::from(match transaction.status {
+                    EntryStatus::Booked => "Booked",
+                    EntryStatus::Pending => "Pending",
+                    EntryStatus::Information => "Information",
+                    EntryStatus::Reversed => "Reversed",
+                }),
+            });
+
+            self.records.write_record(record)?;
+        }
+        Ok(())
+    }
+
+    /// Flush the rows written so far, returning the underlying writer.
+    pub fn finish(self) -> Result<W> {
+        let mut output = self.records.into_inner().map_err(|e| Error::from(e.into_error()))?;
+        output.flush()?;
+        Ok(output.inner)
+    }
+}
+
+/// Writer encoding the UTF-8 text written to it, after a byte order mark.
+struct EncodingWriter<W> {
+    inner: W,
+    /// Output encoding, `None` to write UTF-8 as is.
+    encoding: Option<&'static Encoding>,
+    /// Byte order mark still to be written.
+    bom: &'static [u8],
+    /// Start of a character split between writes.
+    pending: Vec<u8>,
+}
+
+impl<W: Write> Write for EncodingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !self.bom.is_empty() {
+            self.inner.write_all(std::mem::take(&mut self.bom))?;
+        }
+        let Some(encoding) = self.encoding else {
+            self.inner.write_all(buf)?;
+            return Ok(buf.len());
+        };
+        self.pending.extend_from_slice(buf);
+        let complete = match std::str::from_utf8(&self.pending) {
+            Ok(text) => text.len(),
+            Err(err) if err.error_len().is_none() => err.valid_up_to(),
+            Err(err) => return Err(io::Error::new(io::ErrorKind::InvalidData, err)),
+        };
+        let text = String::from_utf8_lossy(&self.pending[..complete]);
+        self.inner.write_all(&encode(&text, encoding))?;
+        self.pending.drain(..complete);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(feature = "async")]
+impl CsvStatement {
+    /// Parse a CSV statement from an async source, such as a socket.
+    ///
+    /// [`CsvStatement::from_read`] runs on tokio's blocking pool and reads the
+    /// input as it arrives, without holding all of it or blocking the
+    /// executor.
+    pub async fn from_async_read<R>(reader: R) -> Result<Self>
+    where
+        R: tokio::io::AsyncRead + Unpin + Send + 'static,
+    {
+        crate::format::read_async(reader, |mut input| Self::from_read(&mut input)).await
+    }
+
+    /// Write the statement to an async destination, like
+    /// [`CsvStatement::write_to`] run on tokio's blocking pool; returns the
+    /// destination.
+    pub async fn write_to_async<W>(self, writer: W) -> Result<W>
+    where
+        W: tokio::io::AsyncWrite + Unpin + Send + 'static,
+    {
+        crate::format::write_async(writer, move |mut output| self.write_to(&mut output)).await
+    }
 }
 
 impl CsvStatement {
     /// Parse a CSV statement from any source implementing `Read`.
     ///
-    /// # Arguments
+    /// # Arguments
+    ///
+    /// * `reader` - A mutable reference to a type implementing `Read`
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::fs::File;
+    /// use ypbank_system::csv_format::CsvStatement;
+    ///
+    /// let mut file = File::open("statement.csv")?;
+    /// let statement = CsvStatement::from_read(&mut file)?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn from_read<R: Read>(reader: &mut R) -> Result<Self> {
+        Self::from_read_with(reader, &CsvProfile::default())
+    }
+
+    /// Parse a CSV statement laid out as described by `profile`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::fs::File;
+    /// use ypbank_system::csv_format::{CsvProfile, CsvStatement};
+    ///
+    /// let profile = CsvProfile { date_formats: vec!["%Y-%m-%d".into()], ..Default::default() };
+    /// let mut file = File::open("statement.csv")?;
+    /// let statement = CsvStatement::from_read_with(&mut file, &profile)?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn from_read_with<R: Read>(reader: &mut R, profile: &CsvProfile) -> Result<Self> {
+        Ok(Self::from_read_with_diagnostics(reader, profile)?.statement)
+    }
+
+    /// Parse like [`CsvStatement::from_read_with`], also returning warnings
+    /// for the unknown columns, the rows skipped for lack of an amount and
+    /// the defaults assumed, such as the profile's currency.
+    ///
+    /// # Examples
     ///
-    /// * `reader` - A mutable reference to a type implementing `Read`
+    /// ```
+    /// use ypbank_system::csv_format::{CsvProfile, CsvStatement};
+    ///
+    /// let input = "Дата проводки,Счет Дебет,Сумма по дебету,Сумма по кредиту\n\
+    ///              20.02.2024,40702810000000000001,100.00,\n\
+    ///              21.02.2024,40702810000000000001,,\n";
+    /// let outcome = CsvStatement::from_read_with_diagnostics(&mut input.as_bytes(), &CsvProfile::default())?;
+    /// assert_eq!(outcome.statement.statement.transactions.len(), 1);
+    /// assert!(outcome.warnings.iter().any(|w| w.line == Some(3)));
+    /// # Ok::<(), ypbank_system::error::Error>(())
+    /// ```
+    pub fn from_read_with_diagnostics<R: Read>(reader: &mut R, profile: &CsvProfile) -> Result<ParseOutcome<Self>> {
+        CsvEntryReader::new(reader, profile)?.collect_outcome()
+    }
+
+    /// Parse a CSV export covering several accounts into one statement per
+    /// account, in order of first appearance.
+    ///
+    /// A row belongs to the account in its [`CsvField::Account`] column, or
+    /// else to the debit account of a debit and the credit account of a credit;
+    /// rows without either go to the account named in the metadata rows.
+    /// Balances from the metadata rows are kept only for that account.
     ///
     /// # Examples
     ///
-    /// ```no_run
-    /// use std::fs::File;
-    /// use ypbank_system::csv_format::CsvStatement;
+    /// ```
+    /// use ypbank_system::csv_format::{CsvProfile, CsvStatement};
     ///
-    /// let mut file = File::open("statement.csv")?;
-    /// let statement = CsvStatement::from_read(&mut file)?;
-    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// let input = "Дата проводки,Счет Дебет,Счет Кредит,Сумма по дебету,Сумма по кредиту\n\
+    ///              20.02.2024,40702810000000000001,40817810000000000009,100.00,\n\
+    ///              20.02.2024,40817810000000000009,40702810000000000002,,50.00\n";
+    /// let statements = CsvStatement::from_read_per_account(&mut input.as_bytes(), &CsvProfile::default())?;
+    /// assert_eq!(statements.len(), 2);
+    /// assert_eq!(statements[1].statement.account.id(), "40702810000000000002");
+    /// # Ok::<(), ypbank_system::error::Error>(())
     /// ```
-    pub fn from_read<R: Read>(reader: &mut R) -> Result<Self> {
-        let mut csv_reader = Reader::from_reader(reader);
+    pub fn from_read_per_account<R: Read>(reader: &mut R, profile: &CsvProfile) -> Result<Vec<Self>> {
+        let rows = Self::read_rows(reader, profile)?;
+        let fallback = rows.metadata.account.clone().unwrap_or_default();
+
+        let mut groups: Vec<(String, Vec<Transaction>)> = Vec::new();
+        for (account, tx) in &rows.transactions {
+            let account = if account.is_empty() { &fallback } else { account };
+            match groups.iter_mut().find(|(a, _)| a == account) {
+                Some((_, transactions)) => transactions.push(tx.clone()),
+                None => groups.push((account.clone(), vec![tx.clone()])),
+            }
+        }
+        if groups.is_empty() {
+            groups.push((fallback.clone(), Vec::new()));
+        }
+
+        let single = groups.len() == 1;
+        Ok(groups
+            .into_iter()
+            .enumerate()
+            .map(|(i, (account, transactions))| {
+                let balances = single || account == fallback;
+                let index = if single { None } else { Some(i + 1) };
+                rows.statement(account, transactions, balances, index, profile, &mut Vec::new())
+            })
+            .collect())
+    }
+
+    /// Transactions of a CSV document with the account each row belongs to
+    /// (empty when the row doesn't name one), plus its metadata.
+    fn read_rows<R: Read>(reader: &mut R, profile: &CsvProfile) -> Result<CsvRows> {
+        let mut entries = CsvEntryReader::new(reader, profile)?;
         let mut transactions = Vec::new();
+        while let Some(tx) = entries.next_transaction()? {
+            transactions.push((tx.account.clone().unwrap_or_default(), tx));
+        }
+        Ok(entries.into_rows(transactions))
+    }
 
-        let mut account = String::new();
-        let currency = String::from("RUB"); // Default currency
+    /// Transaction of a data row with the account it belongs to, or `None`
+    /// when the row has no amount and isn't read strictly.
+    fn parse_row(row: &CsvRow, profile: &CsvProfile, warnings: &mut Vec<Warning>) -> Result<Option<(String, Transaction)>> {
+        let skipped = Warning::new(WarningKind::SkippedRecord, Some(row.line()), "row without an amount skipped");
 
-        for result in csv_reader.deserialize() {
-            let record: CsvRecord = result?;
+        // Try to parse date; the time may follow it in the same cell
+        let (date, time) = Self::parse_date_time(row.get(CsvField::Date), profile)
+            .map_err(|e| row.context(&[CsvField::Date], e))?;
+        let time = match row.get(CsvField::Time).trim() {
+            "" => time,
+            time => Some(Self::parse_time(time).map_err(|e| row.context(&[CsvField::Time], e))?),
+        };
 
-            // Skip empty rows
-            if record.date.trim().is_empty() {
-                continue;
-            }
+        let debit_account = row.get(CsvField::DebitAccount);
+        let credit_account = row.get(CsvField::CreditAccount);
 
-            // Try to parse date
-            let date = Self::parse_date(&record.date)?;
+        // Determine debit or credit
+        let (amount, debit_credit, counterparty_account) = match profile.debit_credit {
+            DebitCreditConvention::SeparateColumns => {
+                if !row.get(CsvField::DebitAmount).is_empty() {
+                    let amount = Self::parse_amount(row.get(CsvField::DebitAmount), profile)
+                        .map_err(|e| row.context(&[CsvField::DebitAmount], e))?;
+                    let counterparty = if !credit_account.is_empty() {
+                        Some(Self::extract_account(credit_account))
+                    } else {
+                        None
+                    };
 
-            // Determine debit or credit
-            let (amount, debit_credit, counterparty_account) = if !record.debit_amount.is_empty() {
-                let amount = Self::parse_amount(&record.debit_amount)?;
-                let counterparty = if !record.credit_account.is_empty() {
-                    Some(Self::extract_account(&record.credit_account))
+                    (amount, DebitCredit::Debit, counterparty)
+                } else if !row.get(CsvField::CreditAmount).is_empty() {
+                    let amount = Self::parse_amount(row.get(CsvField::CreditAmount), profile)
+                        .map_err(|e| row.context(&[CsvField::CreditAmount], e))?;
+                    let counterparty = if !debit_account.is_empty() {
+                        Some(Self::extract_account(debit_account))
+                    } else {
+                        None
+                    };
+
+                    (amount, DebitCredit::Credit, counterparty)
+                } else if profile.strict {
+                    let err = Error::MissingField("debit or credit amount".to_string(), None);
+                    return Err(row.context(&[CsvField::DebitAmount, CsvField::CreditAmount], err));
+                } else {
+                    warnings.push(skipped);
+                    return Ok(None);
+                }
+            }
+            DebitCreditConvention::SignedAmount => {
+                if row.get(CsvField::Amount).trim().is_empty() {
+                    if profile.strict {
+                        let err = Error::MissingField("amount".to_string(), None);
+                        return Err(row.context(&[CsvField::Amount], err));
+                    }
+                    warnings.push(skipped);
+                    return Ok(None);
+                }
+                let amount = Self::parse_amount(row.get(CsvField::Amount), profile)
+                    .map_err(|e| row.context(&[CsvField::Amount], e))?;
+                let debit_credit = if amount.is_sign_negative() {
+                    DebitCredit::Debit
                 } else {
-                    None
+                    DebitCredit::Credit
                 };
-
-                // Update account from debit_account if needed
-                if !record.debit_account.is_empty() && account.is_empty() {
-                    account = Self::extract_account(&record.debit_account);
+                (amount.abs(), debit_credit, None)
+            }
+            DebitCreditConvention::Indicator { ref debit, ref credit } => {
+                if row.get(CsvField::Amount).trim().is_empty() {
+                    if profile.strict {
+                        let err = Error::MissingField("amount".to_string(), None);
+                        return Err(row.context(&[CsvField::Amount], err));
+                    }
+                    warnings.push(skipped);
+                    return Ok(None);
                 }
-
-                (amount, DebitCredit::Debit, counterparty)
-            } else if !record.credit_amount.is_empty() {
-                let amount = Self::parse_amount(&record.credit_amount)?;
-                let counterparty = if !record.debit_account.is_empty() {
-                    Some(Self::extract_account(&record.debit_account))
+                let amount = Self::parse_amount(row.get(CsvField::Amount), profile)
+                    .map_err(|e| row.context(&[CsvField::Amount], e))?;
+                let indicator = row.get(CsvField::DebitCredit).trim();
+                let debit_credit = if indicator.eq_ignore_ascii_case(debit) {
+                    DebitCredit::Debit
+                } else if indicator.eq_ignore_ascii_case(credit) {
+                    DebitCredit::Credit
                 } else {
-                    None
+                    let err = Error::ParseError(format!("Invalid D/C indicator: {}", indicator), None);
+                    return Err(row.context(&[CsvField::DebitCredit], err));
                 };
+                (amount, debit_credit, None)
+            }
+        };
 
-                // Update account from credit_account if needed
-                if !record.credit_account.is_empty() && account.is_empty() {
-                    account = Self::extract_account(&record.credit_account);
-                }
+        // Own account: explicit column, else the side matching the direction
+        let own_account = match (row.get(CsvField::Account).trim(), debit_credit) {
+            ("", DebitCredit::Debit) if !debit_account.is_empty() => Self::extract_account(debit_account),
+            ("", DebitCredit::Credit) if !credit_account.is_empty() => Self::extract_account(credit_account),
+            (acc, _) => acc.to_string(),
+        };
 
-                (amount, DebitCredit::Credit, counterparty)
-            } else {
-                continue; // Skip if no amount
-            };
+        let counterparty_account = match row.get(CsvField::CounterpartyAccount).trim() {
+            "" => counterparty_account,
+            acc => Some(acc.to_string()),
+        };
 
-            // Extract counterparty name from description or account field
-            let counterparty_name = Self::extract_counterparty_name(&record.description, &record.debit_account, &record.credit_account);
-
-            transactions.push(Transaction {
-                reference: record.reference.trim().to_string(),
-                date,
-                value_date: Some(date),
-                amount,
-                currency: currency.clone(),
-                debit_credit,
-                account: None,
-                counterparty_account,
-                counterparty_name,
-                bank_identifier: if !record.bank.is_empty() {
-                    Some(Self::extract_bic(&record.bank))
-                } else {
-                    None
-                },
-                description: record.description.trim().to_string(),
-                additional_info: None,
-            });
-        }
+        // Extract counterparty name from description or the counterparty's
+        // side of the account columns
+        let counterparty_cell = match debit_credit {
+            DebitCredit::Debit => credit_account,
+            DebitCredit::Credit => debit_account,
+        };
+        let counterparty_name = match row.get(CsvField::CounterpartyName).trim() {
+            "" => Self::extract_counterparty_name(row.get(CsvField::Description), counterparty_cell),
+            name => Some(name.to_string()),
+        };
 
-        if account.is_empty() {
-            account = "UNKNOWN".to_string();
-        }
+        let running_balance = match row.get(CsvField::RunningBalance).trim() {
+            "" => None,
+            balance => Some(
+                Self::parse_amount(balance, profile).map_err(|e| row.context(&[CsvField::RunningBalance], e))?,
+            ),
+        };
 
-        let statement_id = format!("CSV-{}", chrono::Utc::now().timestamp());
-        let mut statement = Statement::new(statement_id, account, currency);
-        statement.transactions = transactions;
+        let status = match row.get(CsvField::Status).trim() {
+            "" => EntryStatus::Booked,
+            status => status.parse::<EntryStatus>()
+                .map_err(|e| row.context(&[CsvField::Status], Error::ParseError(e, None)))?,
+        };
 
-        Ok(CsvStatement { statement })
+        let bank_identifier = match row.get(CsvField::Bank).trim() {
+            "" => None,
+            bank => {
+                let identifier = Self::extract_bank(bank);
+                if identifier.is_none() {
+                    warnings.push(Warning::new(WarningKind::IgnoredValue, Some(row.line()),
+                        format!("no BIC or БИК in bank field {:?}, ignored", bank)));
+                }
+                identifier
+            }
+        };
+        let currency = match row.get(CsvField::Currency).trim() {
+            "" => {
+                if row.indices.contains_key(&CsvField::Currency) {
+                    warnings.push(Warning::new(WarningKind::AssumedDefault, Some(row.line()),
+                        format!("no currency, using {}", profile.default_currency)));
+                }
+                profile.default_currency
+            }
+            ccy => ccy.parse().map_err(|_| {
+                row.context(&[CsvField::Currency], Error::InvalidCurrency(ccy.to_string(), None))
+            })?,
+        };
+
+        Ok(Some((own_account.clone(), Transaction {
+            reference: row.get(CsvField::Reference).trim().to_string(),
+            date: time.map_or(date.into(), |time| date.and_time(time).into()),
+            value_date: Some(date.into()),
+            amount,
+            currency,
+            debit_credit,
+            account: (!own_account.is_empty()).then_some(own_account),
+            counterparty_account: counterparty_account.map(AccountIdentifier::from),
+            counterparty_name,
+            bank_identifier,
+            description: row.get(CsvField::Description).trim().to_string(),
+            status,
+            running_balance,
+            category: match row.get(CsvField::Category).trim() {
+                "" => None,
+                category => Some(category.to_string()),
+            },
+            tags: row.get(CsvField::Tags)
+                .split(',')
+                .map(str::trim)
+                .filter(|tag| !tag.is_empty())
+                .map(str::to_string)
+                .collect(),
+            ..Transaction::default()
+        })))
     }
 
     /// Write a CSV statement to any destination implementing `Write`.
@@ -152,78 +1506,116 @@ impl CsvStatement {
     /// ```no_run
     /// use std::fs::File;
     /// use ypbank_system::csv_format::CsvStatement;
-    /// use ypbank_system::types::Statement;
+    /// use ypbank_system::types::{Currency, Statement};
     ///
-    /// let statement = Statement::new("123".into(), "ACC001".into(), "USD".into());
+    /// let statement = Statement::new("123".into(), "ACC001", Currency::USD);
     /// let csv = CsvStatement { statement };
     /// let mut file = File::create("output.csv")?;
     /// csv.write_to(&mut file)?;
     /// # Ok::<(), Box<dyn std::error::Error>>(())
     /// ```
     pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<()> {
-        let mut csv_writer = Writer::from_writer(writer);
+        self.write_to_with(writer, &CsvProfile::default())
+    }
+
+    /// Write a CSV statement laid out as described by `profile`.
+    pub fn write_to_with<W: Write>(&self, writer: &mut W, profile: &CsvProfile) -> Result<()> {
+        // Optional columns are left out of the default layout when empty
+        let mut columns = profile.write_columns();
+        if profile.output_columns.is_none() {
+            let transactions = &self.statement.transactions;
+            columns.retain(|(_, field)| match field {
+                CsvField::RunningBalance => transactions.iter().any(|tx| tx.running_balance.is_some()),
+                CsvField::Category => transactions.iter().any(|tx| tx.category.is_some()),
+                CsvField::Tags => transactions.iter().any(|tx| !tx.tags.is_empty()),
+                CsvField::Status => transactions.iter().any(|tx| tx.status != EntryStatus::Booked),
+                CsvField::Time => transactions.iter().any(|tx| tx.date.time().is_some()),
+                _ => true,
+            });
+        }
+        let columns = columns.into_iter().map(|(name, field)| (name.to_string(), field)).collect();
 
+        let mut csv_writer = CsvWriter::with_columns(writer, profile, columns);
+        csv_writer.write_header(&self.statement)?;
         for transaction in &self.statement.transactions {
-            let (debit_account, credit_account, debit_amount, credit_amount) = match transaction.debit_credit {
-                DebitCredit::Debit => (
-                    self.statement.account.clone(),
-                    transaction.counterparty_account.clone().unwrap_or_default(),
-                    transaction.amount.to_string(),
-                    String::new(),
-                ),
-                DebitCredit::Credit => (
-                    transaction.counterparty_account.clone().unwrap_or_default(),
-                    self.statement.account.clone(),
-                    String::new(),
-                    transaction.amount.to_string(),
-                ),
-            };
+            csv_writer.write_transaction(transaction)?;
+        }
+        csv_writer.finish()?;
+        Ok(())
+    }
 
-            let record = CsvRecord {
-                date: transaction.date.format("%d.%m.%Y").to_string(),
-                debit_account,
-                credit_account,
-                debit_amount,
-                credit_amount,
-                reference: transaction.reference.clone(),
-                description: transaction.description.clone(),
-                bank: transaction.bank_identifier.clone().unwrap_or_default(),
-            };
+    fn format_amount(amount: Decimal, format: &AmountFormat) -> String {
+        let text = amount.abs().to_string();
+        let (integer, fraction) = text.split_once('.').map_or((text.as_str(), None), |(i, f)| (i, Some(f)));
 
-            csv_writer.serialize(record)?;
+        let mut formatted = String::new();
+        for (i, digit) in integer.chars().enumerate() {
+            if i > 0 && (integer.len() - i) % 3 == 0 {
+                if let Some(sep) = format.thousands_separator {
+                    formatted.push(sep);
+                }
+            }
+            formatted.push(digit);
+        }
+        if let Some(fraction) = fraction {
+            formatted.push(format.decimal_separator.unwrap_or('.'));
+            formatted.push_str(fraction);
         }
 
-        csv_writer.flush()?;
-        Ok(())
+        match (amount.is_sign_negative(), format.negative_parentheses) {
+            (false, _) => formatted,
+            (true, true) => format!("({})", formatted),
+            (true, false) => format!("-{}", formatted),
+        }
     }
 
-    fn parse_date(date_str: &str) -> Result<NaiveDate> {
-        // Try various date formats
-        let formats = vec![
-            "%d.%m.%Y",     // 20.02.2024
-            "%Y-%m-%d",     // 2024-02-20
-            "%d/%m/%Y",     // 20/02/2024
-            "%m/%d/%Y",     // 02/20/2024
-        ];
-
-        for format in formats {
-            if let Ok(date) = NaiveDate::parse_from_str(date_str.trim(), format) {
-                return Ok(date);
+    /// Parse a date cell that may carry a time of day after a space or `T`,
+    /// e.g. `20.02.2024 14:35` or `2024-02-20T14:35:00`.
+    fn parse_date_time(cell: &str, profile: &CsvProfile) -> Result<(NaiveDate, Option<NaiveTime>)> {
+        let cell = cell.trim();
+        if let Some((date, time)) = cell.rsplit_once([' ', 'T']) {
+            if let Ok(time) = Self::parse_time(time) {
+                return Ok((Self::parse_date(date, profile)?, Some(time)));
             }
         }
+        Ok((Self::parse_date(cell, profile)?, None))
+    }
 
-        Err(Error::InvalidDate(date_str.to_string()))
+    fn parse_time(time_str: &str) -> Result<NaiveTime> {
+        let time_str = time_str.trim();
+        NaiveTime::parse_from_str(time_str, "%H:%M:%S")
+            .or_else(|_| NaiveTime::parse_from_str(time_str, "%H:%M"))
+            .map_err(|_| Error::InvalidDate(time_str.to_string(), None))
     }
 
-    fn parse_amount(amount_str: &str) -> Result<Decimal> {
-        // Remove spaces and replace comma with dot
-        let cleaned = amount_str
-            .trim()
-            .replace(' ', "")
-            .replace(',', ".");
+    fn parse_date(date_str: &str, profile: &CsvProfile) -> Result<NaiveDate> {
+        let date_str = date_str.trim();
+        let mut parsed = profile.date_formats.iter()
+            .filter_map(|format| NaiveDate::parse_from_str(date_str, format).ok());
+
+        let date = parsed.next().ok_or_else(|| Error::InvalidDate(date_str.to_string(), None))?;
+        if parsed.any(|other| other != date) {
+            return Err(Error::InvalidDate(format!(
+                "{} is ambiguous under the configured date formats",
+                date_str
+            ), None));
+        }
+        Ok(date)
+    }
+
+    fn parse_amount(amount_str: &str, profile: &CsvProfile) -> Result<Decimal> {
+        let format = &profile.amount_format;
+        let text = amount_str.trim();
+
+        // Accounting notation: (1 234,56) is negative
+        let (text, negative) = match text.strip_prefix('(').and_then(|inner| inner.strip_suffix(')')) {
+            Some(inner) if format.negative_parentheses => (inner, true),
+            _ => (text, false),
+        };
 
-        Decimal::from_str(&cleaned)
-            .map_err(|_| Error::InvalidAmount(amount_str.to_string()))
+        let amount = parse_decimal(text, format.decimal_separator.unwrap_or(','), format.thousands_separator)
+            .ok_or_else(|| Error::InvalidAmount(amount_str.to_string(), None))?;
+        Ok(if negative { -amount } else { amount })
     }
 
     fn extract_account(account_field: &str) -> String {
@@ -237,32 +1629,26 @@ impl CsvStatement {
             .to_string()
     }
 
-    fn extract_bic(bank_field: &str) -> String {
-        // Extract BIC from bank field like "БИК 044525545 АО ЮниКредит Банк, г.Москва"
-        // БИК is Cyrillic, "BIC " is ASCII
-        if let Some(bic_start) = bank_field.find("БИК ") {
-            // БИК is 3 UTF-8 chars + space = need to find the space and skip past it
-            let after_bic = &bank_field[bic_start + "БИК ".len()..];
-            after_bic
-                .split_whitespace()
-                .next()
-                .unwrap_or("")
-                .to_string()
-        } else if let Some(bic_start) = bank_field.find("BIC ") {
-            let after_bic = &bank_field[bic_start + "BIC ".len()..];
-            after_bic
-                .split_whitespace()
-                .next()
-                .unwrap_or("")
-                .to_string()
-        } else {
-            bank_field.trim().to_string()
+    fn extract_bank(bank_field: &str) -> Option<BankIdentifier> {
+        // Extract the code from a bank field like "БИК 044525545 АО ЮниКредит Банк, г.Москва";
+        // БИК is the Russian nine-digit bank code, "BIC " a SWIFT BIC
+        let code = ["БИК ", "BIC "].iter()
+            .find_map(|label| {
+                let start = bank_field.find(label)?;
+                Some(bank_field[start + label.len()..].split_whitespace().next().unwrap_or(""))
+            })
+            .unwrap_or(bank_field.trim());
+
+        // A bank name alone or a malformed code is not an identifier
+        match code.parse::<BankIdentifier>().ok()? {
+            BankIdentifier::Bic(_) if bank_field.contains("БИК ") => None,
+            bank => Some(bank),
         }
     }
 
-    fn extract_counterparty_name(description: &str, debit_account: &str, credit_account: &str) -> Option<String> {
-        // Try to extract name from account fields
-        let account_lines: Vec<&str> = debit_account.lines().chain(credit_account.lines()).collect();
+    fn extract_counterparty_name(description: &str, counterparty_account: &str) -> Option<String> {
+        // Try to extract name from the account field
+        let account_lines: Vec<&str> = counterparty_account.lines().collect();
 
         // Third line often contains the name
         if account_lines.len() >= 3 {
@@ -281,6 +1667,243 @@ impl CsvStatement {
     }
 }
 
+/// `Read` adapter decoding CSV input to UTF-8 as it is read, detecting the
+/// encoding from the first chunk when none is given.
+struct DecodingReader<R> {
+    inner: R,
+    decoder: Decoder,
+    input: Vec<u8>,
+    consumed: usize,
+    eof: bool,
+    output: Vec<u8>,
+    position: usize,
+    finished: bool,
+    /// FNV-1a hash of the text decoded so far.
+    digest: u64,
+    /// Encoding the input turned out not to be valid in.
+    malformed: Option<&'static Encoding>,
+}
+
+impl<R: Read> DecodingReader<R> {
+    const CHUNK: usize = 64 * 1024;
+
+    fn new(mut inner: R, encoding: Option<&'static Encoding>) -> io::Result<Self> {
+        let mut input = vec![0; Self::CHUNK];
+        let mut length = 0;
+        while length < input.len() {
+            match inner.read(&mut input[length..]) {
+                Ok(0) => break,
+                Ok(n) => length += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        input.truncate(length);
+        let eof = length < Self::CHUNK;
+
+        let encoding = encoding.unwrap_or_else(|| match Encoding::for_bom(&input) {
+            Some((encoding, _)) => encoding,
+            // The chunk may end in the middle of a character
+            None => match std::str::from_utf8(&input) {
+                Ok(_) => UTF_8,
+                Err(e) if e.error_len().is_none() && !eof => UTF_8,
+                // Legacy Russian bank exports
+                Err(_) => WINDOWS_1251,
+            },
+        });
+
+        // A byte order mark, if present, overrides the requested encoding
+        Ok(DecodingReader {
+            inner,
+            decoder: encoding.new_decoder(),
+            input,
+            consumed: 0,
+            eof,
+            output: Vec::new(),
+            position: 0,
+            finished: false,
+            digest: fnv1a(b""),
+            malformed: None,
+        })
+    }
+
+    /// Decode the next part of the input, reading more once it is used up.
+    fn fill(&mut self) -> io::Result<()> {
+        if self.consumed == self.input.len() && !self.eof {
+            self.input.resize(Self::CHUNK, 0);
+            let length = loop {
+                match self.inner.read(&mut self.input) {
+                    Ok(n) => break n,
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                    Err(e) => return Err(e),
+                }
+            };
+            self.input.truncate(length);
+            self.consumed = 0;
+            self.eof = length == 0;
+        }
+
+        self.output.resize(Self::CHUNK * 3, 0);
+        let (result, read, written) =
+            self.decoder.decode_to_utf8_without_replacement(&self.input[self.consumed..], &mut self.output, self.eof);
+        self.consumed += read;
+        self.output.truncate(written);
+        self.position = 0;
+        self.digest = fnv1a_extend(self.digest, &self.output);
+        match result {
+            DecoderResult::InputEmpty => self.finished = self.eof,
+            DecoderResult::OutputFull => {}
+            DecoderResult::Malformed(..) => {
+                let encoding = self.decoder.encoding();
+                self.malformed = Some(encoding);
+                return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Input is not valid {}", encoding.name())));
+            }
+        }
+        Ok(())
+    }
+
+    /// Parse error for input that isn't valid in its encoding, if it wasn't.
+    fn malformed(&self) -> Option<Error> {
+        self.malformed.map(|encoding| Error::ParseError(format!("Input is not valid {}", encoding.name()), None))
+    }
+
+    /// `err` from decoding, as a parse error when the input was malformed.
+    fn error(&self, err: io::Error) -> Error {
+        self.malformed().unwrap_or_else(|| Error::from(err))
+    }
+}
+
+impl<R: Read> Read for DecodingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let decoded = self.fill_buf()?;
+        let length = buf.len().min(decoded.len());
+        buf[..length].copy_from_slice(&decoded[..length]);
+        self.consume(length);
+        Ok(length)
+    }
+}
+
+impl<R: Read> BufRead for DecodingReader<R> {
+    /// Decoded text not read yet, at most one chunk, decoding more if
+    /// there is none.
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        while self.position == self.output.len() && !self.finished {
+            self.fill()?;
+        }
+        Ok(&self.output[self.position..])
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.position += amount;
+    }
+}
+
+/// Strict-mode check that every header is known and the amount columns of
+/// the profile's debit/credit convention are present.
+fn check_columns(
+    header_record: &StringRecord,
+    headers: &[String],
+    indices: &HashMap<CsvField, usize>,
+    profile: &CsvProfile,
+) -> Result<()> {
+    let row = header_record.position().map_or(0, |p| p.line() as usize);
+    let location = Location {
+        line: Some(row),
+        offset: header_record.position().map(|p| p.byte() as usize),
+        ..Default::default()
+    };
+
+    let unknown: Vec<(usize, &str)> = headers.iter()
+        .map(String::as_str)
+        .enumerate()
+        .filter(|(_, header)| !header.is_empty() && profile.field_of(header).is_none())
+        .collect();
+    if let Some(&(first, header)) = unknown.first() {
+        return Err(Error::CsvRowError {
+            row,
+            column: unknown.iter().map(|(_, header)| *header).collect::<Vec<_>>().join("\", \""),
+            code: ErrorCode::CsvUnknownColumn,
+            message: "unknown column".to_string(),
+            location: None,
+        }
+        .at(Location { column: Some(first + 1), snippet: Some(header.to_string()), ..location }));
+    }
+
+    let required: &[CsvField] = match profile.debit_credit {
+        DebitCreditConvention::SeparateColumns => &[CsvField::DebitAmount, CsvField::CreditAmount],
+        DebitCreditConvention::SignedAmount => &[CsvField::Amount],
+        DebitCreditConvention::Indicator { .. } => &[CsvField::Amount, CsvField::DebitCredit],
+    };
+    let missing: Vec<String> = required.iter()
+        .filter(|field| !indices.contains_key(field))
+        .map(|field| profile.columns.iter()
+            .find(|(_, f)| f == field)
+            .map_or_else(|| format!("{:?}", field), |(name, _)| name.clone()))
+        .collect();
+    if !missing.is_empty() {
+        return Err(Error::CsvRowError {
+            row,
+            code: ErrorCode::CsvMissingColumn,
+            message: if missing.len() == 1 { "missing required column" } else { "missing required columns" }.to_string(),
+            column: missing.join("\", \""),
+            location: None,
+        }
+        .at(location));
+    }
+    Ok(())
+}
+
+/// Encode CSV text; UTF-16 is done by hand, as `encoding_rs` only decodes it.
+fn encode(text: &str, encoding: &'static Encoding) -> Vec<u8> {
+    if encoding == UTF_16LE {
+        text.encode_utf16().flat_map(u16::to_le_bytes).collect()
+    } else if encoding == UTF_16BE {
+        text.encode_utf16().flat_map(u16::to_be_bytes).collect()
+    } else {
+        encoding.encode(text).0.into_owned()
+    }
+}
+
+/// Guess the field delimiter from the first records of a CSV document.
+///
+/// Prefers the candidate that splits every sampled record into the same,
+/// largest number of fields; falls back to a comma.
+fn sniff_delimiter(data: &[u8]) -> u8 {
+    const CANDIDATES: [u8; 3] = [b',', b';', b'\t'];
+    const SAMPLE_RECORDS: usize = 10;
+
+    // Delimiter counts per record, ignoring quoted text
+    let mut records: Vec<[usize; 3]> = Vec::new();
+    let mut counts = [0; 3];
+    let mut in_quotes = false;
+    for &byte in data {
+        match byte {
+            b'"' => in_quotes = !in_quotes,
+            b'\n' if !in_quotes => {
+                records.push(std::mem::take(&mut counts));
+                if records.len() == SAMPLE_RECORDS {
+                    break;
+                }
+            }
+            _ if !in_quotes => {
+                if let Some(i) = CANDIDATES.iter().position(|&c| c == byte) {
+                    counts[i] += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    if counts.iter().any(|&c| c > 0) {
+        records.push(counts);
+    }
+
+    let consistent = |i: usize| records.iter().all(|r| r[i] == records[0][i]);
+    (0..CANDIDATES.len())
+        .filter(|&i| records.first().is_some_and(|r| r[i] > 0))
+        .max_by_key(|&i| (consistent(i), records.iter().map(|r| r[i]).sum::<usize>()))
+        .map_or(b',', |i| CANDIDATES[i])
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -288,7 +1911,7 @@ mod tests {
 
     #[test]
     fn test_parse_date() {
-        let date = CsvStatement::parse_date("20.02.2024").unwrap();
+        let date = CsvStatement::parse_date("20.02.2024", &CsvProfile::default()).unwrap();
         assert_eq!(date.year(), 2024);
         assert_eq!(date.month(), 2);
         assert_eq!(date.day(), 20);
@@ -296,13 +1919,574 @@ mod tests {
 
     #[test]
     fn test_parse_amount() {
-        let amount = CsvStatement::parse_amount("1 540,00").unwrap();
+        let amount = CsvStatement::parse_amount("1 540,00", &CsvProfile::default()).unwrap();
         assert_eq!(amount.to_string(), "1540.00");
+        let amount = CsvStatement::parse_amount(" -12\u{a0}000.5 ", &CsvProfile::default()).unwrap();
+        assert_eq!(amount.to_string(), "-12000.5");
+        for invalid in ["", "-", "1,2,3", "12-", "1e5", "--1"] {
+            assert!(CsvStatement::parse_amount(invalid, &CsvProfile::default()).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_extract_bank() {
+        let bank = CsvStatement::extract_bank("БИК 044525545 АО ЮниКредит Банк, г.Москва").unwrap();
+        assert_eq!(
+            bank,
+            BankIdentifier::ClearingSystem { id: "044525545".into(), system: Some("RUCBC".into()) }
+        );
+        let bank = CsvStatement::extract_bank("BIC SABRRUMM Сбербанк").unwrap();
+        assert_eq!(bank.bic().map(|bic| bic.as_str()), Some("SABRRUMM"));
+
+        assert_eq!(CsvStatement::extract_bank("БИК 04452554 Банк"), None);
+        assert_eq!(CsvStatement::extract_bank("BIC SABR12MM"), None);
+        assert_eq!(CsvStatement::extract_bank("АО ЮниКредит Банк"), None);
+
+        let input = "Дата проводки,Счет Дебет,Счет Кредит,Сумма по дебету,Сумма по кредиту,№ документа,Назначение платежа,Банк (БИК и наименование)\n\
+                     20.02.2024,40702810440000030888,40817810099910004312,1540.00,,15,Оплата,ПАО Сбербанк\n";
+        let outcome = CsvStatement::from_read_with_diagnostics(&mut input.as_bytes(), &CsvProfile::default()).unwrap();
+        assert_eq!(outcome.statement.statement.transactions[0].bank_identifier, None);
+        let ignored: Vec<_> = outcome.warnings.iter().filter(|w| w.kind == WarningKind::IgnoredValue).collect();
+        assert_eq!(ignored.len(), 1);
+        assert_eq!(ignored[0].line, Some(2));
+    }
+
+    #[test]
+    fn test_default_profile_round_trip() {
+        let input = "Дата проводки,Счет Дебет,Счет Кредит,Сумма по дебету,Сумма по кредиту,№ документа,Назначение платежа,Банк (БИК и наименование)\n\
+                     20.02.2024,40702810440000030888,40817810099910004312,1540.00,,15,Оплата,БИК 044525545 Банк\n";
+        let csv = CsvStatement::from_read(&mut input.as_bytes()).unwrap();
+        let tx = &csv.statement.transactions[0];
+        assert_eq!(tx.debit_credit, DebitCredit::Debit);
+        assert_eq!(tx.bank_identifier.as_ref().map(BankIdentifier::id), Some("044525545"));
+
+        let mut output = Vec::new();
+        csv.write_to(&mut output).unwrap();
+        let written = String::from_utf8(output).unwrap();
+        assert!(written.starts_with(input.lines().next().unwrap()));
+        assert!(written.contains("20.02.2024,40702810440000030888,40817810099910004312,1540.00,,15,Оплата,044525545"));
+    }
+
+    #[test]
+    fn test_multi_currency() {
+        let input = "Дата проводки,Сумма по дебету,Сумма по кредиту,Назначение платежа,Валюта\n\
+                     20.02.2024,100.00,,Оплата,EUR\n\
+                     21.02.2024,,50.00,Возврат,USD\n\
+                     22.02.2024,,10.00,Проценты,\n";
+        let profile = CsvProfile { default_currency: Currency::CNY, ..Default::default() };
+        let csv = CsvStatement::from_read_with(&mut input.as_bytes(), &profile).unwrap();
+        let currencies: Vec<_> = csv.statement.transactions.iter().map(|tx| tx.currency.as_str()).collect();
+        assert_eq!(currencies, ["EUR", "USD", "CNY"]);
+        assert_eq!(csv.statement.currency, "EUR");
+
+        let mut output = Vec::new();
+        csv.write_to(&mut output).unwrap();
+        let written = String::from_utf8(output).unwrap();
+        assert!(written.lines().next().unwrap().ends_with(",Валюта"));
+        assert!(written.contains(",USD\n"));
+    }
+
+    #[test]
+    fn test_output_columns() {
+        let input = "Дата проводки,Счет Дебет,Счет Кредит,Сумма по дебету,Сумма по кредиту,№ документа,Назначение платежа\n\
+                     20.02.2024,40702810440000030888,40817810099910004312,1540.00,,15,ООО \"Ромашка\" оплата\n";
+        let csv = CsvStatement::from_read(&mut input.as_bytes()).unwrap();
+
+        let profile = CsvProfile {
+            output_columns: Some(vec![
+                ("Doc".into(), "reference".parse().unwrap()),
+                ("Sum".into(), "signed_amount".parse().unwrap()),
+                ("Account".into(), CsvField::Account),
+                ("Date".into(), CsvField::Date),
+            ]),
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        csv.write_to_with(&mut output, &profile).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "Doc,Sum,Account,Date\n15,-1540.00,40702810440000030888,20.02.2024\n"
+        );
+        assert!("bogus".parse::<CsvField>().is_err());
+    }
+
+    #[test]
+    fn test_excel_output() {
+        let input = "Дата проводки,Сумма по дебету,Сумма по кредиту,Назначение платежа\n\
+                     20.02.2024,1540.50,,Оплата\n";
+        let csv = CsvStatement::from_read(&mut input.as_bytes()).unwrap();
+
+        let mut output = Vec::new();
+        csv.write_to_with(&mut output, &CsvProfile::default().excel()).unwrap();
+        assert!(output.starts_with(b"\xEF\xBB\xBF"));
+        let written = std::str::from_utf8(&output[3..]).unwrap();
+        assert!(written.contains("20.02.2024;UNKNOWN;;1540,50;;;Оплата;;RUB\r\n"));
+
+        // The BOM is recognised on read
+        let parsed = CsvStatement::from_read(&mut output.as_slice()).unwrap();
+        assert_eq!(parsed.statement.transactions[0].amount, Decimal::from_str("1540.50").unwrap());
+    }
+
+    #[test]
+    fn test_strict_mode() {
+        let strict = CsvProfile { strict: true, ..Default::default() };
+        let header = "Дата проводки,Сумма по дебету,Сумма по кредиту,Назначение платежа";
+
+        // Rows without an amount are skipped unless strict
+        let input = format!("{}\n20.02.2024,100.00,,Оплата\n21.02.2024,,,Пусто\n", header);
+        let csv = CsvStatement::from_read(&mut input.as_bytes()).unwrap();
+        assert_eq!(csv.statement.transactions.len(), 1);
+        let err = CsvStatement::from_read_with(&mut input.as_bytes(), &strict).unwrap_err();
+        assert!(matches!(err, Error::CsvRowError { row: 3, ref column, .. } if column == "Сумма по дебету\", \"Сумма по кредиту"));
+        assert_eq!(err.code(), ErrorCode::CsvMissingValue);
+
+        let input = format!("{}\n20.02.2024,abc,,Оплата\n", header);
+        let err = CsvStatement::from_read_with(&mut input.as_bytes(), &strict).unwrap_err();
+        assert!(matches!(err, Error::CsvRowError { row: 2, ref column, .. } if column == "Сумма по дебету"));
+        assert_eq!(err.code().as_str(), "E_CSV_BAD_AMOUNT");
+        let location = err.location().unwrap();
+        assert_eq!((location.line, location.column, location.snippet.as_deref()), (Some(2), Some(2), Some("abc")));
+        assert_eq!(location.offset, Some(header.len() + 1));
+        let input = format!("{}\n2024-02-31,100.00,,Оплата\n", header);
+        let err = CsvStatement::from_read_with(&mut input.as_bytes(), &strict).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::CsvBadDate);
+        assert_eq!(CsvStatement::from_read(&mut input.as_bytes()).unwrap_err().code(), ErrorCode::BadDate);
+
+        let input = format!("{},Комментарий\n20.02.2024,100.00,,Оплата,\n", header);
+        let err = CsvStatement::from_read_with(&mut input.as_bytes(), &strict).unwrap_err();
+        assert!(matches!(err, Error::CsvRowError { row: 1, ref column, .. } if column == "Комментарий"));
+        assert_eq!(err.code(), ErrorCode::CsvUnknownColumn);
+
+        let input = "Дата проводки,Сумма по дебету\n20.02.2024,100.00\n";
+        let err = CsvStatement::from_read_with(&mut input.as_bytes(), &strict).unwrap_err();
+        assert_eq!(err.to_string(), "CSV error at row 1, column \"Сумма по кредиту\": missing required column (line 1, byte 0)");
+        let input = "Дата проводки,Назначение платежа\n20.02.2024,Оплата\n";
+        let err = CsvStatement::from_read_with(&mut input.as_bytes(), &strict).unwrap_err();
+        assert!(matches!(err, Error::CsvRowError { ref column, .. } if column == "Сумма по дебету\", \"Сумма по кредиту"));
+    }
+
+    #[test]
+    fn test_error_context() {
+        let input = "Дата проводки,Сумма по дебету,Сумма по кредиту\n20.02.2024,100.00,\n21.02.2024,,1O.00\n";
+        let err = CsvStatement::from_read(&mut input.as_bytes()).unwrap_err();
+        assert!(matches!(err, Error::InvalidAmount(..)));
+        let location = err.location().unwrap();
+        assert_eq!((location.line, location.column), (Some(3), Some(3)));
+        assert_eq!((location.header.as_deref(), location.snippet.as_deref()), (Some("Сумма по кредиту"), Some("1O.00")));
+        assert!(err.to_string().ends_with("(line 3, column 3 \"Сумма по кредиту\", byte 105, at \"1O.00\")"), "{}", err);
+    }
+
+    #[test]
+    fn test_skip_invalid_rows() {
+        let input = "Дата проводки,Сумма по дебету,Сумма по кредиту\n\
+                     20.02.2024,100.00,\n31.02.2024,5.00,\n21.02.2024,,abc\n22.02.2024,,50.00\n";
+        assert!(CsvStatement::from_read(&mut input.as_bytes()).is_err());
+
+        let profile = CsvProfile { skip_invalid: true, ..Default::default() };
+        let outcome = CsvStatement::from_read_with_diagnostics(&mut input.as_bytes(), &profile).unwrap();
+        assert_eq!(outcome.statement.statement.transactions.len(), 2);
+        let rejected: Vec<(usize, &str)> = outcome.rejected.iter().map(|r| (r.line, r.text.as_str())).collect();
+        assert_eq!(rejected, [(3, "31.02.2024,5.00,"), (4, "21.02.2024,,abc")]);
+        assert_eq!(outcome.rejected[1].error.code(), ErrorCode::BadAmount);
+    }
+
+    #[test]
+    fn test_statement_id_policy() {
+        let input = "Дата проводки,Сумма по дебету,Сумма по кредиту,Назначение платежа\n\
+                     20.02.2024,100.00,,Оплата\n";
+        let read = |policy| {
+            let profile = CsvProfile { statement_id: policy, ..Default::default() };
+            CsvStatement::from_read_with(&mut input.as_bytes(), &profile).unwrap().statement.statement_id
+        };
+
+        let derived = read(StatementIdPolicy::Derived);
+        assert_eq!(derived, read(StatementIdPolicy::Derived));
+        assert_eq!(derived.len(), 16);
+        assert_eq!(read(StatementIdPolicy::Explicit("STMT-1".into())), "STMT-1");
+
+        let other = input.replace("100.00", "100.01");
+        let profile = CsvProfile { statement_id: StatementIdPolicy::Derived, ..Default::default() };
+        let csv = CsvStatement::from_read_with(&mut other.as_bytes(), &profile).unwrap();
+        assert_ne!(csv.statement.statement_id, derived);
+    }
+
+    #[test]
+    fn test_split_per_account() {
+        let input = "Счет,40702810000000000001\n\
+                     Входящий остаток на 20.02.2024,,,,100.00\n\
+                     Дата проводки,Счет Дебет,Счет Кредит,Сумма по дебету,Сумма по кредиту\n\
+                     20.02.2024,40702810000000000001,40817810000000000009,10.00,\n\
+                     20.02.2024,40817810000000000009,40702810000000000002,,20.00\n\
+                     21.02.2024,40817810000000000009,40702810000000000001,,30.00\n";
+        let profile = CsvProfile { statement_id: StatementIdPolicy::Explicit("S".into()), ..Default::default() };
+        let statements = CsvStatement::from_read_per_account(&mut input.as_bytes(), &profile).unwrap();
+
+        let summary: Vec<_> = statements.iter()
+            .map(|csv| (csv.statement.account.id(), csv.statement.statement_id.as_str(), csv.statement.transactions.len()))
+            .collect();
+        assert_eq!(summary, [("40702810000000000001", "S-1", 2), ("40702810000000000002", "S-2", 1)]);
+        assert!(statements[0].statement.opening_balance.is_some());
+        assert!(statements[1].statement.opening_balance.is_none());
+
+        // A single-account read still merges everything
+        let csv = CsvStatement::from_read_with(&mut input.as_bytes(), &profile).unwrap();
+        assert_eq!(csv.statement.transactions.len(), 3);
+        assert_eq!(csv.statement.statement_id, "S");
+    }
+
+    #[test]
+    fn test_russian_amount_round_trip() {
+        let profile = CsvProfile { amount_format: AmountFormat::russian(), ..Default::default() };
+        let input = "Дата проводки,Сумма по дебету,Сумма по кредиту,Назначение платежа\n\
+                     20.02.2024,\"1 540,00\",,Оплата\n\
+                     21.02.2024,,\"1\u{a0}234 567,89\",Поступление\n";
+        let csv = CsvStatement::from_read_with(&mut input.as_bytes(), &profile).unwrap();
+        assert_eq!(csv.statement.transactions[1].amount, Decimal::from_str("1234567.89").unwrap());
+
+        let mut output = Vec::new();
+        csv.write_to_with(&mut output, &profile).unwrap();
+        let written = String::from_utf8(output).unwrap();
+        assert!(written.contains("\"1 540,00\""));
+        assert!(written.contains("\"1 234 567,89\""));
+    }
+
+    #[test]
+    fn test_running_balance() {
+        // Newest first, as many banks export
+        let input = "Дата проводки,Сумма по дебету,Сумма по кредиту,Назначение платежа,Остаток\n\
+                     21.02.2024,,50.00,Поступление,1030.00\n\
+                     20.02.2024,20.00,,Оплата,980.00\n";
+        let csv = CsvStatement::from_read(&mut input.as_bytes()).unwrap();
+        let statement = &csv.statement;
+        assert_eq!(statement.transactions[1].running_balance, Some(Decimal::from_str("980.00").unwrap()));
+
+        let opening = statement.opening_balance.as_ref().unwrap();
+        assert_eq!((opening.amount, opening.date), (Decimal::from_str("1000.00").unwrap(), NaiveDate::from_ymd_opt(2024, 2, 20).unwrap()));
+        let closing = statement.closing_balance.as_ref().unwrap();
+        assert_eq!((closing.amount, closing.date), (Decimal::from_str("1030.00").unwrap(), NaiveDate::from_ymd_opt(2024, 2, 21).unwrap()));
+
+        let mut output = Vec::new();
+        csv.write_to(&mut output).unwrap();
+        let written = String::from_utf8(output).unwrap();
+        assert!(written.lines().next().unwrap().ends_with(",Остаток"));
+        assert!(written.contains(",980.00\n"));
+    }
+
+    #[test]
+    fn test_category_column() {
+        let input = "Дата проводки,Сумма по дебету,Сумма по кредиту,Назначение платежа\n\
+                     20.02.2024,100.00,,Оплата\n";
+        let mut csv = CsvStatement::from_read(&mut input.as_bytes()).unwrap();
+
+        let mut output = Vec::new();
+        csv.write_to(&mut output).unwrap();
+        assert!(!String::from_utf8(output).unwrap().contains("Категория"));
+
+        csv.statement.transactions[0].category = Some("Аренда".into());
+        let mut output = Vec::new();
+        csv.write_to(&mut output).unwrap();
+        let reparsed = CsvStatement::from_read(&mut output.as_slice()).unwrap();
+        assert_eq!(reparsed.statement.transactions[0].category.as_deref(), Some("Аренда"));
+    }
+
+    #[test]
+    fn test_status_column() {
+        let input = "Дата проводки,Сумма по дебету,Сумма по кредиту,Назначение платежа,Статус\n\
+                     20.02.2024,100.00,,Оплата,\n\
+                     21.02.2024,,100.00,Возврат,Reversed\n\
+                     22.02.2024,50.00,,Холд,pdng\n";
+        let csv = CsvStatement::from_read(&mut input.as_bytes()).unwrap();
+        let statuses: Vec<_> = csv.statement.transactions.iter().map(|tx| tx.status).collect();
+        assert_eq!(statuses, [EntryStatus::Booked, EntryStatus::Reversed, EntryStatus::Pending]);
+
+        let mut output = Vec::new();
+        csv.write_to(&mut output).unwrap();
+        let reparsed = CsvStatement::from_read(&mut output.as_slice()).unwrap();
+        assert_eq!(reparsed.statement.transactions[1].status, EntryStatus::Reversed);
+
+        let input = "Дата проводки,Сумма по дебету,Статус\n20.02.2024,100.00,Unknown\n";
+        assert!(CsvStatement::from_read(&mut input.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_tags_column() {
+        let input = "Дата проводки,Сумма по дебету,Назначение платежа,Метки\n\
+                     20.02.2024,100.00,Комиссия,\"fees, bank\"\n\
+                     21.02.2024,50.00,Оплата,\n";
+        let mut csv = CsvStatement::from_read(&mut input.as_bytes()).unwrap();
+        assert_eq!(csv.statement.transactions[0].tags.iter().collect::<Vec<_>>(), ["bank", "fees"]);
+        assert!(csv.statement.transactions[1].tags.is_empty());
+
+        csv.statement.tag_where("intercompany", |tx| tx.description == "Оплата");
+        let mut output = Vec::new();
+        csv.write_to(&mut output).unwrap();
+        assert!(String::from_utf8_lossy(&output).contains(",\"bank, fees\"\n"));
+        let reparsed = CsvStatement::from_read(&mut output.as_slice()).unwrap();
+        assert_eq!(reparsed.statement.transactions, csv.statement.transactions);
+
+        csv.statement.remove_tag("bank");
+        csv.statement.remove_tag("fees");
+        csv.statement.remove_tag("intercompany");
+        let mut output = Vec::new();
+        csv.write_to(&mut output).unwrap();
+        assert!(!String::from_utf8_lossy(&output).contains("Метки"));
+    }
+
+    #[test]
+    fn test_time_of_day() {
+        let input = "Дата проводки,Время проводки,Сумма по дебету,Сумма по кредиту,Назначение платежа\n\
+                     20.02.2024 14:35,,100.00,,Оплата\n\
+                     2024-02-21T09:15:30,,,100.00,Возврат\n\
+                     22.02.2024,18:00,50.00,,Холд\n\
+                     23.02.2024,,50.00,,Без времени\n";
+        let csv = CsvStatement::from_read(&mut input.as_bytes()).unwrap();
+        let times: Vec<_> = csv.statement.transactions.iter()
+            .map(|tx| tx.date.to_string())
+            .collect();
+        assert_eq!(times, ["2024-02-20T14:35:00", "2024-02-21T09:15:30", "2024-02-22T18:00:00", "2024-02-23"]);
+        assert_eq!(csv.statement.transactions[1].date.date(), NaiveDate::from_ymd_opt(2024, 2, 21).unwrap());
+
+        let mut output = Vec::new();
+        csv.write_to(&mut output).unwrap();
+        assert!(String::from_utf8_lossy(&output).starts_with("Дата проводки,Время проводки,"));
+        let reparsed = CsvStatement::from_read(&mut output.as_slice()).unwrap();
+        assert_eq!(reparsed.statement.transactions, csv.statement.transactions);
+
+        let input = "Дата проводки,Время проводки,Сумма по дебету\n20.02.2024,25:00,100.00\n";
+        assert!(CsvStatement::from_read(&mut input.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_custom_profile() {
+        let profile = CsvProfile {
+            columns: vec![
+                ("Booking Date".into(), CsvField::Date),
+                ("Amount".into(), CsvField::Amount),
+                ("D/C".into(), CsvField::DebitCredit),
+                ("Currency".into(), CsvField::Currency),
+                ("Payee".into(), CsvField::CounterpartyName),
+                ("Text".into(), CsvField::Description),
+            ],
+            date_formats: vec!["%Y-%m-%d".into()],
+            amount_format: AmountFormat {
+                decimal_separator: Some(','),
+                thousands_separator: Some('.'),
+                negative_parentheses: false,
+            },
+            debit_credit: DebitCreditConvention::Indicator {
+                debit: "S".into(),
+                credit: "H".into(),
+            },
+            delimiter: None,
+            encoding: None,
+            ..Default::default()
+        };
+        let input = "Booking Date,Amount,D/C,Currency,Payee,Text\n\
+                     2024-03-01,\"1.250,75\",S,EUR,Landlord,Rent March\n\
+                     2024-03-02,\"99,00\",H,EUR,,Refund\n";
+        let csv = CsvStatement::from_read_with(&mut input.as_bytes(), &profile).unwrap();
+        let txs = &csv.statement.transactions;
+        assert_eq!(txs.len(), 2);
+        assert_eq!(txs[0].amount, Decimal::from_str("1250.75").unwrap());
+        assert_eq!(txs[0].debit_credit, DebitCredit::Debit);
+        assert_eq!(txs[0].currency, "EUR");
+        assert_eq!(txs[0].counterparty_name.as_deref(), Some("Landlord"));
+        assert_eq!(txs[1].debit_credit, DebitCredit::Credit);
+
+        let mut output = Vec::new();
+        csv.write_to_with(&mut output, &profile).unwrap();
+        let reparsed = CsvStatement::from_read_with(&mut output.as_slice(), &profile).unwrap();
+        assert_eq!(reparsed.statement.transactions, csv.statement.transactions);
+    }
+
+    #[test]
+    fn test_sniff_delimiter() {
+        assert_eq!(sniff_delimiter(b"a;b;c\n1;\"2,5\";3\n"), b';');
+        assert_eq!(sniff_delimiter(b"a\tb\tc\n1,5\t2\t3\n"), b'\t');
+        assert_eq!(sniff_delimiter(b"a,b\n1,2\n"), b',');
+        assert_eq!(sniff_delimiter(b"single\n"), b',');
+
+        // Only a prefix of the input is sniffed, so endless input still starts
+        let input = "Дата проводки;Сумма по кредиту\n20.02.2024;1,00\n".as_bytes().chain(io::repeat(b'1'));
+        let mut entries = CsvEntryReader::new(input, &CsvProfile::default()).unwrap();
+        assert_eq!(entries.next().unwrap().unwrap().amount, Decimal::ONE);
+    }
+
+    #[test]
+    fn test_read_semicolon_delimited() {
+        let input = "Date;Debit Amount;Credit Amount;Purpose\n20.02.2024;\"1 540,00\";;Rent\n";
+        let csv = CsvStatement::from_read(&mut input.as_bytes()).unwrap();
+        assert_eq!(csv.statement.transactions[0].amount, Decimal::from_str("1540.00").unwrap());
+        assert_eq!(csv.statement.transactions[0].description, "Rent");
+    }
+
+    #[test]
+    fn test_read_windows_1251_and_utf16() {
+        let input = "Дата проводки,Сумма по кредиту,Назначение платежа\n20.02.2024,100.00,Оплата\n";
+
+        let (cp1251, _, _) = WINDOWS_1251.encode(input);
+        let csv = CsvStatement::from_read(&mut cp1251.as_ref()).unwrap();
+        assert_eq!(csv.statement.transactions[0].description, "Оплата");
+
+        let utf16 = [&[0xFF, 0xFE], encode(input, UTF_16LE).as_slice()].concat();
+        let csv = CsvStatement::from_read(&mut utf16.as_slice()).unwrap();
+        assert_eq!(csv.statement.transactions[0].description, "Оплата");
     }
 
     #[test]
-    fn test_extract_bic() {
-        let bic = CsvStatement::extract_bic("БИК 044525545 АО ЮниКредит Банк, г.Москва");
-        assert_eq!(bic, "044525545");
+    fn test_write_windows_1251() {
+        let input = "Дата проводки,Сумма по кредиту,Назначение платежа\n20.02.2024,100.00,Оплата\n";
+        let csv = CsvStatement::from_read(&mut input.as_bytes()).unwrap();
+        let profile = CsvProfile {
+            encoding: Some(WINDOWS_1251),
+            ..Default::default()
+        };
+
+        let mut output = Vec::new();
+        csv.write_to_with(&mut output, &profile).unwrap();
+        assert!(std::str::from_utf8(&output).is_err());
+        let reparsed = CsvStatement::from_read_with(&mut output.as_slice(), &profile).unwrap();
+        assert_eq!(reparsed.statement.transactions, csv.statement.transactions);
+    }
+
+    #[test]
+    fn test_metadata_rows() {
+        let input = ",ВЫПИСКА ОПЕРАЦИЙ ПО ЛИЦЕВОМУ СЧЕТУ,,40702810440000030888\n\
+                     ,за период с 01 января 2024 г.,, по ,31 декабря 2024 г.\n\
+                     ,,,,\n\
+                     ,Дата проводки,Счет,,Сумма по дебету,Сумма по кредиту\n\
+                     ,,Дебет,Кредит,,\n\
+                     ,20.02.2024,40702810440000030888,40702810600014448120,1540.00,\n\
+                     ,Входящий остаток,\"0,00\",1332.54,(П),01 января 2024 г.\n";
+        let csv = CsvStatement::from_read(&mut input.as_bytes()).unwrap();
+        let statement = &csv.statement;
+
+        assert_eq!(statement.account.to_string(), "40702810440000030888");
+        assert_eq!(statement.from_date.map(|date| date.date()), NaiveDate::from_ymd_opt(2024, 1, 1));
+        assert_eq!(statement.to_date.map(|date| date.date()), NaiveDate::from_ymd_opt(2024, 12, 31));
+        let opening = statement.opening_balance.as_ref().unwrap();
+        assert_eq!(opening.amount, Decimal::from_str("1332.54").unwrap());
+        assert_eq!(opening.debit_credit, DebitCredit::Credit);
+
+        assert_eq!(statement.transactions.len(), 1);
+        let tx = &statement.transactions[0];
+        assert_eq!(tx.debit_credit, DebitCredit::Debit);
+        assert_eq!(tx.counterparty_account.as_ref().unwrap().to_string(), "40702810600014448120");
+    }
+
+    #[test]
+    fn test_servicing_bank_rows() {
+        let input = "Банк,ПАО Сбербанк\n\
+                     БИК: 044525225\n\
+                     Дата проводки,Сумма по дебету,Сумма по кредиту,Назначение платежа\n\
+                     20.02.2024,100.00,,Оплата\n";
+        let csv = CsvStatement::from_read(&mut input.as_bytes()).unwrap();
+        let bank = csv.statement.servicing_bank.clone().unwrap();
+        assert_eq!(bank.name.as_deref(), Some("ПАО Сбербанк"));
+        assert_eq!(
+            bank.clearing_member(),
+            Some(BankIdentifier::ClearingSystem { id: "044525225".into(), system: Some("RUCBC".into()) })
+        );
+        assert_eq!(bank.bic, None);
+
+        let mut output = Vec::new();
+        csv.write_to(&mut output).unwrap();
+        assert!(String::from_utf8_lossy(&output).starts_with("Дата проводки,"));
+
+        let profile = CsvProfile { bank_rows: true, ..Default::default() };
+        let mut output = Vec::new();
+        csv.write_to_with(&mut output, &profile).unwrap();
+        assert!(String::from_utf8_lossy(&output).starts_with("Банк,ПАО Сбербанк\nБИК,044525225\nДата проводки,"));
+        let reparsed = CsvStatement::from_read(&mut output.as_slice()).unwrap();
+        assert_eq!(reparsed.statement.servicing_bank, csv.statement.servicing_bank);
+        assert_eq!(reparsed.statement.transactions, csv.statement.transactions);
+    }
+
+    #[test]
+    fn test_named_profiles() {
+        for name in CsvProfile::NAMES {
+            assert!(CsvProfile::named(name).is_some(), "{}", name);
+        }
+
+        let profile = CsvProfile::named("Tinkoff").unwrap();
+        let input = "Дата операции;Дата платежа;Статус;Сумма платежа;Валюта платежа;Описание\n\
+                     01.03.2024 12:00:00;01.03.2024;OK;-1 250,50;RUB;Магазин\n";
+        let (data, _, _) = WINDOWS_1251.encode(input);
+        let csv = CsvStatement::from_read_with(&mut data.as_ref(), &profile).unwrap();
+        let tx = &csv.statement.transactions[0];
+        assert_eq!(tx.amount, Decimal::from_str("1250.50").unwrap());
+        assert_eq!(tx.debit_credit, DebitCredit::Debit);
+        assert_eq!(tx.description, "Магазин");
+    }
+
+    #[test]
+    fn test_ambiguous_date_rejected() {
+        let profile = CsvProfile::default();
+        assert!(CsvStatement::parse_date("02/03/2024", &profile).is_err());
+        assert_eq!(
+            CsvStatement::parse_date("20/02/2024", &profile).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 2, 20).unwrap()
+        );
+
+        let us = CsvProfile {
+            date_formats: vec!["%m/%d/%Y".into()],
+            ..Default::default()
+        };
+        assert_eq!(CsvStatement::parse_date("02/03/2024", &us).unwrap().day(), 3);
+    }
+
+    #[test]
+    fn test_amount_format() {
+        let profile = CsvProfile {
+            amount_format: AmountFormat {
+                decimal_separator: Some('.'),
+                thousands_separator: Some(','),
+                negative_parentheses: true,
+            },
+            ..Default::default()
+        };
+        let amount = CsvStatement::parse_amount("(1,234,567.89)", &profile).unwrap();
+        assert_eq!(amount, Decimal::from_str("-1234567.89").unwrap());
+        assert_eq!(CsvStatement::format_amount(amount, &profile.amount_format), "(1,234,567.89)");
+    }
+
+    #[test]
+    fn test_entry_reader_streams_chunks() {
+        // Rows spanning several decoder chunks, the last one not valid UTF-8
+        let row = "20.02.2024,100.00,Оплата по счёту\n";
+        let mut input = format!("Дата проводки,Сумма по кредиту,Назначение платежа\n{}", row.repeat(3000)).into_bytes();
+        let entries = CsvEntryReader::new(input.as_slice(), &CsvProfile::default()).unwrap();
+        let descriptions: Vec<String> = entries.map(|tx| tx.unwrap().description).collect();
+        assert_eq!(descriptions.len(), 3000);
+        assert!(descriptions.iter().all(|d| d == "Оплата по счёту"));
+
+        input.extend_from_slice(b"21.02.2024,5.00,\xFF\n");
+        let mut entries = CsvEntryReader::new(input.as_slice(), &CsvProfile::default()).unwrap();
+        let error = entries.find_map(|tx| tx.err()).unwrap();
+        assert_eq!(error.to_string(), "Parse error: Input is not valid UTF-8");
+    }
+
+    #[test]
+    fn test_incremental_writer() {
+        let row = "20.02.2024,100.00,Оплата по счёту\n";
+        let input = format!("Дата проводки,Сумма по кредиту,Назначение платежа\n{}", row.repeat(1000));
+        let csv = CsvStatement::from_read(&mut input.as_bytes()).unwrap();
+        let profile = CsvProfile {
+            encoding: Some(UTF_16LE),
+            output_columns: Some(vec![("Дата".into(), CsvField::Date), ("Назначение".into(), CsvField::Description)]),
+            ..Default::default()
+        };
+
+        // Rows spill over the CSV buffer mid-character; the BOM is written once
+        let mut writer = CsvWriter::new(Vec::new(), &profile);
+        writer.write_header(&csv.statement).unwrap();
+        for transaction in &csv.statement.transactions {
+            writer.write_transaction(transaction).unwrap();
+        }
+        let output = writer.finish().unwrap();
+        assert_eq!(&output[..4], [0xFF, 0xFE, 0x14, 0x04]);
+        let units: Vec<u16> = output[2..].chunks(2).map(|pair| u16::from_le_bytes([pair[0], pair[1]])).collect();
+        let text = String::from_utf16(&units).unwrap();
+        assert_eq!(text, format!("Дата,Назначение\n{}", "20.02.2024,Оплата по счёту\n".repeat(1000)));
     }
 }
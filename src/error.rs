@@ -1,5 +1,7 @@
 //! Error types for the yp-converter library.
 
+use crate::messages::{render, translate, Language};
+use crate::Format;
 use std::io;
 use thiserror::Error;
 
@@ -7,57 +9,902 @@ use thiserror::Error;
 pub type Result<T> = std::result::Result<T, Error>;
 
 /// Error types that can occur during parsing and serialization operations.
+///
+/// New variants may be added; match on [`Error::code`] or use the helpers
+/// such as [`Error::is_recoverable`] instead of matching every variant.
 #[derive(Debug, Error)]
+#[non_exhaustive]
 pub enum Error {
     /// I/O error occurred during read or write operations.
-    #[error("I/O error: {0}")]
-    Io(#[from] io::Error),
+    #[error("I/O error: {0}{}", At(.1.as_deref()))]
+    Io(#[source] io::Error, Option<Box<Location>>),
 
     /// Error parsing CSV format.
-    #[error("CSV parsing error: {0}")]
-    CsvError(#[from] csv::Error),
+    #[error("CSV parsing error: {0}{}", At(.1.as_deref()))]
+    CsvError(#[source] csv::Error, Option<Box<Location>>),
 
-    /// Error parsing XML format.
-    #[error("XML parsing error: {0}")]
-    XmlError(String),
+    /// Error parsing XML format; `source` is the XML library's error, if any.
+    #[error("XML parsing error: {message}{}", At(.location.as_deref()))]
+    XmlError {
+        message: String,
+        source: Option<Box<dyn std::error::Error + Send + Sync>>,
+        location: Option<Box<Location>>,
+    },
 
-    /// Error parsing MT940 format.
-    #[error("MT940 parsing error at line {line}: {message}")]
-    Mt940ParseError { line: usize, message: String },
+    /// Error reading or writing JSON; `source` is serde_json's error, if any.
+    #[error("JSON error: {message}{}", At(.location.as_deref()))]
+    JsonError {
+        message: String,
+        source: Option<Box<dyn std::error::Error + Send + Sync>>,
+        location: Option<Box<Location>>,
+    },
+
+    /// Error parsing MT940 format; `code` tells which part was wrong.
+    #[error("MT940 parsing error at line {line}: {message}{}", At(.location.as_deref()))]
+    Mt940ParseError { line: usize, code: ErrorCode, message: String, location: Option<Box<Location>> },
+
+    /// Error in a CSV row, reported in strict mode; `code` tells what was
+    /// wrong with the column.
+    #[error("CSV error at row {row}, column \"{column}\": {message}{}", At(.location.as_deref()))]
+    CsvRowError { row: usize, column: String, code: ErrorCode, message: String, location: Option<Box<Location>> },
 
     /// Invalid date format.
-    #[error("Invalid date format: {0}")]
-    InvalidDate(String),
+    #[error("Invalid date format: {0}{}", At(.1.as_deref()))]
+    InvalidDate(String, Option<Box<Location>>),
 
     /// Invalid amount format.
-    #[error("Invalid amount format: {0}")]
-    InvalidAmount(String),
+    #[error("Invalid amount format: {0}{}", At(.1.as_deref()))]
+    InvalidAmount(String, Option<Box<Location>>),
+
+    /// Unknown or malformed ISO 4217 currency code.
+    #[error("Invalid currency code: {0}{}", At(.1.as_deref()))]
+    InvalidCurrency(String, Option<Box<Location>>),
+
+    /// IBAN with a wrong length or checksum.
+    #[error("Invalid IBAN: {0}{}", At(.1.as_deref()))]
+    InvalidIban(String, Option<Box<Location>>),
+
+    /// BIC not made of 8 or 11 characters in the ISO 9362 structure.
+    #[error("Invalid BIC: {0}{}", At(.1.as_deref()))]
+    InvalidBic(String, Option<Box<Location>>),
 
     /// Missing required field.
-    #[error("Missing required field: {0}")]
-    MissingField(String),
+    #[error("Missing required field: {0}{}", At(.1.as_deref()))]
+    MissingField(String, Option<Box<Location>>),
+
+    /// Several required fields missing, all reported at once.
+    #[error("Missing required fields: {}{}", .0.join(", "), At(.1.as_deref()))]
+    MissingFields(Vec<String>, Option<Box<Location>>),
 
     /// Invalid format specified.
-    #[error("Invalid format: {0}")]
-    InvalidFormat(String),
+    #[error("Invalid format: {0}{}", At(.1.as_deref()))]
+    InvalidFormat(String, Option<Box<Location>>),
 
     /// General parsing error.
-    #[error("Parse error: {0}")]
-    ParseError(String),
+    #[error("Parse error: {0}{}", At(.1.as_deref()))]
+    ParseError(String, Option<Box<Location>>),
 
     /// Conversion error between formats.
-    #[error("Conversion error: {0}")]
-    ConversionError(String),
+    #[error("Conversion error: {0}{}", At(.1.as_deref()))]
+    ConversionError(String, Option<Box<Location>>),
+
+    /// Statement breaking a validation rule.
+    #[error("Validation error: {0}")]
+    Validation(Box<ValidationError>),
+}
+
+/// Location of an error shown after its message, when known.
+struct At<'a>(Option<&'a Location>);
+
+impl std::fmt::Display for At<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            Some(location) => write!(f, " ({})", location),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Position of an error in the input; parts the parser doesn't know are
+/// left out.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Location {
+    /// File the input was read from, added by [`Error::in_file`].
+    pub file: Option<String>,
+    /// Line, starting at 1.
+    pub line: Option<usize>,
+    /// Column: the character column in MT940, XML and JSON, the field
+    /// number in CSV; starting at 1.
+    pub column: Option<usize>,
+    /// Header of the CSV column.
+    pub header: Option<String>,
+    /// Byte offset from the start of the input.
+    pub offset: Option<usize>,
+    /// Path of the XML element, e.g. `BkToCstmrStmt/Stmt[0]/Ntry[42]/Amt`,
+    /// or of the statement field, e.g. `transactions[3].reference`, counting
+    /// repeated elements from 0.
+    pub path: Option<String>,
+    /// The offending text, cut to [`Location::SNIPPET_LENGTH`] characters.
+    pub snippet: Option<String>,
+}
+
+impl Location {
+    /// Longest snippet kept, in characters.
+    pub const SNIPPET_LENGTH: usize = 80;
+
+    /// Location of byte `offset` of `text`, with the rest of its line as
+    /// the snippet.
+    pub fn in_text(text: &str, offset: usize) -> Self {
+        let mut offset = offset.min(text.len());
+        while !text.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        let line_start = text[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = text[offset..].find('\n').map_or(text.len(), |i| offset + i);
+        Location {
+            file: None,
+            line: Some(text[..offset].matches('\n').count() + 1),
+            column: Some(text[line_start..offset].chars().count() + 1),
+            header: None,
+            offset: Some(offset),
+            path: None,
+            snippet: Some(text[offset..line_end].trim_end_matches('\r').to_string()),
+        }
+        .with_snippet_cut()
+    }
+
+    fn with_snippet_cut(mut self) -> Self {
+        if let Some(ref mut snippet) = self.snippet {
+            if let Some((end, _)) = snippet.char_indices().nth(Self::SNIPPET_LENGTH) {
+                snippet.truncate(end);
+                snippet.push('…');
+            }
+        }
+        self
+    }
+
+    /// Fill in the parts this location lacks from `other`.
+    fn or(self, other: Location) -> Location {
+        Location {
+            file: self.file.or(other.file),
+            line: self.line.or(other.line),
+            column: self.column.or(other.column),
+            header: self.header.or(other.header),
+            offset: self.offset.or(other.offset),
+            path: self.path.or(other.path),
+            snippet: self.snippet.or(other.snippet),
+        }
+        .with_snippet_cut()
+    }
+
+    /// The location as displayed, in `language`.
+    pub fn localized(&self, language: Language) -> String {
+        let mut parts = Vec::new();
+        match (&self.file, self.line) {
+            (Some(file), Some(line)) => parts.push(format!("{}:{}", file, line)),
+            (Some(file), None) => parts.push(file.clone()),
+            (None, Some(line)) => parts.push(render("line {}", &[&line.to_string()], language)),
+            (None, None) => {}
+        }
+        match (self.column, &self.header) {
+            (Some(column), Some(header)) => parts.push(render("column {} \"{}\"", &[&column.to_string(), header], language)),
+            (Some(column), None) => parts.push(render("column {}", &[&column.to_string()], language)),
+            (None, Some(header)) => parts.push(render("column \"{}\"", &[header], language)),
+            (None, None) => {}
+        }
+        if let Some(offset) = self.offset {
+            parts.push(render("byte {}", &[&offset.to_string()], language));
+        }
+        if let Some(ref path) = self.path {
+            parts.push(render("element {}", &[path], language));
+        }
+        if let Some(ref snippet) = self.snippet {
+            parts.push(render("at \"{}\"", &[snippet], language));
+        }
+        parts.join(", ")
+    }
+}
+
+impl std::fmt::Display for Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.localized(Language::English))
+    }
+}
+
+impl Error {
+    /// The error at `location`; an error already located keeps the parts
+    /// of its location it knows.
+    pub fn at(mut self, location: Location) -> Error {
+        if let Error::Validation(ref mut failure) = self {
+            failure.location = Some(match failure.location.take() {
+                Some(known) => known.or(location),
+                None => location.with_snippet_cut(),
+            });
+        } else if let Some(slot) = self.location_slot() {
+            *slot = Some(Box::new(match slot.take() {
+                Some(known) => known.or(location),
+                None => location.with_snippet_cut(),
+            }));
+        }
+        self
+    }
+
+    /// The error inside XML element `element`, prepended to the path of
+    /// its location.
+    pub(crate) fn within(mut self, element: &str) -> Error {
+        if let Some(Some(location)) = self.location_slot() {
+            location.path = Some(match location.path.take() {
+                Some(path) => format!("{}/{}", element, path),
+                None => element.to_string(),
+            });
+            return self;
+        }
+        self.at(Location { path: Some(element.to_string()), ..Default::default() })
+    }
+
+    /// The error in input read from `file`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ypbank_system::mt940_format::Mt940Statement;
+    ///
+    /// let input = ":20:STMT\n:25:ACC001\n:60F:C240101EUR100,00\n:61:2413020102D10,00NTRF//REF1\n";
+    /// let err = Mt940Statement::from_read(&mut input.as_bytes()).unwrap_err().in_file("june.mt940");
+    /// let location = err.location().unwrap();
+    /// assert_eq!((location.line, location.offset), (Some(4), Some(42)));
+    /// assert!(err.to_string().ends_with("(june.mt940:4, column 1, byte 42, at \":61:2413020102D10,00NTRF//REF1\")"));
+    /// ```
+    pub fn in_file(self, file: impl Into<String>) -> Error {
+        self.at(Location { file: Some(file.into()), ..Default::default() })
+    }
+
+    /// Where in the input the error is, when known.
+    pub fn location(&self) -> Option<&Location> {
+        match self {
+            Error::Io(_, location)
+            | Error::CsvError(_, location)
+            | Error::XmlError { location, .. }
+            | Error::JsonError { location, .. }
+            | Error::Mt940ParseError { location, .. }
+            | Error::CsvRowError { location, .. }
+            | Error::InvalidDate(_, location)
+            | Error::InvalidAmount(_, location)
+            | Error::InvalidCurrency(_, location)
+            | Error::InvalidIban(_, location)
+            | Error::InvalidBic(_, location)
+            | Error::MissingField(_, location)
+            | Error::MissingFields(_, location)
+            | Error::InvalidFormat(_, location)
+            | Error::ParseError(_, location)
+            | Error::ConversionError(_, location) => location.as_deref(),
+            Error::Validation(failure) => failure.location.as_ref(),
+        }
+    }
+
+    /// The location field of the error; a validation failure keeps its
+    /// location in the [`ValidationError`].
+    fn location_slot(&mut self) -> Option<&mut Option<Box<Location>>> {
+        match self {
+            Error::Io(_, location)
+            | Error::CsvError(_, location)
+            | Error::XmlError { location, .. }
+            | Error::JsonError { location, .. }
+            | Error::Mt940ParseError { location, .. }
+            | Error::CsvRowError { location, .. }
+            | Error::InvalidDate(_, location)
+            | Error::InvalidAmount(_, location)
+            | Error::InvalidCurrency(_, location)
+            | Error::InvalidIban(_, location)
+            | Error::InvalidBic(_, location)
+            | Error::MissingField(_, location)
+            | Error::MissingFields(_, location)
+            | Error::InvalidFormat(_, location)
+            | Error::ParseError(_, location)
+            | Error::ConversionError(_, location) => Some(location),
+            Error::Validation(_) => None,
+        }
+    }
+
+    /// The error message in `language`; details the message catalog
+    /// doesn't know stay in English.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ypbank_system::messages::Language;
+    /// use ypbank_system::mt940_format::Mt940Statement;
+    ///
+    /// let input = ":20:STMT\n:25:ACC001\n:60F:C240101EUR100,00\n:61:2413020102D10,00NTRF//REF1\n";
+    /// let err = Mt940Statement::from_read(&mut input.as_bytes()).unwrap_err();
+    /// assert_eq!(
+    ///     err.localized(Language::Russian),
+    ///     "Ошибка разбора MT940 в строке 4: Неверный формат даты: 2024-13-2 \
+    ///      (строка 4, колонка 1, байт 42, фрагмент \":61:2413020102D10,00NTRF//REF1\")"
+    /// );
+    /// assert_eq!(err.localized(Language::English), err.to_string());
+    /// ```
+    pub fn localized(&self, language: Language) -> String {
+        let message = match self {
+            Error::Validation(failure) => {
+                return render("Validation error: {}", &[&failure.localized(language)], language);
+            }
+            Error::MissingFields(fields, _) => {
+                let fields: Vec<String> = fields.iter().map(|field| translate(field, language)).collect();
+                render("Missing required fields: {}", &[&fields.join(", ")], language)
+            }
+            error => {
+                let text = error.to_string();
+                let suffix = At(error.location()).to_string();
+                translate(text.strip_suffix(&suffix).unwrap_or(&text), language)
+            }
+        };
+        match self.location() {
+            Some(location) => format!("{} ({})", message, location.localized(language)),
+            None => message,
+        }
+    }
+
+    /// Stable code of the error, to branch on instead of its message.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ypbank_system::error::ErrorCode;
+    /// use ypbank_system::mt940_format::Mt940Statement;
+    ///
+    /// let input = ":20:STMT\n:25:ACC001\n:60F:C240101EUR1O0,00\n";
+    /// let err = Mt940Statement::from_read(&mut input.as_bytes()).unwrap_err();
+    /// assert_eq!(err.code(), ErrorCode::Mt940BadBalance);
+    /// assert_eq!(err.code().as_str(), "E_MT940_BAD_BALANCE");
+    /// ```
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Error::Io(..) => ErrorCode::Io,
+            Error::CsvError(..) => ErrorCode::CsvSyntax,
+            Error::XmlError { .. } => ErrorCode::Xml,
+            Error::JsonError { .. } => ErrorCode::Json,
+            Error::Mt940ParseError { code, .. } | Error::CsvRowError { code, .. } => *code,
+            Error::InvalidDate(..) => ErrorCode::BadDate,
+            Error::InvalidAmount(..) => ErrorCode::BadAmount,
+            Error::InvalidCurrency(..) => ErrorCode::BadCurrency,
+            Error::InvalidIban(..) => ErrorCode::BadIban,
+            Error::InvalidBic(..) => ErrorCode::BadBic,
+            Error::MissingField(..) | Error::MissingFields(..) => ErrorCode::MissingField,
+            Error::InvalidFormat(..) => ErrorCode::BadFormat,
+            Error::ParseError(..) => ErrorCode::Parse,
+            Error::ConversionError(..) => ErrorCode::Conversion,
+            Error::Validation(_) => ErrorCode::Validation,
+        }
+    }
+
+    /// Whether the error is in a single value or record, so that skipping
+    /// the record (see the `skip_invalid` read options) lets the rest of the
+    /// input be read; I/O, syntax, missing-field and configuration errors
+    /// aren't.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ypbank_system::csv_format::CsvStatement;
+    ///
+    /// let input = "Дата проводки,Сумма по дебету,Сумма по кредиту\n20.02.2024,1O0.00,\n";
+    /// let err = CsvStatement::from_read(&mut input.as_bytes()).unwrap_err();
+    /// assert!(err.is_recoverable() && !err.is_io());
+    /// ```
+    pub fn is_recoverable(&self) -> bool {
+        matches!(
+            self.code(),
+            ErrorCode::Mt940BadStatementLine
+                | ErrorCode::CsvBadDate
+                | ErrorCode::CsvBadAmount
+                | ErrorCode::CsvBadCurrency
+                | ErrorCode::CsvBadValue
+                | ErrorCode::CsvMissingValue
+                | ErrorCode::BadDate
+                | ErrorCode::BadAmount
+                | ErrorCode::BadCurrency
+                | ErrorCode::BadIban
+                | ErrorCode::BadBic
+                | ErrorCode::Parse
+        )
+    }
+
+    /// The validation failure, when the error is one.
+    pub fn validation(&self) -> Option<&ValidationError> {
+        match self {
+            Error::Validation(failure) => Some(failure),
+            _ => None,
+        }
+    }
+
+    /// Class of the failure, telling unreadable input, broken rules and
+    /// I/O apart.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ypbank_system::error::FailureClass;
+    /// use ypbank_system::mt940_format::Mt940Statement;
+    ///
+    /// let input = ":20:STMT\n:25:ACC001\n:60F:C240101EUR1O0,00\n";
+    /// let err = Mt940Statement::from_read(&mut input.as_bytes()).unwrap_err();
+    /// assert_eq!(err.failure_class(), FailureClass::Parse);
+    /// assert_eq!(err.failure_class().exit_code(), 2);
+    /// ```
+    pub fn failure_class(&self) -> FailureClass {
+        self.code().failure_class()
+    }
+
+    /// Whether reading or writing failed, rather than the data.
+    pub fn is_io(&self) -> bool {
+        matches!(self, Error::Io(..))
+    }
+
+    /// Format whose syntax the error is about, when it's specific to one.
+    pub fn format(&self) -> Option<Format> {
+        match self {
+            Error::CsvError(..) | Error::CsvRowError { .. } => Some(Format::Csv),
+            Error::XmlError { .. } => Some(Format::Camt053),
+            Error::JsonError { .. } => Some(Format::Json),
+            Error::Mt940ParseError { .. } => Some(Format::Mt940),
+            _ => None,
+        }
+    }
+
+    /// Error for the required `fields` found missing, one or several.
+    pub(crate) fn missing_fields(fields: &[&str]) -> Error {
+        match fields {
+            [field] => Error::MissingField(field.to_string(), None),
+            fields => Error::MissingFields(fields.iter().map(|field| field.to_string()).collect(), None),
+        }
+    }
+
+    /// XML error wrapping `err` as its source.
+    pub(crate) fn xml(err: impl std::error::Error + Send + Sync + 'static) -> Error {
+        Error::XmlError { message: err.to_string(), source: Some(Box::new(err)), location: None }
+    }
+}
+
+/// Kind of an [`Error`], see [`Error::code`].
+///
+/// Codes are never renamed or reused; [`ErrorCode::as_str`] gives the form
+/// printed by the command line tools.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorCode {
+    /// `E_IO`: reading or writing failed.
+    Io,
+    /// `E_CSV_SYNTAX`: the CSV text itself is malformed.
+    CsvSyntax,
+    /// `E_XML`: malformed XML or a document not shaped like CAMT.053.
+    Xml,
+    /// `E_JSON`: malformed JSON or a document not shaped like a statement.
+    Json,
+    /// `E_MT940_BAD_BALANCE`: unreadable `:60:`/`:62:` balance line.
+    Mt940BadBalance,
+    /// `E_MT940_BAD_STATEMENT_LINE`: unreadable `:61:` statement line.
+    Mt940BadStatementLine,
+    /// `E_CSV_BAD_DATE`: unreadable date or time cell.
+    CsvBadDate,
+    /// `E_CSV_BAD_AMOUNT`: unreadable amount or balance cell.
+    CsvBadAmount,
+    /// `E_CSV_BAD_CURRENCY`: unknown currency cell.
+    CsvBadCurrency,
+    /// `E_CSV_BAD_VALUE`: unreadable cell of another column.
+    CsvBadValue,
+    /// `E_CSV_MISSING_VALUE`: empty cell where a value is required.
+    CsvMissingValue,
+    /// `E_CSV_UNKNOWN_COLUMN`: header not mapped by the profile.
+    CsvUnknownColumn,
+    /// `E_CSV_MISSING_COLUMN`: column required by the profile not found.
+    CsvMissingColumn,
+    /// `E_BAD_DATE`: invalid date.
+    BadDate,
+    /// `E_BAD_AMOUNT`: invalid amount, or one with too many decimals.
+    BadAmount,
+    /// `E_BAD_CURRENCY`: unknown ISO 4217 currency code.
+    BadCurrency,
+    /// `E_BAD_IBAN`: IBAN with a wrong length or checksum.
+    BadIban,
+    /// `E_BAD_BIC`: malformed BIC.
+    BadBic,
+    /// `E_MISSING_FIELD`: required field not found.
+    MissingField,
+    /// `E_BAD_FORMAT`: unknown format, profile or option value.
+    BadFormat,
+    /// `E_PARSE`: other invalid input.
+    Parse,
+    /// `E_CONVERSION`: the statement can't be converted or written as asked.
+    Conversion,
+    /// `E_VALIDATION`: the statement breaks a rule, see [`ValidationError::rule`].
+    Validation,
+}
+
+impl ErrorCode {
+    /// Code as printed, e.g. `E_MT940_BAD_BALANCE`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::Io => "E_IO",
+            ErrorCode::CsvSyntax => "E_CSV_SYNTAX",
+            ErrorCode::Xml => "E_XML",
+            ErrorCode::Json => "E_JSON",
+            ErrorCode::Mt940BadBalance => "E_MT940_BAD_BALANCE",
+            ErrorCode::Mt940BadStatementLine => "E_MT940_BAD_STATEMENT_LINE",
+            ErrorCode::CsvBadDate => "E_CSV_BAD_DATE",
+            ErrorCode::CsvBadAmount => "E_CSV_BAD_AMOUNT",
+            ErrorCode::CsvBadCurrency => "E_CSV_BAD_CURRENCY",
+            ErrorCode::CsvBadValue => "E_CSV_BAD_VALUE",
+            ErrorCode::CsvMissingValue => "E_CSV_MISSING_VALUE",
+            ErrorCode::CsvUnknownColumn => "E_CSV_UNKNOWN_COLUMN",
+            ErrorCode::CsvMissingColumn => "E_CSV_MISSING_COLUMN",
+            ErrorCode::BadDate => "E_BAD_DATE",
+            ErrorCode::BadAmount => "E_BAD_AMOUNT",
+            ErrorCode::BadCurrency => "E_BAD_CURRENCY",
+            ErrorCode::BadIban => "E_BAD_IBAN",
+            ErrorCode::BadBic => "E_BAD_BIC",
+            ErrorCode::MissingField => "E_MISSING_FIELD",
+            ErrorCode::BadFormat => "E_BAD_FORMAT",
+            ErrorCode::Parse => "E_PARSE",
+            ErrorCode::Conversion => "E_CONVERSION",
+            ErrorCode::Validation => "E_VALIDATION",
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl ErrorCode {
+    /// Class of failure the code falls in, see [`Error::failure_class`].
+    pub fn failure_class(&self) -> FailureClass {
+        match self {
+            ErrorCode::Io => FailureClass::Io,
+            ErrorCode::Validation => FailureClass::Validation,
+            ErrorCode::BadFormat => FailureClass::Usage,
+            ErrorCode::Conversion => FailureClass::Other,
+            ErrorCode::CsvSyntax
+            | ErrorCode::Xml
+            | ErrorCode::Json
+            | ErrorCode::Mt940BadBalance
+            | ErrorCode::Mt940BadStatementLine
+            | ErrorCode::CsvBadDate
+            | ErrorCode::CsvBadAmount
+            | ErrorCode::CsvBadCurrency
+            | ErrorCode::CsvBadValue
+            | ErrorCode::CsvMissingValue
+            | ErrorCode::CsvUnknownColumn
+            | ErrorCode::CsvMissingColumn
+            | ErrorCode::BadDate
+            | ErrorCode::BadAmount
+            | ErrorCode::BadCurrency
+            | ErrorCode::BadIban
+            | ErrorCode::BadBic
+            | ErrorCode::MissingField
+            | ErrorCode::Parse => FailureClass::Parse,
+        }
+    }
+}
+
+/// Class of failure, which the command line tools exit with so that
+/// scripts can branch on it. The statuses are never renumbered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FailureClass {
+    /// 1: the statement can't be converted as asked, or some files of a
+    /// batch failed.
+    Other,
+    /// 2: the input isn't valid in its format.
+    Parse,
+    /// 3: the statement breaks a rule, such as its balances not adding up.
+    Validation,
+    /// 4: reading or writing failed.
+    Io,
+    /// 5: the compared statements differ.
+    Differences,
+    /// 64: invalid command line, unknown format, profile or option value.
+    Usage,
+}
+
+impl FailureClass {
+    /// Exit status of the command line tools.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            FailureClass::Other => 1,
+            FailureClass::Parse => 2,
+            FailureClass::Validation => 3,
+            FailureClass::Io => 4,
+            FailureClass::Differences => 5,
+            FailureClass::Usage => 64,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err, None)
+    }
+}
+
+impl From<csv::Error> for Error {
+    fn from(err: csv::Error) -> Self {
+        Error::CsvError(err, None)
+    }
 }
 
 impl From<quick_xml::Error> for Error {
     fn from(err: quick_xml::Error) -> Self {
-        Error::XmlError(err.to_string())
+        Error::xml(err)
+    }
+}
+
+impl From<quick_xml::DeError> for Error {
+    fn from(err: quick_xml::DeError) -> Self {
+        Error::xml(err)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        // Failing to read or write isn't about the JSON
+        if err.is_io() {
+            return Error::Io(err.into(), None);
+        }
+        Error::JsonError { message: err.to_string(), source: Some(Box::new(err)), location: None }
+    }
+}
+
+/// Statement breaking a validation rule, reported apart from parse errors
+/// so that failures can be filtered by [`RuleId`].
+///
+/// # Examples
+///
+/// ```
+/// use ypbank_system::conversion::{fit_mt940_limits, TruncationPolicy};
+/// use ypbank_system::error::RuleId;
+/// use ypbank_system::types::{Currency, Statement};
+///
+/// let mut statement = Statement::new("STATEMENT-2024-01-31".into(), "ACC001", Currency::EUR);
+/// let err = fit_mt940_limits(&mut statement, TruncationPolicy::Reject).unwrap_err();
+/// let failure = err.validation().unwrap();
+/// assert_eq!(failure.rule, RuleId::Mt940FieldLength);
+/// assert_eq!(err.to_string(), "Validation error: [MT940_FIELD_LENGTH] statement_id is too long for MT940 (element statement_id)");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationError {
+    /// The rule broken.
+    pub rule: RuleId,
+    /// Where in the statement or its input, when known.
+    pub location: Option<Location>,
+    /// What is wrong.
+    pub message: String,
+}
+
+impl ValidationError {
+    /// Failure of `rule`, without a location.
+    pub fn new(rule: RuleId, message: impl Into<String>) -> Self {
+        ValidationError { rule, location: None, message: message.into() }
+    }
+
+    /// The failure at element or field `path`.
+    pub fn at_path(mut self, path: impl Into<String>) -> Self {
+        self.location = Some(Location { path: Some(path.into()), ..Default::default() });
+        self
+    }
+
+    /// The failure as displayed, in `language`.
+    pub fn localized(&self, language: Language) -> String {
+        let message = format!("[{}] {}", self.rule, translate(&self.message, language));
+        match self.location {
+            Some(ref location) => format!("{} ({})", message, location.localized(language)),
+            None => message,
+        }
+    }
+}
+
+impl std::fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.localized(Language::English))
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+impl From<ValidationError> for Error {
+    fn from(failure: ValidationError) -> Self {
+        Error::Validation(Box::new(failure))
+    }
+}
+
+/// Validation rule, see [`ValidationError`].
+///
+/// Rules are never renamed or reused; [`RuleId::as_str`] gives the form
+/// printed in messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum RuleId {
+    /// `BALANCE_CLOSING`: the closing balance isn't the opening balance plus
+    /// the booked transactions.
+    ClosingBalance,
+    /// `BALANCE_CHAIN`: a statement's closing balance isn't the opening
+    /// balance of the statement merged after it.
+    BalanceChain,
+    /// `CAMT_SUMMARY`: the CAMT.053 transactions summary (`TxsSummry`)
+    /// doesn't match the entries.
+    TransactionsSummary,
+    /// `MT940_FIELD_LENGTH`: a field is longer than MT940 allows.
+    Mt940FieldLength,
+    /// `MT940_ACCOUNT_LENGTH`: the account identification is longer than
+    /// MT940's `:25:` allows.
+    Mt940AccountLength,
+    /// `REQUIRED_FIELD`: a field the format makes mandatory is missing.
+    RequiredField,
+}
+
+impl RuleId {
+    /// Rule as printed, e.g. `BALANCE_CLOSING`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RuleId::ClosingBalance => "BALANCE_CLOSING",
+            RuleId::BalanceChain => "BALANCE_CHAIN",
+            RuleId::TransactionsSummary => "CAMT_SUMMARY",
+            RuleId::Mt940FieldLength => "MT940_FIELD_LENGTH",
+            RuleId::Mt940AccountLength => "MT940_ACCOUNT_LENGTH",
+            RuleId::RequiredField => "REQUIRED_FIELD",
+        }
+    }
+}
+
+impl std::fmt::Display for RuleId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Kind of a [`Warning`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WarningKind {
+    /// A tag, element, column or field the parser does not know was ignored.
+    UnknownField,
+    /// A record that could not be read was skipped.
+    SkippedRecord,
+    /// A missing value was replaced by a default.
+    AssumedDefault,
+    /// A value that could not be read was left out.
+    IgnoredValue,
+}
+
+/// Data-quality problem that lenient parsing got past.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    /// What kind of problem it is.
+    pub kind: WarningKind,
+    /// Line or row of the input, when known.
+    pub line: Option<usize>,
+    /// Description of the problem.
+    pub message: String,
+}
+
+impl Warning {
+    /// Warning of `kind` at `line`.
+    pub fn new(kind: WarningKind, line: Option<usize>, message: impl Into<String>) -> Self {
+        Warning { kind, line, message: message.into() }
+    }
+
+    /// The warning as displayed, in `language`.
+    pub fn localized(&self, language: Language) -> String {
+        let message = translate(&self.message, language);
+        match self.line {
+            Some(line) => render("line {}: {}", &[&line.to_string(), &message], language),
+            None => message,
+        }
+    }
+}
+
+impl std::fmt::Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}: {}", line, self.message),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+/// Record left out of a statement read with error recovery.
+#[derive(Debug)]
+pub struct RejectedRecord {
+    /// Line of the input the record starts on.
+    pub line: usize,
+    /// Text of the record as read.
+    pub text: String,
+    /// Why the record could not be read.
+    pub error: Error,
+}
+
+impl std::fmt::Display for RejectedRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.error)
     }
 }
 
-impl From<serde_xml_rs::Error> for Error {
-    fn from(err: serde_xml_rs::Error) -> Self {
-        Error::XmlError(err.to_string())
+impl RejectedRecord {
+    /// The record as displayed, in `language`.
+    pub fn localized(&self, language: Language) -> String {
+        render("line {}: {}", &[&self.line.to_string(), &self.error.localized(language)], language)
+    }
+}
+
+/// Parsed value together with the warnings found while parsing it.
+#[derive(Debug)]
+pub struct ParseOutcome<T> {
+    /// The parsed statement.
+    pub statement: T,
+    /// Problems that did not stop parsing, in input order.
+    pub warnings: Vec<Warning>,
+    /// Records left out because they could not be read, when the format's
+    /// `skip_invalid` option is set; otherwise the first one is an error.
+    pub rejected: Vec<RejectedRecord>,
+}
+
+impl<T> ParseOutcome<T> {
+    /// Outcome with the statement converted by `f`.
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> ParseOutcome<U> {
+        ParseOutcome { statement: f(self.statement), warnings: self.warnings, rejected: self.rejected }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mt940_format::Mt940Statement;
+
+    fn mt940_error(input: &str) -> Error {
+        Mt940Statement::from_read(&mut input.as_bytes()).unwrap_err()
+    }
+
+    #[test]
+    fn test_error_codes() {
+        let io = Error::from(io::Error::new(io::ErrorKind::NotFound, "gone"));
+        assert_eq!((io.code(), io.code().as_str()), (ErrorCode::Io, "E_IO"));
+        assert!(io.is_io() && !io.is_recoverable());
+        assert_eq!(Error::InvalidDate("2024-13-01".into(), None).code().as_str(), "E_BAD_DATE");
+        assert_eq!(Error::missing_fields(&["a", "b"]).code(), ErrorCode::MissingField);
+        assert_eq!(Error::InvalidFormat("xml".into(), None).code().to_string(), "E_BAD_FORMAT");
+
+        let header = ":20:STMT\n:25:ACC001\n";
+        let err = mt940_error(&format!("{}:60F:C240101EUR1O0,00\n", header));
+        assert_eq!(err.code(), ErrorCode::Mt940BadBalance);
+        assert!(!err.is_recoverable());
+        let err = mt940_error(&format!("{}:60F:C240101EUR100,00\n:61:2413020102D10,00NTRF//REF1\n", header));
+        assert_eq!(err.code(), ErrorCode::Mt940BadStatementLine);
+        assert!(err.is_recoverable());
+        assert_eq!(err.format(), Some(Format::Mt940));
+
+        // The code stays the one of the error, wherever it is located
+        let err = err.in_file("june.mt940");
+        assert_eq!(err.code().as_str(), "E_MT940_BAD_STATEMENT_LINE");
+        assert_eq!(err.location().and_then(|location| location.file.as_deref()), Some("june.mt940"));
+    }
+
+    #[test]
+    fn test_validation_errors() {
+        let failure = ValidationError::new(RuleId::BalanceChain, "opening balance 5.00 does not follow closing balance 10.00");
+        assert_eq!(failure.to_string(), "[BALANCE_CHAIN] opening balance 5.00 does not follow closing balance 10.00");
+        let failure = failure.at_path("opening_balance");
+        assert!(failure.to_string().ends_with("(element opening_balance)"));
+
+        let err = Error::from(failure.clone());
+        assert_eq!((err.code(), err.failure_class()), (ErrorCode::Validation, FailureClass::Validation));
+        assert_eq!(err.validation(), Some(&failure));
+        assert!(err.format().is_none() && !err.is_recoverable());
+        assert_eq!(err.to_string(), format!("Validation error: {}", failure));
+
+        // The file is added to the location the failure already has
+        let err = err.in_file("june.json");
+        let location = err.location().unwrap();
+        assert_eq!((location.file.as_deref(), location.path.as_deref()), (Some("june.json"), Some("opening_balance")));
+        assert_eq!(err.validation().unwrap().rule.as_str(), "BALANCE_CHAIN");
     }
 }
@@ -0,0 +1,374 @@
+//! Pluggable statement formats.
+//!
+//! Every format module implements [`StatementFormat`] for a small type
+//! carrying its options ([`Mt940Format`], [`Camt053Format`], [`CsvFormat`],
+//! [`JsonFormat`]). A [`FormatRegistry`] maps each [`Format`] to one of them,
+//! so code converting between formats doesn't need to know any of them:
+//!
+//! ```
+//! use ypbank_system::format::FormatRegistry;
+//! use ypbank_system::Format;
+//!
+//! let registry = FormatRegistry::default();
+//! let input = ":20:STMT\n:25:ACC001\n:60F:C240101EUR100,00\n:62F:C240101EUR100,00\n";
+//! let mut output = Vec::new();
+//! registry.convert(Format::Mt940, Format::Json, &mut input.as_bytes(), &mut output)?;
+//! # Ok::<(), ypbank_system::Error>(())
+//! ```
+//!
+//! Third-party formats implement the trait and register under
+//! [`Format::Custom`]:
+//!
+//! ```
+//! use std::io::{Read, Write};
+//! use ypbank_system::format::{FormatRegistry, StatementFormat};
+//! use ypbank_system::{Format, Result, Statement};
+//!
+//! struct Ofx;
+//!
+//! impl StatementFormat for Ofx {
+//!     fn read(&self, reader: &mut dyn Read) -> Result<Statement> {
+//!         unimplemented!()
+//!     }
+//!
+//!     fn write(&self, statement: &Statement, writer: &mut dyn Write) -> Result<()> {
+//!         unimplemented!()
+//!     }
+//! }
+//!
+//! let mut registry = FormatRegistry::default();
+//! registry.register(Format::Custom("ofx"), Ofx);
+//! assert_eq!(registry.parse_format("OFX")?, Format::Custom("ofx"));
+//! # Ok::<(), ypbank_system::Error>(())
+//! ```
+
+pub use crate::camt053_format::Camt053Format;
+pub use crate::csv_format::CsvFormat;
+pub use crate::json_format::JsonFormat;
+pub use crate::mt940_format::Mt940Format;
+
+use crate::error::{Error, ParseOutcome, Result};
+use crate::types::{Statement, Transaction};
+use crate::Format;
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::sync::Arc;
+
+/// A statement file format that can be read and written.
+///
+/// Formats are shared between threads when files are processed in
+/// parallel, see [`crate::batch`].
+pub trait StatementFormat: Send + Sync {
+    /// Parse a statement from `reader`.
+    fn read(&self, reader: &mut dyn Read) -> Result<Statement>;
+
+    /// Parse a statement from `reader` along with the problems lenient
+    /// parsing got past; formats that don't report any return none.
+    fn read_with_diagnostics(&self, reader: &mut dyn Read) -> Result<ParseOutcome<Statement>> {
+        Ok(ParseOutcome { statement: self.read(reader)?, warnings: Vec::new(), rejected: Vec::new() })
+    }
+
+    /// Write `statement` to `writer`.
+    fn write(&self, statement: &Statement, writer: &mut dyn Write) -> Result<()>;
+
+    /// Read the transactions of the statement in `reader` one at a time.
+    ///
+    /// Formats with a streaming reader hold only the current transaction in
+    /// memory; the default parses the whole statement first.
+    fn entries<'a>(&self, mut reader: Box<dyn Read + 'a>) -> Result<Box<dyn EntryReader + 'a>> {
+        let mut statement = self.read(&mut reader)?;
+        let transactions = std::mem::take(&mut statement.transactions).into_iter();
+        Ok(Box::new(StatementEntries { statement, transactions }))
+    }
+}
+
+/// Transactions of a statement read one at a time, see
+/// [`StatementFormat::entries`].
+pub trait EntryReader: Iterator<Item = Result<Transaction>> {
+    /// Read the rest of the statement: its header and balances, with the
+    /// transactions not yet read.
+    fn collect_statement(self: Box<Self>) -> Result<Statement>;
+}
+
+/// Entries of a statement parsed as a whole.
+struct StatementEntries {
+    statement: Statement,
+    transactions: std::vec::IntoIter<Transaction>,
+}
+
+impl Iterator for StatementEntries {
+    type Item = Result<Transaction>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.transactions.next().map(Ok)
+    }
+}
+
+impl EntryReader for StatementEntries {
+    fn collect_statement(self: Box<Self>) -> Result<Statement> {
+        let StatementEntries { mut statement, transactions } = *self;
+        statement.transactions = transactions.collect();
+        Ok(statement)
+    }
+}
+
+/// Implementations of statement formats, keyed by [`Format`].
+///
+/// The default registry holds every built-in format with default options;
+/// [`FormatRegistry::register`] replaces one or adds a custom format.
+pub struct FormatRegistry {
+    formats: HashMap<Format, Arc<dyn StatementFormat>>,
+}
+
+impl Default for FormatRegistry {
+    fn default() -> Self {
+        let mut registry = FormatRegistry::empty();
+        registry.register(Format::Mt940, Mt940Format::default());
+        registry.register(Format::Camt053, Camt053Format::default());
+        registry.register(Format::Csv, CsvFormat::default());
+        registry.register(Format::Json, JsonFormat::default());
+        registry
+    }
+}
+
+impl FormatRegistry {
+    /// Registry without any format.
+    pub fn empty() -> Self {
+        FormatRegistry { formats: HashMap::new() }
+    }
+
+    /// Use `implementation` for `format`, replacing any earlier one.
+    pub fn register(&mut self, format: Format, implementation: impl StatementFormat + 'static) {
+        self.formats.insert(format, Arc::new(implementation));
+    }
+
+    /// Implementation registered for `format`.
+    pub fn get(&self, format: Format) -> Result<&dyn StatementFormat> {
+        self.formats.get(&format)
+            .map(|implementation| implementation.as_ref())
+            .ok_or_else(|| unregistered(format))
+    }
+
+    /// Parse a format name, accepting built-in and registered custom names.
+    pub fn parse_format(&self, name: &str) -> Result<Format> {
+        name.parse().or_else(|e| {
+            self.formats.keys()
+                .find(|format| matches!(format, Format::Custom(custom) if custom.eq_ignore_ascii_case(name)))
+                .copied()
+                .ok_or(e)
+        })
+    }
+
+    /// Read a statement in `format`.
+    pub fn read(&self, format: Format, reader: &mut dyn Read) -> Result<Statement> {
+        self.get(format)?.read(reader)
+    }
+
+    /// Read a statement in `format` along with its parse warnings.
+    pub fn read_with_diagnostics(&self, format: Format, reader: &mut dyn Read) -> Result<ParseOutcome<Statement>> {
+        self.get(format)?.read_with_diagnostics(reader)
+    }
+
+    /// Read the transactions of a statement in `format` one at a time.
+    pub fn entries<'a>(&self, format: Format, reader: Box<dyn Read + 'a>) -> Result<Box<dyn EntryReader + 'a>> {
+        self.get(format)?.entries(reader)
+    }
+
+    /// Write `statement` in `format`.
+    pub fn write(&self, format: Format, statement: &Statement, writer: &mut dyn Write) -> Result<()> {
+        self.get(format)?.write(statement, writer)
+    }
+
+    /// Read a statement in one format and write it in another.
+    pub fn convert(&self, from: Format, to: Format, reader: &mut dyn Read, writer: &mut dyn Write) -> Result<()> {
+        let statement = self.read(from, reader)?;
+        self.write(to, &statement, writer)
+    }
+
+    /// Read a statement in `format` from an async source, see
+    /// [`read_async`].
+    #[cfg(feature = "async")]
+    pub async fn read_async<R>(&self, format: Format, reader: R) -> Result<Statement>
+    where
+        R: tokio::io::AsyncRead + Unpin + Send + 'static,
+    {
+        let implementation = self.shared(format)?;
+        read_async(reader, move |input| implementation.read(input)).await
+    }
+
+    /// Write `statement` in `format` to an async destination, see
+    /// [`write_async`]; returns the destination.
+    #[cfg(feature = "async")]
+    pub async fn write_async<W>(&self, format: Format, statement: Statement, writer: W) -> Result<W>
+    where
+        W: tokio::io::AsyncWrite + Unpin + Send + 'static,
+    {
+        let implementation = self.shared(format)?;
+        write_async(writer, move |output| implementation.write(&statement, output)).await
+    }
+
+    /// Implementation registered for `format`, to move into another task.
+    #[cfg(feature = "async")]
+    fn shared(&self, format: Format) -> Result<Arc<dyn StatementFormat>> {
+        self.formats.get(&format).cloned().ok_or_else(|| unregistered(format))
+    }
+}
+
+fn unregistered(format: Format) -> Error {
+    Error::InvalidFormat(format!("No implementation registered for {}", format.name()), None)
+}
+
+/// Run the blocking parser `read` over an async `reader` on tokio's
+/// blocking pool, so that the input is streamed rather than read in full
+/// and the executor's threads are not blocked.
+#[cfg(feature = "async")]
+pub(crate) async fn read_async<R, T>(reader: R, read: impl FnOnce(&mut dyn Read) -> Result<T> + Send + 'static) -> Result<T>
+where
+    R: tokio::io::AsyncRead + Unpin + Send + 'static,
+    T: Send + 'static,
+{
+    let mut input = std::io::BufReader::new(tokio_util::io::SyncIoBridge::new(reader));
+    blocking(move || read(&mut input)).await
+}
+
+/// Run the blocking writer `write` into an async `writer` on tokio's
+/// blocking pool, flushing it at the end; the output is streamed in
+/// buffer-sized writes. Returns the writer.
+#[cfg(feature = "async")]
+pub(crate) async fn write_async<W>(writer: W, write: impl FnOnce(&mut dyn Write) -> Result<()> + Send + 'static) -> Result<W>
+where
+    W: tokio::io::AsyncWrite + Unpin + Send + 'static,
+{
+    let mut output = std::io::BufWriter::new(tokio_util::io::SyncIoBridge::new(writer));
+    blocking(move || {
+        write(&mut output)?;
+        output.flush()?;
+        Ok(output.into_inner().map_err(|e| e.into_error())?.into_inner())
+    })
+    .await
+}
+
+/// Result of `task` run on tokio's blocking pool; a panic is resumed.
+#[cfg(feature = "async")]
+async fn blocking<T: Send + 'static>(task: impl FnOnce() -> Result<T> + Send + 'static) -> Result<T> {
+    match tokio::task::spawn_blocking(task).await {
+        Ok(result) => result,
+        Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+        Err(e) => Err(std::io::Error::other(e).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_conversions() {
+        let registry = FormatRegistry::default();
+        let input = ":20:STMT\n:25:ACC001\n:60F:C240101EUR100,00\n\
+                     :61:2401020102D10,00NTRF//REF1\n:86:Payment\n:62F:C240102EUR90,00\n";
+        let statement = registry.read(Format::Mt940, &mut input.as_bytes()).unwrap();
+
+        for format in [Format::Mt940, Format::Camt053, Format::Csv, Format::Json] {
+            let mut output = Vec::new();
+            registry.write(format, &statement, &mut output).unwrap();
+            let back = registry.read(format, &mut output.as_slice()).unwrap();
+            assert_eq!(back.transactions.len(), 1, "{:?}", format);
+            assert_eq!(back.transactions[0].amount, statement.transactions[0].amount, "{:?}", format);
+        }
+
+        assert!(FormatRegistry::empty().read(Format::Json, &mut "{}".as_bytes()).is_err());
+        assert!(registry.parse_format("ofx").is_err());
+    }
+
+    #[test]
+    fn test_registry_entries() {
+        let registry = FormatRegistry::default();
+        let input = ":20:STMT\n:25:ACC001\n:60F:C240101EUR100,00\n:61:2401020102D10,00NTRF//REF1\n\
+                     :61:2401030103C5,00NTRF//REF2\n:62F:C240103EUR95,00\n";
+        let statement = registry.read(Format::Mt940, &mut input.as_bytes()).unwrap();
+
+        for format in [Format::Mt940, Format::Camt053, Format::Csv, Format::Json] {
+            let mut output = Vec::new();
+            registry.write(format, &statement, &mut output).unwrap();
+            let mut entries = registry.entries(format, Box::new(output.as_slice())).unwrap();
+            assert_eq!(entries.next().unwrap().unwrap().reference, "REF1", "{:?}", format);
+
+            // The rest of the statement comes with the transactions not yet read
+            let rest = entries.collect_statement().unwrap();
+            assert_eq!(rest.transactions.len(), 1, "{:?}", format);
+            assert_eq!(rest.transactions[0].reference, "REF2", "{:?}", format);
+            if format != Format::Csv {
+                assert_eq!(rest.closing_balance, statement.closing_balance, "{:?}", format);
+            }
+        }
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_async_round_trip() {
+        use crate::mt940_format::Mt940Statement;
+
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        runtime.block_on(async {
+            let registry = FormatRegistry::default();
+            let input = ":20:STMT\n:25:ACC001\n:60F:C240101EUR100,00\n:61:2401020102D10,00NTRF//REF1\n:62F:C240102EUR90,00\n";
+            let mt940 = Mt940Statement::from_async_read(input.as_bytes()).await.unwrap();
+
+            let output = mt940.clone().write_to_async(Vec::new()).await.unwrap();
+            assert_eq!(Mt940Statement::from_read(&mut output.as_slice()).unwrap(), mt940);
+
+            let output = registry.write_async(Format::Camt053, mt940.statement, Vec::new()).await.unwrap();
+            let back = registry.read_async(Format::Camt053, std::io::Cursor::new(output)).await.unwrap();
+            assert_eq!(back.transactions[0].reference, "REF1");
+        });
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_async_streaming() {
+        use crate::json_format::JsonStatement;
+        use std::pin::Pin;
+        use std::sync::Mutex;
+        use std::task::{Context, Poll};
+        use std::time::Duration;
+        use tokio::io::AsyncReadExt;
+
+        /// Destination recording the size of every write.
+        struct Writes(Arc<Mutex<Vec<usize>>>);
+
+        impl tokio::io::AsyncWrite for Writes {
+            fn poll_write(self: Pin<&mut Self>, _: &mut Context<'_>, buf: &[u8]) -> Poll<std::io::Result<usize>> {
+                self.0.lock().unwrap().push(buf.len());
+                Poll::Ready(Ok(buf.len()))
+            }
+
+            fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+                Poll::Ready(Ok(()))
+            }
+
+            fn poll_shutdown(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+                Poll::Ready(Ok(()))
+            }
+        }
+
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap();
+        runtime.block_on(async {
+            // An error at the start of an endless input is found without
+            // waiting for the input to end
+            let input = AsyncReadExt::chain(&b"{\"schema_version\": oops"[..], tokio::io::repeat(b' '));
+            let read = tokio::time::timeout(Duration::from_secs(10), JsonStatement::from_async_read(input));
+            assert!(matches!(read.await, Ok(Err(Error::JsonError { .. }))));
+
+            let mut statement = Statement::new("STMT".into(), "ACC001", crate::types::Currency::EUR);
+            for i in 0..2000 {
+                statement.transactions.push(Transaction { reference: format!("REF{}", i), ..Transaction::default() });
+            }
+            let writes = Arc::new(Mutex::new(Vec::new()));
+            let registry = FormatRegistry::default();
+            registry.write_async(Format::Json, statement, Writes(Arc::clone(&writes))).await.unwrap();
+            let writes = writes.lock().unwrap();
+            assert!(writes.len() > 1 && writes.iter().all(|&size| size <= 8 * 1024), "{:?}", writes);
+        });
+    }
+}
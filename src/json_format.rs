@@ -0,0 +1,451 @@
+//! Versioned JSON representation of statements.
+//!
+//! Statements are persisted as an envelope naming the schema version of the
+//! statement object it wraps:
+//!
+//! ```json
+//! {
+//!   "schema_version": 2,
+//!   "statement": {
+//!     "statement_id": "STMT-2024-02",
+//!     "account": { "Other": { "id": "40702810440000030888", "scheme": null, "issuer": null } },
+//!     "currency": "RUB",
+//!     "transactions": [
+//!       { "reference": "1", "date": "2024-02-20T14:35:00", "amount": "1540.00", "debit_credit": "Debit", "...": "..." }
+//!     ],
+//!     "...": "..."
+//!   }
+//! }
+//! ```
+//!
+//! The statement object carries every field of [`Statement`] and its nested
+//! types under their Rust names. Amounts are decimal strings, dates
+//! `YYYY-MM-DD`, or `YYYY-MM-DDTHH:MM:SS` when the time of day is known,
+//! currencies ISO 4217 codes and enumerations the names of their variants.
+//!
+//! # Compatibility
+//!
+//! Within a schema version fields are only ever added, never renamed,
+//! removed or given a new meaning, and every added field is optional, so:
+//!
+//! - documents written by an older crate version load, missing fields
+//!   taking their empty value;
+//! - unknown fields are ignored, so documents written by a newer crate
+//!   version of the same schema load as well;
+//! - a `schema_version` above [`SCHEMA_VERSION`] is rejected, as is a
+//!   document without the envelope, which older versions never wrote.
+//!
+//! Incompatible changes raise [`SCHEMA_VERSION`], and readers upgrade
+//! documents of every earlier version. Version 2 carries the time of day in
+//! the date fields themselves; version 1 had it in separate `*_datetime`
+//! fields.
+
+use crate::error::{Error, Location, ParseOutcome, Result, Warning, WarningKind};
+use crate::format::StatementFormat;
+use crate::types::Statement;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::io::{BufRead, BufReader, Read, Write};
+
+/// Schema version written by this crate version.
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// Represents a statement in the versioned JSON representation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonStatement {
+    /// The underlying statement data.
+    pub statement: Statement,
+}
+
+/// Options for writing JSON statements.
+#[derive(Debug, Clone)]
+pub struct JsonWriteOptions {
+    /// Indent nested values by this many spaces; `None` writes a single
+    /// line, e.g. for line-oriented logs and message queues.
+    pub indent: Option<usize>,
+}
+
+impl Default for JsonWriteOptions {
+    fn default() -> Self {
+        JsonWriteOptions { indent: Some(2) }
+    }
+}
+
+/// The JSON representation as a [`StatementFormat`].
+#[derive(Debug, Clone, Default)]
+pub struct JsonFormat {
+    /// Options used when writing.
+    pub write_options: JsonWriteOptions,
+}
+
+impl StatementFormat for JsonFormat {
+    fn read(&self, mut reader: &mut dyn Read) -> Result<Statement> {
+        Ok(JsonStatement::from_read(&mut reader)?.statement)
+    }
+
+    fn read_with_diagnostics(&self, mut reader: &mut dyn Read) -> Result<ParseOutcome<Statement>> {
+        Ok(JsonStatement::from_read_with_diagnostics(&mut reader)?.map(|parsed| parsed.statement))
+    }
+
+    fn write(&self, statement: &Statement, mut writer: &mut dyn Write) -> Result<()> {
+        JsonStatement { statement: statement.clone() }.write_to_with(&mut writer, &self.write_options)
+    }
+}
+
+#[derive(Serialize)]
+struct EnvelopeRef<'a> {
+    schema_version: u32,
+    statement: &'a Statement,
+}
+
+#[derive(Deserialize)]
+struct Envelope {
+    schema_version: u32,
+    statement: Value,
+}
+
+#[cfg(feature = "async")]
+impl JsonStatement {
+    /// Parse a JSON statement from an async source, such as a socket.
+    ///
+    /// [`JsonStatement::from_read`] runs on tokio's blocking pool and reads the
+    /// input as it arrives, without holding all of it or blocking the
+    /// executor.
+    pub async fn from_async_read<R>(reader: R) -> Result<Self>
+    where
+        R: tokio::io::AsyncRead + Unpin + Send + 'static,
+    {
+        crate::format::read_async(reader, |mut input| Self::from_read(&mut input)).await
+    }
+
+    /// Write the statement to an async destination, like
+    /// [`JsonStatement::write_to`] run on tokio's blocking pool; returns the
+    /// destination.
+    pub async fn write_to_async<W>(self, writer: W) -> Result<W>
+    where
+        W: tokio::io::AsyncWrite + Unpin + Send + 'static,
+    {
+        crate::format::write_async(writer, move |mut output| self.write_to(&mut output)).await
+    }
+}
+
+impl JsonStatement {
+    /// Parse a JSON statement of any supported schema version from any
+    /// source implementing `Read`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::fs::File;
+    /// use ypbank_system::json_format::JsonStatement;
+    ///
+    /// let mut file = File::open("statement.json")?;
+    /// let json = JsonStatement::from_read(&mut file)?;
+    /// println!("Statement ID: {}", json.statement.statement_id);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn from_read<R: Read>(reader: &mut R) -> Result<Self> {
+        Ok(Self::from_read_with_diagnostics(reader)?.statement)
+    }
+
+    /// Parse like [`JsonStatement::from_read`], also returning a warning
+    /// for every unknown field that was ignored, named by its path.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ypbank_system::json_format::JsonStatement;
+    ///
+    /// let input = r#"{"schema_version": 1, "statement": {"statement_id": "S1", "account": {"Other": {"id": "ACC001"}},
+    ///                 "currency": "EUR", "memo": "from a newer version"}}"#;
+    /// let outcome = JsonStatement::from_read_with_diagnostics(&mut input.as_bytes())?;
+    /// assert_eq!(outcome.warnings[0].message, "unknown field statement.memo ignored");
+    /// # Ok::<(), ypbank_system::error::Error>(())
+    /// ```
+    pub fn from_read_with_diagnostics<R: Read>(reader: &mut R) -> Result<ParseOutcome<Self>> {
+        let mut input = LineReader::new(BufReader::new(reader));
+        let mut deserializer = serde_json::Deserializer::from_reader(&mut input);
+        let envelope = Envelope::deserialize(&mut deserializer).and_then(|envelope| deserializer.end().map(|()| envelope));
+        let envelope = envelope.map_err(|e| input.located(e))?;
+        if envelope.schema_version > SCHEMA_VERSION {
+            return Err(Error::InvalidFormat(format!(
+                "JSON schema version {} is newer than the supported version {}",
+                envelope.schema_version, SCHEMA_VERSION
+            ), None));
+        }
+
+        let mut document = envelope.statement;
+        if envelope.schema_version < 2 {
+            upgrade_v1(&mut document);
+        }
+        let statement: Statement = serde_json::from_value(document.clone())?;
+        let mut warnings = Vec::new();
+        unknown_fields("statement", &document, &serde_json::to_value(&statement)?, &mut warnings);
+        Ok(ParseOutcome { statement: JsonStatement { statement }, warnings, rejected: Vec::new() })
+    }
+
+    /// Write the statement as indented JSON in the current schema version.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ypbank_system::json_format::JsonStatement;
+    /// use ypbank_system::types::{Currency, Statement};
+    ///
+    /// let statement = Statement::new("123".into(), "ACC001", Currency::USD);
+    /// let mut output = Vec::new();
+    /// JsonStatement { statement }.write_to(&mut output)?;
+    /// assert!(String::from_utf8(output)?.starts_with("{\n  \"schema_version\": 2,"));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<()> {
+        self.write_to_with(writer, &JsonWriteOptions::default())
+    }
+
+    /// Write the statement in the current schema version using explicit
+    /// options, followed by a newline.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ypbank_system::json_format::{JsonStatement, JsonWriteOptions};
+    /// use ypbank_system::types::{Currency, Statement};
+    ///
+    /// let statement = Statement::new("123".into(), "ACC001", Currency::USD);
+    /// let mut output = Vec::new();
+    /// JsonStatement { statement }.write_to_with(&mut output, &JsonWriteOptions { indent: None })?;
+    /// let output = String::from_utf8(output)?;
+    /// assert!(output.starts_with("{\"schema_version\":2,\"statement\":{"));
+    /// assert_eq!(output.lines().count(), 1);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn write_to_with<W: Write>(&self, writer: &mut W, options: &JsonWriteOptions) -> Result<()> {
+        let envelope = EnvelopeRef {
+            schema_version: SCHEMA_VERSION,
+            statement: &self.statement,
+        };
+        match options.indent {
+            Some(indent) => {
+                let indent = " ".repeat(indent);
+                let formatter = serde_json::ser::PrettyFormatter::with_indent(indent.as_bytes());
+                envelope.serialize(&mut serde_json::Serializer::with_formatter(&mut *writer, formatter))?;
+            }
+            None => serde_json::to_writer(&mut *writer, &envelope)?,
+        }
+        writeln!(writer)?;
+        Ok(())
+    }
+}
+
+/// Move the `*_datetime` fields of a version 1 statement into the date
+/// fields they give the time of day of.
+fn upgrade_v1(statement: &mut Value) {
+    fn fold(object: &mut Value, date: &str, datetime: &str) {
+        let Some(object) = object.as_object_mut() else { return };
+        if let Some(Value::String(datetime)) = object.remove(datetime) {
+            // Only a timestamp of the same day refines the date
+            if object.get(date).and_then(Value::as_str).is_some_and(|date| datetime.starts_with(date)) {
+                object.insert(date.to_string(), Value::String(datetime));
+            }
+        }
+    }
+
+    fold(statement, "creation_date", "creation_datetime");
+    fold(statement, "from_date", "from_datetime");
+    fold(statement, "to_date", "to_datetime");
+    if let Some(transactions) = statement.get_mut("transactions").and_then(Value::as_array_mut) {
+        for transaction in transactions {
+            fold(transaction, "date", "booking_datetime");
+            fold(transaction, "value_date", "value_datetime");
+        }
+    }
+}
+
+/// Reader keeping the end of the line being read, so that a JSON error
+/// can be shown with its text without keeping the whole input.
+struct LineReader<R> {
+    inner: R,
+    /// Lines read before the current one.
+    lines: usize,
+    /// Byte offset of the start of the current line.
+    line_start: usize,
+    /// Byte offset of the first byte of `kept`.
+    kept_start: usize,
+    /// The last bytes read of the current line.
+    kept: Vec<u8>,
+}
+
+impl<R: BufRead> LineReader<R> {
+    /// Bytes of the current line kept at least, when it has that many.
+    const KEPT: usize = 4096;
+
+    fn new(inner: R) -> Self {
+        LineReader { inner, lines: 0, line_start: 0, kept_start: 0, kept: Vec::new() }
+    }
+
+    /// `err` with the position serde_json reports, when it does.
+    fn located(&mut self, err: serde_json::Error) -> Error {
+        if err.line() == 0 {
+            return err.into();
+        }
+        let suffix = format!(" at line {} column {}", err.line(), err.column());
+        let message = err.to_string().trim_end_matches(&suffix).to_string();
+        let mut location = Location { line: Some(err.line()), column: Some(err.column()), ..Default::default() };
+        // serde_json counts columns in bytes
+        let offset = self.line_start + err.column().saturating_sub(1);
+        if err.line() == self.lines + 1 && offset >= self.kept_start {
+            let mut rest = Vec::new();
+            // The rest of the line is only needed for the snippet
+            let _ = (&mut self.inner).take(Location::SNIPPET_LENGTH as u64 * 4).read_until(b'\n', &mut rest);
+            self.kept.extend_from_slice(&rest);
+            let text = String::from_utf8_lossy(&self.kept);
+            let in_text = Location::in_text(&text, offset - self.kept_start);
+            location.offset = in_text.offset.map(|i| self.kept_start + i);
+            location.snippet = in_text.snippet;
+            if self.kept_start == self.line_start {
+                location.column = in_text.column;
+            }
+        }
+        Error::JsonError { message, source: Some(Box::new(err)), location: Some(Box::new(location)) }
+    }
+}
+
+impl<R: BufRead> Read for LineReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        for &byte in &buf[..read] {
+            if byte == b'\n' {
+                self.lines += 1;
+                self.line_start = self.kept_start + self.kept.len() + 1;
+                self.kept_start = self.line_start;
+                self.kept.clear();
+            } else {
+                self.kept.push(byte);
+            }
+        }
+        if self.kept.len() > 2 * Self::KEPT {
+            let cut = self.kept.len() - Self::KEPT;
+            self.kept.drain(..cut);
+            self.kept_start += cut;
+        }
+        Ok(read)
+    }
+}
+
+/// Warn about the fields of `input` that `known`, the parsed value written
+/// back, doesn't have.
+fn unknown_fields(path: &str, input: &Value, known: &Value, warnings: &mut Vec<Warning>) {
+    match (input, known) {
+        (Value::Object(input), Value::Object(known)) => {
+            for (key, value) in input {
+                let path = format!("{}.{}", path, key);
+                match known.get(key) {
+                    Some(known) => unknown_fields(&path, value, known, warnings),
+                    None => warnings.push(Warning::new(WarningKind::UnknownField, None,
+                        format!("unknown field {} ignored", path))),
+                }
+            }
+        }
+        (Value::Array(input), Value::Array(known)) => {
+            for (i, (value, known)) in input.iter().zip(known).enumerate() {
+                unknown_fields(&format!("{}[{}]", path, i), value, known, warnings);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::csv_format::CsvStatement;
+
+    fn statement() -> Statement {
+        let input = "Дата проводки,Сумма по дебету,Сумма по кредиту,№ документа,Назначение платежа\n\
+                     20.02.2024 14:35,1540.00,,1,Оплата по счёту 12\n\
+                     21.02.2024,,2000.00,2,Возврат\n";
+        let mut statement = CsvStatement::from_read(&mut input.as_bytes()).unwrap().statement;
+        statement.tag_where("fees", |tx| tx.reference == "1");
+        statement
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let json = JsonStatement { statement: statement() };
+        let mut output = Vec::new();
+        json.write_to(&mut output).unwrap();
+
+        let text = String::from_utf8(output.clone()).unwrap();
+        assert!(text.contains("\"amount\": \"1540.00\""));
+        assert!(text.contains("\"date\": \"2024-02-20T14:35:00\""));
+        assert_eq!(JsonStatement::from_read(&mut output.as_slice()).unwrap(), json);
+
+        let mut compact = Vec::new();
+        json.write_to_with(&mut compact, &JsonWriteOptions { indent: None }).unwrap();
+        assert!(String::from_utf8(compact.clone()).unwrap().contains("\"amount\":\"1540.00\""));
+        assert_eq!(JsonStatement::from_read(&mut compact.as_slice()).unwrap(), json);
+
+        let mut indented = Vec::new();
+        json.write_to_with(&mut indented, &JsonWriteOptions { indent: Some(4) }).unwrap();
+        let text = String::from_utf8(indented.clone()).unwrap();
+        assert!(text.starts_with("{\n    \"schema_version\": 2,\n    \"statement\": {\n        \"statement_id\""));
+        assert!(text.ends_with("}\n"));
+        assert_eq!(JsonStatement::from_read(&mut indented.as_slice()).unwrap(), json);
+    }
+
+    #[test]
+    fn test_compatibility() {
+        // Minimal document as an older writer could have produced it, plus
+        // a field a newer writer might add
+        let input = r#"{
+            "schema_version": 1,
+            "statement": {
+                "statement_id": "S1",
+                "account": { "Other": { "id": "ACC001", "scheme": null, "issuer": null } },
+                "currency": "EUR",
+                "transactions": [{
+                    "reference": "R1",
+                    "date": "2024-02-20",
+                    "amount": "10.00",
+                    "currency": "EUR",
+                    "debit_credit": "Credit",
+                    "description": "Payment",
+                    "added_later": true
+                }]
+            }
+        }"#;
+        let statement = JsonStatement::from_read(&mut input.as_bytes()).unwrap().statement;
+        let tx = &statement.transactions[0];
+        assert_eq!(tx.amount.to_string(), "10.00");
+        assert!(tx.tags.is_empty() && tx.details.is_empty());
+
+        // Version 1 kept the time of day apart from the date
+        let timed = input.replace("\"description\"", "\"booking_datetime\": \"2024-02-20T14:35:00\", \"description\"");
+        let outcome = JsonStatement::from_read_with_diagnostics(&mut timed.as_bytes()).unwrap();
+        assert_eq!(outcome.statement.statement.transactions[0].date.to_string(), "2024-02-20T14:35:00");
+        assert_eq!(outcome.warnings.len(), 1);
+
+        let newer = input.replace("\"schema_version\": 1", "\"schema_version\": 3");
+        assert!(matches!(JsonStatement::from_read(&mut newer.as_bytes()), Err(Error::InvalidFormat(..))));
+        assert!(JsonStatement::from_read(&mut "{\"statement_id\": \"S1\"}".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_error_location() {
+        let input = "{\"schema_version\": 1,\n \"statement\": {\"statement_id\": \"S1\", \"currency\": \"ЕUR\" oops}}";
+        let err = JsonStatement::from_read(&mut input.as_bytes()).unwrap_err();
+        assert!(matches!(err, Error::JsonError { ref message, .. } if message == "expected `,` or `}`"));
+        let location = err.location().unwrap();
+        // Columns count characters, the Cyrillic Е taking two bytes
+        assert_eq!((location.line, location.column, location.offset), (Some(2), Some(56), Some(78)));
+        assert_eq!(location.snippet.as_deref(), Some("oops}}"));
+
+        // A document on one long line is located without keeping all of it
+        let padding = " ".repeat(3 * LineReader::<&[u8]>::KEPT);
+        let input = format!("{{\"schema_version\": 1,{}\"statement\": oops}}", padding);
+        let err = JsonStatement::from_read(&mut input.as_bytes()).unwrap_err();
+        let location = err.location().unwrap();
+        assert_eq!((location.line, location.offset), (Some(1), Some(input.find("oops").unwrap())));
+        assert_eq!(location.snippet.as_deref(), Some("oops}"));
+    }
+}
@@ -7,6 +7,7 @@
 //! - **MT940**: SWIFT-like bank statements
 //! - **CAMT.053**: ISO 20022 XML format
 //! - **CSV**: Comma-separated values format
+//! - **JSON**: Versioned serialization of the statement model
 //!
 //! # Features
 //!
@@ -52,16 +53,29 @@ pub mod types;
 pub mod mt940_format;
 pub mod camt053_format;
 pub mod csv_format;
+pub mod json_format;
+pub mod format;
 pub mod conversion;
+pub mod categorize;
+pub mod query;
+pub mod anonymize;
+pub mod pipeline;
+pub mod mapping;
+pub mod messages;
+pub mod batch;
+pub mod validate;
+pub mod logging;
+#[cfg(feature = "testkit")]
+pub mod testkit;
 
 use std::str::FromStr;
 
 // Re-export commonly used types
 pub use error::{Error, Result};
-pub use types::{Transaction, Statement, Balance, DebitCredit, BalanceType};
+pub use types::{AccountIdentifier, BalanceDiscrepancy, BankIdentifier, Bic, Currency, Iban, MergeOptions, PrecisionPolicy, SortKey, SplitBy, Transaction, Statement, Balance, DebitCredit, BalanceType, EntryStatus};
 
 /// Supported financial data formats.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Format {
     /// MT940 SWIFT format
     Mt940,
@@ -69,6 +83,10 @@ pub enum Format {
     Camt053,
     /// CSV format
     Csv,
+    /// Versioned JSON representation
+    Json,
+    /// Third-party format registered in a [`format::FormatRegistry`] under this name
+    Custom(&'static str),
 }
 
 impl FromStr for Format {
@@ -79,7 +97,8 @@ impl FromStr for Format {
             "mt940" | "mt-940" | "swift" => Ok(Format::Mt940),
             "camt053" | "camt.053" | "camt" | "xml" => Ok(Format::Camt053),
             "csv" => Ok(Format::Csv),
-            _ => Err(Error::InvalidFormat(s.to_string())),
+            "json" => Ok(Format::Json),
+            _ => Err(Error::InvalidFormat(s.to_string(), None)),
         }
     }
 }
@@ -92,12 +111,63 @@ impl Format {
         s.parse()
     }
 
-    /// Get file extension for this format.
+    /// Get file extension for this format; custom formats use their name.
     pub fn extension(&self) -> &'static str {
         match self {
             Format::Mt940 => "mt940",
             Format::Camt053 => "xml",
             Format::Csv => "csv",
+            Format::Json => "json",
+            Format::Custom(name) => name,
+        }
+    }
+
+    /// Guess the format of a statement from its first bytes, e.g. the first
+    /// kilobyte of a file.
+    ///
+    /// MT940 starts with a `{1:` block header or a `:20:` tag, CAMT.053 with
+    /// an XML declaration or element and JSON with an object; other text
+    /// whose first line has a `,`, `;` or tab is taken for CSV. `None` when
+    /// nothing fits.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ypbank_system::Format;
+    ///
+    /// assert_eq!(Format::detect(b"{1:F01BANKXXXXAXXX0000000000}{4:\n:20:STMT"), Some(Format::Mt940));
+    /// assert_eq!(Format::detect(b"\xEF\xBB\xBF<?xml version=\"1.0\"?>"), Some(Format::Camt053));
+    /// assert_eq!(Format::detect("Дата;Сумма\n20.02.2024;100,00".as_bytes()), Some(Format::Csv));
+    /// assert_eq!(Format::detect(b"Hello"), None);
+    /// ```
+    pub fn detect(head: &[u8]) -> Option<Format> {
+        // Only CSV is read in UTF-16
+        if head.starts_with(&[0xFF, 0xFE]) || head.starts_with(&[0xFE, 0xFF]) {
+            return Some(Format::Csv);
+        }
+        let head = head.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(head);
+        let start = &head[head.iter().position(|b| !b.is_ascii_whitespace()).unwrap_or(head.len())..];
+
+        if start.starts_with(b"{1:") || start.starts_with(b":20:") {
+            Some(Format::Mt940)
+        } else if start.starts_with(b"<") {
+            Some(Format::Camt053)
+        } else if start.starts_with(b"{") {
+            Some(Format::Json)
+        } else {
+            let first_line = start.split(|&b| b == b'\n').next().unwrap_or_default();
+            first_line.iter().any(|b| b",;\t".contains(b)).then_some(Format::Csv)
+        }
+    }
+
+    /// Canonical name of the format, as accepted by `FromStr` for built-in formats.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Format::Mt940 => "mt940",
+            Format::Camt053 => "camt053",
+            Format::Csv => "csv",
+            Format::Json => "json",
+            Format::Custom(name) => name,
         }
     }
 }
@@ -115,6 +185,16 @@ mod tests {
         assert!("unknown".parse::<Format>().is_err());
     }
 
+    #[test]
+    fn test_format_detect() {
+        assert_eq!(Format::detect(b"\r\n:20:STMT\r\n:25:ACC001"), Some(Format::Mt940));
+        assert_eq!(Format::detect(b"<Document xmlns=\"urn:iso:std:iso:20022\">"), Some(Format::Camt053));
+        assert_eq!(Format::detect(b"{\"schema_version\": 1}"), Some(Format::Json));
+        assert_eq!(Format::detect(b"\xFF\xFED\x00"), Some(Format::Csv));
+        assert_eq!(Format::detect(b"\xC4\xE0\xF2\xE0,\xD1\xF3\xEC\xEC\xE0\n"), Some(Format::Csv));
+        assert_eq!(Format::detect(b"  \n"), None);
+    }
+
     #[test]
     fn test_format_extension() {
         assert_eq!(Format::Mt940.extension(), "mt940");
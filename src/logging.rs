@@ -0,0 +1,80 @@
+//! Diagnostics of the command line tools.
+//!
+//! The binaries report warnings, phase timings and errors as `tracing`
+//! events. [`init`] writes them to stderr, as text or as one JSON object
+//! per line, down to the level chosen with `-v`, `-vv` and `--quiet`:
+//!
+//! ```
+//! use tracing::level_filters::LevelFilter;
+//! use ypbank_system::logging::max_level;
+//!
+//! assert_eq!(max_level(0, false), LevelFilter::WARN);
+//! assert_eq!(max_level(2, false), LevelFilter::DEBUG);
+//! assert_eq!(max_level(2, true), LevelFilter::ERROR);
+//! ```
+
+use crate::error::{Error, Result};
+use std::io::{self, IsTerminal};
+use std::str::FromStr;
+use tracing::level_filters::LevelFilter;
+
+/// How diagnostics are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogFormat {
+    /// A line of text per event.
+    #[default]
+    Text,
+    /// A JSON object per event, for log collectors.
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(Error::InvalidFormat(format!("Unknown log format: {}", s), None)),
+        }
+    }
+}
+
+/// The most detailed level reported: warnings by default, phase timings
+/// with one `-v`, details of every phase with two or more, and only errors
+/// when `quiet`.
+pub fn max_level(verbose: u8, quiet: bool) -> LevelFilter {
+    match (quiet, verbose) {
+        (true, _) => LevelFilter::ERROR,
+        (false, 0) => LevelFilter::WARN,
+        (false, 1) => LevelFilter::INFO,
+        (false, 2) => LevelFilter::DEBUG,
+        (false, _) => LevelFilter::TRACE,
+    }
+}
+
+/// Write the events of the process up to `level` to stderr in `format`.
+/// Text is colored only on a terminal and has no timestamps, JSON has them.
+pub fn init(level: LevelFilter, format: LogFormat) {
+    let subscriber = tracing_subscriber::fmt()
+        .with_writer(io::stderr)
+        .with_max_level(level)
+        .with_target(false);
+    match format {
+        LogFormat::Text => subscriber.with_ansi(io::stderr().is_terminal()).without_time().init(),
+        LogFormat::Json => subscriber.json().flatten_event(true).with_current_span(false).init(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_format() {
+        assert_eq!("JSON".parse::<LogFormat>().unwrap(), LogFormat::Json);
+        assert_eq!("text".parse::<LogFormat>().unwrap(), LogFormat::Text);
+        assert!("xml".parse::<LogFormat>().is_err());
+        assert_eq!(max_level(5, false), LevelFilter::TRACE);
+    }
+}
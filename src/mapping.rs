@@ -0,0 +1,425 @@
+//! Field mapping audit of conversions.
+//!
+//! [`field_mapping`] describes, for every field a conversion writes, which
+//! input fields it comes from and what happens to the value on the way, as
+//! a [`MappingDocument`] serializable to JSON:
+//!
+//! ```
+//! use ypbank_system::conversion::ConversionOptions;
+//! use ypbank_system::mapping::field_mapping;
+//! use ypbank_system::Format;
+//!
+//! let mapping = field_mapping(Format::Mt940, Format::Camt053, &ConversionOptions::default())?;
+//! let amount = mapping.fields.iter().find(|f| f.target == "Ntry/Amt").unwrap();
+//! assert_eq!(amount.sources, [":61: amount"]);
+//! assert_eq!(amount.transformations, [":61: amount: decimal comma"]);
+//! # Ok::<(), ypbank_system::Error>(())
+//! ```
+//!
+//! Fields are named in each format's own terms: MT940 tags and subfields,
+//! CAMT.053 element paths below `Stmt`, CSV columns by their
+//! [`CsvField`](crate::csv_format::CsvField) name and JSON paths into the
+//! statement object. The mapping goes through the statement model: each
+//! format maps its fields to model fields, and the conversion options
+//! derive model fields from others.
+
+use crate::conversion::{BatchPolicy, ChargesPolicy, ConversionOptions, InformationPart};
+use crate::error::{Error, Result};
+use crate::Format;
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::io::Write;
+
+/// Lineage of one output field.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FieldMapping {
+    /// Output field.
+    pub target: String,
+
+    /// Input fields the value comes from; empty for generated values.
+    pub sources: Vec<String>,
+
+    /// Transformations applied on the way, in order.
+    pub transformations: Vec<String>,
+}
+
+/// Field mapping of a conversion from one format to another.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MappingDocument {
+    /// Input format name.
+    pub from: String,
+
+    /// Output format name.
+    pub to: String,
+
+    /// Output fields with a source or a generated value.
+    pub fields: Vec<FieldMapping>,
+
+    /// Input fields that land in no output field.
+    pub dropped: Vec<String>,
+
+    /// Conversion steps affecting whole entries rather than fields.
+    pub notes: Vec<String>,
+}
+
+impl MappingDocument {
+    /// Write the document as indented JSON.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<()> {
+        serde_json::to_writer_pretty(&mut *writer, self)?;
+        writeln!(writer)?;
+        Ok(())
+    }
+}
+
+/// Model field derived from others during conversion.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Derivation {
+    /// Derived model field.
+    pub(crate) target: &'static str,
+
+    /// Model fields it is derived from.
+    pub(crate) sources: Vec<&'static str>,
+
+    /// What the derivation does.
+    pub(crate) transformation: String,
+}
+
+impl Derivation {
+    pub(crate) fn new(target: &'static str, sources: &[&'static str], transformation: impl Into<String>) -> Self {
+        Derivation { target, sources: sources.to_vec(), transformation: transformation.into() }
+    }
+}
+
+/// A format field and the model field it holds, with the transformation
+/// on reading and on writing; `None` where the direction doesn't map it.
+struct Rule {
+    field: &'static str,
+    model: &'static str,
+    read: Option<&'static str>,
+    write: Option<&'static str>,
+}
+
+const fn both(field: &'static str, model: &'static str, read: &'static str, write: &'static str) -> Rule {
+    Rule { field, model, read: Some(read), write: Some(write) }
+}
+
+const fn read_only(field: &'static str, model: &'static str, read: &'static str) -> Rule {
+    Rule { field, model, read: Some(read), write: None }
+}
+
+const fn write_only(field: &'static str, model: &'static str, write: &'static str) -> Rule {
+    Rule { field, model, read: None, write: Some(write) }
+}
+
+const MT940_RULES: &[Rule] = &[
+    both("{1:}{2:} header", "servicing_bank", "BIC of the sending terminal", "BIC as logical terminal, placeholder when unknown"),
+    both(":20:", "statement_id", "", "cut to 16 characters under the truncation policy"),
+    both(":25:", "account", "IBAN when its check digits are valid, else other identification", ""),
+    both(":28C:", "sequence_number", "", "statement number, with the page number of paginated statements"),
+    read_only(":13D:", "creation_date", "converted to UTC"),
+    write_only(":28C:", "pagination", "page number as sequence number"),
+    both(":60F:/:60M:", "opening_balance", "D/C mark, YYMMDD date, currency, decimal comma", ":60M: on pages after the first"),
+    both(":60F: currency", "currency", "", ""),
+    read_only(":60F: currency", "transactions[].currency", "currency of the opening balance"),
+    both(":62F:/:62M:", "closing_balance", "D/C mark, YYMMDD date, currency, decimal comma", ":62M: on pages before the last"),
+    both(":61: value date", "transactions[].value_date", "YYMMDD", "YYMMDD, the booking date when missing"),
+    both(":61: entry date", "transactions[].date", "MMDD in the year of the value date, the value date when missing", "MMDD"),
+    both(":61: D/C mark", "transactions[].debit_credit", "RC/RD reversal marks name the opposite direction", "RC/RD for reversals"),
+    both(":61: D/C mark", "transactions[].status", "R prefix marks a reversal", "R prefix for reversals"),
+    both(":61: amount", "transactions[].amount", "decimal comma", "decimal comma"),
+    write_only(":61: type code", "transactions[].transaction_type", "NTRF when missing"),
+    both(":61: reference", "transactions[].reference", "text after //, date and amount when empty", "after the type code and //, cut to 16 characters under the truncation policy"),
+    both(":86:", "transactions[].description", "continuation lines joined with spaces", "wrapped at 65 characters, at most 6 lines under the truncation policy"),
+];
+
+const CAMT053_RULES: &[Rule] = &[
+    both("Stmt/Id", "statement_id", "", ""),
+    both("Stmt/Acct/Id", "account", "IBAN or other identification", ""),
+    both("Stmt/Acct/Ccy", "currency", "", ""),
+    both("Stmt/Acct/Nm", "account_holder", "", ""),
+    both("Stmt/Acct/Ownr", "account_owner", "", ""),
+    both("Stmt/Acct/Svcr", "servicing_bank", "", ""),
+    both("Stmt/ElctrncSeqNb", "sequence_number", "", "only when numeric"),
+    both("Stmt/CreDtTm", "creation_date", "with an offset converted to UTC", ""),
+    both("Stmt/FrToDt/FrDtTm", "from_date", "with an offset converted to UTC", ""),
+    both("Stmt/FrToDt/ToDtTm", "to_date", "with an offset converted to UTC", ""),
+    both("Stmt/StmtPgntn", "pagination", "", ""),
+    both("Stmt/Bal[OPBD]", "opening_balance", "", ""),
+    both("Stmt/Bal[CLBD]", "closing_balance", "", ""),
+    both("Ntry/NtryRef", "transactions[].reference", "UNKNOWN when missing", ""),
+    both("Ntry/Amt", "transactions[].amount", "", ""),
+    both("Ntry/Amt/@Ccy", "transactions[].currency", "account currency when missing", ""),
+    both("Ntry/CdtDbtInd", "transactions[].debit_credit", "", ""),
+    both("Ntry/Sts", "transactions[].status", "", ""),
+    both("Ntry/RvslInd", "transactions[].status", "reversal of a booked entry", "set for reversals"),
+    both("Ntry/BookgDt", "transactions[].date", "", ""),
+    both("Ntry/BookgDt/DtTm", "transactions[].date", "with time of day, with an offset converted to UTC", "when the time of day is known"),
+    both("Ntry/ValDt", "transactions[].value_date", "", ""),
+    both("Ntry/ValDt/DtTm", "transactions[].value_date", "with time of day, with an offset converted to UTC", "when the time of day is known"),
+    both("Ntry/AcctSvcrRef", "transactions[].refs.account_servicer_reference", "", ""),
+    both("Ntry/Avlbty", "transactions[].funds_availability", "", ""),
+    both("Ntry/Chrgs", "transactions[].charges", "", ""),
+    both("Ntry/NtryDtls/Btch", "transactions[].details", "", "number of details"),
+    both("TxDtls/RmtInf/Ustrd", "transactions[].description", "", ""),
+    write_only("Ntry/BkTxCd/Prtry/Cd", "transactions[].description", ""),
+    both("TxDtls/AddtlTxInf", "transactions[].additional_info", "", ""),
+    both("TxDtls/RltdPties/Dbtr|Cdtr/Nm", "transactions[].counterparty_name", "debtor of a credit, creditor of a debit", "debtor of a credit, creditor of a debit"),
+    both("TxDtls/RltdPties/Dbtr|Cdtr/PstlAdr", "transactions[].counterparty_address", "", ""),
+    both("TxDtls/RltdPties/DbtrAcct|CdtrAcct", "transactions[].counterparty_account", "counterparty side", "counterparty side"),
+    both("TxDtls/RltdPties/CdtrAcct|DbtrAcct", "transactions[].account", "own side", "own side"),
+    both("TxDtls/RltdPties/UltmtDbtr|UltmtCdtr", "transactions[].ultimate_counterparty_name", "", ""),
+    both("TxDtls/RltdAgts/DbtrAgt|CdtrAgt", "transactions[].bank_identifier", "counterparty side", "counterparty side"),
+    both("TxDtls/AmtDtls/InstdAmt", "transactions[].original_amount", "", ""),
+    both("TxDtls/AmtDtls/InstdAmt/@Ccy", "transactions[].original_currency", "", ""),
+    both("TxDtls/AmtDtls/InstdAmt/CcyXchg/XchgRate", "transactions[].exchange_rate", "", ""),
+    both("TxDtls/AmtDtls/CntrValAmt", "transactions[].counter_value_amount", "", ""),
+    both("TxDtls/AmtDtls/CntrValAmt/@Ccy", "transactions[].counter_value_currency", "", ""),
+    both("TxDtls/AmtDtls/CntrValAmt/CcyXchg/XchgRate", "transactions[].counter_value_rate", "", ""),
+    both("TxDtls/RmtInf/Strd", "transactions[].structured_remittance", "", ""),
+    both("TxDtls/RtrInf", "transactions[].return_reason", "", ""),
+    both("TxDtls/Purp/Cd", "transactions[].purpose_code", "", ""),
+    both("TxDtls/Refs/EndToEndId", "transactions[].refs.end_to_end_id", "", ""),
+    both("TxDtls/Refs/InstrId", "transactions[].refs.instruction_id", "", ""),
+    both("TxDtls/Refs/MndtId", "transactions[].refs.mandate_id", "", ""),
+    both("TxDtls", "transactions[].details", "one per underlying payment of a batch", "one per detail"),
+];
+
+const CSV_RULES: &[Rule] = &[
+    both("date", "transactions[].date", "profile date formats", "DD.MM.YYYY"),
+    both("date", "transactions[].date", "time of day after the date", "time of day after the date"),
+    both("time", "transactions[].date", "time of day", "time of day"),
+    both("debit_account", "account", "own side of the first row", "own side of debits"),
+    both("credit_account", "account", "own side of the first row", "own side of credits"),
+    both("debit_account", "transactions[].account", "own side of debits", "own side of debits"),
+    both("credit_account", "transactions[].account", "own side of credits", "own side of credits"),
+    both("debit_account", "transactions[].counterparty_account", "counterparty side of credits", "counterparty side of credits"),
+    both("credit_account", "transactions[].counterparty_account", "counterparty side of debits", "counterparty side of debits"),
+    both("account", "account", "", ""),
+    both("counterparty_account", "transactions[].counterparty_account", "", ""),
+    both("debit_amount", "transactions[].amount", "profile amount format", "profile amount format"),
+    both("credit_amount", "transactions[].amount", "profile amount format", "profile amount format"),
+    both("debit_amount", "transactions[].debit_credit", "debit when filled", "filled for debits"),
+    both("credit_amount", "transactions[].debit_credit", "credit when filled", "filled for credits"),
+    both("amount", "transactions[].amount", "profile amount format and debit/credit convention", "profile amount format and debit/credit convention"),
+    both("debit_credit", "transactions[].debit_credit", "", ""),
+    write_only("signed_amount", "transactions[].amount", "minus sign for debits"),
+    both("currency", "transactions[].currency", "profile default currency when missing", ""),
+    both("reference", "transactions[].reference", "", ""),
+    both("description", "transactions[].description", "", ""),
+    read_only("description", "transactions[].counterparty_name", "name at the start of the description when no column gives it"),
+    both("counterparty_name", "transactions[].counterparty_name", "", ""),
+    both("bank", "transactions[].bank_identifier", "BIC out of the bank text", "BIC"),
+    both("running_balance", "transactions[].running_balance", "", ""),
+    both("category", "transactions[].category", "", ""),
+    both("tags", "transactions[].tags", "split at commas", "joined with commas"),
+    both("status", "transactions[].status", "", ""),
+];
+
+/// Model fields the JSON format holds, as is, under `statement.`.
+const JSON_FIELDS: &[&str] = &[
+    "statement_id", "account", "currency", "sequence_number", "account_holder", "account_owner", "servicing_bank",
+    "creation_date", "from_date", "to_date", "pagination", "opening_balance", "closing_balance",
+    "transactions[].reference", "transactions[].date", "transactions[].value_date", "transactions[].amount", "transactions[].currency", "transactions[].debit_credit",
+    "transactions[].account", "transactions[].counterparty_account", "transactions[].counterparty_name",
+    "transactions[].counterparty_address", "transactions[].ultimate_counterparty_name", "transactions[].bank_identifier",
+    "transactions[].description", "transactions[].additional_info", "transactions[].details", "transactions[].charges",
+    "transactions[].original_amount", "transactions[].original_currency", "transactions[].exchange_rate",
+    "transactions[].counter_value_amount", "transactions[].counter_value_currency", "transactions[].counter_value_rate",
+    "transactions[].structured_remittance", "transactions[].status", "transactions[].return_reason",
+    "transactions[].purpose_code", "transactions[].transaction_type", "transactions[].funds_availability", "transactions[].running_balance",
+    "transactions[].category", "transactions[].tags", "transactions[].refs.account_servicer_reference",
+    "transactions[].refs.instruction_id", "transactions[].refs.end_to_end_id", "transactions[].refs.mandate_id",
+];
+
+/// `(format field, model field, transformation)` of `format`, reading or
+/// writing.
+fn rules(format: Format, reading: bool) -> Result<Vec<(String, &'static str, &'static str)>> {
+    let table = match format {
+        Format::Mt940 => MT940_RULES,
+        Format::Camt053 => CAMT053_RULES,
+        Format::Csv => CSV_RULES,
+        Format::Json => {
+            return Ok(JSON_FIELDS.iter().map(|model| (format!("statement.{}", model), *model, "")).collect());
+        }
+        Format::Custom(name) => {
+            return Err(Error::InvalidFormat(format!("No field mapping for custom format {}", name), None));
+        }
+    };
+    Ok(table.iter()
+        .filter_map(|rule| {
+            let transformation = if reading { rule.read } else { rule.write }?;
+            Some((rule.field.to_string(), rule.model, transformation))
+        })
+        .collect())
+}
+
+/// Model fields derived during conversion from `from` to `to` with
+/// `options`, and notes on steps affecting whole entries.
+fn derivations(from: Format, to: Format, options: &ConversionOptions) -> (Vec<Derivation>, Vec<String>) {
+    let mut derived = Vec::new();
+    let mut notes = Vec::new();
+
+    if options.synthesize_balances {
+        let transformation = "computed from the transactions when missing";
+        derived.push(Derivation::new("opening_balance", &["closing_balance", "transactions[].amount"], transformation));
+        derived.push(Derivation::new("closing_balance", &["opening_balance", "transactions[].amount"], transformation));
+    }
+    match (from, to) {
+        (Format::Mt940, Format::Camt053) => {
+            derived.push(Derivation::new("creation_date", &[], "date of the conversion when missing"));
+            if options.information.decompose {
+                let transformation = format!("split out of the description at \"{}\"", options.information.separator);
+                for target in ["transactions[].additional_info", "transactions[].counterparty_name",
+                               "transactions[].purpose_code", "transactions[].structured_remittance"] {
+                    derived.push(Derivation::new(target, &["transactions[].description"], transformation.clone()));
+                }
+            }
+            for (target, codes) in [
+                ("transactions[].counterparty_name", "/CRNM/, /DBNM/, /NAME/ or ?32 and ?33"),
+                ("transactions[].counterparty_account", "/CACT/, /DACT/, /IBAN/, /ACCT/, ?31 or an IBAN in the text"),
+                ("transactions[].bank_identifier", "/CBIC/, /DBIC/, /BIC/ or ?30"),
+            ] {
+                derived.push(Derivation::new(target, &["transactions[].description"], format!("recovered from {} when missing", codes)));
+            }
+        }
+        (Format::Camt053, Format::Mt940) => {
+            let information = &options.information;
+            let mut sources = vec!["transactions[].description"];
+            sources.extend(information.parts.iter().map(|part| match part {
+                InformationPart::AdditionalInfo => "transactions[].additional_info",
+                InformationPart::Counterparty => "transactions[].counterparty_name",
+                InformationPart::PurposeCode => "transactions[].purpose_code",
+                InformationPart::CreditorReference => "transactions[].structured_remittance",
+            }));
+            derived.push(Derivation {
+                target: "transactions[].description",
+                sources,
+                transformation: format!("other fields appended after \"{}\"", information.separator),
+            });
+        }
+        (Format::Csv, Format::Camt053) => {
+            derived.push(Derivation::new("transactions[].account", &["account"], "statement account for entries without their own"));
+        }
+        _ => {}
+    }
+
+    if !options.excluded_statuses.is_empty() {
+        notes.push(format!("entries with status {:?} are left out", options.excluded_statuses));
+    }
+    match options.batches {
+        Some(BatchPolicy::Expand) => notes.push("batch entries become one entry per underlying payment".to_string()),
+        Some(BatchPolicy::Collapse) => notes.push("batch entries keep their total, their details are dropped".to_string()),
+        None => {}
+    }
+    match options.charges {
+        Some(ChargesPolicy::Merge) => {
+            derived.push(Derivation::new("transactions[].amount", &["transactions[].amount", "transactions[].charges"], "charges merged into the amount"));
+        }
+        Some(ChargesPolicy::SeparateEntries) => notes.push("charges become entries of their own".to_string()),
+        Some(ChargesPolicy::Ignore) => notes.push("charges are dropped".to_string()),
+        None => {}
+    }
+    for (i, hook) in options.hooks.iter().enumerate() {
+        match hook.derivation() {
+            Some(derivation) => derived.push(derivation.clone()),
+            None => notes.push(format!("hook {} may change any transaction field", i + 1)),
+        }
+    }
+
+    (derived, notes)
+}
+
+/// Field mapping of converting from `from` to `to` with `options`, as done
+/// by the `From` conversions and [`apply_options`](crate::conversion::apply_options).
+///
+/// Fails for custom formats, whose fields aren't known.
+pub fn field_mapping(from: Format, to: Format, options: &ConversionOptions) -> Result<MappingDocument> {
+    let reader = rules(from, true)?;
+    let writer = rules(to, false)?;
+    let (derived, notes) = derivations(from, to, options);
+
+    let mut fields = Vec::new();
+    let mut used = BTreeSet::new();
+    for (target, model, write) in &writer {
+        // The model field itself, then whatever it was derived from
+        let mut models = vec![*model];
+        let derivations: Vec<&Derivation> = derived.iter().filter(|d| d.target == *model).collect();
+        models.extend(derivations.iter().flat_map(|d| d.sources.iter().copied()));
+
+        let mut mapping = FieldMapping { target: target.clone(), sources: Vec::new(), transformations: Vec::new() };
+        for (source, source_model, read) in &reader {
+            if !models.contains(source_model) {
+                continue;
+            }
+            if !mapping.sources.contains(source) {
+                mapping.sources.push(source.clone());
+            }
+            if !read.is_empty() {
+                mapping.transformations.push(format!("{}: {}", source.trim_end_matches(':'), read));
+            }
+            used.insert(source.clone());
+        }
+        if mapping.sources.is_empty() && derivations.is_empty() {
+            continue;
+        }
+        mapping.transformations.extend(derivations.iter().map(|d| d.transformation.clone()));
+        if !write.is_empty() {
+            mapping.transformations.push(write.to_string());
+        }
+        fields.push(mapping);
+    }
+
+    let mut dropped = Vec::new();
+    for (source, _, _) in &reader {
+        if !used.contains(source) && !dropped.contains(source) {
+            dropped.push(source.clone());
+        }
+    }
+
+    Ok(MappingDocument { from: from.name().to_string(), to: to.name().to_string(), fields, dropped, notes })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conversion::TransactionHook;
+    use regex::Regex;
+
+    #[test]
+    fn test_field_mapping() {
+        let field = |mapping: &MappingDocument, target: &str| {
+            mapping.fields.iter().find(|f| f.target == target).cloned()
+        };
+
+        let mapping = field_mapping(Format::Mt940, Format::Camt053, &ConversionOptions::default()).unwrap();
+        let name = field(&mapping, "TxDtls/RltdPties/Dbtr|Cdtr/Nm").unwrap();
+        assert_eq!(name.sources, [":86:"]);
+        assert!(name.transformations[1].starts_with("recovered from /CRNM/"));
+        assert_eq!(field(&mapping, "Stmt/CreDtTm").unwrap().sources, [":13D:"]);
+        assert!(field(&mapping, "TxDtls/RtrInf").is_none());
+        assert!(mapping.dropped.is_empty());
+
+        let mapping = field_mapping(Format::Camt053, Format::Mt940, &ConversionOptions::default()).unwrap();
+        let narrative = field(&mapping, ":86:").unwrap();
+        assert_eq!(narrative.sources, ["TxDtls/RmtInf/Ustrd", "TxDtls/AddtlTxInf", "TxDtls/RltdPties/Dbtr|Cdtr/Nm", "TxDtls/RmtInf/Strd", "TxDtls/Purp/Cd"]);
+        assert!(mapping.dropped.contains(&"Stmt/Acct/Nm".to_string()));
+
+        let options = ConversionOptions {
+            hooks: vec![
+                TransactionHook::rewrite_reference(Regex::new("^0+").unwrap(), ""),
+                TransactionHook::new(|_| {}),
+            ],
+            ..Default::default()
+        };
+        let mapping = field_mapping(Format::Csv, Format::Json, &options).unwrap();
+        let reference = field(&mapping, "statement.transactions[].reference").unwrap();
+        assert_eq!(reference.transformations, ["rewritten with ^0+ => \"\""]);
+        assert_eq!(mapping.notes, ["hook 2 may change any transaction field"]);
+
+        let mut json = Vec::new();
+        mapping.write_to(&mut json).unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(value["from"], "csv");
+        assert!(field_mapping(Format::Custom("ofx"), Format::Json, &options).is_err());
+    }
+}
@@ -0,0 +1,247 @@
+//! Message catalog for rendering errors and warnings in other languages.
+//!
+//! Messages are keyed by their English text, as produced by `Display`.
+//! In a template `{}` stands for a value kept as is and `{msg}` for a
+//! nested message, translated in turn. Messages missing from the catalog
+//! are left in English.
+
+use crate::error::{Error, Result};
+use std::str::FromStr;
+
+/// Language of rendered messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Language {
+    /// English, the language of `Display`.
+    #[default]
+    English,
+    /// Russian.
+    Russian,
+}
+
+impl FromStr for Language {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "en" | "english" => Ok(Language::English),
+            "ru" | "russian" => Ok(Language::Russian),
+            _ => Err(Error::InvalidFormat(format!("Unknown language: {}", s), None)),
+        }
+    }
+}
+
+/// English templates with their Russian translations.
+const CATALOG: &[(&str, &str)] = &[
+    // Errors
+    ("I/O error: {}", "Ошибка ввода-вывода: {}"),
+    ("CSV parsing error: {}", "Ошибка разбора CSV: {}"),
+    ("XML parsing error: {}", "Ошибка разбора XML: {}"),
+    ("JSON error: {}", "Ошибка JSON: {}"),
+    ("MT940 parsing error at line {}: {msg}", "Ошибка разбора MT940 в строке {}: {msg}"),
+    ("CSV error at row {}, column \"{}\": {msg}", "Ошибка CSV в строке {}, колонка \"{}\": {msg}"),
+    ("Invalid date format: {}", "Неверный формат даты: {}"),
+    ("Invalid amount format: {}", "Неверный формат суммы: {}"),
+    ("Invalid currency code: {}", "Неверный код валюты: {}"),
+    ("Invalid IBAN: {}", "Неверный IBAN: {}"),
+    ("Invalid BIC: {}", "Неверный BIC: {}"),
+    ("Missing required field: {msg}", "Отсутствует обязательное поле: {msg}"),
+    ("Missing required fields: {}", "Отсутствуют обязательные поля: {}"),
+    ("Invalid format: {msg}", "Неверный формат: {msg}"),
+    ("Parse error: {msg}", "Ошибка разбора: {msg}"),
+    ("Conversion error: {msg}", "Ошибка преобразования: {msg}"),
+    ("Validation error: {}", "Ошибка проверки: {}"),
+    ("Unknown language: {}", "Неизвестный язык: {}"),
+    // Details of parse errors
+    ("unknown column", "неизвестная колонка"),
+    ("missing required column", "нет обязательной колонки"),
+    ("missing required columns", "нет обязательных колонок"),
+    ("statement reference :20:", "референс выписки :20:"),
+    ("account identification :25:", "идентификатор счёта :25:"),
+    ("opening balance :60F:", "входящий остаток :60F:"),
+    ("closing balance :62F:", "исходящий остаток :62F:"),
+    ("date column", "колонка даты"),
+    ("amount", "сумма"),
+    ("debit or credit amount", "сумма по дебету или кредиту"),
+    ("entry currency", "валюта проводки"),
+    ("balance date", "дата остатка"),
+    ("statement pages", "страницы выписки"),
+    ("Invalid balance line: {}", "Неверная строка остатка: {}"),
+    ("Balance line too short: {}", "Слишком короткая строка остатка: {}"),
+    ("Invalid D/C indicator in: {}", "Неверный признак дебета/кредита в: {}"),
+    ("Invalid D/C indicator: {}", "Неверный признак дебета/кредита: {}"),
+    ("Invalid D/C: {}", "Неверный признак дебета/кредита: {}"),
+    ("Invalid date in balance line: {}", "Неверная дата в строке остатка: {}"),
+    ("Invalid currency in balance line: {}", "Неверная валюта в строке остатка: {}"),
+    ("Missing amount in balance line: {}", "Нет суммы в строке остатка: {}"),
+    ("Transaction line too short: {}", "Слишком короткая строка операции: {}"),
+    ("Invalid value date in: {}", "Неверная дата валютирования в: {}"),
+    ("Invalid entry date in: {}", "Неверная дата проводки в: {}"),
+    ("Missing D/C indicator in: {}", "Нет признака дебета/кредита в: {}"),
+    ("Missing amount in: {}", "Нет суммы в: {}"),
+    ("Invalid amount in: {}", "Неверная сумма в: {}"),
+    ("Invalid format in: {}", "Неверный формат в: {}"),
+    ("Invalid availability days: {}", "Неверное число дней доступности: {}"),
+    // Validation failures
+    ("closing balance {} does not match expected {} (difference {})", "исходящий остаток {} не совпадает с ожидаемым {} (разница {})"),
+    (
+        "Closing balance {} of statement {} doesn't match opening balance {} of statement {}",
+        "Исходящий остаток {} выписки {} не совпадает с входящим остатком {} выписки {}",
+    ),
+    ("Transactions summary does not match entries: {}", "Итоги выписки не совпадают с проводками: {}"),
+    ("Account {} is longer than the {} characters MT940 allows", "Счёт {} длиннее {} символов, допустимых в MT940"),
+    ("Transaction {}: {} is too long for MT940", "Операция {}: поле {} длиннее, чем допускает MT940"),
+    ("{} is too long for MT940", "Поле {} длиннее, чем допускает MT940"),
+    // Warnings
+    ("unknown tag {} ignored", "неизвестный тег {} пропущен"),
+    ("unknown field {} ignored", "неизвестное поле {} пропущено"),
+    ("unknown column \"{}\" ignored", "неизвестная колонка \"{}\" пропущена"),
+    ("no reference in :61:, using {}", "нет референса в :61:, используется {}"),
+    ("no currency column, using {}", "нет колонки валюты, используется {}"),
+    ("no currency, using {}", "нет валюты, используется {}"),
+    ("row without an amount skipped", "строка без суммы пропущена"),
+    ("no account, using UNKNOWN", "нет счёта, используется UNKNOWN"),
+    ("no transactions, using currency {}", "нет операций, используется валюта {}"),
+    ("no opening balance :60F:, using currency {}", "нет входящего остатка :60F:, используется валюта {}"),
+    (
+        "statement line before opening balance :60F:, currency taken from the balances",
+        "строка выписки :61: до входящего остатка :60F:, валюта взята из остатков",
+    ),
+    ("no statement ID, using {} from the current time", "нет идентификатора выписки, используется {} по текущему времени"),
+    ("no account identification, using UNKNOWN", "нет идентификатора счёта, используется UNKNOWN"),
+    ("unreadable {} {} ignored", "нечитаемое значение {} {} пропущено"),
+    ("unknown balance type {} read as intermediate", "неизвестный тип остатка {} прочитан как промежуточный"),
+    ("balance {} has no currency, using {}", "у остатка {} нет валюты, используется {}"),
+    ("entry {} has no NtryRef, using UNKNOWN", "у проводки {} нет NtryRef, используется UNKNOWN"),
+    ("entry {} has no currency, using {}", "у проводки {} нет валюты, используется {}"),
+    ("entry {} has no booking date, using today", "у проводки {} нет даты проводки, используется сегодняшняя"),
+    ("entry {} has unknown status {}, read as INFO", "у проводки {} неизвестный статус {}, прочитан как INFO"),
+    // Locations
+    ("line {}: {}", "строка {}: {}"),
+    ("line {}", "строка {}"),
+    ("column {} \"{}\"", "колонка {} \"{}\""),
+    ("column \"{}\"", "колонка \"{}\""),
+    ("column {}", "колонка {}"),
+    ("byte {}", "байт {}"),
+    ("element {}", "элемент {}"),
+    ("at \"{}\"", "фрагмент \"{}\""),
+    // Command line tools
+    ("Error [{}]: {}", "Ошибка [{}]: {}"),
+    ("Warning: {}", "Предупреждение: {}"),
+    ("Skipped: {}", "Пропущено: {}"),
+    ("Files converted: {}", "Сконвертировано файлов: {}"),
+    ("{}: valid {}", "{}: корректный {}"),
+    ("{}: invalid {}, errors: {}", "{}: некорректный {}, ошибок: {}"),
+    ("{} of {} files failed", "не удалось сконвертировать файлов: {} из {}"),
+    ("No files match {}", "Нет файлов по шаблону {}"),
+    ("{} and {} would be converted to the same file", "{} и {} были бы записаны в один и тот же файл"),
+    ("Several inputs need --output-dir or --merge", "Для нескольких входных файлов нужен --output-dir или --merge"),
+];
+
+/// `text`, an English message, in `language`.
+///
+/// # Examples
+///
+/// ```
+/// use ypbank_system::messages::{translate, Language};
+///
+/// let text = "MT940 parsing error at line 4: Invalid date format: 2024-13-2";
+/// assert_eq!(translate(text, Language::Russian), "Ошибка разбора MT940 в строке 4: Неверный формат даты: 2024-13-2");
+/// assert_eq!(translate("Something else", Language::Russian), "Something else");
+/// ```
+pub fn translate(text: &str, language: Language) -> String {
+    if language == Language::English {
+        return text.to_string();
+    }
+    CATALOG.iter()
+        .find_map(|&(english, translation)| {
+            let (literals, nested) = parse(english);
+            let args = captures(&literals, text)?;
+            let args: Vec<String> = args.into_iter()
+                .zip(nested)
+                .map(|(arg, nested)| if nested { translate(arg, language) } else { arg.to_string() })
+                .collect();
+            Some(fill(translation, &args))
+        })
+        .unwrap_or_else(|| text.to_string())
+}
+
+/// Catalog template `template` in `language`, filled in with `args` as is.
+pub fn render(template: &str, args: &[&str], language: Language) -> String {
+    let template = match language {
+        Language::English => template,
+        Language::Russian => CATALOG.iter()
+            .find(|(english, _)| *english == template)
+            .map_or(template, |(_, translation)| translation),
+    };
+    let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+    fill(template, &args)
+}
+
+/// Literal parts of `template` around its placeholders, and whether each
+/// placeholder holds a nested message.
+fn parse(template: &str) -> (Vec<&str>, Vec<bool>) {
+    let mut literals = Vec::new();
+    let mut nested = Vec::new();
+    let mut rest = template;
+    while let Some((i, placeholder)) = ["{}", "{msg}"].iter().filter_map(|p| rest.find(p).map(|i| (i, *p))).min() {
+        literals.push(&rest[..i]);
+        nested.push(placeholder == "{msg}");
+        rest = &rest[i + placeholder.len()..];
+    }
+    literals.push(rest);
+    (literals, nested)
+}
+
+/// Values of the placeholders when `text` matches the template made of
+/// `literals`; the last one takes the rest of the text.
+fn captures<'a>(literals: &[&str], text: &'a str) -> Option<Vec<&'a str>> {
+    let mut rest = text.strip_prefix(literals[0])?;
+    let mut args = Vec::new();
+    for (i, literal) in literals.iter().enumerate().skip(1) {
+        let end = if i + 1 == literals.len() {
+            rest.strip_suffix(literal)?.len()
+        } else {
+            rest.find(literal)?
+        };
+        args.push(&rest[..end]);
+        rest = &rest[end + literal.len()..];
+    }
+    rest.is_empty().then_some(args)
+}
+
+/// `template` with its placeholders replaced by `args` in order.
+fn fill(template: &str, args: &[String]) -> String {
+    let (literals, _) = parse(template);
+    let mut text = literals[0].to_string();
+    for (literal, arg) in literals[1..].iter().zip(args) {
+        text.push_str(arg);
+        text.push_str(literal);
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_translate() {
+        let ru = Language::Russian;
+        assert_eq!(translate("Missing required field: amount", ru), "Отсутствует обязательное поле: сумма");
+        assert_eq!(translate("Invalid amount format: amount", ru), "Неверный формат суммы: amount");
+        assert_eq!(
+            translate("CSV error at row 3, column \"amount\": Invalid D/C indicator: X", ru),
+            "Ошибка CSV в строке 3, колонка \"amount\": Неверный признак дебета/кредита: X"
+        );
+        assert_eq!(translate("unreadable CreDtTm 2024-13-01 ignored", ru), "нечитаемое значение CreDtTm 2024-13-01 пропущено");
+        assert_eq!(translate("Missing required field: amount", Language::English), "Missing required field: amount");
+        assert_eq!(render("Warning: {}", &["line 1"], ru), "Предупреждение: line 1");
+    }
+
+    #[test]
+    fn test_language_from_str() {
+        assert_eq!("RU".parse::<Language>().unwrap(), Language::Russian);
+        assert_eq!("english".parse::<Language>().unwrap(), Language::English);
+        assert!("de".parse::<Language>().is_err());
+    }
+}
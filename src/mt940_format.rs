@@ -3,12 +3,31 @@
 //! MT940 is a SWIFT format for electronic account statements.
 //! This module provides parsing and writing capabilities for MT940 format.
 
-use crate::error::{Error, Result};
-use crate::types::{Balance, BalanceType, DebitCredit, Statement, Transaction};
-use chrono::{Datelike, NaiveDate};
+use crate::error::{Error, ErrorCode, Location, ParseOutcome, RejectedRecord, Result, Warning, WarningKind};
+use crate::format::{EntryReader, StatementFormat};
+use crate::types::{
+    parse_decimal, Balance, BalanceType, BankDate, BankIdentifier, BankInfo, Currency, DebitCredit, EntryStatus, SourceFormat, Statement,
+    Transaction,
+};
+use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta};
 use rust_decimal::Decimal;
-use std::io::{BufRead, Write};
-use std::str::FromStr;
+use std::fmt::{self, Write as _};
+use std::io::{BufRead, BufReader, Read, Write};
+
+/// Logical terminal address written when the servicing bank is unknown.
+const PLACEHOLDER_TERMINAL: &str = "BANKXXXXAXXX";
+
+/// Maximum length of the `:20:` statement reference and `:61:` references.
+pub const REFERENCE_LENGTH: usize = 16;
+
+/// Maximum length of the `:25:` account identification.
+pub const ACCOUNT_LENGTH: usize = 35;
+
+/// Maximum length of a line of `:86:` text.
+pub const LINE_LENGTH: usize = 65;
+
+/// Maximum number of lines of `:86:` text.
+pub const NARRATIVE_LINES: usize = 6;
 
 /// Represents an MT940 statement.
 #[derive(Debug, Clone, PartialEq)]
@@ -17,6 +36,70 @@ pub struct Mt940Statement {
     pub statement: Statement,
 }
 
+/// Options for reading MT940 statements.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Mt940ReadOptions {
+    /// Leave out `:61:` statement lines that fail to parse, listing them in
+    /// [`ParseOutcome::rejected`], instead of failing on the first one.
+    pub skip_invalid: bool,
+}
+
+/// MT940 as a [`StatementFormat`].
+///
+/// Statements with transactions in several currencies are written as one
+/// message per currency, see [`Statement::by_currency`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Mt940Format {
+    /// Options used when reading.
+    pub read_options: Mt940ReadOptions,
+}
+
+impl StatementFormat for Mt940Format {
+    fn read(&self, mut reader: &mut dyn Read) -> Result<Statement> {
+        Ok(Mt940Statement::from_read_with(&mut reader, &self.read_options)?.statement)
+    }
+
+    fn read_with_diagnostics(&self, mut reader: &mut dyn Read) -> Result<ParseOutcome<Statement>> {
+        Ok(Mt940Statement::from_read_with_diagnostics(&mut reader, &self.read_options)?.map(|parsed| parsed.statement))
+    }
+
+    fn write(&self, statement: &Statement, mut writer: &mut dyn Write) -> Result<()> {
+        for statement in statement.by_currency() {
+            Mt940Statement { statement }.write_to(&mut writer)?;
+        }
+        Ok(())
+    }
+
+    fn entries<'a>(&self, reader: Box<dyn Read + 'a>) -> Result<Box<dyn EntryReader + 'a>> {
+        Ok(Box::new(Mt940EntryReader::with_options(BufReader::new(reader), self.read_options)))
+    }
+}
+
+#[cfg(feature = "async")]
+impl Mt940Statement {
+    /// Parse an MT940 statement from an async source, such as a socket.
+    ///
+    /// [`Mt940Statement::from_read`] runs on tokio's blocking pool and reads the
+    /// input as it arrives, without holding all of it or blocking the
+    /// executor.
+    pub async fn from_async_read<R>(reader: R) -> Result<Self>
+    where
+        R: tokio::io::AsyncRead + Unpin + Send + 'static,
+    {
+        crate::format::read_async(reader, |mut input| Self::from_read(&mut input)).await
+    }
+
+    /// Write the statement to an async destination, like
+    /// [`Mt940Statement::write_to`] run on tokio's blocking pool; returns the
+    /// destination.
+    pub async fn write_to_async<W>(self, writer: W) -> Result<W>
+    where
+        W: tokio::io::AsyncWrite + Unpin + Send + 'static,
+    {
+        crate::format::write_async(writer, move |mut output| self.write_to(&mut output)).await
+    }
+}
+
 impl Mt940Statement {
     /// Parse an MT940 statement from any source implementing `Read`.
     ///
@@ -34,13 +117,44 @@ impl Mt940Statement {
     /// let statement = Mt940Statement::from_read(&mut file)?;
     /// # Ok::<(), Box<dyn std::error::Error>>(())
     /// ```
-    pub fn from_read<R: std::io::Read>(reader: &mut R) -> Result<Self> {
-        let buf_reader = std::io::BufReader::new(reader);
-        Self::parse_mt940(buf_reader)
+    pub fn from_read<R: Read>(reader: &mut R) -> Result<Self> {
+        Self::from_read_with(reader, &Mt940ReadOptions::default())
+    }
+
+    /// Parse an MT940 statement with explicit read options.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ypbank_system::mt940_format::{Mt940ReadOptions, Mt940Statement};
+    ///
+    /// let input = ":20:STMT\n:25:ACC001\n:60F:C240101EUR100,00\n\
+    ///              :61:2413020102D10,00NTRF//REF1\n:86:Corrupt\n\
+    ///              :61:2401020102D20,00NTRF//REF2\n:62F:C240102EUR80,00\n";
+    /// assert!(Mt940Statement::from_read(&mut input.as_bytes()).is_err());
+    ///
+    /// let options = Mt940ReadOptions { skip_invalid: true };
+    /// let outcome = Mt940Statement::from_read_with_diagnostics(&mut input.as_bytes(), &options)?;
+    /// assert_eq!(outcome.statement.statement.transactions.len(), 1);
+    /// assert_eq!(outcome.rejected[0].line, 4);
+    /// # Ok::<(), ypbank_system::Error>(())
+    /// ```
+    pub fn from_read_with<R: Read>(reader: &mut R, options: &Mt940ReadOptions) -> Result<Self> {
+        Ok(Self::from_read_with_diagnostics(reader, options)?.statement)
+    }
+
+    /// Parse like [`Mt940Statement::from_read_with`], also returning
+    /// warnings for the unknown tags that were ignored and the references
+    /// that had to be generated.
+    pub fn from_read_with_diagnostics<R: Read>(reader: &mut R, options: &Mt940ReadOptions) -> Result<ParseOutcome<Self>> {
+        Self::parse_mt940(BufReader::new(reader), options)
     }
 
     /// Write an MT940 statement to any destination implementing `Write`.
     ///
+    /// Every transaction must be in the statement currency; split
+    /// multi-currency statements with [`Statement::by_currency`] first.
+    ///
     /// # Arguments
     ///
     /// * `writer` - A mutable reference to a type implementing `Write`
@@ -50,9 +164,9 @@ impl Mt940Statement {
     /// ```no_run
     /// use std::fs::File;
     /// use ypbank_system::mt940_format::Mt940Statement;
-    /// use ypbank_system::types::Statement;
+    /// use ypbank_system::types::{Currency, Statement};
     ///
-    /// let statement = Statement::new("123".into(), "ACC001".into(), "USD".into());
+    /// let statement = Statement::new("123".into(), "ACC001", Currency::USD);
     /// let mt940 = Mt940Statement { statement };
     /// let mut file = File::create("output.mt940")?;
     /// mt940.write_to(&mut file)?;
@@ -62,100 +176,26 @@ impl Mt940Statement {
         self.serialize_mt940(writer)
     }
 
-    fn parse_mt940<R: BufRead>(reader: R) -> Result<Self> {
-        let mut lines: Vec<String> = Vec::new();
-
-        for line in reader.lines() {
-            let line = line?;
-            lines.push(line);
-        }
-
-        let mut statement_id = String::new();
-        let mut account = String::new();
-        let mut sequence_number = None;
-        let mut currency = String::new();
-        let mut opening_balance = None;
-        let mut closing_balance = None;
-        let mut transactions = Vec::new();
-
-        let mut current_line = 0;
-        let mut current_transaction: Option<Transaction> = None;
-        let mut transaction_description = String::new();
-
-        while current_line < lines.len() {
-            let line = &lines[current_line];
-
-            if line.starts_with(":20:") {
-                // Transaction Reference Number
-                statement_id = line.get(4..).unwrap_or("").trim().to_string();
-            } else if line.starts_with(":25:") {
-                // Account Identification
-                account = line.get(4..).unwrap_or("").trim().to_string();
-            } else if line.starts_with(":28C:") {
-                // Statement Number/Sequence Number
-                sequence_number = Some(line.get(5..).unwrap_or("").trim().to_string());
-            } else if line.starts_with(":60") {
-                // Opening Balance
-                opening_balance = Some(Self::parse_balance(line, BalanceType::Opening)?);
-                if currency.is_empty() {
-                    if let Some(ref bal) = opening_balance {
-                        currency = bal.currency.clone();
-                    }
-                }
-            } else if line.starts_with(":61:") {
-                // Save previous transaction if exists
-                if let Some(mut trans) = current_transaction.take() {
-                    trans.description = transaction_description.trim().to_string();
-                    transactions.push(trans);
-                    transaction_description.clear();
-                }
-
-                // Statement Line (Transaction)
-                current_transaction = Some(Self::parse_transaction_line(line, &currency)?);
-            } else if line.starts_with(":86:") {
-                // Information to Account Owner
-                transaction_description = line.get(4..).unwrap_or("").trim().to_string();
-
-                // Check for continuation lines
-                let mut next_line = current_line + 1;
-                while next_line < lines.len() {
-                    let next = &lines[next_line];
-                    if next.starts_with(':') {
-                        break;
-                    }
-                    transaction_description.push(' ');
-                    transaction_description.push_str(next.trim());
-                    current_line = next_line;
-                    next_line += 1;
-                }
-            } else if line.starts_with(":62") {
-                // Closing Balance
-                closing_balance = Some(Self::parse_balance(line, BalanceType::Closing)?);
-            }
-
-            current_line += 1;
-        }
-
-        // Don't forget the last transaction
-        if let Some(mut trans) = current_transaction.take() {
-            trans.description = transaction_description.trim().to_string();
-            transactions.push(trans);
-        }
-
-        if statement_id.is_empty() {
-            return Err(Error::MissingField("statement reference :20:".to_string()));
-        }
-        if account.is_empty() {
-            return Err(Error::MissingField("account identification :25:".to_string()));
-        }
+    fn parse_mt940<R: BufRead>(reader: R, options: &Mt940ReadOptions) -> Result<ParseOutcome<Self>> {
+        Mt940EntryReader::with_options(reader, *options).collect_outcome()
+    }
 
-        let mut statement = Statement::new(statement_id, account, currency);
-        statement.sequence_number = sequence_number;
-        statement.opening_balance = opening_balance;
-        statement.closing_balance = closing_balance;
-        statement.transactions = transactions;
+    /// Servicing bank from the headers: the sender in the input reference of
+    /// an output message `{2:O940...}`, else the logical terminal of `{1:...}`.
+    fn parse_header_bank(line: &str) -> Option<BankInfo> {
+        let block = |tag: &str| {
+            line.split_once(tag).map(|(_, rest)| rest.split('}').next().unwrap_or(""))
+        };
+        let terminal = block("{2:")
+            .filter(|application| application.starts_with('O'))
+            .and_then(|application| application.get(14..26))
+            .or_else(|| block("{1:").and_then(|basic| basic.get(3..15)))
+            .filter(|terminal| *terminal != PLACEHOLDER_TERMINAL)?;
 
-        Ok(Mt940Statement { statement })
+        // BIC8, logical terminal code, then the branch code
+        let (bic8, branch) = (terminal.get(..8)?, terminal.get(9..12)?);
+        let bic = if branch == "XXX" { bic8.to_string() } else { format!("{}{}", bic8, branch) };
+        Some(BankInfo::from_identifier(BankIdentifier::Bic(bic.parse().ok()?)))
     }
 
     fn parse_balance(line: &str, balance_type: BalanceType) -> Result<Balance> {
@@ -166,34 +206,31 @@ impl Mt940Statement {
         // Position 11+: Amount
 
         let content = if line.starts_with(":60") {
-            line.get(5..).ok_or_else(|| Error::ParseError(format!("Invalid balance line: {}", line)))?
+            line.get(5..).ok_or_else(|| Error::ParseError(format!("Invalid balance line: {}", line), None))?
         } else if line.starts_with(":62") {
-            line.get(5..).ok_or_else(|| Error::ParseError(format!("Invalid balance line: {}", line)))?
+            line.get(5..).ok_or_else(|| Error::ParseError(format!("Invalid balance line: {}", line), None))?
         } else {
-            return Err(Error::ParseError(format!("Invalid balance line: {}", line)));
+            return Err(Error::ParseError(format!("Invalid balance line: {}", line), None));
         };
 
         if content.len() < 11 {
-            return Err(Error::ParseError(format!("Balance line too short: {}", line)));
+            return Err(Error::ParseError(format!("Balance line too short: {}", line), None));
         }
 
-        let dc = content.get(0..1).unwrap_or("")
-            .parse::<DebitCredit>()
-            .map_err(|_| Error::ParseError(format!("Invalid D/C indicator in: {}", line)))?;
+        let dc = content.chars().next()
+            .and_then(parse_mark)
+            .ok_or_else(|| Error::ParseError(format!("Invalid D/C indicator in: {}", line), None))?;
 
         let date_str = content.get(1..7)
-            .ok_or_else(|| Error::ParseError(format!("Invalid date in balance line: {}", line)))?;
+            .ok_or_else(|| Error::ParseError(format!("Invalid date in balance line: {}", line), None))?;
         let date = parse_mt940_date(date_str)?;
 
         let currency = content.get(7..10)
-            .ok_or_else(|| Error::ParseError(format!("Invalid currency in balance line: {}", line)))?
-            .to_string();
+            .ok_or_else(|| Error::ParseError(format!("Invalid currency in balance line: {}", line), None))?;
+        let currency = currency.parse().map_err(|_| Error::InvalidCurrency(currency.to_string(), None))?;
 
-        let amount_str = content.get(10..)
-            .ok_or_else(|| Error::ParseError(format!("Missing amount in balance line: {}", line)))?
-            .replace(',', ".");
-        let amount = Decimal::from_str(&amount_str)
-            .map_err(|_| Error::InvalidAmount(amount_str.to_string()))?;
+        let amount = parse_mt940_amount(content.get(10..)
+            .ok_or_else(|| Error::ParseError(format!("Missing amount in balance line: {}", line), None))?)?;
 
         Ok(Balance {
             balance_type,
@@ -204,7 +241,9 @@ impl Mt940Statement {
         })
     }
 
-    fn parse_transaction_line(line: &str, default_currency: &str) -> Result<Transaction> {
+    /// Transaction of a `:61:` line, and whether the line has a reference
+    /// of its own rather than the one made up from the date and amount.
+    fn parse_transaction_line(line: &str, currency: Currency) -> Result<(Transaction, bool)> {
         // Format: :61:2502180218D12,01NTRFGSLNVSHSUTKWDR//GI2504900007841
         // Position 1-6: Value date (YYMMDD)
         // Position 7-10: Entry date (MMDD) - optional
@@ -214,139 +253,637 @@ impl Mt940Statement {
         // Then reference
 
         let content = line.get(4..)
-            .ok_or_else(|| Error::ParseError(format!("Transaction line too short: {}", line)))?;
+            .ok_or_else(|| Error::ParseError(format!("Transaction line too short: {}", line), None))?;
 
         if content.len() < 6 {
-            return Err(Error::ParseError(format!("Transaction line too short: {}", line)));
+            return Err(Error::ParseError(format!("Transaction line too short: {}", line), None));
         }
 
         let value_date_str = content.get(0..6)
-            .ok_or_else(|| Error::ParseError(format!("Invalid value date in: {}", line)))?;
+            .ok_or_else(|| Error::ParseError(format!("Invalid value date in: {}", line), None))?;
         let value_date = parse_mt940_date(value_date_str)?;
 
         // Try to parse entry date (may not always be present)
         let mut pos = 6;
-        let date = if content.len() > pos + 4 && content.chars().nth(pos + 2).unwrap_or('X').is_ascii_digit() {
+        let date = if content.len() > pos + 4
+            && content.get(pos..pos + 4).is_some_and(|d| d.bytes().all(|b| b.is_ascii_digit()))
+        {
             let entry_date_str = content.get(pos..pos + 4)
-                .ok_or_else(|| Error::ParseError(format!("Invalid entry date in: {}", line)))?;
+                .ok_or_else(|| Error::ParseError(format!("Invalid entry date in: {}", line), None))?;
             pos += 4;
             parse_mt940_entry_date(entry_date_str, value_date.year())?
         } else {
             value_date
         };
 
-        // D/C indicator
-        let dc_char = content.chars().nth(pos).ok_or_else(|| {
-            Error::Mt940ParseError {
-                line: 0,
-                message: "Missing D/C indicator".to_string(),
-            }
-        })?;
-        let debit_credit = dc_char.to_string()
-            .parse::<DebitCredit>()
-            .map_err(|_| Error::ParseError(format!("Invalid D/C: {}", dc_char)))?;
+        // D/C indicator; RC (reversal of credit) and RD (reversal of debit)
+        // move the balance the opposite way of the reversed entry
+        let missing_mark = || Error::ParseError(format!("Missing D/C indicator in: {}", line), None);
+        let mut dc_char = content.chars().nth(pos).ok_or_else(missing_mark)?;
+        let reversal = dc_char == 'R';
+        if reversal {
+            pos += 1;
+            dc_char = content.chars().nth(pos).ok_or_else(missing_mark)?;
+        }
+        let mut debit_credit = parse_mark(dc_char)
+            .ok_or_else(|| Error::ParseError(format!("Invalid D/C: {}", dc_char), None))?;
+        if reversal {
+            debit_credit = match debit_credit {
+                DebitCredit::Credit => DebitCredit::Debit,
+                DebitCredit::Debit => DebitCredit::Credit,
+            };
+        }
         pos += 1;
 
         // Parse amount
         let rest_of_line = content.get(pos..)
-            .ok_or_else(|| Error::ParseError(format!("Missing amount in: {}", line)))?;
+            .ok_or_else(|| Error::ParseError(format!("Missing amount in: {}", line), None))?;
+        // The transaction type that follows starts with N, F or S
         let amount_end = rest_of_line
-            .find(|c: char| c.is_alphabetic())
+            .find(['N', 'F', 'S'])
             .unwrap_or(rest_of_line.len());
 
-        let amount_str = rest_of_line.get(0..amount_end)
-            .ok_or_else(|| Error::ParseError(format!("Invalid amount in: {}", line)))?
-            .replace(',', ".");
-        let amount = Decimal::from_str(&amount_str)
-            .map_err(|_| Error::InvalidAmount(amount_str.to_string()))?;
+        let amount = parse_mt940_amount(rest_of_line.get(0..amount_end)
+            .ok_or_else(|| Error::ParseError(format!("Invalid amount in: {}", line), None))?)?;
 
         // Extract reference from the rest
         let rest = rest_of_line.get(amount_end..)
-            .ok_or_else(|| Error::ParseError(format!("Invalid format in: {}", line)))?;
+            .ok_or_else(|| Error::ParseError(format!("Invalid format in: {}", line), None))?;
         let reference = rest
             .split("//")
             .last()
             .unwrap_or(rest)
-            .trim()
-            .to_string();
+            .trim();
+        let has_reference = !reference.is_empty();
 
-        Ok(Transaction {
-            reference: if reference.is_empty() {
-                format!("{}-{}", date, amount)
+        Ok((Transaction {
+            reference: if has_reference {
+                reference.to_string()
             } else {
-                reference
+                format!("{}-{}", date, amount)
             },
-            date,
-            value_date: Some(value_date),
+            date: date.into(),
+            value_date: Some(value_date.into()),
             amount,
-            currency: default_currency.to_string(),
+            currency,
             debit_credit,
-            account: None,
-            counterparty_account: None,
-            counterparty_name: None,
-            bank_identifier: None,
-            description: String::new(),
-            additional_info: None,
-        })
+            status: if reversal { EntryStatus::Reversed } else { EntryStatus::Booked },
+            ..Transaction::default()
+        }, has_reference))
     }
 
     fn serialize_mt940<W: Write>(&self, writer: &mut W) -> Result<()> {
         let stmt = &self.statement;
 
-        // Header (simplified)
-        writeln!(writer, "{{1:F01BANKXXXXAXXX0000000000}}{{2:I940BANKXXXXAXXXXN}}{{4:")?;
+        // :61: lines have no currency of their own; see Statement::by_currency.
+        // Checked up front so that nothing is written for a mixed statement
+        if let Some(tx) = stmt.transactions.iter().find(|tx| tx.currency != stmt.currency) {
+            return Err(currency_mismatch(tx, &stmt.statement_id, stmt.currency));
+        }
+
+        let mut mt940 = Mt940Writer::new(writer);
+        mt940.write_header(stmt)?;
+        for transaction in &stmt.transactions {
+            mt940.write_transaction(transaction)?;
+        }
+        mt940.finish(stmt.closing_balance.as_ref())?;
+        Ok(())
+    }
+}
+
+/// Incremental MT940 writer: the statement header, then the transactions
+/// one at a time, then the closing balance.
+///
+/// Unlike [`Mt940Statement::write_to`], the statement never has to be held
+/// in memory as a whole, so a converted statement can be written while its
+/// input is still being read.
+///
+/// # Examples
+///
+/// ```
+/// use ypbank_system::mt940_format::{Mt940EntryReader, Mt940Writer};
+/// use ypbank_system::types::{Currency, Statement};
+///
+/// let input = ":20:STMT\n:25:ACC001\n:60F:C240101EUR100,00\n:61:2401020102D10,00NTRF//REF1\n:62F:C240102EUR90,00\n-}";
+/// let header = Statement::new("STMT".into(), "ACC001", Currency::EUR);
+/// let mut writer = Mt940Writer::new(Vec::new());
+/// writer.write_header(&header)?;
+/// let mut reader = Mt940EntryReader::new(input.as_bytes());
+/// for transaction in &mut reader {
+///     writer.write_transaction(&transaction?)?;
+/// }
+/// let statement = reader.collect_statement()?.statement;
+/// let output = String::from_utf8(writer.finish(statement.closing_balance.as_ref())?).unwrap();
+/// assert!(output.contains(":61:2401020102D10,00NTRF//REF1\n:62F:C240102EUR90,00\n-}"));
+/// # Ok::<(), ypbank_system::Error>(())
+/// ```
+pub struct Mt940Writer<W: Write> {
+    writer: W,
+    statement_id: String,
+    /// Statement currency, known once the header has been written.
+    currency: Option<Currency>,
+}
+
+impl<W: Write> Mt940Writer<W> {
+    /// Create a writer to `writer`.
+    pub fn new(writer: W) -> Self {
+        Mt940Writer { writer, statement_id: String::new(), currency: None }
+    }
+
+    /// Write everything up to the statement lines: the message header,
+    /// `:20:`, `:25:`, `:28C:` and the opening balance. The transactions of
+    /// `header` are not written, and its closing balance goes to
+    /// [`Mt940Writer::finish`].
+    pub fn write_header(&mut self, header: &Statement) -> Result<()> {
+        self.statement_id = header.statement_id.clone();
+        self.currency = Some(header.currency);
+
+        // Header (simplified), sent from the servicing bank's terminal when known
+        let terminal = header.servicing_bank.as_ref()
+            .and_then(|bank| bank.bic.as_ref())
+            .map_or_else(
+                || PLACEHOLDER_TERMINAL.to_string(),
+                |bic| format!("{}A{}", &bic.as_str()[..8], bic.branch_code().unwrap_or("XXX")),
+            );
+        writeln!(self.writer, "{{1:F01{}0000000000}}{{2:I940BANKXXXXAXXXXN}}{{4:", terminal)?;
 
         // :20: Transaction Reference Number
-        writeln!(writer, ":20:{}", stmt.statement_id)?;
+        writeln!(self.writer, ":20:{}", header.statement_id)?;
 
         // :25: Account Identification
-        writeln!(writer, ":25:{}", stmt.account)?;
+        writeln!(self.writer, ":25:{}", header.account)?;
 
-        // :28C: Statement Number
-        if let Some(ref seq) = stmt.sequence_number {
-            writeln!(writer, ":28C:{}", seq)?;
+        // :28C: Statement Number, and the page as sequence number of paginated statements
+        match (&header.sequence_number, header.pagination) {
+            (seq, Some(page)) => {
+                let number = seq.as_deref().and_then(|seq| seq.split('/').next()).unwrap_or("1");
+                writeln!(self.writer, ":28C:{}/{}", number, page.page_number)?;
+            }
+            (Some(seq), None) => writeln!(self.writer, ":28C:{}", seq)?,
+            (None, None) => {}
         }
 
         // :60: Opening Balance
-        if let Some(ref balance) = stmt.opening_balance {
-            write!(writer, ":60{}:", if balance.balance_type == BalanceType::Opening { "F" } else { "M" })?;
-            write!(writer, "{}", balance.debit_credit.to_string())?;
-            write!(writer, "{}", format_mt940_date(&balance.date))?;
-            write!(writer, "{}", balance.currency)?;
-            writeln!(writer, "{}", balance.amount.to_string().replace('.', ","))?;
+        if let Some(ref balance) = header.opening_balance {
+            write!(self.writer, ":60{}:", if balance.balance_type == BalanceType::Opening { "F" } else { "M" })?;
+            write!(self.writer, "{}", balance.debit_credit)?;
+            write!(self.writer, "{}", format_mt940_date(&balance.date))?;
+            write!(self.writer, "{}", balance.currency)?;
+            writeln!(self.writer, "{}", Mt940Amount(&balance.amount))?;
         }
+        Ok(())
+    }
 
-        // :61: Statement Lines (Transactions)
-        for transaction in &stmt.transactions {
-            write!(writer, ":61:")?;
-            if let Some(value_date) = transaction.value_date {
-                write!(writer, "{}", format_mt940_date(&value_date))?;
-            } else {
-                write!(writer, "{}", format_mt940_date(&transaction.date))?;
+    /// Write the `:61:` and `:86:` lines of a transaction, one set per
+    /// detail of a batch; it must be in the currency of the header.
+    pub fn write_transaction(&mut self, transaction: &Transaction) -> Result<()> {
+        let Some(currency) = self.currency else {
+            return Err(Error::ConversionError("MT940 header must be written before transactions".to_string(), None));
+        };
+        if transaction.currency != currency {
+            return Err(currency_mismatch(transaction, &self.statement_id, currency));
+        }
+
+        // :61: Statement Lines (Transactions), batches fanned out per detail
+        for transaction in transaction.expand_details() {
+            // Lines read from MT940 are kept as long as their fields are unchanged
+            if let Some(raw) = transaction.unchanged_source_raw(SourceFormat::Mt940) {
+                writeln!(self.writer, "{}", raw)?;
+                continue;
             }
+
+            write!(self.writer, ":61:")?;
+            let date = transaction.date.date();
+            write!(self.writer, "{}", format_mt940_date(&transaction.value_date.map_or(date, |value| value.date())))?;
             // Entry date (same as value date for simplicity)
-            write!(writer, "{:02}{:02}", transaction.date.month(), transaction.date.day())?;
-            write!(writer, "{}", transaction.debit_credit.to_string())?;
-            write!(writer, "{}", transaction.amount.to_string().replace('.', ","))?;
-            writeln!(writer, "NTRF//{}", transaction.reference)?;
+            write!(self.writer, "{:02}{:02}", date.month(), date.day())?;
+            if transaction.status == EntryStatus::Reversed {
+                // The mark names the reversed entry: a debit reverses a credit
+                write!(self.writer, "R{}", match transaction.debit_credit {
+                    DebitCredit::Debit => "C",
+                    DebitCredit::Credit => "D",
+                })?;
+            } else {
+                write!(self.writer, "{}", transaction.debit_credit)?;
+            }
+            write!(self.writer, "{}", Mt940Amount(&transaction.amount))?;
+            let transaction_type = transaction.transaction_type.as_deref().unwrap_or("NTRF");
+            writeln!(self.writer, "{}//{}", transaction_type, transaction.reference)?;
 
             // :86: Information to Account Owner
-            if !transaction.description.is_empty() {
-                writeln!(writer, ":86:{}", transaction.description)?;
+            for (i, line) in wrap_narrative(&transaction.description).iter().enumerate() {
+                writeln!(self.writer, "{}{}", if i == 0 { ":86:" } else { "" }, line)?;
             }
         }
+        Ok(())
+    }
 
+    /// Write the closing balance and the end of the message, returning the
+    /// underlying writer.
+    pub fn finish(mut self, closing_balance: Option<&Balance>) -> Result<W> {
         // :62: Closing Balance
-        if let Some(ref balance) = stmt.closing_balance {
-            write!(writer, ":62{}:", if balance.balance_type == BalanceType::Closing { "F" } else { "M" })?;
-            write!(writer, "{}", balance.debit_credit.to_string())?;
-            write!(writer, "{}", format_mt940_date(&balance.date))?;
-            write!(writer, "{}", balance.currency)?;
-            writeln!(writer, "{}", balance.amount.to_string().replace('.', ","))?;
+        if let Some(balance) = closing_balance {
+            write!(self.writer, ":62{}:", if balance.balance_type == BalanceType::Closing { "F" } else { "M" })?;
+            write!(self.writer, "{}", balance.debit_credit)?;
+            write!(self.writer, "{}", format_mt940_date(&balance.date))?;
+            write!(self.writer, "{}", balance.currency)?;
+            writeln!(self.writer, "{}", Mt940Amount(&balance.amount))?;
+        }
+
+        writeln!(self.writer, "-}}")?;
+        Ok(self.writer)
+    }
+}
+
+/// Streaming reader yielding the transactions of an MT940 statement one at
+/// a time.
+///
+/// Unlike [`Mt940Statement::from_read`], lines are read as they are needed
+/// and only the transaction being parsed is held in memory, so archives of
+/// any size can be processed; [`Mt940EntryReader::collect_statement`] reads
+/// the rest into a whole statement.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::fs::File;
+/// use std::io::BufReader;
+/// use ypbank_system::mt940_format::Mt940EntryReader;
+///
+/// let file = BufReader::new(File::open("statement.mt940")?);
+/// for transaction in Mt940EntryReader::new(file) {
+///     let transaction = transaction?;
+///     println!("{} {}", transaction.reference, transaction.amount);
+/// }
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub struct Mt940EntryReader<R: BufRead> {
+    reader: R,
+    options: Mt940ReadOptions,
+    /// Current line, the one buffer every line is read into.
+    line: String,
+    /// Whether `line` was read ahead and is still to be handled.
+    pending: bool,
+    /// Index of the current line and the byte offset it starts at.
+    index: usize,
+    line_offset: usize,
+    lines: usize,
+    offset: usize,
+    servicing_bank: Option<BankInfo>,
+    statement_id: String,
+    account: String,
+    sequence_number: Option<String>,
+    creation_date: Option<BankDate>,
+    currency: Option<Currency>,
+    /// Whether transactions were read in XXX before the currency was known.
+    currency_assumed: bool,
+    opening_balance: Option<Balance>,
+    closing_balance: Option<Balance>,
+    current: Option<Transaction>,
+    description: String,
+    // Original lines of the current transaction, while they continue
+    raw: String,
+    raw_open: bool,
+    warnings: Vec<Warning>,
+    rejected: Vec<RejectedRecord>,
+    finished: bool,
+}
+
+impl<R: BufRead> Mt940EntryReader<R> {
+    /// Create a streaming reader over an MT940 statement.
+    pub fn new(reader: R) -> Self {
+        Self::with_options(reader, Mt940ReadOptions::default())
+    }
+
+    /// Create a streaming reader with explicit read options; with
+    /// `skip_invalid`, unreadable `:61:` lines are left out instead of
+    /// ending the iteration.
+    pub fn with_options(reader: R, options: Mt940ReadOptions) -> Self {
+        Mt940EntryReader {
+            reader,
+            options,
+            line: String::new(),
+            pending: false,
+            index: 0,
+            line_offset: 0,
+            lines: 0,
+            offset: 0,
+            servicing_bank: None,
+            statement_id: String::new(),
+            account: String::new(),
+            sequence_number: None,
+            creation_date: None,
+            currency: None,
+            currency_assumed: false,
+            opening_balance: None,
+            closing_balance: None,
+            current: None,
+            description: String::new(),
+            raw: String::new(),
+            raw_open: false,
+            warnings: Vec::new(),
+            rejected: Vec::new(),
+            finished: false,
+        }
+    }
+
+    /// Statement currency, known once the opening balance has been read.
+    pub fn currency(&self) -> Option<Currency> {
+        self.currency
+    }
+
+    /// Read the remaining transactions into the whole statement.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ypbank_system::mt940_format::Mt940EntryReader;
+    ///
+    /// let input = ":20:STMT\n:25:ACC001\n:60F:C240101EUR100,00\n:61:2401020102D10,00NTRF//REF1\n:62F:C240102EUR90,00\n-}";
+    /// let mut reader = Mt940EntryReader::new(input.as_bytes());
+    /// assert_eq!(reader.next().unwrap()?.reference, "REF1");
+    /// assert!(reader.collect_statement()?.statement.transactions.is_empty());
+    /// # Ok::<(), ypbank_system::Error>(())
+    /// ```
+    pub fn collect_statement(self) -> Result<Mt940Statement> {
+        Ok(self.collect_outcome()?.statement)
+    }
+
+    /// Read the remaining transactions into the whole statement, with the
+    /// warnings and the rejected lines.
+    pub(crate) fn collect_outcome(mut self) -> Result<ParseOutcome<Mt940Statement>> {
+        let mut transactions = Vec::new();
+        while let Some(transaction) = self.next_transaction()? {
+            transactions.push(transaction);
         }
 
-        writeln!(writer, "-}}")?;
+        // Report every missing tag at once rather than one per run
+        let missing: Vec<&str> = [
+            (self.statement_id.is_empty(), "statement reference :20:"),
+            (self.account.is_empty(), "account identification :25:"),
+        ]
+        .into_iter()
+        .filter_map(|(absent, field)| absent.then_some(field))
+        .collect();
+        if !missing.is_empty() {
+            return Err(Error::missing_fields(&missing));
+        }
+
+        // Without an opening balance, the statement is in the closing balance's currency
+        let currency = self.currency
+            .or(self.closing_balance.as_ref().map(|balance| balance.currency))
+            .unwrap_or(Currency::XXX);
+        if self.opening_balance.is_none() {
+            self.warnings.push(Warning::new(WarningKind::AssumedDefault, None,
+                format!("no opening balance :60F:, using currency {}", currency)));
+        }
+        if self.currency_assumed {
+            for transaction in transactions.iter_mut().filter(|tx| tx.currency == Currency::XXX) {
+                transaction.currency = currency;
+            }
+        }
+
+        let mut statement = Statement::new(self.statement_id, self.account, currency);
+        statement.sequence_number = self.sequence_number;
+        statement.creation_date = self.creation_date;
+        statement.servicing_bank = self.servicing_bank;
+        statement.opening_balance = self.opening_balance;
+        statement.closing_balance = self.closing_balance;
+        statement.transactions = transactions;
 
+        Ok(ParseOutcome { statement: Mt940Statement { statement }, warnings: self.warnings, rejected: self.rejected })
+    }
+
+    /// Read the next line into `line` without its ending, unless it holds
+    /// one read ahead; false at the end of the input.
+    fn next_line(&mut self) -> Result<bool> {
+        if std::mem::take(&mut self.pending) {
+            return Ok(true);
+        }
+        self.line.clear();
+        let length = self.reader.read_line(&mut self.line)?;
+        if length == 0 {
+            return Ok(false);
+        }
+        (self.index, self.line_offset) = (self.lines, self.offset);
+        self.lines += 1;
+        self.offset += length;
+        if self.line.ends_with('\n') {
+            self.line.pop();
+        }
+        if self.line.ends_with('\r') {
+            self.line.pop();
+        }
+        Ok(true)
+    }
+
+    /// The transaction being read, completed with its `:86:` text.
+    fn take_current(&mut self) -> Option<Transaction> {
+        let mut transaction = self.current.take()?;
+        let description = &mut transaction.description;
+        *description = std::mem::take(&mut self.description);
+        description.truncate(description.trim_end().len());
+        description.drain(..description.len() - description.trim_start().len());
+        transaction.attach_source_raw(SourceFormat::Mt940, std::mem::take(&mut self.raw));
+        Some(transaction)
+    }
+
+    fn next_transaction(&mut self) -> Result<Option<Transaction>> {
+        while self.next_line()? {
+            let (index, offset, line) = (self.index, self.line_offset, self.line.as_str());
+            let located = |code: ErrorCode, message: String| {
+                let location = Location { line: Some(index + 1), offset: Some(offset), ..Location::in_text(line, 0) };
+                Error::Mt940ParseError { line: index + 1, code, message, location: Some(Box::new(location)) }
+            };
+            if line.starts_with(':') || line.starts_with('{') || line.starts_with('-') {
+                self.raw_open = line.starts_with(":86:") && self.raw_open;
+            }
+
+            if line.starts_with("{1:") {
+                // Basic and application headers
+                if self.servicing_bank.is_none() {
+                    self.servicing_bank = Mt940Statement::parse_header_bank(line);
+                }
+            } else if line.starts_with(":20:") {
+                // Transaction Reference Number
+                self.statement_id = line.get(4..).unwrap_or("").trim().to_string();
+            } else if line.starts_with(":25:") {
+                // Account Identification
+                self.account = line.get(4..).unwrap_or("").trim().to_string();
+            } else if line.starts_with(":28C:") {
+                // Statement Number/Sequence Number
+                self.sequence_number = Some(line.get(5..).unwrap_or("").trim().to_string());
+            } else if line.starts_with(":13D:") {
+                // Date/Time Indication, kept in UTC
+                let text = line.get(5..).unwrap_or("").trim();
+                match parse_mt940_datetime(text) {
+                    Ok(datetime) => self.creation_date = Some(datetime.into()),
+                    Err(e) => self.warnings.push(Warning::new(WarningKind::IgnoredValue, Some(index + 1),
+                        format!("invalid :13D: {}: {}", text, e))),
+                }
+            } else if line.starts_with(":60") {
+                // Opening Balance
+                let balance = Mt940Statement::parse_balance(line, BalanceType::Opening)
+                    .map_err(|e| located(ErrorCode::Mt940BadBalance, e.to_string()))?;
+                self.currency = self.currency.or(Some(balance.currency));
+                self.opening_balance = Some(balance);
+            } else if line.starts_with(":61:") {
+                // The previous transaction ends here; handle this line again next time
+                if self.current.is_some() {
+                    self.pending = true;
+                    return Ok(self.take_current());
+                }
+                // A rejected line's :86: must not go to the next transaction
+                self.description.clear();
+                self.raw.clear();
+                self.raw.push_str(line);
+                self.raw_open = true;
+
+                // Statement Line (Transaction), in the currency of the opening balance;
+                // before it in XXX, until the whole statement is collected
+                let currency = match self.currency {
+                    Some(currency) => currency,
+                    None => {
+                        if !std::mem::replace(&mut self.currency_assumed, true) {
+                            self.warnings.push(Warning::new(WarningKind::AssumedDefault, Some(index + 1),
+                                "statement line before opening balance :60F:, currency taken from the balances".to_string()));
+                        }
+                        Currency::XXX
+                    }
+                };
+                let (transaction, has_reference) = match Mt940Statement::parse_transaction_line(line, currency) {
+                    Ok(parsed) => parsed,
+                    Err(error) if self.options.skip_invalid => {
+                        self.rejected.push(RejectedRecord { line: index + 1, text: line.to_string(), error });
+                        continue;
+                    }
+                    Err(e) => return Err(located(ErrorCode::Mt940BadStatementLine, e.to_string())),
+                };
+                if !has_reference {
+                    self.warnings.push(Warning::new(WarningKind::AssumedDefault, Some(index + 1),
+                        format!("no reference in :61:, using {}", transaction.reference)));
+                }
+                self.current = Some(transaction);
+            } else if line.starts_with(":86:") {
+                // Information to Account Owner
+                self.description.clear();
+                self.description.push_str(line.get(4..).unwrap_or("").trim());
+                if self.raw_open {
+                    self.raw.push('\n');
+                    self.raw.push_str(line);
+                }
+
+                // Check for continuation lines
+                while self.next_line()? {
+                    let next = self.line.as_str();
+                    if next.starts_with(':') || next.starts_with("-}") {
+                        self.pending = true;
+                        break;
+                    }
+                    self.description.push(' ');
+                    self.description.push_str(next.trim());
+                    if self.raw_open {
+                        self.raw.push('\n');
+                        self.raw.push_str(next);
+                    }
+                }
+            } else if line.starts_with(":62") {
+                // Closing Balance
+                self.closing_balance = Some(Mt940Statement::parse_balance(line, BalanceType::Closing)
+                    .map_err(|e| located(ErrorCode::Mt940BadBalance, e.to_string()))?);
+            } else if line.starts_with(':') {
+                let tag = line.get(1..).and_then(|rest| rest.find(':')).map_or(line, |end| &line[..end + 2]);
+                self.warnings.push(Warning::new(WarningKind::UnknownField, Some(index + 1),
+                    format!("unknown tag {} ignored", tag)));
+            } else if self.raw_open && !line.trim().is_empty() {
+                // Supplementary details of the :61: line
+                self.raw.push('\n');
+                self.raw.push_str(line);
+            }
+        }
+        Ok(self.take_current())
+    }
+}
+
+impl<R: BufRead> Iterator for Mt940EntryReader<R> {
+    type Item = Result<Transaction>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        let next = self.next_transaction().transpose();
+        if !matches!(next, Some(Ok(_))) {
+            self.finished = true;
+        }
+        next
+    }
+}
+
+impl<R: BufRead> EntryReader for Mt940EntryReader<R> {
+    fn collect_statement(self: Box<Self>) -> Result<Statement> {
+        Ok(Mt940EntryReader::collect_statement(*self)?.statement)
+    }
+}
+
+/// Error for a transaction outside the currency of its MT940 statement.
+fn currency_mismatch(transaction: &Transaction, statement_id: &str, currency: Currency) -> Error {
+    Error::ConversionError(format!(
+        "Transaction {} is in {}, but MT940 statement {} is in {}",
+        transaction.reference, transaction.currency, statement_id, currency
+    ), None)
+}
+
+/// Debit or credit of an MT940 `D`/`C` mark.
+fn parse_mark(mark: char) -> Option<DebitCredit> {
+    match mark {
+        'D' | 'd' => Some(DebitCredit::Debit),
+        'C' | 'c' => Some(DebitCredit::Credit),
+        _ => None,
+    }
+}
+
+/// Parse an MT940 amount, which has a decimal comma.
+fn parse_mt940_amount(text: &str) -> Result<Decimal> {
+    // Digits with a decimal comma, without signs, spaces or points
+    let invalid = || Error::InvalidAmount(text.to_string(), None);
+    let (units, fraction) = text.split_once(',').ok_or_else(invalid)?;
+    if units.is_empty() || !units.bytes().chain(fraction.bytes()).all(|b| b.is_ascii_digit()) {
+        return Err(invalid());
+    }
+    parse_decimal(text, ',', None).ok_or_else(invalid)
+}
+
+/// Amount written with a decimal comma, which MT940 requires even
+/// without a fraction.
+struct Mt940Amount<'a>(&'a Decimal);
+
+impl fmt::Display for Mt940Amount<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        /// Writes through to `f` with the point as a comma.
+        struct Comma<'a, 'b> {
+            f: &'a mut fmt::Formatter<'b>,
+            written: bool,
+        }
+
+        impl fmt::Write for Comma<'_, '_> {
+            fn write_str(&mut self, s: &str) -> fmt::Result {
+                for (i, part) in s.split('.').enumerate() {
+                    if i > 0 {
+                        self.f.write_char(',')?;
+                        self.written = true;
+                    }
+                    self.f.write_str(part)?;
+                }
+                Ok(())
+            }
+        }
+
+        let mut comma = Comma { f, written: false };
+        write!(comma, "{}", self.0)?;
+        if !comma.written {
+            comma.f.write_char(',')?;
+        }
         Ok(())
     }
 }
@@ -354,46 +891,65 @@ impl Mt940Statement {
 /// Parse MT940 date format (YYMMDD) to NaiveDate.
 fn parse_mt940_date(date_str: &str) -> Result<NaiveDate> {
     if date_str.len() != 6 {
-        return Err(Error::InvalidDate(format!("Invalid MT940 date length: {}", date_str)));
+        return Err(Error::InvalidDate(format!("Invalid MT940 date length: {}", date_str), None));
     }
 
     let year = date_str.get(0..2)
-        .ok_or_else(|| Error::InvalidDate(date_str.to_string()))?
+        .ok_or_else(|| Error::InvalidDate(date_str.to_string(), None))?
         .parse::<i32>()
-        .map_err(|_| Error::InvalidDate(date_str.to_string()))?;
+        .map_err(|_| Error::InvalidDate(date_str.to_string(), None))?;
     let month = date_str.get(2..4)
-        .ok_or_else(|| Error::InvalidDate(date_str.to_string()))?
+        .ok_or_else(|| Error::InvalidDate(date_str.to_string(), None))?
         .parse::<u32>()
-        .map_err(|_| Error::InvalidDate(date_str.to_string()))?;
+        .map_err(|_| Error::InvalidDate(date_str.to_string(), None))?;
     let day = date_str.get(4..6)
-        .ok_or_else(|| Error::InvalidDate(date_str.to_string()))?
+        .ok_or_else(|| Error::InvalidDate(date_str.to_string(), None))?
         .parse::<u32>()
-        .map_err(|_| Error::InvalidDate(date_str.to_string()))?;
+        .map_err(|_| Error::InvalidDate(date_str.to_string(), None))?;
 
     // Assume 2000+ for years < 50, otherwise 1900+
     let full_year = if year < 50 { 2000 + year } else { 1900 + year };
 
     NaiveDate::from_ymd_opt(full_year, month, day)
-        .ok_or_else(|| Error::InvalidDate(format!("{}-{}-{}", full_year, month, day)))
+        .ok_or_else(|| Error::InvalidDate(format!("{}-{}-{}", full_year, month, day), None))
+}
+
+/// Parse an MT940 date and time with UTC offset (YYMMDDHHMM+HHMM) to the
+/// time in UTC.
+fn parse_mt940_datetime(text: &str) -> Result<NaiveDateTime> {
+    let invalid = || Error::InvalidDate(text.to_string(), None);
+    if text.len() != 15 || !text.is_ascii() {
+        return Err(invalid());
+    }
+    let date = parse_mt940_date(&text[0..6])?;
+    let time = NaiveTime::parse_from_str(&text[6..10], "%H%M").map_err(|_| invalid())?;
+    let hours = text[11..13].parse::<i64>().map_err(|_| invalid())?;
+    let minutes = text[13..15].parse::<i64>().map_err(|_| invalid())?;
+    let offset = TimeDelta::minutes(hours * 60 + minutes);
+    match &text[10..11] {
+        "+" => Ok(date.and_time(time) - offset),
+        "-" => Ok(date.and_time(time) + offset),
+        _ => Err(invalid()),
+    }
 }
 
 /// Parse MT940 entry date (MMDD) using year from value date.
 fn parse_mt940_entry_date(date_str: &str, year: i32) -> Result<NaiveDate> {
     if date_str.len() != 4 {
-        return Err(Error::InvalidDate(format!("Invalid entry date length: {}", date_str)));
+        return Err(Error::InvalidDate(format!("Invalid entry date length: {}", date_str), None));
     }
 
     let month = date_str.get(0..2)
-        .ok_or_else(|| Error::InvalidDate(date_str.to_string()))?
+        .ok_or_else(|| Error::InvalidDate(date_str.to_string(), None))?
         .parse::<u32>()
-        .map_err(|_| Error::InvalidDate(date_str.to_string()))?;
+        .map_err(|_| Error::InvalidDate(date_str.to_string(), None))?;
     let day = date_str.get(2..4)
-        .ok_or_else(|| Error::InvalidDate(date_str.to_string()))?
+        .ok_or_else(|| Error::InvalidDate(date_str.to_string(), None))?
         .parse::<u32>()
-        .map_err(|_| Error::InvalidDate(date_str.to_string()))?;
+        .map_err(|_| Error::InvalidDate(date_str.to_string(), None))?;
 
     NaiveDate::from_ymd_opt(year, month, day)
-        .ok_or_else(|| Error::InvalidDate(format!("{}-{}-{}", year, month, day)))
+        .ok_or_else(|| Error::InvalidDate(format!("{}-{}-{}", year, month, day), None))
 }
 
 /// Format NaiveDate to MT940 format (YYMMDD).
@@ -401,9 +957,45 @@ fn format_mt940_date(date: &NaiveDate) -> String {
     format!("{:02}{:02}{:02}", date.year() % 100, date.month(), date.day())
 }
 
+/// Split `:86:` text into lines of at most [`LINE_LENGTH`] characters,
+/// breaking at spaces where possible.
+///
+/// The reader joins continuation lines with a space, so text broken at
+/// single spaces reads back unchanged.
+pub(crate) fn wrap_narrative(text: &str) -> Vec<String> {
+    if text.chars().count() <= LINE_LENGTH {
+        return if text.is_empty() { Vec::new() } else { vec![text.to_string()] };
+    }
+
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        let mut word: Vec<char> = word.chars().collect();
+        if !line.is_empty() && line.chars().count() + 1 + word.len() > LINE_LENGTH {
+            lines.push(std::mem::take(&mut line));
+        }
+        // Words longer than a line are split wherever they hit the limit
+        while word.len() > LINE_LENGTH {
+            lines.push(word.drain(..LINE_LENGTH).collect());
+        }
+        if !word.is_empty() {
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.extend(word);
+        }
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::AccountIdentifier;
+    use std::str::FromStr;
 
     #[test]
     fn test_parse_mt940_date() {
@@ -413,10 +1005,235 @@ mod tests {
         assert_eq!(date.day(), 18);
     }
 
+    #[test]
+    fn test_parse_mt940_amount() {
+        assert_eq!(parse_mt940_amount("2732398848,02").unwrap(), Decimal::from_str("2732398848.02").unwrap());
+        assert_eq!(parse_mt940_amount("10,00").unwrap().to_string(), "10.00");
+        assert_eq!(parse_mt940_amount("1O,00").unwrap_err().to_string(), "Invalid amount format: 1O,00");
+        assert_eq!(parse_mt940_amount("100,").unwrap(), Decimal::from(100));
+        for invalid in ["100", "-1,00", "+1,00", "1.00", "1 000,00", ",50", "1,0,0", ""] {
+            assert!(parse_mt940_amount(invalid).is_err(), "{}", invalid);
+        }
+        assert_eq!(Mt940Amount(&Decimal::new(1234, 2)).to_string(), "12,34");
+        assert_eq!(Mt940Amount(&Decimal::from(100)).to_string(), "100,");
+        assert!(Mt940Statement::parse_transaction_line(":61:2401020102D1O,00NTRF//REF1", Currency::EUR).is_err());
+        assert!(parse_mt940_amount(&"9".repeat(40)).is_err());
+    }
+
     #[test]
     fn test_debit_credit() {
         assert_eq!("D".parse::<DebitCredit>().ok(), Some(DebitCredit::Debit));
         assert_eq!("C".parse::<DebitCredit>().ok(), Some(DebitCredit::Credit));
         assert!("X".parse::<DebitCredit>().is_err());
     }
+
+    #[test]
+    fn test_account_identification() {
+        let account = |id: &str| {
+            let input = format!(":20:STMT\n:25:{}\n:28C:1\n:60F:C240101EUR100,00\n:62F:C240101EUR100,00\n", id);
+            Mt940Statement::from_read(&mut input.as_bytes()).unwrap().statement.account
+        };
+
+        assert!(matches!(account("DE89370400440532013000"), AccountIdentifier::Iban(_)));
+        assert_eq!(
+            account("DE89370400440532013001"),
+            AccountIdentifier::Other { id: "DE89370400440532013001".into(), scheme: None, issuer: None }
+        );
+        assert!(matches!(account("107048825"), AccountIdentifier::Other { .. }));
+    }
+
+    #[test]
+    fn test_servicing_bank_header() {
+        let bank = |header: &str| {
+            let input = format!("{}\n:20:STMT\n:25:ACC\n:60F:C240101EUR100,00\n:62F:C240101EUR100,00\n-}}", header);
+            Mt940Statement::from_read(&mut input.as_bytes()).unwrap().statement.servicing_bank
+                .and_then(|bank| bank.bic)
+                .map(|bic| bic.to_string())
+        };
+
+        assert_eq!(bank("{1:F01ASNBNL21XXXX0000000000}{2:O940ASNBNL21XXXXN}{4:").as_deref(), Some("ASNBNL21"));
+        assert_eq!(
+            bank("{1:F01CUSTDEFFAXXX0000000000}{2:O9401200240101DEUTDEFFA5001234123456240101N}{4:").as_deref(),
+            Some("DEUTDEFF500")
+        );
+        assert_eq!(bank("{1:F01BANKXXXXAXXX0000000000}{2:I940BANKXXXXAXXXXN}{4:"), None);
+
+        let mut statement = Statement::new("STMT".into(), "ACC", Currency::EUR);
+        let date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        statement.opening_balance = Some(Balance::from_signed(BalanceType::Opening, Decimal::ONE, Currency::EUR, date));
+        statement.servicing_bank = Some(BankInfo::from_identifier(BankIdentifier::Bic("DEUTDEFF500".parse().unwrap())));
+        let mut output = Vec::new();
+        Mt940Statement { statement: statement.clone() }.write_to(&mut output).unwrap();
+        assert!(output.starts_with(b"{1:F01DEUTDEFFA5000000000000}"));
+        let reparsed = Mt940Statement::from_read(&mut output.as_slice()).unwrap();
+        assert_eq!(reparsed.statement.servicing_bank, statement.servicing_bank);
+    }
+
+    #[test]
+    fn test_multi_currency_statement() {
+        let mut statement = Statement::new("STMT".into(), "ACC001", Currency::EUR);
+        for (reference, currency) in [("R1", Currency::EUR), ("R2", Currency::USD), ("R3", Currency::EUR)] {
+            let (mut tx, _) = Mt940Statement::parse_transaction_line(":61:2502180218D5,00NTRF//X", currency).unwrap();
+            tx.reference = reference.into();
+            statement.transactions.push(tx);
+        }
+
+        let mut output = Vec::new();
+        let mt940 = Mt940Statement { statement: statement.clone() };
+        assert!(matches!(mt940.write_to(&mut output), Err(Error::ConversionError(..))));
+
+        let parts = statement.by_currency();
+        assert_eq!(parts.iter().map(|p| p.currency).collect::<Vec<_>>(), [Currency::EUR, Currency::USD]);
+        assert_eq!(parts[0].transactions.len(), 2);
+        assert_eq!(parts[1].statement_id, "STMT-USD");
+        for statement in parts {
+            Mt940Statement { statement }.write_to(&mut output).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_reversal_marks() {
+        let (tx, _) = Mt940Statement::parse_transaction_line(":61:2502180218RC12,01NTRF//REF1", Currency::EUR).unwrap();
+        assert_eq!(tx.status, EntryStatus::Reversed);
+        assert_eq!(tx.debit_credit, DebitCredit::Debit);
+        assert_eq!(tx.amount, Decimal::new(1201, 2));
+
+        let (tx, _) = Mt940Statement::parse_transaction_line(":61:250218RD5,00NTRF//REF2", Currency::EUR).unwrap();
+        assert_eq!(tx.status, EntryStatus::Reversed);
+        assert_eq!(tx.debit_credit, DebitCredit::Credit);
+        assert_eq!(tx.date.date(), NaiveDate::from_ymd_opt(2025, 2, 18).unwrap());
+
+        let mut statement = Statement::new("1".into(), "ACC001", Currency::EUR);
+        statement.transactions.push(tx);
+        let mut output = Vec::new();
+        Mt940Statement { statement }.write_to(&mut output).unwrap();
+        assert!(String::from_utf8(output).unwrap().contains("0218RD5,00NTRF//REF2"));
+    }
+
+    #[test]
+    fn test_parse_warnings() {
+        let input = ":20:STMT\n:25:ACC001\n:60F:C240101EUR100,00\n:61:2401020102D10,00\n\
+                     :62F:C240102EUR90,00\n:64:C240102EUR90,00\n-}";
+        let outcome = Mt940Statement::from_read_with_diagnostics(&mut input.as_bytes(), &Default::default()).unwrap();
+        let warnings: Vec<(WarningKind, Option<usize>)> = outcome.warnings.iter().map(|w| (w.kind, w.line)).collect();
+        assert_eq!(warnings, [(WarningKind::AssumedDefault, Some(4)), (WarningKind::UnknownField, Some(6))]);
+        assert_eq!(outcome.warnings[1].message, "unknown tag :64: ignored");
+        assert_eq!(outcome.statement, Mt940Statement::from_read(&mut input.as_bytes()).unwrap());
+    }
+
+    #[test]
+    fn test_missing_fields_reported_together() {
+        let input = ":60F:C240101EUR100,00\n:62F:C240101EUR100,00\n-}";
+        let err = Mt940Statement::from_read(&mut input.as_bytes()).unwrap_err();
+        assert!(matches!(err, Error::MissingFields(ref fields, _) if fields.len() == 2));
+        assert_eq!(err.to_string(), "Missing required fields: statement reference :20:, account identification :25:");
+        assert_eq!(err.code(), ErrorCode::MissingField);
+
+        let err = Mt940Statement::from_read(&mut ":20:STMT\n:60F:C240101EUR100,00\n-}".as_bytes()).unwrap_err();
+        assert!(matches!(err, Error::MissingField(ref field, _) if field == "account identification :25:"));
+    }
+
+    #[test]
+    fn test_missing_opening_balance() {
+        // :61: before :60F: takes the currency of the balance after it
+        let input = ":20:STMT\n:25:ACC001\n:61:2401020102D10,00NTRF//REF1\n:60F:C240101USD100,00\n:62F:C240102USD90,00\n-}";
+        let outcome = Mt940Statement::from_read_with_diagnostics(&mut input.as_bytes(), &Mt940ReadOptions::default()).unwrap();
+        assert_eq!(outcome.statement.statement.currency, Currency::USD);
+        assert_eq!(outcome.statement.statement.transactions[0].currency, Currency::USD);
+        assert_eq!(outcome.warnings.len(), 1);
+        assert_eq!(outcome.warnings[0].line, Some(3));
+
+        // Without :60F:, the closing balance's
+        let input = ":20:STMT\n:25:ACC001\n:61:2401020102D10,00NTRF//REF1\n:62F:C240102USD90,00\n-}";
+        let outcome = Mt940Statement::from_read_with_diagnostics(&mut input.as_bytes(), &Mt940ReadOptions::default()).unwrap();
+        let statement = &outcome.statement.statement;
+        assert_eq!((statement.currency, statement.transactions[0].currency), (Currency::USD, Currency::USD));
+        assert!(statement.opening_balance.is_none());
+        assert_eq!(outcome.warnings[1].message, "no opening balance :60F:, using currency USD");
+
+        let statement = Mt940Statement::from_read(&mut ":20:STMT\n:25:ACC001\n-}".as_bytes()).unwrap().statement;
+        assert_eq!(statement.currency, Currency::XXX);
+    }
+
+    #[test]
+    fn test_paginated_sequence_number() {
+        let input = ":20:STMT\n:25:ACC001\n:28C:00042/001\n:60F:C240101EUR100,00\n\
+                     :61:2401020102D10,00NTRF//REF1\n:61:2401030103D20,00NTRF//REF2\n:62F:C240103EUR70,00\n-}";
+        let statement = Mt940Statement::from_read(&mut input.as_bytes()).unwrap().statement;
+
+        let mut output = Vec::new();
+        for statement in statement.paginate(1) {
+            Mt940Statement { statement }.write_to(&mut output).unwrap();
+        }
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains(":28C:00042/1\n:60F:C240101EUR100,00\n"));
+        assert!(output.contains(":62M:C240102EUR90,00\n"));
+        assert!(output.contains(":28C:00042/2\n:60M:C240103EUR90,00\n"));
+        assert!(output.contains(":62F:C240103EUR70,00\n"));
+    }
+
+    #[test]
+    fn test_creation_date_time() {
+        let input = ":20:STMT\n:25:ACC001\n:13D:2401020930+0200\n:60F:C240101EUR100,00\n:62F:C240101EUR100,00\n-}";
+        let outcome = Mt940Statement::from_read_with_diagnostics(&mut input.as_bytes(), &Mt940ReadOptions::default()).unwrap();
+        assert_eq!(outcome.statement.statement.creation_date.unwrap().to_string(), "2024-01-02T07:30:00");
+        assert!(outcome.warnings.is_empty());
+
+        let outcome = Mt940Statement::from_read_with_diagnostics(&mut input.replace("+0200", "-0030").as_bytes(), &Mt940ReadOptions::default()).unwrap();
+        assert_eq!(outcome.statement.statement.creation_date.unwrap().to_string(), "2024-01-02T10:00:00");
+
+        let outcome = Mt940Statement::from_read_with_diagnostics(&mut input.replace("0930+", "2530+").as_bytes(), &Mt940ReadOptions::default()).unwrap();
+        assert_eq!(outcome.statement.statement.creation_date, None);
+        assert_eq!(outcome.warnings[0].kind, WarningKind::IgnoredValue);
+        assert_eq!(outcome.warnings[0].line, Some(3));
+    }
+
+    #[test]
+    fn test_wrap_narrative() {
+        let narrative = "Payment for invoice 2024/117 of 1 March 2024, contract 55-K, delivery of office furniture";
+        let lines = wrap_narrative(narrative);
+        assert_eq!(lines.len(), 2);
+        assert!(lines.iter().all(|line| line.chars().count() <= LINE_LENGTH));
+        assert_eq!(lines.join(" "), narrative);
+        assert_eq!(wrap_narrative(&"X".repeat(70)), ["X".repeat(65), "X".repeat(5)]);
+        assert!(wrap_narrative("").is_empty());
+
+        let mut statement = Statement::new("1".into(), "ACC001", Currency::EUR);
+        statement.opening_balance = Some(Balance::from_signed(BalanceType::Opening, Decimal::ZERO, Currency::EUR, NaiveDate::from_ymd_opt(2024, 3, 1).unwrap()));
+        let (mut tx, _) = Mt940Statement::parse_transaction_line(":61:2403010301D5,00NTRF//REF1", Currency::EUR).unwrap();
+        tx.description = narrative.to_string();
+        statement.transactions.push(tx);
+        let mut output = Vec::new();
+        Mt940Statement { statement }.write_to(&mut output).unwrap();
+        let back = Mt940Statement::from_read(&mut output.as_slice()).unwrap();
+        assert_eq!(back.statement.transactions[0].description, narrative);
+    }
+
+    #[test]
+    fn test_entry_reader() {
+        let input = ":20:STMT\n:25:ACC001\n:60F:C240101EUR100,00\n:61:2401020102D10,00NTRF//REF1\n:86:Coffee\nbeans\n\
+                     :61:2401030103D20,00NTRF//REF2\n:62F:C240103EUR70,00\n:61:XX\n-}";
+        let mut reader = Mt940EntryReader::new(input.as_bytes());
+        let first = reader.next().unwrap().unwrap();
+        assert_eq!((first.reference.as_str(), first.description.as_str()), ("REF1", "Coffee beans"));
+        assert_eq!(reader.currency(), Some(Currency::EUR));
+        assert_eq!(reader.next().unwrap().unwrap().reference, "REF2");
+        let error = reader.next().unwrap().unwrap_err();
+        assert_eq!(error.location().and_then(|location| location.line), Some(9));
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn test_error_location() {
+        let input = ":20:STMT\r\n:25:ACC001\r\n:60F:C240101EUR100,00\r\n:61:2413020102D10,00NTRF//REF1\r\n";
+        let err = Mt940Statement::from_read(&mut input.as_bytes()).unwrap_err();
+        assert!(matches!(err, Error::Mt940ParseError { line: 4, code: ErrorCode::Mt940BadStatementLine, .. }));
+        let location = err.location().unwrap();
+        assert_eq!((location.line, location.column, location.offset), (Some(4), Some(1), Some(45)));
+        assert_eq!(location.snippet.as_deref(), Some(":61:2413020102D10,00NTRF//REF1"));
+
+        let err = err.in_file("june.mt940");
+        assert_eq!(err.location().unwrap().file.as_deref(), Some("june.mt940"));
+        assert!(err.to_string().ends_with("(june.mt940:4, column 1, byte 45, at \":61:2413020102D10,00NTRF//REF1\")"));
+    }
 }
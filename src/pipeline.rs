@@ -0,0 +1,416 @@
+//! Conversion pipelines.
+//!
+//! A [`Pipeline`] reads a statement in one format, runs it through a list
+//! of [`Step`]s in order and writes it in another format. Pipelines are
+//! built in code:
+//!
+//! ```
+//! use ypbank_system::pipeline::{Pipeline, Step};
+//! use ypbank_system::query::TransactionFilter;
+//! use ypbank_system::{Format, SortKey};
+//!
+//! let pipeline = Pipeline::new(Format::Mt940, Format::Json)
+//!     .step(Step::Dedup)
+//!     .step(Step::Filter(TransactionFilter::default().counterparty_contains("acme")))
+//!     .step(Step::Sort(SortKey::Amount));
+//!
+//! let input = ":20:STMT\n:25:ACC001\n:60F:C240101EUR100,00\n:62F:C240101EUR100,00\n";
+//! let mut output = Vec::new();
+//! pipeline.run(&mut input.as_bytes(), &mut output)?;
+//! # Ok::<(), ypbank_system::Error>(())
+//! ```
+//!
+//! or loaded from a TOML file with the formats and one `[[step]]` table
+//! per step, its `type` named in kebab case:
+//!
+//! ```toml
+//! input = "csv"
+//! output = "mt940"
+//! csv_profile = "tinkoff"
+//!
+//! [[step]]
+//! type = "categorize"
+//! rules = "rules.toml"
+//!
+//! [[step]]
+//! type = "filter"
+//! from_date = "2024-01-01"
+//! direction = "debit"
+//!
+//! [[step]]
+//! type = "convert"
+//! charges = "separate"
+//!
+//! [[step]]
+//! type = "truncation"
+//! policy = "reject"
+//! ```
+
+use crate::anonymize::Anonymizer;
+use crate::camt053_format::Camt053Statement;
+use crate::categorize::Categorizer;
+use crate::conversion::{
+    apply_options, camt053_to_csv, camt053_to_mt940, csv_to_camt053, fit_mt940_limits, mt940_to_camt053,
+    ConversionOptions, ConversionReport, Converted, TransactionHook, TruncationPolicy,
+};
+use crate::csv_format::{CsvProfile, CsvStatement};
+use crate::error::{Error, ParseOutcome, Result, RuleId, ValidationError};
+use crate::format::{CsvFormat, FormatRegistry};
+use crate::mapping::{field_mapping, MappingDocument};
+use crate::mt940_format::Mt940Statement;
+use crate::query::TransactionFilter;
+use crate::types::{EntryStatus, PrecisionPolicy, SortKey, Statement};
+use crate::Format;
+use chrono::NaiveDate;
+use regex::Regex;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use std::fs::File;
+use std::io::{Read, Write};
+
+/// A processing step of a [`Pipeline`].
+#[derive(Debug, Clone)]
+pub enum Step {
+    /// Keep only the transactions matching the filter.
+    Filter(TransactionFilter),
+    /// Drop duplicate transactions, see [`Statement::dedup_transactions`].
+    Dedup,
+    /// Replace identifying data with pseudonyms.
+    Anonymize(Anonymizer),
+    /// Assign categories to transactions.
+    Categorize(Categorizer),
+    /// Sort transactions, see [`Statement::sort_transactions`].
+    Sort(SortKey),
+    /// Fill in missing balances, see [`Statement::fill_missing_balances`],
+    /// with the opening balance to assume when none is stated.
+    SynthesizeBalances(Option<Decimal>),
+    /// Fail unless the opening balance plus the transactions gives the
+    /// closing balance.
+    VerifyBalances,
+    /// Fill in the balance after each transaction.
+    RunningBalances,
+    /// Adapt the statement from the input to the output format with
+    /// these options: the direct conversion between MT940, CAMT.053 and
+    /// CSV where there is one (e.g. [`camt053_to_mt940`]), else
+    /// [`apply_options`].
+    Convert(ConversionOptions),
+    /// Fit amounts to their currency's minor unit.
+    Precision(PrecisionPolicy),
+    /// Fit fields into MT940's lengths with [`fit_mt940_limits`] when the
+    /// output is MT940; truncated fields are reported.
+    FitMt940(TruncationPolicy),
+}
+
+/// Reading, processing and writing of a statement, from one format to
+/// another.
+pub struct Pipeline {
+    input: Format,
+    output: Format,
+    registry: FormatRegistry,
+    steps: Vec<Step>,
+}
+
+impl Pipeline {
+    /// Pipeline from `input` to `output` without any step, using the
+    /// default format registry.
+    pub fn new(input: Format, output: Format) -> Self {
+        Pipeline { input, output, registry: FormatRegistry::default(), steps: Vec::new() }
+    }
+
+    /// Read and write with the formats of `registry`, e.g. to use a CSV
+    /// profile.
+    pub fn registry(mut self, registry: FormatRegistry) -> Self {
+        self.registry = registry;
+        self
+    }
+
+    /// Add `step` after the existing ones.
+    pub fn step(mut self, step: Step) -> Self {
+        self.steps.push(step);
+        self
+    }
+
+    /// Input format.
+    pub fn input(&self) -> Format {
+        self.input
+    }
+
+    /// Output format.
+    pub fn output(&self) -> Format {
+        self.output
+    }
+
+    /// Steps in the order they run.
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    /// Parse a pipeline from TOML, see the [module documentation](self).
+    ///
+    /// Paths, such as the categorization rules, are relative to the
+    /// current directory.
+    pub fn from_toml(toml: &str) -> Result<Self> {
+        let file: PipelineFile = toml::from_str(toml)
+            .map_err(|e| Error::InvalidFormat(format!("Invalid pipeline: {}", e), None))?;
+
+        let mut pipeline = Pipeline::new(file.input.parse()?, file.output.parse()?);
+        if let Some(ref name) = file.csv_profile {
+            let profile = CsvProfile::named(name)
+                .ok_or_else(|| Error::InvalidFormat(format!("Unknown CSV profile: {}", name), None))?;
+            pipeline.registry.register(Format::Csv, CsvFormat { profile });
+        }
+        for spec in file.step {
+            pipeline.steps.push(spec.into_step()?);
+        }
+        Ok(pipeline)
+    }
+
+    /// Field mapping from the input to the output format with the options
+    /// of the first [`Step::Convert`], see [`field_mapping`].
+    pub fn field_mapping(&self) -> Result<MappingDocument> {
+        let options = self.steps.iter()
+            .find_map(|step| match step {
+                Step::Convert(options) => Some(options.clone()),
+                _ => None,
+            })
+            .unwrap_or_default();
+        field_mapping(self.input, self.output, &options)
+    }
+
+    /// Read a statement in the input format.
+    pub fn read(&self, reader: &mut dyn Read) -> Result<Statement> {
+        self.registry.read(self.input, reader)
+    }
+
+    /// Read a statement in the input format along with its parse warnings.
+    pub fn read_with_diagnostics(&self, reader: &mut dyn Read) -> Result<ParseOutcome<Statement>> {
+        self.registry.read_with_diagnostics(self.input, reader)
+    }
+
+    /// Run every step on `statement`; the report lists the fields steps
+    /// had to truncate.
+    pub fn process(&self, mut statement: Statement) -> Result<Converted<Statement>> {
+        let mut report = ConversionReport::default();
+
+        for step in &self.steps {
+            match step {
+                Step::Filter(filter) => statement.retain_matching(filter),
+                Step::Dedup => {
+                    statement.dedup_transactions();
+                }
+                Step::Anonymize(anonymizer) => anonymizer.apply(&mut statement),
+                Step::Categorize(categorizer) => categorizer.apply(&mut statement),
+                Step::Sort(key) => statement.sort_transactions(*key),
+                Step::SynthesizeBalances(opening) => statement.fill_missing_balances(*opening),
+                Step::VerifyBalances => {
+                    statement.verify_balances().map_err(|discrepancy| {
+                        let failure = ValidationError::new(RuleId::ClosingBalance, discrepancy.to_string());
+                        match statement.first_running_balance_mismatch() {
+                            Some(i) => failure.at_path(format!("transactions[{}].running_balance", i)),
+                            None => failure.at_path("closing_balance"),
+                        }
+                    })?;
+                }
+                Step::RunningBalances => statement = statement.with_running_balances(),
+                Step::Convert(options) => statement = self.convert(statement, options),
+                Step::Precision(policy) => statement.enforce_precision(*policy)?,
+                Step::FitMt940(policy) => {
+                    if self.output == Format::Mt940 {
+                        report.losses.extend(fit_mt940_limits(&mut statement, *policy)?.losses);
+                    }
+                }
+            }
+        }
+
+        Ok(Converted { output: statement, report })
+    }
+
+    /// Write `statement` in the output format.
+    pub fn write(&self, statement: &Statement, writer: &mut dyn Write) -> Result<()> {
+        self.registry.write(self.output, statement, writer)
+    }
+
+    /// Read, process and write a statement; returns the report of
+    /// [`Pipeline::process`].
+    pub fn run(&self, reader: &mut dyn Read, writer: &mut dyn Write) -> Result<ConversionReport> {
+        let processed = self.process(self.read(reader)?)?;
+        self.write(&processed.output, writer)?;
+        Ok(processed.report)
+    }
+
+    fn convert(&self, mut statement: Statement, options: &ConversionOptions) -> Statement {
+        match (self.input, self.output) {
+            (Format::Mt940, Format::Camt053) => mt940_to_camt053(Mt940Statement { statement }, options).statement,
+            (Format::Camt053, Format::Mt940) => camt053_to_mt940(Camt053Statement { statement }, options).statement,
+            (Format::Csv, Format::Camt053) => csv_to_camt053(CsvStatement { statement }, options).statement,
+            (Format::Camt053, Format::Csv) => camt053_to_csv(Camt053Statement { statement }, options).statement,
+            _ => {
+                apply_options(&mut statement, options);
+                statement
+            }
+        }
+    }
+}
+
+/// A pipeline as written in a TOML file.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PipelineFile {
+    input: String,
+    output: String,
+    csv_profile: Option<String>,
+    #[serde(default)]
+    step: Vec<StepSpec>,
+}
+
+/// A step as written in a TOML file.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case", deny_unknown_fields)]
+enum StepSpec {
+    Filter {
+        from_date: Option<NaiveDate>,
+        to_date: Option<NaiveDate>,
+        min_amount: Option<Decimal>,
+        max_amount: Option<Decimal>,
+        direction: Option<String>,
+        counterparty: Option<String>,
+        counterparty_pattern: Option<String>,
+    },
+    Dedup,
+    Anonymize {
+        salt: Option<String>,
+    },
+    Categorize {
+        rules: String,
+    },
+    Sort {
+        key: String,
+    },
+    SynthesizeBalances {
+        opening_balance: Option<Decimal>,
+    },
+    VerifyBalances,
+    RunningBalances,
+    Convert {
+        charges: Option<String>,
+        batches: Option<String>,
+        #[serde(default)]
+        exclude_statuses: Vec<String>,
+        counterparty_pattern: Option<String>,
+        rewrite_reference: Option<String>,
+    },
+    Precision {
+        policy: String,
+    },
+    Truncation {
+        policy: String,
+    },
+}
+
+impl StepSpec {
+    fn into_step(self) -> Result<Step> {
+        let regex = |pattern: &str| {
+            Regex::new(pattern).map_err(|e| Error::InvalidFormat(format!("Invalid pattern: {}", e), None))
+        };
+
+        Ok(match self {
+            StepSpec::Filter { from_date, to_date, min_amount, max_amount, direction, counterparty, counterparty_pattern } => {
+                Step::Filter(TransactionFilter {
+                    from_date,
+                    to_date,
+                    min_amount,
+                    max_amount,
+                    direction: direction.map(|d| d.parse().map_err(|e| Error::InvalidFormat(e, None))).transpose()?,
+                    counterparty,
+                    counterparty_pattern: counterparty_pattern.as_deref().map(regex).transpose()?,
+                    ..Default::default()
+                })
+            }
+            StepSpec::Dedup => Step::Dedup,
+            StepSpec::Anonymize { salt } => Step::Anonymize(match salt {
+                Some(salt) => Anonymizer::new(salt),
+                None => Anonymizer::random()?,
+            }),
+            StepSpec::Categorize { rules } => Step::Categorize(Categorizer::from_read(&mut File::open(rules)?)?),
+            StepSpec::Sort { key } => Step::Sort(key.parse().map_err(|e| Error::InvalidFormat(e, None))?),
+            StepSpec::SynthesizeBalances { opening_balance } => Step::SynthesizeBalances(opening_balance),
+            StepSpec::VerifyBalances => Step::VerifyBalances,
+            StepSpec::RunningBalances => Step::RunningBalances,
+            StepSpec::Convert { charges, batches, exclude_statuses, counterparty_pattern, rewrite_reference } => {
+                let mut options = ConversionOptions {
+                    charges: charges.as_deref().map(str::parse).transpose()?,
+                    batches: batches.as_deref().map(str::parse).transpose()?,
+                    excluded_statuses: exclude_statuses.iter()
+                        .map(|status| status.parse::<EntryStatus>().map_err(|e| Error::InvalidFormat(e, None)))
+                        .collect::<Result<_>>()?,
+                    ..Default::default()
+                };
+                if let Some(ref pattern) = counterparty_pattern {
+                    options.hooks.push(TransactionHook::counterparty_from_description(regex(pattern)?));
+                }
+                if let Some(ref spec) = rewrite_reference {
+                    let (pattern, replacement) = spec.split_once("=>").ok_or_else(|| {
+                        Error::InvalidFormat(format!("Expected PATTERN=>REPLACEMENT: {}", spec), None)
+                    })?;
+                    options.hooks.push(TransactionHook::rewrite_reference(regex(pattern)?, replacement));
+                }
+                Step::Convert(options)
+            }
+            StepSpec::Precision { policy } => Step::Precision(policy.parse().map_err(|e| Error::InvalidFormat(e, None))?),
+            StepSpec::Truncation { policy } => Step::FitMt940(policy.parse()?),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pipeline_from_toml() {
+        let toml = r#"
+            input = "mt940"
+            output = "csv"
+
+            [[step]]
+            type = "filter"
+            direction = "debit"
+            min_amount = 5
+
+            [[step]]
+            type = "sort"
+            key = "amount"
+
+            [[step]]
+            type = "convert"
+            rewrite-reference = "^REF=>R"
+        "#;
+        assert!(Pipeline::from_toml(toml).is_err());
+
+        let pipeline = Pipeline::from_toml(&toml.replace("rewrite-reference", "rewrite_reference")).unwrap();
+        assert_eq!((pipeline.input(), pipeline.output()), (Format::Mt940, Format::Csv));
+        assert_eq!(pipeline.steps().len(), 3);
+
+        let input = ":20:STMT\n:25:ACC001\n:60F:C240101EUR100,00\n\
+                     :61:2401020102D10,00NTRF//REF1\n:86:Payment\n\
+                     :61:2401020102D7,00NTRF//REF2\n:86:Payment\n\
+                     :61:2401020102D1,00NTRF//REF3\n:86:Fee\n\
+                     :61:2401020102C20,00NTRF//REF4\n:86:Refund\n:62F:C240102EUR102,00\n";
+        let statement = pipeline.read(&mut input.as_bytes()).unwrap();
+        let processed = pipeline.process(statement).unwrap();
+        let references: Vec<&str> = processed.output.transactions.iter().map(|tx| tx.reference.as_str()).collect();
+        assert_eq!(references, ["R1", "R2"]);
+        assert!(processed.report.is_lossless());
+
+        let verifying = Pipeline::new(Format::Mt940, Format::Json).step(Step::VerifyBalances);
+        assert!(verifying.run(&mut input.as_bytes(), &mut Vec::new()).is_ok());
+        let unbalanced = input.replace("EUR102,00", "EUR100,00");
+        let err = verifying.run(&mut unbalanced.as_bytes(), &mut Vec::new()).unwrap_err();
+        assert_eq!(err.validation().unwrap().rule, RuleId::ClosingBalance);
+        assert_eq!(err.code().as_str(), "E_VALIDATION");
+        assert!(Pipeline::from_toml("input = \"mt940\"\noutput = \"csv\"\n[[step]]\ntype = \"shuffle\"\n").is_err());
+        let invalid_pattern = toml.replace("rewrite-reference", "rewrite_reference").replace("min_amount = 5", "counterparty_pattern = \"(\"");
+        assert!(Pipeline::from_toml(&invalid_pattern).is_err());
+        assert!(Pipeline::from_toml(&invalid_pattern.replace("\"(\"", "\"^ООО\"")).is_ok());
+    }
+}
@@ -0,0 +1,360 @@
+//! Fluent filtering of statement transactions.
+//!
+//! [`TransactionFilter`] holds the conditions. [`Statement::transactions_where`]
+//! starts a [`TransactionQuery`]; each method narrows it down and the query
+//! iterates over the matching transactions:
+//!
+//! ```
+//! use chrono::NaiveDate;
+//! use rust_decimal::Decimal;
+//! use ypbank_system::types::{Currency, Statement};
+//!
+//! let statement = Statement::new("1".into(), "ACC001", Currency::RUB);
+//! let large_payments_to_acme = statement
+//!     .transactions_where()
+//!     .date_between(NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(), NaiveDate::from_ymd_opt(2024, 1, 31).unwrap())
+//!     .min_amount(Decimal::new(1000, 0))
+//!     .debit_only()
+//!     .counterparty_contains("acme")
+//!     .count();
+//! assert_eq!(large_payments_to_acme, 0);
+//! ```
+
+use crate::types::{DebitCredit, EntryStatus, Statement, Transaction};
+use chrono::NaiveDate;
+use regex::Regex;
+use rust_decimal::Decimal;
+use std::slice;
+
+/// Conditions on transactions, every one set having to hold; used by
+/// [`TransactionQuery`], [`Statement::retain_matching`] and the filter step
+/// of a pipeline.
+///
+/// # Examples
+///
+/// ```
+/// use rust_decimal::Decimal;
+/// use ypbank_system::query::TransactionFilter;
+/// use ypbank_system::types::{DebitCredit, Transaction};
+///
+/// let filter = TransactionFilter::default().debit_only().min_amount(Decimal::new(100, 0));
+/// let payment = Transaction { amount: Decimal::new(150, 0), debit_credit: DebitCredit::Debit, ..Transaction::default() };
+/// assert!(filter.matches(&payment));
+/// assert!(!filter.matches(&Transaction { amount: Decimal::new(50, 0), ..payment }));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct TransactionFilter {
+    /// Booked on or after this date.
+    pub from_date: Option<NaiveDate>,
+    /// Booked on or before this date.
+    pub to_date: Option<NaiveDate>,
+    /// Amount of at least this much, regardless of direction.
+    pub min_amount: Option<Decimal>,
+    /// Amount of at most this much, regardless of direction.
+    pub max_amount: Option<Decimal>,
+    /// Only debits or only credits.
+    pub direction: Option<DebitCredit>,
+    /// Only entries with this status.
+    pub status: Option<EntryStatus>,
+    /// Counterparty name containing this text, ignoring case.
+    pub counterparty: Option<String>,
+    /// Counterparty name in which this pattern is found.
+    pub counterparty_pattern: Option<Regex>,
+    /// Description containing this text, ignoring case.
+    pub description: Option<String>,
+    /// Assigned to this category.
+    pub category: Option<String>,
+    /// Carrying all of these tags.
+    pub tags: Vec<String>,
+}
+
+impl TransactionFilter {
+    /// Booked between `from` and `to`, both inclusive.
+    pub fn date_between(self, from: NaiveDate, to: NaiveDate) -> Self {
+        self.date_from(from).date_to(to)
+    }
+
+    /// Booked on or after `from`.
+    pub fn date_from(mut self, from: NaiveDate) -> Self {
+        self.from_date = Some(from);
+        self
+    }
+
+    /// Booked on or before `to`.
+    pub fn date_to(mut self, to: NaiveDate) -> Self {
+        self.to_date = Some(to);
+        self
+    }
+
+    /// Amount of at least `amount`, regardless of direction.
+    pub fn min_amount(mut self, amount: Decimal) -> Self {
+        self.min_amount = Some(amount);
+        self
+    }
+
+    /// Amount of at most `amount`, regardless of direction.
+    pub fn max_amount(mut self, amount: Decimal) -> Self {
+        self.max_amount = Some(amount);
+        self
+    }
+
+    /// Only transactions in the given direction.
+    pub fn direction(mut self, direction: DebitCredit) -> Self {
+        self.direction = Some(direction);
+        self
+    }
+
+    /// Only debits.
+    pub fn debit_only(self) -> Self {
+        self.direction(DebitCredit::Debit)
+    }
+
+    /// Only credits.
+    pub fn credit_only(self) -> Self {
+        self.direction(DebitCredit::Credit)
+    }
+
+    /// Only entries with the given status.
+    pub fn status(mut self, status: EntryStatus) -> Self {
+        self.status = Some(status);
+        self
+    }
+
+    /// Counterparty name containing `text`, ignoring case.
+    pub fn counterparty_contains(mut self, text: &str) -> Self {
+        self.counterparty = Some(text.to_string());
+        self
+    }
+
+    /// Counterparty name in which `pattern` is found.
+    pub fn counterparty_matches(mut self, pattern: &Regex) -> Self {
+        self.counterparty_pattern = Some(pattern.clone());
+        self
+    }
+
+    /// Description containing `text`, ignoring case.
+    pub fn description_contains(mut self, text: &str) -> Self {
+        self.description = Some(text.to_string());
+        self
+    }
+
+    /// Only transactions assigned to `category`.
+    pub fn category(mut self, category: &str) -> Self {
+        self.category = Some(category.to_string());
+        self
+    }
+
+    /// Only transactions carrying `tag`; repeat to require several tags.
+    pub fn tagged(mut self, tag: &str) -> Self {
+        self.tags.push(tag.to_string());
+        self
+    }
+
+    /// Whether `transaction` satisfies all conditions of the filter.
+    pub fn matches(&self, transaction: &Transaction) -> bool {
+        let contains = |haystack: Option<&str>, needle: &Option<String>| {
+            needle.as_ref().is_none_or(|n| haystack.is_some_and(|h| h.to_lowercase().contains(&n.to_lowercase())))
+        };
+
+        self.from_date.is_none_or(|from| transaction.date.date() >= from)
+            && self.to_date.is_none_or(|to| transaction.date.date() <= to)
+            && self.min_amount.is_none_or(|min| transaction.amount >= min)
+            && self.max_amount.is_none_or(|max| transaction.amount <= max)
+            && self.direction.is_none_or(|direction| transaction.debit_credit == direction)
+            && self.status.is_none_or(|status| transaction.status == status)
+            && contains(transaction.counterparty_name.as_deref(), &self.counterparty)
+            && self.counterparty_pattern.as_ref().is_none_or(|p| transaction.counterparty_name.as_ref().is_some_and(|n| p.is_match(n)))
+            && contains(Some(&transaction.description), &self.description)
+            && self.category.as_ref().is_none_or(|c| transaction.category.as_ref() == Some(c))
+            && self.tags.iter().all(|tag| transaction.tags.contains(tag))
+    }
+}
+
+/// Iterator over the transactions of a statement matching all conditions
+/// set, each method adding the [`TransactionFilter`] condition of the same
+/// name.
+#[derive(Debug, Clone)]
+pub struct TransactionQuery<'a> {
+    transactions: slice::Iter<'a, Transaction>,
+    filter: TransactionFilter,
+}
+
+impl<'a> TransactionQuery<'a> {
+    /// Query over `transactions` matching `filter`.
+    pub fn new(transactions: &'a [Transaction], filter: TransactionFilter) -> Self {
+        TransactionQuery { transactions: transactions.iter(), filter }
+    }
+
+    /// The conditions of the query.
+    pub fn filter(&self) -> &TransactionFilter {
+        &self.filter
+    }
+
+    fn with(mut self, condition: impl FnOnce(TransactionFilter) -> TransactionFilter) -> Self {
+        self.filter = condition(self.filter);
+        self
+    }
+
+    /// Booked between `from` and `to`, both inclusive.
+    pub fn date_between(self, from: NaiveDate, to: NaiveDate) -> Self {
+        self.with(|filter| filter.date_between(from, to))
+    }
+
+    /// Booked on or after `from`.
+    pub fn date_from(self, from: NaiveDate) -> Self {
+        self.with(|filter| filter.date_from(from))
+    }
+
+    /// Booked on or before `to`.
+    pub fn date_to(self, to: NaiveDate) -> Self {
+        self.with(|filter| filter.date_to(to))
+    }
+
+    /// Amount of at least `amount`, regardless of direction.
+    pub fn min_amount(self, amount: Decimal) -> Self {
+        self.with(|filter| filter.min_amount(amount))
+    }
+
+    /// Amount of at most `amount`, regardless of direction.
+    pub fn max_amount(self, amount: Decimal) -> Self {
+        self.with(|filter| filter.max_amount(amount))
+    }
+
+    /// Only transactions in the given direction.
+    pub fn direction(self, direction: DebitCredit) -> Self {
+        self.with(|filter| filter.direction(direction))
+    }
+
+    /// Only debits.
+    pub fn debit_only(self) -> Self {
+        self.direction(DebitCredit::Debit)
+    }
+
+    /// Only credits.
+    pub fn credit_only(self) -> Self {
+        self.direction(DebitCredit::Credit)
+    }
+
+    /// Only entries with the given status.
+    pub fn status(self, status: EntryStatus) -> Self {
+        self.with(|filter| filter.status(status))
+    }
+
+    /// Counterparty name containing `text`, ignoring case.
+    pub fn counterparty_contains(self, text: &str) -> Self {
+        self.with(|filter| filter.counterparty_contains(text))
+    }
+
+    /// Counterparty name in which `pattern` is found.
+    pub fn counterparty_matches(self, pattern: &Regex) -> Self {
+        self.with(|filter| filter.counterparty_matches(pattern))
+    }
+
+    /// Description containing `text`, ignoring case.
+    pub fn description_contains(self, text: &str) -> Self {
+        self.with(|filter| filter.description_contains(text))
+    }
+
+    /// Only transactions assigned to `category`.
+    pub fn category(self, category: &str) -> Self {
+        self.with(|filter| filter.category(category))
+    }
+
+    /// Only transactions carrying `tag`; repeat to require several tags.
+    pub fn tagged(self, tag: &str) -> Self {
+        self.with(|filter| filter.tagged(tag))
+    }
+}
+
+impl<'a> Iterator for TransactionQuery<'a> {
+    type Item = &'a Transaction;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let filter = &self.filter;
+        self.transactions.find(|transaction| filter.matches(transaction))
+    }
+}
+
+impl Statement {
+    /// Start a [`TransactionQuery`] over the statement's transactions.
+    pub fn transactions_where(&self) -> TransactionQuery<'_> {
+        TransactionQuery::new(&self.transactions, TransactionFilter::default())
+    }
+
+    /// Keep only the transactions matching `filter`.
+    ///
+    /// Balances are left as they are, so a filtered statement no longer
+    /// reconciles in general.
+    pub fn retain_matching(&mut self, filter: &TransactionFilter) {
+        self.transactions.retain(|tx| filter.matches(tx));
+    }
+
+    /// Add `tag` to the transactions matching `filter`; returns how many
+    /// transactions matched.
+    pub fn tag_matching(&mut self, tag: &str, filter: &TransactionFilter) -> usize {
+        self.tag_where(tag, |tx| filter.matches(tx))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::csv_format::CsvStatement;
+
+    fn statement() -> Statement {
+        let input = "Дата проводки,Сумма по дебету,Сумма по кредиту,№ документа,Назначение платежа\n\
+                     01.02.2024,1500.00,,1,Оплата ООО Acme Corp по счёту 12\n\
+                     05.02.2024,,2000.00,2,Возврат от ACME\n\
+                     10.02.2024,300.00,,3,Оплата ООО Acme Corp по счёту 15\n\
+                     20.02.2024,5000.00,,4,Аренда\n";
+        CsvStatement::from_read(&mut input.as_bytes()).unwrap().statement
+    }
+
+    #[test]
+    fn test_query_conditions() {
+        let statement = statement();
+        let references = |query: TransactionQuery| query.map(|tx| tx.reference.clone()).collect::<Vec<_>>();
+
+        assert_eq!(references(statement.transactions_where().debit_only()), ["1", "3", "4"]);
+        assert_eq!(references(statement.transactions_where().description_contains("acme")), ["1", "2", "3"]);
+        assert_eq!(
+            references(
+                statement.transactions_where()
+                    .date_between(NaiveDate::from_ymd_opt(2024, 2, 1).unwrap(), NaiveDate::from_ymd_opt(2024, 2, 10).unwrap())
+                    .min_amount(Decimal::new(1000, 0))
+                    .debit_only()
+            ),
+            ["1"]
+        );
+
+        let mut statement = statement;
+        for (tx, name) in statement.transactions.iter_mut().zip(["ООО Acme Corp", "ACME", "ООО «Acme»"]) {
+            tx.counterparty_name = Some(name.to_string());
+        }
+        assert_eq!(references(statement.transactions_where().counterparty_contains("acme")), ["1", "2", "3"]);
+        let pattern = Regex::new("^ООО.*Acme").unwrap();
+        assert_eq!(references(statement.transactions_where().counterparty_matches(&pattern)), ["1", "3"]);
+    }
+
+    #[test]
+    fn test_tags() {
+        let mut statement = statement();
+        assert_eq!(statement.tag_matching("acme", &TransactionFilter::default().description_contains("acme")), 3);
+        assert_eq!(statement.tag_where("large", |tx| tx.amount >= Decimal::new(2000, 0)), 2);
+
+        let references = |query: TransactionQuery| query.map(|tx| tx.reference.clone()).collect::<Vec<_>>();
+        assert_eq!(references(statement.transactions_where().tagged("acme").tagged("large")), ["2"]);
+
+        statement.remove_tag("acme");
+        assert_eq!(statement.transactions_where().tagged("acme").count(), 0);
+        assert_eq!(statement.transactions[3].tags.iter().collect::<Vec<_>>(), ["large"]);
+    }
+
+    #[test]
+    fn test_retain_matching() {
+        let mut statement = statement();
+        statement.retain_matching(&TransactionFilter::default().credit_only().max_amount(Decimal::new(2000, 0)));
+        assert_eq!(statement.transactions.len(), 1);
+        assert_eq!(statement.transactions[0].reference, "2");
+    }
+}
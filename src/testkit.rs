@@ -0,0 +1,209 @@
+//! Synthetic statements for benchmarks and integration tests.
+//!
+//! Available with the `testkit` feature. [`generate_statement`] builds a
+//! statement of plausible payments from a seed, so the same options always
+//! give the same statement:
+//!
+//! ```
+//! use ypbank_system::testkit::{generate_statement, GeneratorOptions};
+//!
+//! let statement = generate_statement(&GeneratorOptions { transactions: 500, ..Default::default() });
+//! assert_eq!(statement.transactions.len(), 500);
+//! assert!(statement.verify_balances().is_ok());
+//! ```
+
+use crate::types::{
+    AccountIdentifier, Balance, BalanceType, BankDate, Currency, DebitCredit, Statement, Transaction,
+};
+use chrono::{Days, NaiveDate};
+use rust_decimal::Decimal;
+
+/// Options for [`generate_statement`].
+#[derive(Debug, Clone)]
+pub struct GeneratorOptions {
+    /// Seed of the random generator.
+    pub seed: u64,
+
+    /// Number of transactions.
+    pub transactions: usize,
+
+    /// Transaction currencies; the first one is the account currency and
+    /// is used for about four in five transactions.
+    pub currencies: Vec<Currency>,
+
+    /// Counterparty names; transactions cycle through them at random.
+    pub counterparties: Vec<String>,
+
+    /// First booking date.
+    pub start_date: NaiveDate,
+
+    /// Number of days the bookings are spread over.
+    pub days: u32,
+
+    /// Whether the closing balance matches the opening balance plus the
+    /// booked transactions; otherwise it is off by a random amount.
+    pub balanced: bool,
+}
+
+impl Default for GeneratorOptions {
+    fn default() -> Self {
+        GeneratorOptions {
+            seed: 1,
+            transactions: 100,
+            currencies: vec![Currency::RUB],
+            counterparties: [
+                "ООО Ромашка",
+                "АО Северсталь",
+                "ИП Иванов И.И.",
+                "ООО Вектор",
+                "ПАО Ростелеком",
+                "ООО Лента",
+            ]
+            .iter()
+            .map(|name| name.to_string())
+            .collect(),
+            start_date: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap_or_default(),
+            days: 30,
+            balanced: true,
+        }
+    }
+}
+
+/// Generate a statement as described by `options`.
+///
+/// Transactions are in booking date order, with references `TX000001`,
+/// `TX000002`, ... Debits and credits alternate at random, amounts spread
+/// from small card payments to large transfers and are rounded to the
+/// currency's minor unit.
+pub fn generate_statement(options: &GeneratorOptions) -> Statement {
+    let mut rng = SplitMix64(options.seed);
+    let currency = options.currencies.first().copied().unwrap_or(Currency::RUB);
+
+    let mut statement = Statement::new(
+        format!("TESTKIT-{}", options.seed),
+        AccountIdentifier::from(format!("40702810{:012}", rng.below(1_000_000_000_000))),
+        currency,
+    );
+    let end = options.start_date.checked_add_days(Days::new(u64::from(options.days.saturating_sub(1))));
+    statement.from_date = Some(options.start_date.into());
+    statement.to_date = end.map(BankDate::from);
+
+    let mut dates: Vec<NaiveDate> = (0..options.transactions)
+        .map(|_| options.start_date + Days::new(rng.below(u64::from(options.days.max(1)))))
+        .collect();
+    dates.sort();
+
+    for (i, date) in dates.into_iter().enumerate() {
+        let tx_currency = match options.currencies.as_slice() {
+            [_, others @ ..] if !others.is_empty() && rng.below(5) == 0 => others[rng.below(others.len() as u64) as usize],
+            _ => currency,
+        };
+        let debit_credit = if rng.below(3) == 0 { DebitCredit::Credit } else { DebitCredit::Debit };
+        let counterparty = match options.counterparties.len() {
+            0 => None,
+            n => Some(options.counterparties[rng.below(n as u64) as usize].clone()),
+        };
+        statement.transactions.push(transaction(&mut rng, i + 1, date, tx_currency, debit_credit, counterparty));
+    }
+
+    let opening = random_amount(&mut rng, currency, 1_000_000);
+    let booked: Decimal = statement.transactions.iter()
+        .filter(|tx| tx.currency == currency)
+        .map(Transaction::signed_amount)
+        .sum();
+    let mut closing = opening + booked;
+    if !options.balanced {
+        closing += random_amount(&mut rng, currency, 1_000) + Decimal::ONE;
+    }
+    let end = end.unwrap_or(options.start_date);
+    statement.opening_balance = Some(Balance::from_signed(BalanceType::Opening, opening, currency, options.start_date));
+    statement.closing_balance = Some(Balance::from_signed(BalanceType::Closing, closing, currency, end));
+    statement
+}
+
+fn transaction(
+    rng: &mut SplitMix64,
+    number: usize,
+    date: NaiveDate,
+    currency: Currency,
+    debit_credit: DebitCredit,
+    counterparty_name: Option<String>,
+) -> Transaction {
+    const PURPOSES: [&str; 6] = [
+        "Оплата по счёту №{}",
+        "Оплата по договору поставки №{}",
+        "Возврат излишне уплаченных средств по заявлению №{}",
+        "Заработная плата, реестр №{}",
+        "Комиссия за ведение счёта, документ №{}",
+        "Оплата аренды, акт №{}",
+    ];
+
+    // Mostly small payments with the occasional large transfer
+    let scale = [10_000, 10_000, 10_000, 100_000, 1_000_000][rng.below(5) as usize];
+    let amount = random_amount(rng, currency, scale) + Decimal::ONE;
+    let purpose = PURPOSES[rng.below(PURPOSES.len() as u64) as usize].replace("{}", &rng.below(10_000).to_string());
+
+    Transaction {
+        reference: format!("TX{:06}", number),
+        date: date.into(),
+        value_date: Some(date.into()),
+        amount,
+        currency,
+        debit_credit,
+        counterparty_account: Some(AccountIdentifier::from(format!("40702810{:012}", rng.below(1_000_000_000_000)))),
+        counterparty_name,
+        description: purpose,
+        ..Transaction::default()
+    }
+}
+
+/// Random amount below `units`, rounded to the currency's minor unit.
+fn random_amount(rng: &mut SplitMix64, currency: Currency, units: u64) -> Decimal {
+    currency.round(Decimal::new(rng.below(units * 100) as i64, 2))
+}
+
+/// SplitMix64 generator: small, fast and identical on every platform.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Random number in `0..bound`; `bound` must not be zero.
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next() % bound
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_statement() {
+        let options = GeneratorOptions {
+            transactions: 200,
+            currencies: vec![Currency::EUR, Currency::USD],
+            days: 60,
+            ..Default::default()
+        };
+        let statement = generate_statement(&options);
+
+        assert_eq!(statement.transactions.len(), 200);
+        assert_eq!(statement.currency, Currency::EUR);
+        assert_eq!(statement.currencies(), [Currency::EUR, Currency::USD]);
+        assert!(statement.transactions.windows(2).all(|pair| pair[0].date <= pair[1].date));
+        assert!(statement.transactions.iter().all(|tx| tx.amount.scale() == 2 && tx.amount > Decimal::ZERO));
+        assert!(statement.verify_balances().is_ok());
+        assert_eq!(generate_statement(&options), statement);
+
+        let unbalanced = generate_statement(&GeneratorOptions { balanced: false, ..options.clone() });
+        assert!(unbalanced.verify_balances().is_err());
+        assert_ne!(generate_statement(&GeneratorOptions { seed: 2, ..options }), statement);
+    }
+}
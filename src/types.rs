@@ -1,8 +1,14 @@
 //! Common types used across different financial formats.
 
-use chrono::NaiveDate;
-use rust_decimal::Decimal;
+use crate::error::{Error, Result as CrateResult, RuleId, ValidationError};
+use chrono::{Datelike, Months, NaiveDate, NaiveDateTime, NaiveTime};
+use rust_decimal::{Decimal, RoundingStrategy};
 use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::hash::Hash;
+use std::ops::RangeInclusive;
 use std::str::FromStr;
 
 /// Represents a financial transaction.
@@ -11,38 +17,662 @@ pub struct Transaction {
     /// Unique transaction reference.
     pub reference: String,
 
-    /// Date of the transaction.
-    pub date: NaiveDate,
+    /// Date of the transaction, with time of day when the bank reports it.
+    pub date: BankDate,
 
-    /// Valuation date (value date).
-    pub value_date: Option<NaiveDate>,
+    /// Valuation date (value date), with time of day when the bank reports it.
+    pub value_date: Option<BankDate>,
 
     /// Transaction amount.
     pub amount: Decimal,
 
     /// Currency code (e.g., USD, EUR, RUB).
-    pub currency: String,
+    pub currency: Currency,
 
     /// Debit (D) or Credit (C) indicator.
     pub debit_credit: DebitCredit,
 
-    /// Account identification.
+    /// Account holder's account the transaction was booked to, when given
+    /// per transaction and different from the statement's (e.g. the debit
+    /// account of a CSV debit row, or the own side of camt.053 related parties).
     pub account: Option<String>,
 
     /// Counterparty account.
-    pub counterparty_account: Option<String>,
+    pub counterparty_account: Option<AccountIdentifier>,
 
     /// Counterparty name.
     pub counterparty_name: Option<String>,
 
-    /// Bank identifier (BIC).
-    pub bank_identifier: Option<String>,
+    /// Counterparty postal address.
+    pub counterparty_address: Option<PostalAddress>,
+
+    /// Ultimate debtor or creditor on whose behalf the counterparty acted.
+    pub ultimate_counterparty_name: Option<String>,
+
+    /// Counterparty bank identifier.
+    pub bank_identifier: Option<BankIdentifier>,
 
     /// Transaction description/purpose.
     pub description: String,
 
     /// Additional information.
     pub additional_info: Option<String>,
+
+    /// Underlying transactions of a batch booking (empty for single entries).
+    #[serde(default)]
+    pub details: Vec<TransactionDetail>,
+
+    /// Charges applied to the transaction.
+    #[serde(default)]
+    pub charges: Vec<Charge>,
+
+    /// Originally instructed amount (before any currency exchange).
+    pub original_amount: Option<Decimal>,
+
+    /// Currency of the originally instructed amount.
+    pub original_currency: Option<Currency>,
+
+    /// Exchange rate applied between the original and booked currency.
+    pub exchange_rate: Option<Decimal>,
+
+    /// Booked amount converted into another currency for information,
+    /// e.g. the account owner's reporting currency (camt.053 `CntrValAmt`).
+    pub counter_value_amount: Option<Decimal>,
+
+    /// Currency of the counter value amount.
+    pub counter_value_currency: Option<Currency>,
+
+    /// Exchange rate between the booked currency and the counter value.
+    pub counter_value_rate: Option<Decimal>,
+
+    /// Structured remittance information (creditor reference, referred documents).
+    pub structured_remittance: Option<StructuredRemittance>,
+
+    /// Booking status of the entry.
+    #[serde(default)]
+    pub status: EntryStatus,
+
+    /// Reason why the payment was returned, for returned transactions.
+    pub return_reason: Option<ReturnReason>,
+
+    /// ISO 20022 purpose code (e.g., SALA salary, SUPP supplier payment).
+    pub purpose_code: Option<String>,
+
+    /// SWIFT transaction type of the MT940 `:61:` line (e.g., NCHG for
+    /// charges); `None` for ordinary transfers, written as NTRF.
+    pub transaction_type: Option<String>,
+
+    /// When the booked funds become available for use.
+    #[serde(default)]
+    pub funds_availability: Vec<FundsAvailability>,
+
+    /// Account balance after this transaction, as reported by the bank;
+    /// negative for a debit balance.
+    pub running_balance: Option<Decimal>,
+
+    /// User-assigned category, e.g. from [`crate::categorize`] rules.
+    pub category: Option<String>,
+
+    /// User-assigned labels such as "fees" or "intercompany", see [`Statement::tag_where`].
+    #[serde(default)]
+    pub tags: BTreeSet<String>,
+
+    /// Bank and payment chain identifiers used for reconciliation.
+    #[serde(default)]
+    pub refs: TransactionRefs,
+
+    /// Original text of the transaction in the format it was read from.
+    pub source_raw: Option<SourceRaw>,
+}
+
+/// An empty booked credit of zero in no currency (`XXX`) on 1970-01-01,
+/// to fill in the fields a transaction built in code doesn't set.
+///
+/// ```
+/// use rust_decimal::Decimal;
+/// use ypbank_system::{types::Currency, Transaction};
+///
+/// let fee = Transaction { reference: "FEE".into(), amount: Decimal::TEN, currency: Currency::EUR, ..Transaction::default() };
+/// assert!(fee.counterparty_name.is_none() && fee.details.is_empty());
+/// ```
+impl Default for Transaction {
+    fn default() -> Self {
+        Transaction {
+            reference: String::new(),
+            date: NaiveDate::default().into(),
+            value_date: None,
+            amount: Decimal::ZERO,
+            currency: Currency::XXX,
+            debit_credit: DebitCredit::Credit,
+            account: None,
+            counterparty_account: None,
+            counterparty_name: None,
+            counterparty_address: None,
+            ultimate_counterparty_name: None,
+            bank_identifier: None,
+            description: String::new(),
+            additional_info: None,
+            details: Vec::new(),
+            charges: Vec::new(),
+            original_amount: None,
+            original_currency: None,
+            exchange_rate: None,
+            counter_value_amount: None,
+            counter_value_currency: None,
+            counter_value_rate: None,
+            structured_remittance: None,
+            status: EntryStatus::Booked,
+            return_reason: None,
+            purpose_code: None,
+            transaction_type: None,
+            funds_availability: Vec::new(),
+            running_balance: None,
+            category: None,
+            tags: BTreeSet::new(),
+            refs: TransactionRefs::default(),
+            source_raw: None,
+        }
+    }
+}
+
+/// Identifiers of a transaction assigned along the payment chain.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TransactionRefs {
+    /// Unique reference assigned by the account servicing bank (AcctSvcrRef).
+    pub account_servicer_reference: Option<String>,
+
+    /// Instruction identification assigned by the instructing party (InstrId).
+    pub instruction_id: Option<String>,
+
+    /// End-to-end identification assigned by the initiating party (EndToEndId).
+    pub end_to_end_id: Option<String>,
+
+    /// Identification of the direct debit mandate (MndtId).
+    pub mandate_id: Option<String>,
+}
+
+/// Format a [`SourceRaw`] attachment was read from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SourceFormat {
+    /// The `:61:` statement line and `:86:` information lines.
+    Mt940,
+    /// The `Ntry` element.
+    Camt053,
+}
+
+/// Original text of a transaction, attached by the MT940 and CAMT.053
+/// readers and carried along by conversions and the JSON format.
+///
+/// The MT940 writer reproduces the original lines as long as the fields
+/// they were read into are unchanged, so MT940 → CAMT.053 → MT940 keeps
+/// the original `:61:` and `:86:` content. CAMT.053 entries are always
+/// written from the fields.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceRaw {
+    /// Format of the text.
+    pub format: SourceFormat,
+
+    /// The original text, lines separated by `\n`.
+    pub text: String,
+
+    /// Digest of the fields the text was read into, see
+    /// [`Transaction::attach_source_raw`].
+    pub digest: u64,
+}
+
+/// Return information of a returned payment (e.g. rejected direct debit).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReturnReason {
+    /// ISO return reason code (e.g., AC04 closed account, MD06 refund request).
+    pub code: Option<String>,
+
+    /// Additional return information provided by the bank.
+    #[serde(default)]
+    pub additional_info: Vec<String>,
+}
+
+/// Status of a statement entry.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum EntryStatus {
+    /// Booked on the account.
+    #[default]
+    Booked,
+    /// Pending, not yet booked.
+    Pending,
+    /// Informational entry, not affecting the balance.
+    Information,
+    /// Booked reversal of an earlier entry (ISO 20022 `RvslInd`, MT940
+    /// `RC`/`RD` marks); `debit_credit` holds the direction of the reversal
+    /// itself.
+    Reversed,
+}
+
+impl FromStr for EntryStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_uppercase().as_str() {
+            "BOOK" | "BOOKED" => Ok(EntryStatus::Booked),
+            // FUTR, booking on a future date, is in the code list from camt.053.001.08 on
+            "PDNG" | "FUTR" | "PENDING" => Ok(EntryStatus::Pending),
+            "INFO" | "INFORMATION" => Ok(EntryStatus::Information),
+            "RVSL" | "REVERSED" => Ok(EntryStatus::Reversed),
+            _ => Err(format!("Invalid entry status: {}", s)),
+        }
+    }
+}
+
+impl EntryStatus {
+    /// Convert to ISO 20022 format; reversals are booked entries flagged
+    /// with `RvslInd`.
+    pub fn to_iso_format(&self) -> &'static str {
+        match self {
+            EntryStatus::Booked | EntryStatus::Reversed => "BOOK",
+            EntryStatus::Pending => "PDNG",
+            EntryStatus::Information => "INFO",
+        }
+    }
+
+    /// Whether the entry has been booked and so moves the account balance.
+    pub fn is_booked(&self) -> bool {
+        matches!(self, EntryStatus::Booked | EntryStatus::Reversed)
+    }
+}
+
+/// Structured remittance information of a payment.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StructuredRemittance {
+    /// Creditor reference type code (e.g., SCOR).
+    pub reference_type: Option<String>,
+
+    /// Creditor reference (e.g., RF18539007547034).
+    pub reference: Option<String>,
+
+    /// Documents (invoices, credit notes) the payment refers to.
+    #[serde(default)]
+    pub referred_documents: Vec<ReferredDocument>,
+}
+
+/// Document referred to by a payment's structured remittance information.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReferredDocument {
+    /// Document type code (e.g., CINV for commercial invoice).
+    pub document_type: Option<String>,
+
+    /// Document number.
+    pub number: Option<String>,
+
+    /// Date of the document.
+    pub date: Option<NaiveDate>,
+}
+
+/// Portion of an entry amount that becomes available on a given day.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FundsAvailability {
+    /// Number of days after booking until the funds are available.
+    pub days: Option<i32>,
+
+    /// Actual date on which the funds are available.
+    pub date: Option<NaiveDate>,
+
+    /// Available amount, in the entry currency.
+    pub amount: Decimal,
+
+    /// Whether the available amount is a credit or a debit.
+    pub debit_credit: DebitCredit,
+}
+
+/// Charge applied by a bank to a transaction.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Charge {
+    /// Charge amount.
+    pub amount: Decimal,
+
+    /// Currency code of the charge.
+    pub currency: Currency,
+
+    /// Whether the charge is debited or credited.
+    pub debit_credit: DebitCredit,
+
+    /// Charge type code (e.g., BRKF, COMM).
+    pub charge_type: Option<String>,
+
+    /// Charge bearer (e.g., DEBT, CRED, SHAR, SLEV).
+    pub bearer: Option<String>,
+}
+
+/// A single underlying transaction of a batched statement entry.
+///
+/// CAMT.053 reports batch bookings as one `Ntry` containing several `TxDtls`;
+/// each of them is represented by one `TransactionDetail`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransactionDetail {
+    /// Reference of the underlying transaction (e.g. EndToEndId).
+    pub reference: Option<String>,
+
+    /// Amount of the underlying transaction, if reported.
+    pub amount: Option<Decimal>,
+
+    /// Currency of the underlying transaction amount.
+    pub currency: Option<Currency>,
+
+    /// Counterparty account.
+    pub counterparty_account: Option<AccountIdentifier>,
+
+    /// Counterparty name.
+    pub counterparty_name: Option<String>,
+
+    /// Counterparty postal address.
+    pub counterparty_address: Option<PostalAddress>,
+
+    /// Ultimate debtor or creditor on whose behalf the counterparty acted.
+    pub ultimate_counterparty_name: Option<String>,
+
+    /// Counterparty bank identifier.
+    pub bank_identifier: Option<BankIdentifier>,
+
+    /// Remittance information.
+    pub description: String,
+
+    /// Additional information.
+    pub additional_info: Option<String>,
+}
+
+impl Transaction {
+    /// Amount with the sign of its direction: negative for debits.
+    pub fn signed_amount(&self) -> Decimal {
+        match self.debit_credit {
+            DebitCredit::Debit => -self.amount,
+            DebitCredit::Credit => self.amount,
+        }
+    }
+
+    /// Whether this transaction is a returned payment.
+    pub fn is_return(&self) -> bool {
+        self.return_reason.is_some()
+    }
+
+    /// Expand a batched entry into one transaction per underlying detail.
+    ///
+    /// Formats that cannot represent batches (MT940, CSV) use this to fan out
+    /// sub-transactions. Details without an amount get what the others leave
+    /// of the entry's amount: the one such detail as a transaction of its
+    /// own, several as one transaction with the entry's fields. Entries whose
+    /// details carry no amount at all are returned unchanged, see
+    /// [`Transaction::is_expandable`].
+    pub fn expand_details(&self) -> Vec<Transaction> {
+        if !self.is_expandable() {
+            return vec![self.clone()];
+        }
+
+        let known: Decimal = self.details.iter().filter_map(|detail| detail.amount).sum();
+        let remainder = self.amount - known;
+        let missing = self.details.iter().filter(|detail| detail.amount.is_none()).count();
+        let mut expanded = Vec::new();
+        for (i, detail) in self.details.iter().enumerate() {
+            let amount = match detail.amount {
+                Some(amount) => amount,
+                None if remainder <= Decimal::ZERO => continue,
+                None if missing == 1 => remainder,
+                None => {
+                    // The first of several without an amount stands for them all
+                    if self.details[..i].iter().all(|detail| detail.amount.is_some()) {
+                        let mut rest = self.clone();
+                        rest.reference = format!("{}/{}", self.reference, i + 1);
+                        rest.amount = remainder;
+                        rest.details.clear();
+                        if i != 0 {
+                            rest.charges.clear();
+                        }
+                        rest.source_raw = None;
+                        expanded.push(rest);
+                    }
+                    continue;
+                }
+            };
+            expanded.push(self.detail_transaction(i, detail, amount));
+        }
+        expanded
+    }
+
+    /// Whether [`Transaction::expand_details`] splits the entry: it has
+    /// details, at least one of them with an amount.
+    pub fn is_expandable(&self) -> bool {
+        self.details.iter().any(|detail| detail.amount.is_some())
+    }
+
+    /// The transaction of the `i`th detail of the entry, for `amount`.
+    fn detail_transaction(&self, i: usize, detail: &TransactionDetail, amount: Decimal) -> Transaction {
+        Transaction {
+            reference: detail
+                .reference
+                .clone()
+                .unwrap_or_else(|| format!("{}/{}", self.reference, i + 1)),
+            date: self.date,
+            value_date: self.value_date,
+            amount,
+            currency: detail.currency.unwrap_or(self.currency),
+            debit_credit: self.debit_credit,
+            account: self.account.clone(),
+            counterparty_account: detail.counterparty_account.clone(),
+            counterparty_name: detail.counterparty_name.clone(),
+            counterparty_address: detail.counterparty_address.clone(),
+            ultimate_counterparty_name: detail.ultimate_counterparty_name.clone(),
+            bank_identifier: detail.bank_identifier.clone(),
+            description: if detail.description.is_empty() {
+                self.description.clone()
+            } else {
+                detail.description.clone()
+            },
+            additional_info: detail.additional_info.clone(),
+            details: Vec::new(),
+            // Entry-level charges stay with the first sub-transaction
+            charges: if i == 0 { self.charges.clone() } else { Vec::new() },
+            original_amount: None,
+            original_currency: None,
+            exchange_rate: None,
+            counter_value_amount: None,
+            counter_value_currency: None,
+            counter_value_rate: None,
+            structured_remittance: None,
+            status: self.status,
+            return_reason: None,
+            purpose_code: self.purpose_code.clone(),
+            transaction_type: self.transaction_type.clone(),
+            funds_availability: Vec::new(),
+            running_balance: None,
+            category: self.category.clone(),
+            tags: self.tags.clone(),
+            refs: TransactionRefs {
+                account_servicer_reference: self.refs.account_servicer_reference.clone(),
+                end_to_end_id: detail.reference.clone(),
+                ..TransactionRefs::default()
+            },
+            source_raw: None,
+        }
+    }
+
+    /// Hash of the fields identifying a booking: booking date, amount,
+    /// direction, reference and description.
+    ///
+    /// Amounts compare by value (`100.0` equals `100.00`), references and
+    /// descriptions ignore case, punctuation and spacing; a zero amount has
+    /// no direction. The hash is
+    /// stable across runs and platforms, so it can be stored. Two exports of
+    /// the same entry share a fingerprint, which is how
+    /// [`Statement::merge`] recognises overlapping statements.
+    pub fn fingerprint(&self) -> u64 {
+        // Zero is the same signed amount either way, see canonical_key
+        let direction = if self.amount.is_zero() { DebitCredit::Credit } else { self.debit_credit };
+        let key = format!(
+            "{}|{}|{}|{}|{}",
+            self.date,
+            self.amount.normalize(),
+            direction.to_iso_format(),
+            normalize_text(&self.reference),
+            normalize_text(&self.description),
+        );
+        fnv1a(key.as_bytes())
+    }
+
+    /// Attach the original `text` read from `format`, with a digest of the
+    /// fields as they are now, see [`Transaction::unchanged_source_raw`].
+    pub fn attach_source_raw(&mut self, format: SourceFormat, text: impl Into<String>) {
+        self.source_raw = Some(SourceRaw { format, text: text.into(), digest: self.source_digest(format) });
+    }
+
+    /// Original text in `format`, if the transaction was read from it and
+    /// the fields that format carries haven't changed since.
+    pub fn unchanged_source_raw(&self, format: SourceFormat) -> Option<&str> {
+        self.source_raw.as_ref()
+            .filter(|raw| raw.format == format && raw.digest == self.source_digest(format))
+            .map(|raw| raw.text.as_str())
+    }
+
+    /// Digest of the fields written in `format`.
+    fn source_digest(&self, format: SourceFormat) -> u64 {
+        match format {
+            SourceFormat::Mt940 => {
+                let key = format!(
+                    "{}|{:?}|{}|{}|{:?}|{:?}|{}|{}",
+                    self.date, self.value_date, self.amount, self.currency, self.debit_credit,
+                    self.status, self.reference, self.description,
+                );
+                fnv1a(key.as_bytes())
+            }
+            // Every field but the user-assigned ones has an element
+            SourceFormat::Camt053 => {
+                let mut content = self.clone();
+                content.source_raw = None;
+                content.running_balance = None;
+                content.category = None;
+                content.tags.clear();
+                fnv1a(&serde_json::to_vec(&content).unwrap_or_default())
+            }
+        }
+    }
+
+    /// Key ordering transactions canonically, the order shared by
+    /// [`Statement::sort_transactions`], [`Statement::merge`] and the
+    /// comparer: booking date, booking time (entries without one first),
+    /// value date (falling back to the booking date), signed amount (largest
+    /// debit first), reference and description.
+    ///
+    /// Transactions with equal keys also share a [`Transaction::fingerprint`].
+    pub fn canonical_key(&self) -> CanonicalKey<'_> {
+        CanonicalKey {
+            date: self.date,
+            value_date: self.value_date.unwrap_or(self.date),
+            signed_amount: self.signed_amount(),
+            reference: &self.reference,
+            description: &self.description,
+        }
+    }
+
+    /// Compare two transactions by their [`Transaction::canonical_key`].
+    pub fn canonical_cmp(&self, other: &Transaction) -> Ordering {
+        self.canonical_key().cmp(&other.canonical_key())
+    }
+}
+
+/// Canonical sort key of a transaction, see [`Transaction::canonical_key`].
+///
+/// # Examples
+///
+/// ```
+/// use ypbank_system::csv_format::CsvStatement;
+///
+/// let input = "Дата проводки,Сумма по дебету,Сумма по кредиту,№ документа\n\
+///              21.02.2024,,50.00,2\n\
+///              20.02.2024,20.00,,1\n";
+/// let mut statement = CsvStatement::from_read(&mut input.as_bytes())?.statement;
+/// statement.transactions.sort_by(|a, b| a.canonical_cmp(b));
+/// assert_eq!(statement.transactions[0].reference, "1");
+/// assert!(statement.transactions[0].canonical_key() < statement.transactions[1].canonical_key());
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct CanonicalKey<'a> {
+    date: BankDate,
+    value_date: BankDate,
+    signed_amount: Decimal,
+    reference: &'a str,
+    description: &'a str,
+}
+
+/// Interleave two transaction lists in canonical order, keeping the order
+/// within each list; on ties `first` leads.
+fn merge_canonical(first: Vec<Transaction>, second: Vec<Transaction>) -> Vec<Transaction> {
+    let mut merged = Vec::with_capacity(first.len() + second.len());
+    let (mut first, mut second) = (first.into_iter().peekable(), second.into_iter().peekable());
+    loop {
+        let take_second = match (first.peek(), second.peek()) {
+            (Some(a), Some(b)) => b.canonical_cmp(a) == Ordering::Less,
+            (Some(_), None) => false,
+            (None, Some(_)) => true,
+            (None, None) => break,
+        };
+        merged.extend(if take_second { second.next() } else { first.next() });
+    }
+    merged
+}
+
+/// Lower-case words of `text`, without punctuation, joined by single spaces.
+pub(crate) fn normalize_text(text: &str) -> String {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// 64-bit FNV-1a hash, stable across Rust versions and platforms.
+pub(crate) fn fnv1a(data: &[u8]) -> u64 {
+    fnv1a_extend(0xcbf2_9ce4_8422_2325, data)
+}
+
+/// Continue a 64-bit FNV-1a `hash` over more `data`.
+pub(crate) fn fnv1a_extend(hash: u64, data: &[u8]) -> u64 {
+    data.iter().fold(hash, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Parse an amount straight into a [`Decimal`], without building a
+/// normalized copy of `text`.
+///
+/// Either `.` or `decimal_separator` marks the fraction; spaces, no-break
+/// spaces and `thousands_separator` are skipped, and a sign may lead.
+pub(crate) fn parse_decimal(text: &str, decimal_separator: char, thousands_separator: Option<char>) -> Option<Decimal> {
+    let mut mantissa: i128 = 0;
+    let mut digits = 0;
+    // Digits after the separator, once there was one
+    let mut scale: Option<u32> = None;
+    let mut negative = false;
+    let mut signed = false;
+    for c in text.chars() {
+        match c {
+            '0'..='9' => {
+                mantissa = mantissa.checked_mul(10)?.checked_add(i128::from(c as u8 - b'0'))?;
+                digits += 1;
+                if let Some(ref mut scale) = scale {
+                    *scale += 1;
+                }
+            }
+            ' ' | '\u{a0}' => {}
+            c if Some(c) == thousands_separator && scale.is_none() => {}
+            c if (c == '.' || c == decimal_separator) && scale.is_none() => scale = Some(0),
+            '-' | '+' if digits == 0 && scale.is_none() && !signed => {
+                negative = c == '-';
+                signed = true;
+            }
+            _ => return None,
+        }
+    }
+    if digits == 0 {
+        return None;
+    }
+    Decimal::try_from_i128_with_scale(if negative { -mantissa } else { mantissa }, scale.unwrap_or(0)).ok()
 }
 
 /// Debit/Credit indicator.
@@ -91,6 +721,259 @@ impl DebitCredit {
     }
 }
 
+impl fmt::Display for DebitCredit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.to_string())
+    }
+}
+
+/// ISO 4217 alpha-3 currency code.
+///
+/// Only codes of the ISO 4217 list are accepted; lowercase input is
+/// normalized. Serializes as the plain code.
+///
+/// # Examples
+///
+/// ```
+/// use ypbank_system::types::Currency;
+///
+/// let rub: Currency = "rub".parse().unwrap();
+/// assert_eq!(rub, "RUB");
+/// assert_eq!(rub.minor_units(), Some(2));
+/// assert!("RUR".parse::<Currency>().is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Currency([u8; 3]);
+
+/// ISO 4217 codes with their number of minor units (`None` where not applicable).
+///
+/// Taken from List One of the maintenance agency, SIX, up to the 2025
+/// introduction of XCG; withdrawn codes such as HRK and ANG are kept so
+/// older statements still read. Update it from
+/// <https://www.six-group.com/en/products-services/financial-information/data-standards.html>
+/// when an amendment adds a code or changes its minor units.
+const ISO_4217: &[(&str, Option<u32>)] = &[
+    ("AED", Some(2)), ("AFN", Some(2)), ("ALL", Some(2)), ("AMD", Some(2)), ("ANG", Some(2)),
+    ("AOA", Some(2)), ("ARS", Some(2)), ("AUD", Some(2)), ("AWG", Some(2)), ("AZN", Some(2)),
+    ("BAM", Some(2)), ("BBD", Some(2)), ("BDT", Some(2)), ("BGN", Some(2)), ("BHD", Some(3)),
+    ("BIF", Some(0)), ("BMD", Some(2)), ("BND", Some(2)), ("BOB", Some(2)), ("BOV", Some(2)),
+    ("BRL", Some(2)), ("BSD", Some(2)), ("BTN", Some(2)), ("BWP", Some(2)), ("BYN", Some(2)),
+    ("BZD", Some(2)), ("CAD", Some(2)), ("CDF", Some(2)), ("CHE", Some(2)), ("CHF", Some(2)),
+    ("CHW", Some(2)), ("CLF", Some(4)), ("CLP", Some(0)), ("CNY", Some(2)), ("COP", Some(2)),
+    ("COU", Some(2)), ("CRC", Some(2)), ("CUC", Some(2)), ("CUP", Some(2)), ("CVE", Some(2)),
+    ("CZK", Some(2)), ("DJF", Some(0)), ("DKK", Some(2)), ("DOP", Some(2)), ("DZD", Some(2)),
+    ("EGP", Some(2)), ("ERN", Some(2)), ("ETB", Some(2)), ("EUR", Some(2)), ("FJD", Some(2)),
+    ("FKP", Some(2)), ("GBP", Some(2)), ("GEL", Some(2)), ("GHS", Some(2)), ("GIP", Some(2)),
+    ("GMD", Some(2)), ("GNF", Some(0)), ("GTQ", Some(2)), ("GYD", Some(2)), ("HKD", Some(2)),
+    ("HNL", Some(2)), ("HRK", Some(2)), ("HTG", Some(2)), ("HUF", Some(2)), ("IDR", Some(2)),
+    ("ILS", Some(2)), ("INR", Some(2)), ("IQD", Some(3)), ("IRR", Some(2)), ("ISK", Some(0)),
+    ("JMD", Some(2)), ("JOD", Some(3)), ("JPY", Some(0)), ("KES", Some(2)), ("KGS", Some(2)),
+    ("KHR", Some(2)), ("KMF", Some(0)), ("KPW", Some(2)), ("KRW", Some(0)), ("KWD", Some(3)),
+    ("KYD", Some(2)), ("KZT", Some(2)), ("LAK", Some(2)), ("LBP", Some(2)), ("LKR", Some(2)),
+    ("LRD", Some(2)), ("LSL", Some(2)), ("LYD", Some(3)), ("MAD", Some(2)), ("MDL", Some(2)),
+    ("MGA", Some(2)), ("MKD", Some(2)), ("MMK", Some(2)), ("MNT", Some(2)), ("MOP", Some(2)),
+    ("MRU", Some(2)), ("MUR", Some(2)), ("MVR", Some(2)), ("MWK", Some(2)), ("MXN", Some(2)),
+    ("MXV", Some(2)), ("MYR", Some(2)), ("MZN", Some(2)), ("NAD", Some(2)), ("NGN", Some(2)),
+    ("NIO", Some(2)), ("NOK", Some(2)), ("NPR", Some(2)), ("NZD", Some(2)), ("OMR", Some(3)),
+    ("PAB", Some(2)), ("PEN", Some(2)), ("PGK", Some(2)), ("PHP", Some(2)), ("PKR", Some(2)),
+    ("PLN", Some(2)), ("PYG", Some(0)), ("QAR", Some(2)), ("RON", Some(2)), ("RSD", Some(2)),
+    ("RUB", Some(2)), ("RWF", Some(0)), ("SAR", Some(2)), ("SBD", Some(2)), ("SCR", Some(2)),
+    ("SDG", Some(2)), ("SEK", Some(2)), ("SGD", Some(2)), ("SHP", Some(2)), ("SLE", Some(2)),
+    ("SLL", Some(2)), ("SOS", Some(2)), ("SRD", Some(2)), ("SSP", Some(2)), ("STN", Some(2)),
+    ("SVC", Some(2)), ("SYP", Some(2)), ("SZL", Some(2)), ("THB", Some(2)), ("TJS", Some(2)),
+    ("TMT", Some(2)), ("TND", Some(3)), ("TOP", Some(2)), ("TRY", Some(2)), ("TTD", Some(2)),
+    ("TWD", Some(2)), ("TZS", Some(2)), ("UAH", Some(2)), ("UGX", Some(0)), ("USD", Some(2)),
+    ("USN", Some(2)), ("UYI", Some(0)), ("UYU", Some(2)), ("UYW", Some(4)), ("UZS", Some(2)),
+    ("VED", Some(2)), ("VES", Some(2)), ("VND", Some(0)), ("VUV", Some(0)), ("WST", Some(2)),
+    ("XAF", Some(0)), ("XAG", None), ("XAU", None), ("XBA", None), ("XBB", None),
+    ("XBC", None), ("XBD", None), ("XCD", Some(2)), ("XCG", Some(2)), ("XDR", None),
+    ("XOF", Some(0)), ("XPD", None), ("XPF", Some(0)), ("XPT", None), ("XSU", None),
+    ("XTS", None), ("XUA", None), ("XXX", None), ("YER", Some(2)), ("ZAR", Some(2)),
+    ("ZMW", Some(2)), ("ZWG", Some(2)), ("ZWL", Some(2)),
+];
+
+impl Currency {
+    /// Russian ruble.
+    pub const RUB: Currency = Currency(*b"RUB");
+    /// US dollar.
+    pub const USD: Currency = Currency(*b"USD");
+    /// Euro.
+    pub const EUR: Currency = Currency(*b"EUR");
+    /// Chinese yuan.
+    pub const CNY: Currency = Currency(*b"CNY");
+    /// No currency involved, the ISO 4217 code for transactions without one.
+    pub const XXX: Currency = Currency(*b"XXX");
+
+    /// The alpha-3 code.
+    pub fn as_str(&self) -> &str {
+        // Only ASCII codes from the ISO 4217 list are ever stored
+        std::str::from_utf8(&self.0).unwrap_or_default()
+    }
+
+    /// Number of decimal places of the currency's minor unit (2 for RUB,
+    /// 0 for JPY), or `None` for units such as gold (XAU).
+    pub fn minor_units(&self) -> Option<u32> {
+        ISO_4217.iter().find(|(code, _)| *code == self.as_str()).and_then(|(_, units)| *units)
+    }
+
+    /// `amount` rounded half away from zero to the minor unit and written
+    /// with exactly that many decimal places; unchanged for currencies
+    /// without a minor unit.
+    ///
+    /// ```
+    /// use rust_decimal::Decimal;
+    /// use ypbank_system::types::Currency;
+    ///
+    /// assert_eq!(Currency::EUR.round(Decimal::new(100005, 3)).to_string(), "100.01");
+    /// assert_eq!(Currency::EUR.round(Decimal::new(1005, 1)).to_string(), "100.50");
+    /// assert_eq!("JPY".parse::<Currency>().unwrap().round(Decimal::new(1005, 1)).to_string(), "101");
+    /// ```
+    pub fn round(&self, amount: Decimal) -> Decimal {
+        match self.minor_units() {
+            Some(units) => {
+                let mut rounded = amount.round_dp_with_strategy(units, RoundingStrategy::MidpointAwayFromZero);
+                rounded.rescale(units);
+                rounded
+            }
+            None => amount,
+        }
+    }
+}
+
+impl FromStr for Currency {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let code = s.trim().to_ascii_uppercase();
+        if !ISO_4217.iter().any(|(known, _)| *known == code) {
+            return Err(format!("Invalid currency code: {}", s));
+        }
+        let mut bytes = [0; 3];
+        bytes.copy_from_slice(code.as_bytes());
+        Ok(Currency(bytes))
+    }
+}
+
+impl TryFrom<String> for Currency {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Currency> for String {
+    fn from(currency: Currency) -> Self {
+        currency.as_str().to_string()
+    }
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl PartialEq<str> for Currency {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for Currency {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+/// A date as banks report it: a calendar day, with the time of day when
+/// the source carries one.
+///
+/// Dates order by day, a day without time first.
+///
+/// # Examples
+///
+/// ```
+/// use chrono::NaiveDate;
+/// use ypbank_system::types::BankDate;
+///
+/// let booked: BankDate = "2024-01-15T09:30:15".parse().unwrap();
+/// assert_eq!(booked.date(), NaiveDate::from_ymd_opt(2024, 1, 15).unwrap());
+/// assert_eq!(booked.to_string(), "2024-01-15T09:30:15");
+/// assert_eq!("2024-01-15".parse::<BankDate>().unwrap().time(), None);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct BankDate {
+    date: NaiveDate,
+    time: Option<NaiveTime>,
+}
+
+impl BankDate {
+    /// The calendar day.
+    pub fn date(&self) -> NaiveDate {
+        self.date
+    }
+
+    /// The time of day, when known.
+    pub fn time(&self) -> Option<NaiveTime> {
+        self.time
+    }
+
+    /// The day and time of day, when the time is known.
+    pub fn datetime(&self) -> Option<NaiveDateTime> {
+        self.time.map(|time| self.date.and_time(time))
+    }
+}
+
+impl From<NaiveDate> for BankDate {
+    fn from(date: NaiveDate) -> Self {
+        BankDate { date, time: None }
+    }
+}
+
+impl From<NaiveDateTime> for BankDate {
+    fn from(datetime: NaiveDateTime) -> Self {
+        BankDate { date: datetime.date(), time: Some(datetime.time()) }
+    }
+}
+
+impl FromStr for BankDate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%.f")
+            .map(BankDate::from)
+            .or_else(|_| NaiveDate::parse_from_str(s, "%Y-%m-%d").map(BankDate::from))
+            .map_err(|_| format!("Invalid date: {}", s))
+    }
+}
+
+impl TryFrom<String> for BankDate {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<BankDate> for String {
+    fn from(date: BankDate) -> Self {
+        date.to_string()
+    }
+}
+
+impl fmt::Display for BankDate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.datetime() {
+            Some(datetime) => write!(f, "{}", datetime.format("%Y-%m-%dT%H:%M:%S%.f")),
+            None => write!(f, "{}", self.date.format("%Y-%m-%d")),
+        }
+    }
+}
+
 /// Account statement balance information.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Balance {
@@ -101,7 +984,7 @@ pub struct Balance {
     pub amount: Decimal,
 
     /// Currency code.
-    pub currency: String,
+    pub currency: Currency,
 
     /// Debit/Credit indicator.
     pub debit_credit: DebitCredit,
@@ -110,6 +993,57 @@ pub struct Balance {
     pub date: NaiveDate,
 }
 
+impl Balance {
+    /// Balance from an amount that is negative for a debit balance.
+    pub fn from_signed(balance_type: BalanceType, amount: Decimal, currency: Currency, date: NaiveDate) -> Self {
+        Balance {
+            balance_type,
+            amount: amount.abs(),
+            currency,
+            debit_credit: if amount.is_sign_negative() { DebitCredit::Debit } else { DebitCredit::Credit },
+            date,
+        }
+    }
+
+    /// Amount with the sign of its direction: negative for a debit balance.
+    pub fn signed_amount(&self) -> Decimal {
+        match self.debit_credit {
+            DebitCredit::Debit => -self.amount,
+            DebitCredit::Credit => self.amount,
+        }
+    }
+}
+
+/// Closing balance that doesn't match the opening balance plus the booked
+/// transactions, as found by [`Statement::verify_balances`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BalanceDiscrepancy {
+    /// Opening balance plus booked transactions, negative for a debit balance.
+    pub expected_closing: Decimal,
+
+    /// Closing balance reported in the statement, negative for a debit balance.
+    pub actual_closing: Decimal,
+}
+
+impl BalanceDiscrepancy {
+    /// Reported minus expected closing balance.
+    pub fn difference(&self) -> Decimal {
+        self.actual_closing - self.expected_closing
+    }
+}
+
+impl fmt::Display for BalanceDiscrepancy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "closing balance {} does not match expected {} (difference {})",
+            self.actual_closing,
+            self.expected_closing,
+            self.difference()
+        )
+    }
+}
+
 /// Types of balance in a statement.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum BalanceType {
@@ -123,49 +1057,562 @@ pub enum BalanceType {
     ForwardAvailable,
 }
 
-/// Account statement containing transactions and balances.
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct Statement {
-    /// Statement identification.
-    pub statement_id: String,
-
-    /// Account identification.
-    pub account: String,
+/// IBAN lengths by country, from the SWIFT IBAN registry.
+const IBAN_LENGTHS: &[(&str, usize)] = &[
+    ("AD", 24), ("AE", 23), ("AL", 28), ("AT", 20), ("AZ", 28), ("BA", 20), ("BE", 16), ("BG", 22),
+    ("BH", 22), ("BI", 27), ("BR", 29), ("BY", 28), ("CH", 21), ("CR", 22), ("CY", 28), ("CZ", 24),
+    ("DE", 22), ("DJ", 27), ("DK", 18), ("DO", 28), ("EE", 20), ("EG", 29), ("ES", 24), ("FI", 18),
+    ("FK", 18), ("FO", 18), ("FR", 27), ("GB", 22), ("GE", 22), ("GI", 23), ("GL", 18), ("GR", 27),
+    ("GT", 28), ("HN", 28), ("HR", 21), ("HU", 28), ("IE", 22), ("IL", 23), ("IQ", 23), ("IS", 26),
+    ("IT", 27), ("JO", 30), ("KW", 30), ("KZ", 20), ("LB", 28), ("LC", 32), ("LI", 21), ("LT", 20),
+    ("LU", 20), ("LV", 21), ("LY", 25), ("MC", 27), ("MD", 24), ("ME", 22), ("MK", 19), ("MN", 20),
+    ("MR", 27), ("MT", 31), ("MU", 30), ("NI", 28), ("NL", 18), ("NO", 15), ("OM", 23), ("PK", 24),
+    ("PL", 28), ("PS", 29), ("PT", 25), ("QA", 29), ("RO", 24), ("RS", 22), ("RU", 33), ("SA", 24),
+    ("SC", 31), ("SD", 18), ("SE", 24), ("SI", 19), ("SK", 24), ("SM", 27), ("SO", 23), ("ST", 25),
+    ("SV", 28), ("TL", 23), ("TN", 24), ("TR", 26), ("UA", 29), ("VA", 22), ("VG", 24), ("XK", 20),
+    ("YE", 30),
+];
 
-    /// Statement sequence number.
-    pub sequence_number: Option<String>,
+/// International Bank Account Number, validated against the country's
+/// length and the ISO 13616 mod-97 checksum.
+///
+/// Stored in electronic form (upper case, no spaces); [`Iban::to_formatted`]
+/// gives the printed form in groups of four.
+///
+/// # Examples
+///
+/// ```
+/// use ypbank_system::types::Iban;
+///
+/// let iban: Iban = "gb82 west 1234 5698 7654 32".parse()?;
+/// assert_eq!(iban.as_str(), "GB82WEST12345698765432");
+/// assert_eq!(iban.to_formatted(), "GB82 WEST 1234 5698 7654 32");
+/// assert!("GB83WEST12345698765432".parse::<Iban>().is_err());
+/// # Ok::<(), String>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Iban(String);
 
-    /// Account owner/holder name.
-    pub account_holder: Option<String>,
+impl Iban {
+    /// The IBAN in electronic form.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
 
-    /// Opening balance.
-    pub opening_balance: Option<Balance>,
+    /// ISO 3166 country code.
+    pub fn country_code(&self) -> &str {
+        &self.0[..2]
+    }
 
-    /// Closing balance.
-    pub closing_balance: Option<Balance>,
+    /// The two check digits.
+    pub fn check_digits(&self) -> &str {
+        &self.0[2..4]
+    }
 
-    /// List of transactions.
-    pub transactions: Vec<Transaction>,
+    /// The domestic account number (BBAN).
+    pub fn bban(&self) -> &str {
+        &self.0[4..]
+    }
 
-    /// Currency code for the account.
-    pub currency: String,
+    /// The IBAN in print form, in groups of four characters.
+    pub fn to_formatted(&self) -> String {
+        let chars: Vec<char> = self.0.chars().collect();
+        chars.chunks(4).map(|group| group.iter().collect::<String>()).collect::<Vec<_>>().join(" ")
+    }
 
-    /// Statement creation date.
-    pub creation_date: Option<NaiveDate>,
+    /// Remainder of the IBAN, read as a number with letters A-Z as 10-35
+    /// and the first four characters moved to the end, divided by 97.
+    pub(crate) fn mod97(iban: &str) -> u32 {
+        iban[4..].bytes().chain(iban[..4].bytes()).fold(0, |rem, b| match b {
+            b'0'..=b'9' => (rem * 10 + u32::from(b - b'0')) % 97,
+            _ => (rem * 100 + u32::from(b - b'A') + 10) % 97,
+        })
+    }
+}
+
+impl FromStr for Iban {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let iban: String = s.chars().filter(|c| !c.is_whitespace()).collect::<String>().to_ascii_uppercase();
+        let bytes = iban.as_bytes();
+
+        let well_formed = bytes.len() >= 5
+            && bytes[..2].iter().all(u8::is_ascii_uppercase)
+            && bytes[2..4].iter().all(u8::is_ascii_digit)
+            && bytes[4..].iter().all(u8::is_ascii_alphanumeric);
+        if !well_formed {
+            return Err(format!("Invalid IBAN: {}", s));
+        }
+        match IBAN_LENGTHS.iter().find(|(country, _)| *country == &iban[..2]) {
+            Some((_, length)) if *length == iban.len() => {}
+            Some((country, length)) => {
+                return Err(format!("Invalid IBAN: {} ({} IBANs have {} characters)", s, country, length));
+            }
+            None => return Err(format!("Invalid IBAN: {} (unknown country {})", s, &iban[..2])),
+        }
+        if Self::mod97(&iban) != 1 {
+            return Err(format!("Invalid IBAN: {} (checksum mismatch)", s));
+        }
+        Ok(Iban(iban))
+    }
+}
+
+impl TryFrom<String> for Iban {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Iban> for String {
+    fn from(iban: Iban) -> Self {
+        iban.0
+    }
+}
+
+impl fmt::Display for Iban {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// SWIFT Business Identifier Code (ISO 9362): 4-character institution code,
+/// 2-letter country code, 2-character location code and an optional
+/// 3-character branch code.
+///
+/// # Examples
+///
+/// ```
+/// use ypbank_system::types::Bic;
+///
+/// let bic: Bic = "deutdeff500".parse()?;
+/// assert_eq!(bic.as_str(), "DEUTDEFF500");
+/// assert_eq!(bic.country_code(), "DE");
+/// assert_eq!(bic.branch_code(), Some("500"));
+/// assert!("DEUT12FF".parse::<Bic>().is_err());
+/// # Ok::<(), String>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Bic(String);
 
-    /// From date for the statement period.
-    pub from_date: Option<NaiveDate>,
+impl Bic {
+    /// The BIC in upper case.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Institution (bank) code.
+    pub fn institution_code(&self) -> &str {
+        &self.0[..4]
+    }
+
+    /// ISO 3166 country code.
+    pub fn country_code(&self) -> &str {
+        &self.0[4..6]
+    }
+
+    /// Location code.
+    pub fn location_code(&self) -> &str {
+        &self.0[6..8]
+    }
+
+    /// Branch code of an 11-character BIC.
+    pub fn branch_code(&self) -> Option<&str> {
+        self.0.get(8..).filter(|branch| !branch.is_empty())
+    }
+}
+
+impl FromStr for Bic {
+    type Err = String;
 
-    /// To date for the statement period.
-    pub to_date: Option<NaiveDate>,
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bic = s.trim().to_ascii_uppercase();
+        let bytes = bic.as_bytes();
+
+        let valid = matches!(bytes.len(), 8 | 11)
+            && bytes[..4].iter().all(u8::is_ascii_alphanumeric)
+            && bytes[4..6].iter().all(u8::is_ascii_uppercase)
+            && bytes[6..].iter().all(u8::is_ascii_alphanumeric);
+        if !valid {
+            return Err(format!("Invalid BIC: {}", s));
+        }
+        Ok(Bic(bic))
+    }
+}
+
+impl TryFrom<String> for Bic {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Bic> for String {
+    fn from(bic: Bic) -> Self {
+        bic.0
+    }
+}
+
+impl fmt::Display for Bic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Identification of a bank.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BankIdentifier {
+    /// SWIFT BIC.
+    Bic(Bic),
+
+    /// Member of a national clearing system, such as a Russian БИК.
+    ClearingSystem {
+        /// Member identification.
+        id: String,
+
+        /// ISO 20022 clearing system code (e.g., RUCBC, DEBLZ).
+        system: Option<String>,
+    },
+}
+
+impl BankIdentifier {
+    /// Clearing system code of the Bank of Russia БИК.
+    pub const RUSSIAN_BIK: &'static str = "RUCBC";
+
+    /// The BIC or clearing system member ID.
+    pub fn id(&self) -> &str {
+        match self {
+            BankIdentifier::Bic(bic) => bic.as_str(),
+            BankIdentifier::ClearingSystem { id, .. } => id,
+        }
+    }
+
+    /// The BIC, if the bank is identified by one.
+    pub fn bic(&self) -> Option<&Bic> {
+        match self {
+            BankIdentifier::Bic(bic) => Some(bic),
+            BankIdentifier::ClearingSystem { .. } => None,
+        }
+    }
+}
+
+impl FromStr for BankIdentifier {
+    type Err = String;
+
+    /// Parse a BIC or a nine-digit Russian БИК.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.len() == 9 && s.bytes().all(|b| b.is_ascii_digit()) {
+            return Ok(BankIdentifier::ClearingSystem {
+                id: s.to_string(),
+                system: Some(Self::RUSSIAN_BIK.to_string()),
+            });
+        }
+        Ok(BankIdentifier::Bic(s.parse()?))
+    }
+}
+
+impl From<Bic> for BankIdentifier {
+    fn from(bic: Bic) -> Self {
+        BankIdentifier::Bic(bic)
+    }
+}
+
+impl fmt::Display for BankIdentifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.id())
+    }
+}
+
+/// Identification of an account.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AccountIdentifier {
+    /// International Bank Account Number.
+    Iban(Iban),
+
+    /// Any other identification, such as a domestic BBAN.
+    Other {
+        /// Account number.
+        id: String,
+
+        /// Identification scheme code (e.g., BBAN, UPIC).
+        scheme: Option<String>,
+
+        /// Entity that assigned the identification.
+        issuer: Option<String>,
+    },
+}
+
+impl AccountIdentifier {
+    /// The account number without scheme information.
+    pub fn id(&self) -> &str {
+        match self {
+            AccountIdentifier::Iban(iban) => iban.as_str(),
+            AccountIdentifier::Other { id, .. } => id,
+        }
+    }
+}
+
+impl From<String> for AccountIdentifier {
+    /// Classify a plain account number as an IBAN when it is a valid one;
+    /// anything else, such as a domestic account number, is kept as is.
+    fn from(s: String) -> Self {
+        match s.parse() {
+            Ok(iban) => AccountIdentifier::Iban(iban),
+            Err(_) => AccountIdentifier::Other {
+                id: s,
+                scheme: None,
+                issuer: None,
+            },
+        }
+    }
+}
+
+impl From<Iban> for AccountIdentifier {
+    fn from(iban: Iban) -> Self {
+        AccountIdentifier::Iban(iban)
+    }
+}
+
+impl From<&str> for AccountIdentifier {
+    fn from(s: &str) -> Self {
+        s.to_string().into()
+    }
+}
+
+impl fmt::Display for AccountIdentifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.id())
+    }
+}
+
+/// Account statement containing transactions and balances.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Statement {
+    /// Statement identification.
+    pub statement_id: String,
+
+    /// Account identification.
+    pub account: AccountIdentifier,
+
+    /// Statement sequence number.
+    pub sequence_number: Option<String>,
+
+    /// Account owner/holder name.
+    pub account_holder: Option<String>,
+
+    /// Opening balance.
+    pub opening_balance: Option<Balance>,
+
+    /// Closing balance.
+    pub closing_balance: Option<Balance>,
+
+    /// List of transactions.
+    #[serde(default)]
+    pub transactions: Vec<Transaction>,
+
+    /// Currency code for the account.
+    pub currency: Currency,
+
+    /// Statement creation date, with time of day when the source carries it.
+    pub creation_date: Option<BankDate>,
+
+    /// Start of the statement period, with time of day when the source carries it.
+    pub from_date: Option<BankDate>,
+
+    /// End of the statement period, with time of day when the source carries it.
+    pub to_date: Option<BankDate>,
+
+    /// Page information when the statement is delivered in several parts.
+    pub pagination: Option<Pagination>,
+
+    /// Legal owner of the account.
+    pub account_owner: Option<Party>,
+
+    /// Bank servicing the account.
+    pub servicing_bank: Option<BankInfo>,
+}
+
+/// A party (person or organisation) with optional address.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Party {
+    /// Name of the party.
+    pub name: Option<String>,
+
+    /// Postal address of the party.
+    pub postal_address: Option<PostalAddress>,
+}
+
+/// Postal address in ISO 20022 structure.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PostalAddress {
+    /// Street name.
+    pub street_name: Option<String>,
+
+    /// Building number.
+    pub building_number: Option<String>,
+
+    /// Post code.
+    pub post_code: Option<String>,
+
+    /// Town name.
+    pub town_name: Option<String>,
+
+    /// Country code (ISO 3166 alpha-2).
+    pub country: Option<String>,
+
+    /// Unstructured address lines.
+    #[serde(default)]
+    pub address_lines: Vec<String>,
+}
+
+/// Identification of a bank (financial institution).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BankInfo {
+    /// Bank identifier code (BIC).
+    pub bic: Option<Bic>,
+
+    /// Name of the bank.
+    pub name: Option<String>,
+
+    /// Postal address of the bank.
+    pub address: Option<PostalAddress>,
+
+    /// Member ID in a national clearing system, e.g. a Russian БИК.
+    pub clearing_code: Option<String>,
+
+    /// Clearing system of `clearing_code`, e.g. [`BankIdentifier::RUSSIAN_BIK`].
+    pub clearing_system: Option<String>,
+}
+
+impl BankInfo {
+    /// Bank with just an identifier, filling the BIC or the clearing code.
+    pub fn from_identifier(identifier: BankIdentifier) -> Self {
+        match identifier {
+            BankIdentifier::Bic(bic) => BankInfo { bic: Some(bic), ..Default::default() },
+            BankIdentifier::ClearingSystem { id, system } => BankInfo {
+                clearing_code: Some(id),
+                clearing_system: system,
+                ..Default::default()
+            },
+        }
+    }
+
+    /// The clearing system membership, if a clearing code is known.
+    pub fn clearing_member(&self) -> Option<BankIdentifier> {
+        self.clearing_code.as_ref().map(|id| BankIdentifier::ClearingSystem {
+            id: id.clone(),
+            system: self.clearing_system.clone(),
+        })
+    }
+}
+
+/// Position of a statement page within a paginated statement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Pagination {
+    /// Page number, starting at 1.
+    pub page_number: u32,
+
+    /// Whether this is the last page of the statement.
+    pub last_page: bool,
+}
+
+/// Options for [`Statement::merge`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MergeOptions {
+    /// Keep transactions of the merged statement that are already present,
+    /// instead of dropping them by [`Transaction::fingerprint`].
+    pub keep_duplicates: bool,
+
+    /// Fail unless the closing balance of the earlier statement equals the
+    /// opening balance of the later one.
+    pub require_balance_chain: bool,
+}
+
+/// Primary key for [`Statement::sort_transactions`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    /// Booking date.
+    Date,
+    /// Value date, falling back to the booking date.
+    ValueDate,
+    /// Signed amount, from the largest debit to the largest credit.
+    Amount,
+    /// Transaction reference.
+    Reference,
+}
+
+impl FromStr for SortKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().replace('_', "-").as_str() {
+            "date" | "booking-date" => Ok(SortKey::Date),
+            "value-date" => Ok(SortKey::ValueDate),
+            "amount" => Ok(SortKey::Amount),
+            "reference" | "ref" => Ok(SortKey::Reference),
+            _ => Err(format!("Invalid sort key: {}", s)),
+        }
+    }
+}
+
+/// How [`Statement::split`] divides a statement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitBy {
+    /// One statement per account the transactions were booked to.
+    Account,
+    /// One statement per calendar month.
+    Month,
+    /// One statement per day.
+    Day,
+}
+
+impl FromStr for SplitBy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "account" => Ok(SplitBy::Account),
+            "month" => Ok(SplitBy::Month),
+            "day" => Ok(SplitBy::Day),
+            _ => Err(format!("Invalid split: {} (expected account, month or day)", s)),
+        }
+    }
+}
+
+/// Handling of amounts with more decimal places than their currency's
+/// minor unit, see [`Statement::enforce_precision`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrecisionPolicy {
+    /// Fail on the first amount that can't be written exactly.
+    Reject,
+    /// Round such amounts with [`Currency::round`].
+    Round,
+}
+
+impl FromStr for PrecisionPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "reject" | "error" => Ok(PrecisionPolicy::Reject),
+            "round" => Ok(PrecisionPolicy::Round),
+            _ => Err(format!("Invalid precision policy: {}", s)),
+        }
+    }
 }
 
 impl Statement {
     /// Create a new statement with basic information.
-    pub fn new(statement_id: String, account: String, currency: String) -> Self {
+    pub fn new(statement_id: String, account: impl Into<AccountIdentifier>, currency: Currency) -> Self {
         Self {
             statement_id,
-            account,
+            account: account.into(),
             currency,
             sequence_number: None,
             account_holder: None,
@@ -175,6 +1622,9 @@ impl Statement {
             creation_date: None,
             from_date: None,
             to_date: None,
+            pagination: None,
+            account_owner: None,
+            servicing_bank: None,
         }
     }
 
@@ -182,4 +1632,1107 @@ impl Statement {
     pub fn add_transaction(&mut self, transaction: Transaction) {
         self.transactions.push(transaction);
     }
+
+    /// Compute entry counts and totals over the statement's transactions.
+    pub fn transactions_summary(&self) -> TransactionsSummary {
+        let mut summary = TransactionsSummary::default();
+
+        for transaction in &self.transactions {
+            match transaction.debit_credit {
+                DebitCredit::Credit => {
+                    summary.credit_count += 1;
+                    summary.credit_sum += transaction.amount;
+                }
+                DebitCredit::Debit => {
+                    summary.debit_count += 1;
+                    summary.debit_sum += transaction.amount;
+                }
+            }
+        }
+
+        summary
+    }
+
+    /// Compute descriptive statistics over the statement's transactions.
+    ///
+    /// Amounts are taken as they are, so for a statement mixing currencies
+    /// the totals and extremes mix them too; split it with
+    /// [`Statement::by_currency`] first where that matters.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ypbank_system::types::{Currency, Statement};
+    ///
+    /// let statistics = Statement::new("1".into(), "ACC001", Currency::RUB).statistics();
+    /// assert_eq!(statistics.summary.entry_count(), 0);
+    /// assert_eq!(statistics.average_amount, None);
+    /// ```
+    pub fn statistics(&self) -> StatementStatistics {
+        let summary = self.transactions_summary();
+        let amounts = self.transactions.iter().map(|tx| tx.amount);
+
+        let counterparties: HashSet<String> = self.transactions.iter()
+            .filter_map(|tx| {
+                tx.counterparty_name.as_deref()
+                    .map(|name| name.trim().to_lowercase())
+                    .filter(|name| !name.is_empty())
+                    .or_else(|| tx.counterparty_account.as_ref().map(|account| account.to_string()))
+            })
+            .collect();
+        let dates: BTreeSet<NaiveDate> = self.transactions.iter().map(|tx| tx.date.date()).collect();
+
+        StatementStatistics {
+            min_amount: amounts.clone().min(),
+            max_amount: amounts.max(),
+            average_amount: (summary.entry_count() > 0)
+                .then(|| self.currency.round(summary.total_sum() / Decimal::from(summary.entry_count()))),
+            counterparties: counterparties.len(),
+            first_date: dates.first().copied(),
+            last_date: dates.last().copied(),
+            active_days: dates.len(),
+            summary,
+        }
+    }
+
+    /// Sum of debit transaction amounts.
+    pub fn total_debits(&self) -> Decimal {
+        self.transactions_summary().debit_sum
+    }
+
+    /// Sum of credit transaction amounts.
+    pub fn total_credits(&self) -> Decimal {
+        self.transactions_summary().credit_sum
+    }
+
+    /// Change of the balance over the statement: credits minus debits.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ypbank_system::types::{Currency, Statement};
+    ///
+    /// let statement = Statement::new("1".into(), "ACC001", Currency::RUB);
+    /// assert!(statement.net_change().is_zero());
+    /// ```
+    pub fn net_change(&self) -> Decimal {
+        self.transactions.iter().map(Transaction::signed_amount).sum()
+    }
+
+    /// Check that the opening balance plus the booked transactions gives the
+    /// closing balance.
+    ///
+    /// Pending and informational entries don't count towards the balance,
+    /// nor do transactions in a currency other than the account's.
+    /// Statements lacking an opening or closing balance pass.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chrono::NaiveDate;
+    /// use rust_decimal::Decimal;
+    /// use ypbank_system::types::{Balance, BalanceType, Currency, DebitCredit, Statement};
+    ///
+    /// let balance = |balance_type, amount| Balance {
+    ///     balance_type,
+    ///     amount: Decimal::new(amount, 2),
+    ///     currency: Currency::RUB,
+    ///     debit_credit: DebitCredit::Credit,
+    ///     date: NaiveDate::from_ymd_opt(2024, 1, 31).unwrap().into(),
+    /// };
+    /// let mut statement = Statement::new("1".into(), "ACC001", Currency::RUB);
+    /// statement.opening_balance = Some(balance(BalanceType::Opening, 10000));
+    /// statement.closing_balance = Some(balance(BalanceType::Closing, 12500));
+    ///
+    /// let discrepancy = statement.verify_balances().unwrap_err();
+    /// assert_eq!(discrepancy.difference(), Decimal::new(2500, 2));
+    /// ```
+    pub fn verify_balances(&self) -> Result<(), BalanceDiscrepancy> {
+        let (Some(opening), Some(closing)) = (&self.opening_balance, &self.closing_balance) else {
+            return Ok(());
+        };
+
+        let expected_closing = opening.signed_amount() + self.booked_change();
+        let actual_closing = closing.signed_amount();
+
+        if expected_closing == actual_closing {
+            Ok(())
+        } else {
+            Err(BalanceDiscrepancy { expected_closing, actual_closing })
+        }
+    }
+
+    /// Fill in balances missing from the statement, as formats such as MT940
+    /// require them: the opening balance from `opening` (negative for a debit
+    /// balance) in the currency's minor unit, dated at the start of the
+    /// period, and the closing balance as
+    /// the opening balance plus the booked transactions, dated at its end.
+    ///
+    /// Balances already present are kept, so an opening balance stated in
+    /// the source wins over `opening`. Without any opening balance nothing
+    /// is filled in.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rust_decimal::Decimal;
+    /// use ypbank_system::types::{BankDate, Currency, Statement};
+    ///
+    /// let mut statement = Statement::new("1".into(), "ACC001", Currency::RUB);
+    /// statement.creation_date = chrono::NaiveDate::from_ymd_opt(2024, 1, 31).map(BankDate::from);
+    /// statement.fill_missing_balances(Some(Decimal::new(100, 0)));
+    /// assert_eq!(statement.closing_balance.unwrap().amount, Decimal::new(100, 0));
+    /// ```
+    pub fn fill_missing_balances(&mut self, opening: Option<Decimal>) {
+        if self.opening_balance.is_none() {
+            let date = self.period_start().or(self.creation_date.map(|date| date.date()));
+            self.opening_balance = opening.zip(date).map(|(amount, date)| {
+                Balance::from_signed(BalanceType::Opening, self.currency.round(amount), self.currency, date)
+            });
+        }
+        if self.closing_balance.is_none() {
+            if let Some(ref opening) = self.opening_balance {
+                let date = self.to_date
+                    .or(self.transactions.iter().map(|tx| tx.date).max())
+                    .map(|date| date.date())
+                    .unwrap_or(opening.date)
+                    .max(opening.date);
+                let amount = opening.signed_amount() + self.booked_change();
+                self.closing_balance = Some(Balance::from_signed(BalanceType::Closing, amount, self.currency, date));
+            }
+        }
+    }
+
+    /// Set [`Transaction::running_balance`] to the account balance after each
+    /// transaction, counting from the opening balance.
+    ///
+    /// Only booked transactions in the account currency move the balance, the
+    /// others get no running balance. Transactions listed newest first are
+    /// counted from the end. Without an opening balance the statement is
+    /// returned unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chrono::NaiveDate;
+    /// use rust_decimal::Decimal;
+    /// use ypbank_system::types::{Balance, BalanceType, Currency, Statement};
+    ///
+    /// let date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+    /// let mut statement = Statement::new("1".into(), "ACC001", Currency::RUB);
+    /// statement.opening_balance = Some(Balance::from_signed(BalanceType::Opening, Decimal::new(100, 0), Currency::RUB, date));
+    ///
+    /// let statement = statement.with_running_balances();
+    /// assert!(statement.transactions.iter().all(|tx| tx.running_balance.is_some()));
+    /// ```
+    pub fn with_running_balances(mut self) -> Self {
+        let Some(mut balance) = self.opening_balance.as_ref().map(Balance::signed_amount) else {
+            return self;
+        };
+
+        let newest_first = self.is_newest_first();
+        let currency = self.currency;
+        let mut update = |tx: &mut Transaction| {
+            tx.running_balance = if tx.status.is_booked() && tx.currency == currency {
+                balance += tx.signed_amount();
+                Some(balance)
+            } else {
+                None
+            };
+        };
+        if newest_first {
+            self.transactions.iter_mut().rev().for_each(&mut update);
+        } else {
+            self.transactions.iter_mut().for_each(&mut update);
+        }
+        self
+    }
+
+    /// Index of the first transaction whose reported running balance differs
+    /// from the one computed by [`Statement::with_running_balances`], i.e.
+    /// the entry from which the statement stops reconciling.
+    pub fn first_running_balance_mismatch(&self) -> Option<usize> {
+        self.opening_balance.as_ref()?;
+
+        let computed = self.clone().with_running_balances();
+        let mut mismatches = self.transactions.iter()
+            .zip(&computed.transactions)
+            .enumerate()
+            .filter(|(_, (reported, computed))| {
+                reported.running_balance.is_some_and(|balance| Some(balance) != computed.running_balance)
+            })
+            .map(|(i, _)| i);
+
+        // In a newest-first statement the earliest broken entry comes last
+        if self.is_newest_first() {
+            mismatches.next_back()
+        } else {
+            mismatches.next()
+        }
+    }
+
+    /// Merge another statement of the same account into this one, e.g. to
+    /// combine daily statements into a monthly one.
+    ///
+    /// The statements are ordered by period: the earlier one supplies the
+    /// opening balance, the later one the closing balance. Transactions are
+    /// interleaved in [`Transaction::canonical_key`] order, keeping the order
+    /// within each statement. Transactions already present are skipped unless
+    /// [`MergeOptions::keep_duplicates`] is set, and the period spans both
+    /// statements. Identification and metadata of `self` are kept.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chrono::NaiveDate;
+    /// use ypbank_system::types::{BankDate, Currency, MergeOptions, Statement};
+    ///
+    /// let day = |d| {
+    ///     let mut statement = Statement::new(format!("D{}", d), "ACC001", Currency::RUB);
+    ///     statement.from_date = NaiveDate::from_ymd_opt(2024, 2, d).map(BankDate::from);
+    ///     statement.to_date = statement.from_date;
+    ///     statement
+    /// };
+    /// let mut month = day(2);
+    /// month.merge(day(1), &MergeOptions::default())?;
+    /// assert_eq!(month.from_date.map(|date| date.date()), NaiveDate::from_ymd_opt(2024, 2, 1));
+    /// assert_eq!(month.to_date.map(|date| date.date()), NaiveDate::from_ymd_opt(2024, 2, 2));
+    /// # Ok::<(), ypbank_system::Error>(())
+    /// ```
+    pub fn merge(&mut self, other: Statement, options: &MergeOptions) -> CrateResult<()> {
+        if other.account != self.account || other.currency != self.currency {
+            return Err(Error::ConversionError(format!(
+                "Cannot merge statement of {} ({}) into {} ({})",
+                other.account, other.currency, self.account, self.currency
+            ), None));
+        }
+
+        let mut other = other;
+        let other_first = other.period_start() < self.period_start();
+
+        if options.require_balance_chain {
+            let (earlier, later) = if other_first { (&other, &*self) } else { (&*self, &other) };
+            if let (Some(closing), Some(opening)) = (&earlier.closing_balance, &later.opening_balance) {
+                if closing.signed_amount() != opening.signed_amount() {
+                    let message = format!(
+                        "Closing balance {} of statement {} doesn't match opening balance {} of statement {}",
+                        closing.signed_amount(), earlier.statement_id,
+                        opening.signed_amount(), later.statement_id
+                    );
+                    return Err(ValidationError::new(RuleId::BalanceChain, message).at_path("opening_balance").into());
+                }
+            }
+        }
+
+        if !options.keep_duplicates {
+            // Count fingerprints so that repeated identical bookings survive
+            // as far as this statement holds fewer of them
+            let mut seen: HashMap<u64, usize> = HashMap::new();
+            for transaction in &self.transactions {
+                *seen.entry(transaction.fingerprint()).or_default() += 1;
+            }
+            other.transactions.retain(|transaction| match seen.get_mut(&transaction.fingerprint()) {
+                Some(count) if *count > 0 => {
+                    *count -= 1;
+                    false
+                }
+                _ => true,
+            });
+        }
+
+        let transactions = std::mem::take(&mut self.transactions);
+        if other_first {
+            self.transactions = merge_canonical(other.transactions, transactions);
+            self.opening_balance = other.opening_balance.or(self.opening_balance.take());
+        } else {
+            self.transactions = merge_canonical(transactions, other.transactions);
+            self.closing_balance = other.closing_balance.or(self.closing_balance.take());
+        }
+        self.from_date = match (self.from_date, other.from_date) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        self.to_date = match (self.to_date, other.to_date) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (a, b) => a.or(b),
+        };
+        self.pagination = None;
+        Ok(())
+    }
+
+    /// Merge statements of one account into a single statement, e.g. the
+    /// daily statements of a month.
+    ///
+    /// The statements are merged in period order whatever their order here,
+    /// so that with [`MergeOptions::require_balance_chain`] each closing
+    /// balance is checked against the next opening balance. The earliest
+    /// statement keeps its identification.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chrono::NaiveDate;
+    /// use rust_decimal::Decimal;
+    /// use ypbank_system::types::{Balance, BalanceType, Currency, MergeOptions, Statement};
+    ///
+    /// let day = |d, opening, closing| {
+    ///     let date = NaiveDate::from_ymd_opt(2024, 2, d).unwrap();
+    ///     let mut statement = Statement::new(format!("D{}", d), "ACC001", Currency::RUB);
+    ///     statement.from_date = Some(date.into());
+    ///     statement.opening_balance = Some(Balance::from_signed(BalanceType::Opening, Decimal::from(opening), Currency::RUB, date));
+    ///     statement.closing_balance = Some(Balance::from_signed(BalanceType::Closing, Decimal::from(closing), Currency::RUB, date));
+    ///     statement
+    /// };
+    /// let options = MergeOptions { require_balance_chain: true, ..Default::default() };
+    /// let month = Statement::merge_all(vec![day(3, 120, 90), day(1, 100, 120)], &options)?;
+    /// assert_eq!(month.statement_id, "D1");
+    /// assert_eq!(month.closing_balance.unwrap().amount, Decimal::from(90));
+    ///
+    /// assert!(Statement::merge_all(vec![day(1, 100, 120), day(2, 100, 130)], &options).is_err());
+    /// # Ok::<(), ypbank_system::Error>(())
+    /// ```
+    pub fn merge_all(statements: impl IntoIterator<Item = Statement>, options: &MergeOptions) -> CrateResult<Statement> {
+        let mut statements: Vec<Statement> = statements.into_iter().collect();
+        statements.sort_by_key(Statement::period_start);
+
+        let mut statements = statements.into_iter();
+        let mut merged = statements.next()
+            .ok_or_else(|| Error::ConversionError("No statements to merge".to_string(), None))?;
+        for statement in statements {
+            merged.merge(statement, options)?;
+        }
+        Ok(merged)
+    }
+
+    /// Currencies of the statement's transactions, account currency first.
+    pub fn currencies(&self) -> Vec<Currency> {
+        let mut currencies = vec![self.currency];
+        for transaction in &self.transactions {
+            if !currencies.contains(&transaction.currency) {
+                currencies.push(transaction.currency);
+            }
+        }
+        currencies
+    }
+
+    /// Split the statement into one statement per transaction currency, for
+    /// formats such as MT940 that carry a single currency per statement.
+    ///
+    /// The part in the account currency comes first and keeps the ID and
+    /// balances; parts in other currencies are identified as `<ID>-<CCY>`
+    /// and have no balances, which are only known in the account currency.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ypbank_system::types::{Currency, Statement};
+    ///
+    /// let statement = Statement::new("1".into(), "ACC001", Currency::RUB);
+    /// let parts = statement.by_currency();
+    /// assert_eq!(parts.len(), 1);
+    /// assert_eq!(parts[0].currency, Currency::RUB);
+    /// ```
+    pub fn by_currency(&self) -> Vec<Statement> {
+        let template = Statement { transactions: Vec::new(), ..self.clone() };
+
+        self.currencies()
+            .into_iter()
+            .map(|currency| {
+                let mut part = Statement {
+                    transactions: self.transactions.iter().filter(|tx| tx.currency == currency).cloned().collect(),
+                    ..template.clone()
+                };
+                if currency != self.currency {
+                    part.statement_id = format!("{}-{}", self.statement_id, currency);
+                    part.currency = currency;
+                    part.opening_balance = None;
+                    part.closing_balance = None;
+                }
+                part
+            })
+            .collect()
+    }
+
+    /// Sort transactions by `key`.
+    ///
+    /// Ties are broken by [`Transaction::canonical_key`], so the result
+    /// doesn't depend on the order of the input entries. Fully identical
+    /// transactions keep their relative order.
+    pub fn sort_transactions(&mut self, key: SortKey) {
+        self.transactions.sort_by(|a, b| {
+            let primary = match key {
+                SortKey::Date => Ordering::Equal,
+                SortKey::ValueDate => a.value_date.unwrap_or(a.date).cmp(&b.value_date.unwrap_or(b.date)),
+                SortKey::Amount => a.signed_amount().cmp(&b.signed_amount()),
+                SortKey::Reference => a.reference.cmp(&b.reference),
+            };
+            primary.then_with(|| a.canonical_cmp(b))
+        });
+    }
+
+    /// Remove transactions sharing a [`Transaction::fingerprint`] with an
+    /// earlier one, keeping the first occurrence; returns how many were removed.
+    ///
+    /// Note that genuinely repeated bookings (two identical payments on the
+    /// same day without distinct references) are collapsed as well.
+    pub fn dedup_transactions(&mut self) -> usize {
+        let before = self.transactions.len();
+        let mut seen = HashSet::new();
+        self.transactions.retain(|tx| seen.insert(tx.fingerprint()));
+        before - self.transactions.len()
+    }
+
+    /// Add `tag` to every transaction satisfying `predicate`; returns how
+    /// many transactions matched.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ypbank_system::types::{Currency, Statement};
+    ///
+    /// let mut statement = Statement::new("1".into(), "ACC001", Currency::RUB);
+    /// let fees = statement.tag_where("fees", |tx| tx.description.contains("комиссия"));
+    /// assert_eq!(fees, 0);
+    /// ```
+    pub fn tag_where(&mut self, tag: &str, predicate: impl Fn(&Transaction) -> bool) -> usize {
+        let mut matched = 0;
+        for tx in self.transactions.iter_mut().filter(|tx| predicate(tx)) {
+            tx.tags.insert(tag.to_string());
+            matched += 1;
+        }
+        matched
+    }
+
+    /// Remove `tag` from every transaction.
+    pub fn remove_tag(&mut self, tag: &str) {
+        for tx in &mut self.transactions {
+            tx.tags.remove(tag);
+        }
+    }
+
+    /// Bring every amount to the number of decimal places of its currency's
+    /// minor unit (2 for EUR, 0 for JPY, 3 for BHD).
+    ///
+    /// Amounts that only differ in trailing zeros are always rescaled; other
+    /// excess precision is rounded or rejected with [`Error::InvalidAmount`]
+    /// according to `policy`.
+    pub fn enforce_precision(&mut self, policy: PrecisionPolicy) -> CrateResult<()> {
+        let adjust = |amount: &mut Decimal, currency: Currency| {
+            let rounded = currency.round(*amount);
+            if rounded != *amount && policy == PrecisionPolicy::Reject {
+                return Err(Error::InvalidAmount(format!(
+                    "{} {} has more decimal places than the currency allows",
+                    amount, currency
+                ), None));
+            }
+            *amount = rounded;
+            Ok(())
+        };
+
+        for balance in self.opening_balance.iter_mut().chain(self.closing_balance.iter_mut()) {
+            adjust(&mut balance.amount, balance.currency)?;
+        }
+        for tx in &mut self.transactions {
+            adjust(&mut tx.amount, tx.currency)?;
+            if let Some(ref mut balance) = tx.running_balance {
+                adjust(balance, self.currency)?;
+            }
+            if let (Some(amount), Some(currency)) = (tx.original_amount.as_mut(), tx.original_currency) {
+                adjust(amount, currency)?;
+            }
+            if let (Some(amount), Some(currency)) = (tx.counter_value_amount.as_mut(), tx.counter_value_currency) {
+                adjust(amount, currency)?;
+            }
+            for availability in &mut tx.funds_availability {
+                adjust(&mut availability.amount, tx.currency)?;
+            }
+            for charge in &mut tx.charges {
+                adjust(&mut charge.amount, charge.currency)?;
+            }
+            for detail in &mut tx.details {
+                if let Some(ref mut amount) = detail.amount {
+                    adjust(amount, detail.currency.unwrap_or(tx.currency))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Split the statement as `by` says.
+    pub fn split(&self, by: SplitBy) -> Vec<Statement> {
+        match by {
+            SplitBy::Account => self.split_by_account(),
+            SplitBy::Month => self.split_by_month(),
+            SplitBy::Day => self.split_by_day(),
+        }
+    }
+
+    /// Split the statement into one statement per account its transactions
+    /// were booked to, see [`Transaction::account`].
+    ///
+    /// The part of the statement's own account comes first and keeps the
+    /// balances; parts of other accounts are identified as `<ID>-<N>` and
+    /// have no balances, which are only known for the statement's account.
+    /// A statement of a single account is returned unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ypbank_system::csv_format::{CsvProfile, CsvStatement};
+    ///
+    /// let input = "Дата проводки,Счет Дебет,Счет Кредит,Сумма по дебету,Сумма по кредиту\n\
+    ///              20.02.2024,40702810000000000001,40817810000000000009,100.00,\n\
+    ///              20.02.2024,40817810000000000009,40702810000000000002,,50.00\n";
+    /// let statement = CsvStatement::from_read_with(&mut input.as_bytes(), &CsvProfile::default())?.statement;
+    ///
+    /// let parts = statement.split_by_account();
+    /// assert_eq!(parts.len(), 2);
+    /// assert_eq!(parts[1].account.id(), "40702810000000000002");
+    /// assert!(parts.iter().all(|part| part.transactions.len() == 1 && part.transactions[0].account.is_none()));
+    /// # Ok::<(), ypbank_system::error::Error>(())
+    /// ```
+    pub fn split_by_account(&self) -> Vec<Statement> {
+        let mut accounts: Vec<Option<&str>> = vec![None];
+        for transaction in &self.transactions {
+            let account = transaction.account.as_deref().filter(|account| *account != self.account.id());
+            if !accounts.contains(&account) {
+                accounts.push(account);
+            }
+        }
+        if accounts.len() == 1 {
+            return vec![self.clone()];
+        }
+
+        let template = Statement { transactions: Vec::new(), ..self.clone() };
+        accounts.into_iter()
+            .enumerate()
+            .map(|(i, account)| {
+                let mut part = template.clone();
+                part.transactions = self.transactions.iter()
+                    .filter(|tx| tx.account.as_deref().filter(|a| *a != self.account.id()) == account)
+                    .map(|tx| Transaction { account: None, ..tx.clone() })
+                    .collect();
+                if let Some(account) = account {
+                    part.statement_id = format!("{}-{}", self.statement_id, i + 1);
+                    part.account = account.to_string().into();
+                    part.opening_balance = None;
+                    part.closing_balance = None;
+                }
+                part
+            })
+            .collect()
+    }
+
+    /// Split the statement into one statement per day, as
+    /// [`Statement::split_by_month`] does per month.
+    pub fn split_by_day(&self) -> Vec<Statement> {
+        self.split_by(|date| date..=date)
+    }
+
+    /// Split the statement into one statement per calendar month.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chrono::NaiveDate;
+    /// use ypbank_system::types::{BankDate, Currency, Statement};
+    ///
+    /// let mut statement = Statement::new("Q1".into(), "ACC001", Currency::RUB);
+    /// statement.from_date = NaiveDate::from_ymd_opt(2024, 1, 15).map(BankDate::from);
+    /// statement.to_date = NaiveDate::from_ymd_opt(2024, 3, 10).map(BankDate::from);
+    ///
+    /// let months = statement.split_by_month();
+    /// assert_eq!(months.len(), 3);
+    /// assert_eq!(months[1].from_date.map(|date| date.date()), NaiveDate::from_ymd_opt(2024, 2, 1));
+    /// assert_eq!(months[1].to_date.map(|date| date.date()), NaiveDate::from_ymd_opt(2024, 2, 29));
+    /// assert_eq!(months[2].statement_id, "Q1-3");
+    /// ```
+    pub fn split_by_month(&self) -> Vec<Statement> {
+        self.split_by(|date| {
+            let start = date.with_day(1).unwrap_or(date);
+            let end = start
+                .checked_add_months(Months::new(1))
+                .and_then(|next| next.pred_opt())
+                .unwrap_or(date);
+            start..=end
+        })
+    }
+
+    /// Split the statement into pages of at most `max_entries` transactions,
+    /// for receivers that cap message size.
+    ///
+    /// Pages keep the statement ID and period and are numbered in
+    /// [`Statement::pagination`], which CAMT.053 writes as `StmtPgntn` and
+    /// MT940 as the sequence number of `:28C:`. When the statement has
+    /// balances, each page opens with the balance the previous one closed
+    /// with; balances between pages are intermediate (`:60M:`/`:62M:`). A
+    /// statement that fits on one page is returned unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rust_decimal::Decimal;
+    /// use ypbank_system::mt940_format::Mt940Statement;
+    /// use ypbank_system::types::{BalanceType, Pagination};
+    ///
+    /// let input = ":20:STMT\n:25:ACC001\n:60F:C240101EUR100,00\n\
+    ///              :61:2401020102D10,00NTRF//REF1\n:61:2401030103D20,00NTRF//REF2\n\
+    ///              :61:2401040104C5,00NTRF//REF3\n:62F:C240104EUR75,00\n";
+    /// let statement = Mt940Statement::from_read(&mut input.as_bytes())?.statement;
+    ///
+    /// let pages = statement.paginate(2);
+    /// assert_eq!(pages.len(), 2);
+    /// assert_eq!(pages[1].pagination, Some(Pagination { page_number: 2, last_page: true }));
+    /// let intermediate = pages[0].closing_balance.as_ref().unwrap();
+    /// assert_eq!((intermediate.balance_type, intermediate.amount), (BalanceType::Intermediate, Decimal::from(70)));
+    /// assert_eq!(pages[1].opening_balance.as_ref().unwrap().amount, Decimal::from(70));
+    /// # Ok::<(), ypbank_system::Error>(())
+    /// ```
+    pub fn paginate(&self, max_entries: usize) -> Vec<Statement> {
+        let max_entries = max_entries.max(1);
+        if self.transactions.len() <= max_entries {
+            return vec![self.clone()];
+        }
+
+        let chunks: Vec<&[Transaction]> = self.transactions.chunks(max_entries).collect();
+        let mut balance = self.opening_balance.as_ref().map(Balance::signed_amount)
+            .or_else(|| self.closing_balance.as_ref().map(|closing| closing.signed_amount() - self.booked_change()));
+
+        let mut pages = Vec::new();
+        for (i, chunk) in chunks.iter().enumerate() {
+            let last_page = i + 1 == chunks.len();
+            let mut page = Statement {
+                transactions: chunk.to_vec(),
+                pagination: Some(Pagination { page_number: i as u32 + 1, last_page }),
+                ..self.clone()
+            };
+
+            if let Some(opening) = balance {
+                let closing = opening + page.booked_change();
+                if i > 0 {
+                    page.opening_balance = Some(Balance::from_signed(BalanceType::Intermediate, opening, self.currency, chunk[0].date.date()));
+                }
+                if !last_page {
+                    let date = chunk[chunk.len() - 1].date.date();
+                    page.closing_balance = Some(Balance::from_signed(BalanceType::Intermediate, closing, self.currency, date));
+                }
+                balance = Some(closing);
+            }
+            pages.push(page);
+        }
+        pages
+    }
+
+    /// Split the statement into consecutive periods; `period_of` returns the
+    /// period containing a date.
+    ///
+    /// Periods run from the start to the end of the statement, including
+    /// those without transactions, and are clipped to it. Each part carries
+    /// the statement data, the transactions booked in its period and an ID
+    /// suffixed with its number. When the statement has an opening or
+    /// closing balance, the balances of every part are recomputed from the
+    /// booked transactions so that they chain.
+    pub fn split_by<F>(&self, period_of: F) -> Vec<Statement>
+    where
+        F: Fn(NaiveDate) -> RangeInclusive<NaiveDate>,
+    {
+        let dates = self.transactions.iter().map(|tx| tx.date.date());
+        let first = dates.clone().chain(self.period_start()).min();
+        let last = dates
+            .chain(self.to_date.map(|date| date.date()))
+            .chain(self.closing_balance.as_ref().map(|b| b.date))
+            .max();
+        let (Some(mut date), Some(last)) = (first, last) else {
+            return vec![self.clone()];
+        };
+
+        let booked = |transactions: &[Transaction]| -> Decimal {
+            transactions.iter()
+                .filter(|tx| tx.status.is_booked())
+                .map(Transaction::signed_amount)
+                .sum()
+        };
+        let mut balance = match (&self.opening_balance, &self.closing_balance) {
+            (Some(opening), _) => Some(opening.signed_amount()),
+            (None, Some(closing)) => Some(closing.signed_amount() - booked(&self.transactions)),
+            (None, None) => None,
+        };
+
+        let template = Statement { transactions: Vec::new(), ..self.clone() };
+        let mut parts = Vec::new();
+        while date <= last {
+            let period = period_of(date);
+            let (from, to) = (date, (*period.end()).clamp(date, last));
+
+            let mut part = Statement {
+                statement_id: format!("{}-{}", self.statement_id, parts.len() + 1),
+                transactions: Vec::new(),
+                from_date: Some(from.into()),
+                to_date: Some(to.into()),
+                pagination: None,
+                ..template.clone()
+            };
+            part.transactions = self.transactions.iter()
+                .filter(|tx| (from..=to).contains(&tx.date.date()))
+                .cloned()
+                .collect();
+
+            if let Some(opening) = balance {
+                let closing = opening + booked(&part.transactions);
+                part.opening_balance = Some(Balance::from_signed(BalanceType::Opening, opening, self.currency, from));
+                part.closing_balance = Some(Balance::from_signed(BalanceType::Closing, closing, self.currency, to));
+                balance = Some(closing);
+            }
+
+            parts.push(part);
+            match to.succ_opt() {
+                Some(next) => date = next,
+                None => break,
+            }
+        }
+        parts
+    }
+
+    /// First day covered by the statement, if known.
+    fn period_start(&self) -> Option<NaiveDate> {
+        self.from_date
+            .map(|date| date.date())
+            .or(self.opening_balance.as_ref().map(|b| b.date))
+            .or(self.transactions.iter().map(|tx| tx.date.date()).min())
+    }
+
+    /// Sum of the booked transactions in the account currency, i.e. the
+    /// change they make to the balance.
+    fn booked_change(&self) -> Decimal {
+        self.transactions.iter()
+            .filter(|tx| tx.status.is_booked() && tx.currency == self.currency)
+            .map(Transaction::signed_amount)
+            .sum()
+    }
+
+    /// Whether transactions are listed from the latest to the earliest.
+    fn is_newest_first(&self) -> bool {
+        self.transactions.first().zip(self.transactions.last()).is_some_and(|(first, last)| first.date > last.date)
+    }
+}
+
+/// Entry counts and totals of a statement, as reported in camt.053 `TxsSummry`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransactionsSummary {
+    /// Number of credit entries.
+    pub credit_count: usize,
+
+    /// Sum of credit entry amounts.
+    pub credit_sum: Decimal,
+
+    /// Number of debit entries.
+    pub debit_count: usize,
+
+    /// Sum of debit entry amounts.
+    pub debit_sum: Decimal,
+}
+
+impl TransactionsSummary {
+    /// Total number of entries.
+    pub fn entry_count(&self) -> usize {
+        self.credit_count + self.debit_count
+    }
+
+    /// Sum of all entry amounts regardless of direction.
+    pub fn total_sum(&self) -> Decimal {
+        self.credit_sum + self.debit_sum
+    }
+
+    /// Net amount of all entries (credits minus debits).
+    pub fn net_amount(&self) -> Decimal {
+        self.credit_sum - self.debit_sum
+    }
+}
+
+/// Descriptive statistics of a statement, as computed by [`Statement::statistics`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StatementStatistics {
+    /// Entry counts and totals per direction.
+    pub summary: TransactionsSummary,
+
+    /// Smallest transaction amount.
+    pub min_amount: Option<Decimal>,
+
+    /// Largest transaction amount.
+    pub max_amount: Option<Decimal>,
+
+    /// Mean transaction amount, rounded to the account currency's minor unit.
+    pub average_amount: Option<Decimal>,
+
+    /// Number of distinct counterparties, told apart by name ignoring case,
+    /// or by account where the name is missing.
+    pub counterparties: usize,
+
+    /// Earliest booking date.
+    pub first_date: Option<NaiveDate>,
+
+    /// Latest booking date.
+    pub last_date: Option<NaiveDate>,
+
+    /// Number of distinct booking dates.
+    pub active_days: usize,
+}
+
+impl StatementStatistics {
+    /// Number of calendar days from the first to the last booking date,
+    /// both inclusive.
+    pub fn days_spanned(&self) -> usize {
+        match (self.first_date, self.last_date) {
+            (Some(first), Some(last)) => (last - first).num_days() as usize + 1,
+            _ => 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::csv_format::CsvStatement;
+
+    /// A booked transaction of `amount` in `currency` on February `day`.
+    fn transaction(reference: &str, day: u32, amount: &str, currency: Currency, debit_credit: DebitCredit) -> Transaction {
+        Transaction {
+            reference: reference.into(),
+            date: NaiveDate::from_ymd_opt(2024, 2, day).unwrap().into(),
+            amount: Decimal::from_str(amount).unwrap(),
+            currency,
+            debit_credit,
+            ..Transaction::default()
+        }
+    }
+
+    #[test]
+    fn test_signed_amounts_and_totals() {
+        let mut statement = Statement::new("STMT".into(), "ACC001", Currency::EUR);
+        statement.transactions = vec![
+            transaction("1", 1, "10.00", Currency::EUR, DebitCredit::Debit),
+            transaction("2", 2, "25.50", Currency::EUR, DebitCredit::Credit),
+            transaction("3", 3, "0.50", Currency::EUR, DebitCredit::Debit),
+        ];
+        assert_eq!(statement.transactions[0].signed_amount(), Decimal::from_str("-10.00").unwrap());
+        assert_eq!(statement.transactions[1].signed_amount(), Decimal::from_str("25.50").unwrap());
+        assert_eq!(statement.total_debits(), Decimal::from_str("10.50").unwrap());
+        assert_eq!(statement.total_credits(), Decimal::from_str("25.50").unwrap());
+        assert_eq!(statement.net_change(), Decimal::from_str("15.00").unwrap());
+
+        let debit = Balance::from_signed(BalanceType::Closing, Decimal::new(-5, 0), Currency::EUR, NaiveDate::default());
+        assert_eq!(debit.debit_credit, DebitCredit::Debit);
+        assert_eq!(debit.signed_amount(), Decimal::new(-5, 0));
+    }
+
+    #[test]
+    fn test_verify_balances() {
+        let date = NaiveDate::from_ymd_opt(2024, 2, 29).unwrap();
+        let mut statement = Statement::new("STMT".into(), "ACC001", Currency::EUR);
+        statement.transactions = vec![
+            transaction("1", 1, "30.00", Currency::EUR, DebitCredit::Debit),
+            transaction("2", 2, "50.00", Currency::EUR, DebitCredit::Credit),
+            // Neither pending entries nor other currencies move the balance
+            Transaction { status: EntryStatus::Pending, ..transaction("3", 3, "99.00", Currency::EUR, DebitCredit::Credit) },
+            transaction("4", 4, "99.00", Currency::USD, DebitCredit::Credit),
+        ];
+        assert_eq!(statement.verify_balances(), Ok(()));
+
+        statement.opening_balance = Some(Balance::from_signed(BalanceType::Opening, Decimal::new(-10, 0), Currency::EUR, date));
+        statement.closing_balance = Some(Balance::from_signed(BalanceType::Closing, Decimal::new(10, 0), Currency::EUR, date));
+        assert_eq!(statement.verify_balances(), Ok(()));
+
+        statement.closing_balance = Some(Balance::from_signed(BalanceType::Closing, Decimal::new(12, 0), Currency::EUR, date));
+        let discrepancy = statement.verify_balances().unwrap_err();
+        assert_eq!((discrepancy.expected_closing, discrepancy.actual_closing), (Decimal::new(10, 0), Decimal::new(12, 0)));
+        assert_eq!(discrepancy.to_string(), "closing balance 12 does not match expected 10.00 (difference 2.00)");
+    }
+
+    #[test]
+    fn test_by_currency() {
+        let mut statement = Statement::new("STMT".into(), "ACC001", Currency::RUB);
+        statement.transactions = vec![
+            transaction("1", 1, "10.00", Currency::USD, DebitCredit::Debit),
+            transaction("2", 2, "20.00", Currency::RUB, DebitCredit::Credit),
+            transaction("3", 3, "30.00", Currency::USD, DebitCredit::Credit),
+        ];
+        statement.opening_balance = Some(Balance::from_signed(
+            BalanceType::Opening, Decimal::new(100, 0), Currency::RUB, NaiveDate::from_ymd_opt(2024, 2, 1).unwrap(),
+        ));
+
+        let parts = statement.by_currency();
+        let ids: Vec<_> = parts.iter().map(|part| part.statement_id.as_str()).collect();
+        assert_eq!(ids, ["STMT", "STMT-USD"]);
+        assert!(parts[0].opening_balance.is_some() && parts[1].opening_balance.is_none());
+        assert_eq!(parts[1].transactions.len(), 2);
+        assert!(parts[1].transactions.iter().all(|tx| tx.currency == Currency::USD));
+    }
+
+    #[test]
+    fn test_enforce_precision() {
+        let jpy: Currency = "JPY".parse().unwrap();
+        let mut statement = Statement::new("STMT".into(), "ACC001", Currency::EUR);
+        statement.transactions = vec![
+            transaction("1", 1, "10.5", Currency::EUR, DebitCredit::Debit),
+            transaction("2", 2, "100.40", jpy, DebitCredit::Credit),
+        ];
+
+        let mut rejected = statement.clone();
+        assert!(matches!(rejected.enforce_precision(PrecisionPolicy::Reject), Err(Error::InvalidAmount(..))));
+
+        statement.enforce_precision(PrecisionPolicy::Round).unwrap();
+        let amounts: Vec<_> = statement.transactions.iter().map(|tx| tx.amount.to_string()).collect();
+        assert_eq!(amounts, ["10.50", "100"]);
+    }
+
+    #[test]
+    fn test_tag_where() {
+        let mut statement = Statement::new("STMT".into(), "ACC001", Currency::EUR);
+        statement.transactions = vec![
+            transaction("1", 1, "10.00", Currency::EUR, DebitCredit::Debit),
+            transaction("2", 2, "20.00", Currency::EUR, DebitCredit::Credit),
+        ];
+
+        assert_eq!(statement.tag_where("income", |tx| tx.debit_credit == DebitCredit::Credit), 1);
+        assert_eq!(statement.tag_where("income", |tx| tx.debit_credit == DebitCredit::Credit), 1);
+        assert_eq!(statement.transactions[1].tags.len(), 1);
+        assert!(statement.transactions[0].tags.is_empty());
+        statement.remove_tag("income");
+        assert!(statement.transactions.iter().all(|tx| tx.tags.is_empty()));
+    }
+
+    #[test]
+    fn test_statistics() {
+        let input = "Дата проводки,Сумма по дебету,Сумма по кредиту,Контрагент,Назначение платежа\n\
+                     20.02.2024,10.00,,ООО Ромашка,Оплата\n\
+                     20.02.2024,,50.00,ооо ромашка,Возврат\n\
+                     24.02.2024,20.01,,АО Вектор,Оплата\n";
+        let statistics = CsvStatement::from_read(&mut input.as_bytes()).unwrap().statement.statistics();
+        assert_eq!((statistics.summary.debit_count, statistics.summary.credit_count), (2, 1));
+        assert_eq!(statistics.summary.debit_sum, Decimal::from_str("30.01").unwrap());
+        assert_eq!(statistics.min_amount, Some(Decimal::from_str("10.00").unwrap()));
+        assert_eq!(statistics.max_amount, Some(Decimal::from_str("50.00").unwrap()));
+        assert_eq!(statistics.average_amount, Some(Decimal::from_str("26.67").unwrap()));
+        assert_eq!(statistics.counterparties, 2);
+        assert_eq!((statistics.active_days, statistics.days_spanned()), (2, 5));
+    }
+
+    #[test]
+    fn test_with_running_balances() {
+        let input = "Дата проводки,Сумма по дебету,Сумма по кредиту,Назначение платежа,Остаток\n\
+                     22.02.2024,10.00,,Оплата,1020.00\n\
+                     21.02.2024,,50.00,Поступление,1040.00\n\
+                     20.02.2024,20.00,,Оплата,980.00\n";
+        let mut statement = CsvStatement::from_read(&mut input.as_bytes()).unwrap().statement;
+        // 980 + 50 is 1030, so the bank's balance breaks at the second entry
+        assert_eq!(statement.first_running_balance_mismatch(), Some(1));
+
+        let reported: Vec<_> = statement.transactions.iter().map(|tx| tx.running_balance).collect();
+        let computed: Vec<_> = statement.clone().with_running_balances().transactions.iter()
+            .map(|tx| tx.running_balance)
+            .collect();
+        assert_eq!(computed[2], reported[2]);
+        assert_eq!(computed[1], Some(Decimal::from_str("1030.00").unwrap()));
+
+        statement.transactions[1].running_balance = computed[1];
+        assert_eq!(statement.first_running_balance_mismatch(), None);
+    }
+
+    #[test]
+    fn test_merge_overlapping_statements() {
+        let header = "Дата проводки,Сумма по дебету,Сумма по кредиту,№ документа,Назначение платежа\n";
+        let first = format!("{}01.02.2024,100.00,,1,Кофе\n01.02.2024,100.00,,1,Кофе\n02.02.2024,,500.00,2,Возврат\n", header);
+        let second = format!("{}02.02.2024,,500.00,2,Возврат\n03.02.2024,40.00,,3,Такси\n", header);
+
+        let mut merged = CsvStatement::from_read(&mut second.as_bytes()).unwrap().statement;
+        let first = CsvStatement::from_read(&mut first.as_bytes()).unwrap().statement;
+        merged.merge(first, &MergeOptions::default()).unwrap();
+
+        let references: Vec<_> = merged.transactions.iter().map(|tx| tx.reference.as_str()).collect();
+        assert_eq!(references, ["1", "1", "2", "3"]);
+        assert_eq!(merged.net_change(), Decimal::new(26000, 2));
+    }
+
+    #[test]
+    fn test_merge_interleaves_canonically() {
+        let header = "Дата проводки,Сумма по дебету,Сумма по кредиту,№ документа\n";
+        let first = format!("{}01.02.2024,10.00,,A\n03.02.2024,,10.00,C\n", header);
+        let second = format!("{}02.02.2024,10.00,,B\n04.02.2024,,10.00,D\n", header);
+
+        let mut merged = CsvStatement::from_read(&mut second.as_bytes()).unwrap().statement;
+        merged.merge(CsvStatement::from_read(&mut first.as_bytes()).unwrap().statement, &MergeOptions::default()).unwrap();
+
+        let references: Vec<_> = merged.transactions.iter().map(|tx| tx.reference.as_str()).collect();
+        assert_eq!(references, ["A", "B", "C", "D"]);
+        assert!(merged.transactions.windows(2).all(|pair| pair[0].canonical_key() <= pair[1].canonical_key()));
+    }
+
+    #[test]
+    fn test_split_by_month() {
+        let input = "Дата проводки,Сумма по дебету,Сумма по кредиту,Назначение платежа\n\
+                     30.01.2024,100.00,,Январь\n\
+                     05.03.2024,,250.00,Март\n";
+        let mut statement = CsvStatement::from_read(&mut input.as_bytes()).unwrap().statement;
+        statement.opening_balance = Some(Balance::from_signed(
+            BalanceType::Opening, Decimal::new(1000, 0), Currency::RUB, NaiveDate::from_ymd_opt(2024, 1, 30).unwrap(),
+        ));
+        statement.closing_balance = None;
+
+        let months = statement.split_by_month();
+        assert_eq!(months.len(), 3);
+        assert!(months[1].transactions.is_empty());
+        let closings: Vec<_> = months.iter()
+            .map(|m| m.closing_balance.as_ref().unwrap().signed_amount())
+            .collect();
+        assert_eq!(closings, [Decimal::new(900, 0), Decimal::new(900, 0), Decimal::new(1150, 0)]);
+        assert_eq!(months[2].opening_balance.as_ref().unwrap().date, NaiveDate::from_ymd_opt(2024, 3, 1).unwrap());
+        assert!(months.iter().all(|m| m.verify_balances().is_ok()));
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let input = "Дата проводки,Сумма по дебету,Сумма по кредиту,№ документа,Назначение платежа\n\
+                     01.02.2024,100.00,,15,\"Оплата, счёт 12\"\n\
+                     01.02.2024,100.0,,15, ОПЛАТА счёт  12.\n\
+                     01.02.2024,,100.00,15,Оплата счёт 12\n";
+        let transactions = CsvStatement::from_read(&mut input.as_bytes()).unwrap().statement.transactions;
+
+        assert_eq!(transactions[0].fingerprint(), transactions[1].fingerprint());
+        assert_ne!(transactions[0].fingerprint(), transactions[2].fingerprint());
+        // Stable across runs, so fingerprints can be persisted
+        assert_eq!(transactions[0].fingerprint(), 0xfba8_a36e_23c2_00c9);
+
+        // Equal canonical keys mean equal fingerprints, zero amounts included
+        let zero = "Дата проводки,Сумма по дебету,Сумма по кредиту,№ документа\n\
+                    01.02.2024,0.00,,16\n\
+                    01.02.2024,,0,16\n";
+        let transactions = CsvStatement::from_read(&mut zero.as_bytes()).unwrap().statement.transactions;
+        assert_ne!(transactions[0].debit_credit, transactions[1].debit_credit);
+        assert_eq!(transactions[0].canonical_key(), transactions[1].canonical_key());
+        assert_eq!(transactions[0].fingerprint(), transactions[1].fingerprint());
+    }
+
+    #[test]
+    fn test_sort_and_dedup() {
+        let header = "Дата проводки,Сумма по дебету,Сумма по кредиту,№ документа,Назначение платежа\n";
+        let rows = [
+            "02.02.2024,,500.00,7,Возврат\n",
+            "01.02.2024,100.00,,9,Кофе\n",
+            "02.02.2024,40.00,,3,Такси\n",
+            "01.02.2024,100.00,,9,Кофе\n",
+        ];
+        let forward = format!("{}{}", header, rows.concat());
+        let backward = format!("{}{}", header, rows.iter().rev().copied().collect::<String>());
+
+        let sorted = |input: &str, key| {
+            let mut statement = CsvStatement::from_read(&mut input.as_bytes()).unwrap().statement;
+            assert_eq!(statement.dedup_transactions(), 1);
+            statement.sort_transactions(key);
+            statement.transactions.iter().map(|tx| tx.reference.clone()).collect::<Vec<_>>()
+        };
+        assert_eq!(sorted(&forward, SortKey::Date), ["9", "3", "7"]);
+        assert_eq!(sorted(&backward, SortKey::Date), ["9", "3", "7"]);
+        assert_eq!(sorted(&backward, SortKey::Amount), ["9", "3", "7"]);
+        assert_eq!(sorted(&forward, "reference".parse().unwrap()), ["3", "7", "9"]);
+    }
 }
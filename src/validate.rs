@@ -0,0 +1,185 @@
+//! Checking statement files against the rules of their format.
+//!
+//! [`validate`] parses a file in full and then checks the statement:
+//! balances against the transactions, MT940 field lengths and mandatory
+//! fields, and for CAMT.053 the transactions summary against the entries.
+//! Every finding goes into the [`ValidationReport`] rather than stopping
+//! at the first one:
+//!
+//! ```
+//! use ypbank_system::validate::validate;
+//! use ypbank_system::Format;
+//!
+//! let input = ":20:STMT\n:25:ACC001\n:60F:C240101EUR100,00\n\
+//!              :61:2401020102D10,00NTRF//REF1\n:62F:C240102EUR95,00\n";
+//! let report = validate(Format::Mt940, &mut input.as_bytes());
+//! assert!(!report.is_valid());
+//! assert_eq!(report.failures[0].rule.as_str(), "BALANCE_CLOSING");
+//! ```
+
+use crate::camt053_format::{Camt053Format, Camt053ReadOptions};
+use crate::conversion::{fit_mt940_limits, TruncationPolicy};
+use crate::error::{Error, FailureClass, RuleId, ValidationError, Warning};
+use crate::format::FormatRegistry;
+use crate::messages::{render, Language};
+use crate::types::Statement;
+use crate::Format;
+use std::fmt;
+use std::io::Read;
+
+/// Findings of [`validate`].
+#[derive(Debug, Default)]
+pub struct ValidationReport {
+    /// Error that stopped parsing; the statement couldn't be checked.
+    pub parse_error: Option<Error>,
+
+    /// Rules the statement breaks.
+    pub failures: Vec<ValidationError>,
+
+    /// Problems that lenient parsing got past.
+    pub warnings: Vec<Warning>,
+}
+
+impl ValidationReport {
+    /// Whether the file parsed and breaks no rule; warnings don't count.
+    pub fn is_valid(&self) -> bool {
+        self.parse_error.is_none() && self.failures.is_empty()
+    }
+
+    /// Why the file is invalid: the class of its parse error, or
+    /// [`FailureClass::Validation`]; `None` when it's valid.
+    pub fn failure_class(&self) -> Option<FailureClass> {
+        match self.parse_error {
+            Some(ref error) => Some(error.failure_class()),
+            None => (!self.failures.is_empty()).then_some(FailureClass::Validation),
+        }
+    }
+
+    /// The findings one per line, errors first, in `language`.
+    pub fn localized(&self, language: Language) -> String {
+        let error = |error: &Error| render("Error [{}]: {}", &[error.code().as_str(), &error.localized(language)], language);
+        let mut lines = Vec::new();
+        lines.extend(self.parse_error.iter().map(error));
+        lines.extend(self.failures.iter().map(|failure| error(&failure.clone().into())));
+        lines.extend(self.warnings.iter().map(|warning| render("Warning: {}", &[&warning.localized(language)], language)));
+        lines.iter().map(|line| format!("{}\n", line)).collect()
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.localized(Language::English))
+    }
+}
+
+/// Parse `reader` as `format` and check the statement, with the default
+/// formats and the CAMT.053 transactions summary verified.
+pub fn validate(format: Format, reader: &mut dyn Read) -> ValidationReport {
+    let mut registry = FormatRegistry::default();
+    registry.register(Format::Camt053, Camt053Format {
+        read_options: Camt053ReadOptions { verify_summary: true, ..Default::default() },
+        ..Default::default()
+    });
+    validate_with(format, reader, &registry)
+}
+
+/// Parse `reader` as `format` with the formats of `registry`, e.g. to use
+/// a CSV profile, and check the statement.
+pub fn validate_with(format: Format, reader: &mut dyn Read, registry: &FormatRegistry) -> ValidationReport {
+    let mut report = ValidationReport::default();
+    let parsed = match registry.read_with_diagnostics(format, reader) {
+        Ok(parsed) => parsed,
+        Err(error) => {
+            // Checks made while parsing, such as the CAMT.053 summary
+            match error.validation() {
+                Some(failure) => report.failures.push(failure.clone()),
+                None => report.parse_error = Some(error),
+            }
+            return report;
+        }
+    };
+    report.warnings = parsed.warnings;
+    report.failures = check(&parsed.statement, format);
+    report
+}
+
+/// Rules `statement`, read as `format`, breaks.
+fn check(statement: &Statement, format: Format) -> Vec<ValidationError> {
+    let mut failures = Vec::new();
+
+    if let Err(discrepancy) = statement.verify_balances() {
+        let failure = ValidationError::new(RuleId::ClosingBalance, discrepancy.to_string());
+        failures.push(match statement.first_running_balance_mismatch() {
+            Some(i) => failure.at_path(format!("transactions[{}].running_balance", i)),
+            None => failure.at_path("closing_balance"),
+        });
+    }
+
+    if format == Format::Mt940 {
+        let missing = [
+            ("opening balance :60F:", "opening_balance", statement.opening_balance.is_none()),
+            ("closing balance :62F:", "closing_balance", statement.closing_balance.is_none()),
+        ];
+        for (field, path, _) in missing.into_iter().filter(|(_, _, missing)| *missing) {
+            failures.push(ValidationError::new(RuleId::RequiredField, format!("Missing required field: {}", field)).at_path(path));
+        }
+
+        // Every field cut to fit is one over its length limit
+        match fit_mt940_limits(&mut statement.clone(), TruncationPolicy::Truncate) {
+            Ok(fitted) => failures.extend(fitted.losses.iter().map(|loss| {
+                let message = match loss.transaction {
+                    Some(ref reference) => format!("Transaction {}: {} is too long for MT940", reference, loss.field),
+                    None => format!("{} is too long for MT940", loss.field),
+                };
+                ValidationError::new(RuleId::Mt940FieldLength, message)
+            })),
+            Err(error) => failures.extend(error.validation().cloned()),
+        }
+    }
+
+    failures
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camt053_format::Camt053Statement;
+    use crate::mt940_format::Mt940Statement;
+
+    #[test]
+    fn test_validate_mt940() {
+        let input = ":20:STMT\n:25:ACC001\n:60F:C240101EUR100,00\n\
+                     :61:2401020102D10,00NTRF//REF1\n:86:Payment\n:62F:C240102EUR90,00\n";
+        let report = validate(Format::Mt940, &mut input.as_bytes());
+        assert!(report.is_valid(), "{}", report);
+
+        let broken = input.replace("//REF1", "//REFERENCE-OVER-16").replace(":62F:C240102EUR90,00\n", "");
+        let report = validate(Format::Mt940, &mut broken.as_bytes());
+        let rules: Vec<RuleId> = report.failures.iter().map(|failure| failure.rule).collect();
+        assert_eq!(rules, [RuleId::RequiredField, RuleId::Mt940FieldLength]);
+        assert_eq!(report.failure_class(), Some(FailureClass::Validation));
+        assert!(report.to_string().contains(
+            "Error [E_VALIDATION]: Validation error: [MT940_FIELD_LENGTH] Transaction REFERENCE-OVER-16: reference is too long for MT940\n"
+        ));
+
+        let report = validate(Format::Mt940, &mut ":20:STMT\n".as_bytes());
+        assert!(!report.is_valid());
+        assert!(report.parse_error.is_some());
+        assert_eq!(report.failure_class(), Some(FailureClass::Parse));
+    }
+
+    #[test]
+    fn test_validate_camt053_summary() {
+        let input = ":20:STMT\n:25:ACC001\n:60F:C240101EUR0,00\n:61:2401020102C10,00NTRF//REF1\n:62F:C240102EUR10,00\n";
+        let statement = Mt940Statement::from_read(&mut input.as_bytes()).unwrap().statement;
+        let mut xml = Vec::new();
+        Camt053Statement { statement }.write_to(&mut xml).unwrap();
+        let xml = String::from_utf8(xml).unwrap();
+        assert!(validate(Format::Camt053, &mut xml.as_bytes()).is_valid());
+
+        let tampered = xml.replace("<Sum>10.00</Sum>", "<Sum>11.00</Sum>");
+        assert_ne!(tampered, xml);
+        let report = validate(Format::Camt053, &mut tampered.as_bytes());
+        assert_eq!(report.failures[0].rule, RuleId::TransactionsSummary);
+    }
+}
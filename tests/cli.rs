@@ -0,0 +1,199 @@
+//! Tests of the command line tools, run as separate processes.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+const STATEMENT: &str = ":20:STMT\n:25:ACC001\n:60F:C240101EUR100,00\n\
+                         :61:2401020102D10,00NTRF//REF1\n:86:Payment one\n\
+                         :61:2401030103C20,00NTRF//REF2\n:86:Payment two\n\
+                         :62F:C240103EUR110,00\n-}\n";
+
+/// Run the converter with `args`, feeding it `input` on stdin.
+fn converter(args: &[&str], input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_ypbank_converter"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // The converter may exit without reading, e.g. on a usage error
+    let _ = child.stdin.take().unwrap().write_all(input.as_bytes());
+    child.wait_with_output().unwrap()
+}
+
+/// Run the statement comparer with `args`.
+fn compare(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_ypbank_compare")).args(args).output().unwrap()
+}
+
+/// Directory for the files of `test`, created empty.
+fn dir(test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("ypbank-cli-{}-{}", std::process::id(), test));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// File `name` in `dir` holding `contents`.
+fn file(dir: &Path, name: &str, contents: &str) -> PathBuf {
+    let path = dir.join(name);
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+fn stdout(output: &Output) -> String {
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+#[test]
+fn test_json_layout() {
+    let json = stdout(&converter(&["--input-format", "mt940", "--output-format", "json"], STATEMENT));
+    assert!(json.starts_with("{\n  \"schema_version\": 2,"));
+
+    let json = stdout(&converter(&["--input-format", "mt940", "--output-format", "json", "--indent", "4"], STATEMENT));
+    assert!(json.starts_with("{\n    \"schema_version\": 2,"));
+
+    let json = stdout(&converter(&["--input-format", "mt940", "--output-format", "json", "--compact"], STATEMENT));
+    assert_eq!(json.lines().count(), 1);
+    assert!(json.starts_with("{\"schema_version\":2,\"statement\":{"));
+}
+
+#[test]
+fn test_exit_codes() {
+    let code = |output: Output| output.status.code().unwrap();
+    let dir = dir("exit_codes");
+    let statement = file(&dir, "statement.mt940", STATEMENT);
+    let statement = statement.to_str().unwrap();
+
+    assert_eq!(code(converter(&["--no-such-option"], "")), 64);
+    assert_eq!(code(converter(&["--input-format", "mt940", "--output-format", "pdf"], STATEMENT)), 64);
+    assert_eq!(code(converter(&["--input-format", "mt940", "--output-format", "json"], ":20:STMT\n:25:ACC001\n:60F:C240101EUR1O0,00\n")), 2);
+    let unbalanced = file(&dir, "unbalanced.mt940", &STATEMENT.replace(":62F:C240103EUR110,00", ":62F:C240103EUR95,00"));
+    assert_eq!(code(converter(&["validate", unbalanced.to_str().unwrap()], "")), 3);
+    assert_eq!(code(converter(&["validate", statement], "")), 0);
+    let missing = dir.join("missing.mt940");
+    assert_eq!(code(converter(&["-i", missing.to_str().unwrap(), "--output-format", "json"], "")), 4);
+
+    let other = file(&dir, "other.mt940", &STATEMENT.replace("Payment two", "Payment 2"));
+    let other = other.to_str().unwrap();
+    assert_eq!(code(compare(&["--file1", statement, "--format1", "mt940", "--file2", statement, "--format2", "mt940"])), 0);
+    assert_eq!(code(compare(&["--file1", statement, "--format1", "mt940", "--file2", other, "--format2", "mt940"])), 5);
+    assert_eq!(code(compare(&["--file1", statement])), 64);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+/// February statement following [`STATEMENT`].
+const NEXT_STATEMENT: &str = ":20:STMT2\n:25:ACC001\n:60F:C240131EUR110,00\n\
+                              :61:2402010201D5,00NTRF//REF3\n:86:Fee\n:62F:C240201EUR105,00\n-}\n";
+
+/// References of the transactions in CSV `output`, in order.
+fn references(csv: &str) -> Vec<String> {
+    csv.lines().skip(1).map(|row| row.split(',').nth(5).unwrap().to_string()).collect()
+}
+
+#[test]
+fn test_merge() {
+    let dir = dir("merge");
+    let first = file(&dir, "january.mt940", STATEMENT);
+    let second = file(&dir, "february.mt940", NEXT_STATEMENT);
+    let (first, second) = (first.to_str().unwrap(), second.to_str().unwrap());
+
+    let csv = stdout(&converter(&["--merge", "-i", second, first, "--output-format", "csv"], ""));
+    assert_eq!(references(&csv), ["REF1", "REF2", "REF3"]);
+    let mt940 = stdout(&converter(&["--merge", "-i", first, second, "--output-format", "mt940"], ""));
+    assert!(mt940.contains(":60F:C240101EUR100,00") && mt940.contains(":62F:C240201EUR105,00"));
+    assert_eq!(mt940.matches(":61:").count(), 3);
+
+    let gap = file(&dir, "gap.mt940", &NEXT_STATEMENT.replace("EUR110,00", "EUR120,00").replace("EUR105,00", "EUR115,00"));
+    let output = converter(&["--merge", "-i", first, gap.to_str().unwrap(), "--output-format", "csv"], "");
+    assert_eq!(output.status.code(), Some(3));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("BALANCE_CHAIN"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_split_by() {
+    let dir = dir("split_by");
+    let input = file(&dir, "statements.mt940", &format!("{}{}", STATEMENT, NEXT_STATEMENT));
+    let input = input.to_str().unwrap();
+    let output = dir.join("out.csv");
+
+    stdout(&converter(&["-i", input, "--output-format", "csv", "--split-by", "month", "-o", output.to_str().unwrap()], ""));
+    let month = |name: &str| references(&std::fs::read_to_string(dir.join(name)).unwrap());
+    assert_eq!(month("out-2024-01.csv"), ["REF1", "REF2"]);
+    assert_eq!(month("out-2024-02.csv"), ["REF3"]);
+    assert_eq!(converter(&["-i", input, "--output-format", "csv", "--split-by", "month"], "").status.code(), Some(64));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_filters() {
+    let filter = |args: &[&str]| {
+        let args = [&["--input-format", "mt940", "--output-format", "csv"], args].concat();
+        references(&stdout(&converter(&args, STATEMENT)))
+    };
+    assert_eq!(filter(&["--direction", "debit"]), ["REF1"]);
+    assert_eq!(filter(&["--direction", "credit", "--min-amount", "15"]), ["REF2"]);
+    assert_eq!(filter(&["--max-amount", "15"]), ["REF1"]);
+    assert_eq!(filter(&["--from-date", "2024-01-03"]), ["REF2"]);
+    assert_eq!(filter(&["--to-date", "2024-01-02"]), ["REF1"]);
+
+    let output = converter(&["--input-format", "mt940", "--output-format", "csv", "--direction", "both"], STATEMENT);
+    assert_eq!(output.status.code(), Some(64));
+}
+
+#[test]
+fn test_watch() {
+    let dir = dir("watch");
+    let inbox = dir.join("inbox");
+    std::fs::create_dir(&inbox).unwrap();
+    // Dropped before the converter starts, and picked up once settled
+    file(&inbox, "january.mt940", STATEMENT);
+    file(&inbox, "broken.mt940", ":20:STMT\n");
+    let output_dir = dir.join("out");
+    let mut child = Command::new(env!("CARGO_BIN_EXE_ypbank_converter"))
+        .args(["--watch", inbox.to_str().unwrap(), "--output-dir", output_dir.to_str().unwrap(), "--output-format", "json"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let converted = output_dir.join("january.json");
+    let settled = || inbox.join("done/january.mt940").exists() && inbox.join("failed/broken.mt940").exists();
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(20);
+    while !settled() && std::time::Instant::now() < deadline {
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+    child.kill().unwrap();
+    child.wait().unwrap();
+    assert!(std::fs::read_to_string(&converted).unwrap().contains("\"statement_id\": \"STMT\""));
+    assert!(inbox.join("done/january.mt940").exists());
+    assert!(inbox.join("failed/broken.mt940").exists());
+    assert!(!inbox.join("january.mt940").exists());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_anonymize() {
+    let anonymized = |args: &[&str]| {
+        let mut args = [&["--input-format", "mt940", "--output-format", "json", "--anonymize"], args].concat();
+        args.push("--compact");
+        stdout(&converter(&args, STATEMENT))
+    };
+    let salted = anonymized(&["--anonymize-salt", "secret"]);
+    assert!(!salted.contains("ACC001"));
+    assert_eq!(anonymized(&["--anonymize-salt", "secret"]), salted);
+    assert_ne!(anonymized(&["--anonymize-salt", "pepper"]), salted);
+
+    // Without a salt, a fresh random one every run
+    let random = anonymized(&[]);
+    assert!(!random.contains("ACC001"));
+    assert_ne!(anonymized(&[]), random);
+
+    let code = |args: &[&str]| converter(args, STATEMENT).status.code().unwrap();
+    assert_eq!(code(&["--input-format", "mt940", "--output-format", "json", "--anonymize", "--anonymize-salt", ""]), 64);
+    assert_eq!(code(&["--input-format", "mt940", "--output-format", "json", "--anonymize-salt", "secret"]), 64);
+}